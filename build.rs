@@ -0,0 +1,37 @@
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+/// What goes into the game a player downloads: the crate's code, and the
+/// page, scripts and assets served next to it.
+const SOURCES: [&str; 4] = ["src", "web/public", "web/src", "web/index.html"];
+
+/// Names the build after what is in it, as `BUILD_HASH`, so the offline
+/// cache changes whenever the code or any asset does.
+fn main() {
+    let mut hasher = DefaultHasher::new();
+    for source in SOURCES {
+        println!("cargo:rerun-if-changed={}", source);
+        hash_path(Path::new(source), &mut hasher);
+    }
+    println!("cargo:rustc-env=BUILD_HASH={:016x}", hasher.finish());
+}
+
+fn hash_path(path: &Path, hasher: &mut DefaultHasher) {
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+        paths.iter().for_each(|path| hash_path(path, hasher));
+    } else if let Ok(contents) = fs::read(path) {
+        path.hash(hasher);
+        contents.hash(hasher);
+    }
+}
@@ -1,12 +1,26 @@
 use anyhow::{anyhow, Result};
-use std::future::Future;
-use wasm_bindgen::{closure::WasmClosure, prelude::Closure, JsCast, JsValue};
+use futures::channel::oneshot;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    rc::Rc,
+};
+use wasm_bindgen::{closure::WasmClosure, convert::FromWasmAbi, prelude::Closure, JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    js_sys::ArrayBuffer, CanvasRenderingContext2d, Document, Element, HtmlCanvasElement,
-    HtmlElement, HtmlImageElement, Response, Window,
+    js_sys::{Array, ArrayBuffer, Function, Object, Promise, Reflect, Uint8Array},
+    BeforeUnloadEvent, Blob, CanvasRenderingContext2d, CryptoKey, Document, Element, EventTarget,
+    File, FilePropertyBag, HtmlAnchorElement, HtmlCanvasElement, HtmlElement, HtmlImageElement,
+    HtmlInputElement, KeyboardEvent, Node, Performance, RequestInit, Response, ServiceWorker,
+    ServiceWorkerRegistration, Storage, SubtleCrypto, Url, UrlSearchParams, Window,
 };
 
+pub mod recorder;
+pub mod storage;
+pub mod webrtc;
+pub mod websocket;
+
 pub fn window() -> Result<Window> {
     web_sys::window().ok_or_else(|| anyhow!("No Window Found"))
 }
@@ -18,15 +32,41 @@ pub fn document() -> Result<Document> {
 }
 
 pub fn canvas() -> Result<HtmlCanvasElement> {
+    canvas_by_id("canvas")
+}
+
+pub fn canvas_by_id(id: &str) -> Result<HtmlCanvasElement> {
     document()?
-        .get_element_by_id("canvas")
-        .ok_or_else(|| anyhow!("No Canvas Element found with ID 'canvas'"))?
+        .get_element_by_id(id)
+        .ok_or_else(|| anyhow!("No Canvas Element found with ID '{}'", id))?
         .dyn_into::<web_sys::HtmlCanvasElement>()
         .map_err(|element| anyhow!("Error converting {:#?} to HtmlCanvasElement", element))
 }
 
-pub fn context() -> Result<CanvasRenderingContext2d> {
-    canvas()?
+/// The canvas matched by a CSS `selector`, for running a game somewhere
+/// other than the page's own canvas.
+pub fn find_canvas(selector: &str) -> Result<HtmlCanvasElement> {
+    document()?
+        .query_selector(selector)
+        .map_err(|err| anyhow!("Invalid selector {} {:#?}", selector, err))?
+        .ok_or_else(|| anyhow!("No element found matching {}", selector))?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(|element| anyhow!("Error converting {:#?} to HtmlCanvasElement", element))
+}
+
+pub fn create_canvas(width: u32, height: u32) -> Result<HtmlCanvasElement> {
+    let canvas = document()?
+        .create_element("canvas")
+        .map_err(|err| anyhow!("Could not create canvas {:#?}", err))?
+        .dyn_into::<HtmlCanvasElement>()
+        .map_err(|element| anyhow!("Error converting {:#?} to HtmlCanvasElement", element))?;
+    canvas.set_width(width);
+    canvas.set_height(height);
+    Ok(canvas)
+}
+
+pub fn canvas_context(canvas: &HtmlCanvasElement) -> Result<CanvasRenderingContext2d> {
+    canvas
         .get_context("2d")
         .map_err(|js_value| anyhow!("Error getting 2d context {:#?}", js_value))?
         .ok_or_else(|| anyhow!("No 2d context found"))?
@@ -46,8 +86,44 @@ where
     wasm_bindgen_futures::spawn_local(future);
 }
 
+thread_local! {
+    static ASSET_BASE: RefCell<Option<String>> = const { RefCell::new(None) };
+    /// Closures the page can still call, forgotten ones included.
+    static LIVE_CLOSURES: Cell<usize> = const { Cell::new(0) };
+    /// The arrow key listeners of the overlays drawn, until they're hidden.
+    static OVERLAY_KEYS: RefCell<Vec<(Element, EventListener<KeyboardEvent>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Loads assets from `base` instead of next to the page, for pages that
+/// embed the game and keep its files somewhere else.
+pub fn set_asset_base(base: Option<String>) {
+    ASSET_BASE.with(|asset_base| *asset_base.borrow_mut() = base);
+}
+
+/// Where to load the asset at `path` from.
+pub fn asset_url(path: &str) -> String {
+    ASSET_BASE.with(|base| match base.borrow().as_deref() {
+        Some(base) => join_asset_url(base, path),
+        None => path.to_string(),
+    })
+}
+
+/// Only relative paths go under `base`.
+fn join_asset_url(base: &str, path: &str) -> String {
+    let absolute = path.starts_with('/')
+        || path.contains("://")
+        || path.starts_with("data:")
+        || path.starts_with("blob:");
+    if absolute {
+        path.to_string()
+    } else {
+        format!("{}/{}", base.trim_end_matches('/'), path)
+    }
+}
+
 pub async fn fetch_with_str(resource: &str) -> Result<JsValue> {
-    JsFuture::from(window()?.fetch_with_str(resource))
+    JsFuture::from(window()?.fetch_with_str(&asset_url(resource)))
         .await
         .map_err(|err| anyhow!("error fetching {:#?}", err))
 }
@@ -60,13 +136,109 @@ pub async fn fetch_response(resource: &str) -> Result<Response> {
 }
 
 pub async fn fetch_json(json_path: &str) -> Result<JsValue> {
-    let resp = fetch_response(json_path).await?;
+    response_json(&fetch_response(json_path).await?).await
+}
+
+/// Sends `body` to `url` as JSON, and fails unless the server takes it.
+pub async fn post_json(url: &str, body: &JsValue) -> Result<Response> {
+    let json: String = web_sys::js_sys::JSON::stringify(body)
+        .map_err(|err| anyhow!("Could not stringify {:#?}", err))?
+        .into();
+    let headers = Object::new();
+    Reflect::set(&headers, &"Content-Type".into(), &"application/json".into())
+        .map_err(|err| anyhow!("Could not set the content type {:#?}", err))?;
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_headers(&headers);
+    init.set_body(&JsValue::from_str(&json));
+    let response: Response = JsFuture::from(window()?.fetch_with_str_and_init(url, &init))
+        .await
+        .map_err(|err| anyhow!("error posting to {} {:#?}", url, err))?
+        .dyn_into()
+        .map_err(|err| anyhow!("Error converting fetch to Response {:#?}", err))?;
+    if !response.ok() {
+        return Err(anyhow!("{} answered {}", url, response.status()));
+    }
+    Ok(response)
+}
+
+pub async fn response_json(response: &Response) -> Result<JsValue> {
     JsFuture::from(
-        resp.json()
+        response
+            .json()
             .map_err(|err| anyhow!("Could not get JSON from resonse {:#?}", err))?,
     )
     .await
-    .map_err(|err| anyhow!("error fetching JSON {:#?}", err))
+    .map_err(|err| anyhow!("error reading JSON {:#?}", err))
+}
+
+fn subtle_crypto() -> Result<SubtleCrypto> {
+    Ok(window()?
+        .crypto()
+        .map_err(|err| anyhow!("No crypto found {:#?}", err))?
+        .subtle())
+}
+
+/// The SHA-256 hash of `data`, in hex.
+pub async fn sha256(data: &[u8]) -> Result<String> {
+    let digest = subtle_crypto()?
+        .digest_with_str_and_u8_array("SHA-256", data)
+        .map_err(|err| anyhow!("Could not hash {:#?}", err))?;
+    let digest = JsFuture::from(digest)
+        .await
+        .map_err(|err| anyhow!("Hashing failed {:#?}", err))?;
+    Ok(hex(&Uint8Array::new(&digest).to_vec()))
+}
+
+/// The HMAC SHA-256 of `message` under `key`, in hex.
+pub async fn hmac_sha256(key: &[u8], message: &[u8]) -> Result<String> {
+    let subtle = subtle_crypto()?;
+    let algorithm = Object::new();
+    Reflect::set(&algorithm, &"name".into(), &"HMAC".into())
+        .and_then(|_| Reflect::set(&algorithm, &"hash".into(), &"SHA-256".into()))
+        .map_err(|err| anyhow!("Could not describe HMAC {:#?}", err))?;
+    let import = subtle
+        .import_key_with_object(
+            "raw",
+            &Uint8Array::from(key),
+            &algorithm,
+            false,
+            &Array::of1(&"sign".into()),
+        )
+        .map_err(|err| anyhow!("Could not import the key {:#?}", err))?;
+    let key: CryptoKey = JsFuture::from(import)
+        .await
+        .map_err(|err| anyhow!("Importing the key failed {:#?}", err))?
+        .dyn_into()
+        .map_err(|err| anyhow!("Error converting {:#?} to CryptoKey", err))?;
+    let signature = subtle
+        .sign_with_str_and_u8_array("HMAC", &key, message)
+        .map_err(|err| anyhow!("Could not sign {:#?}", err))?;
+    let signature = JsFuture::from(signature)
+        .await
+        .map_err(|err| anyhow!("Signing failed {:#?}", err))?;
+    Ok(hex(&Uint8Array::new(&signature).to_vec()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+#[cfg(feature = "dev")]
+pub async fn fetch_text(resource: &str) -> Result<String> {
+    let resp = fetch_response(resource).await?;
+    JsFuture::from(
+        resp.text()
+            .map_err(|err| anyhow!("Could not get text from response {:#?}", err))?,
+    )
+    .await
+    .map_err(|err| anyhow!("error fetching text {:#?}", err))?
+    .as_string()
+    .ok_or_else(|| anyhow!("{} is not text", resource))
 }
 
 pub async fn fetch_array_buffer(resource: &str) -> Result<ArrayBuffer> {
@@ -82,6 +254,29 @@ pub async fn fetch_array_buffer(resource: &str) -> Result<ArrayBuffer> {
         .map_err(|err| anyhow!("Error converting raw JSValue to array buffer {:#?}", err))
 }
 
+/// Registers the service worker at `script` and waits until it is active.
+pub async fn register_service_worker(script: &str) -> Result<ServiceWorker> {
+    let container = window()?.navigator().service_worker();
+    JsFuture::from(container.register(script))
+        .await
+        .map_err(|err| anyhow!("Could not register service worker {:#?}", err))?;
+    let ready = container
+        .ready()
+        .map_err(|err| anyhow!("Could not wait for service worker {:#?}", err))?;
+    JsFuture::from(ready)
+        .await
+        .map_err(|err| anyhow!("Service worker never became ready {:#?}", err))?
+        .dyn_into::<ServiceWorkerRegistration>()
+        .map_err(|err| {
+            anyhow!(
+                "Could not convert into ServiceWorkerRegistration {:#?}",
+                err
+            )
+        })?
+        .active()
+        .ok_or_else(|| anyhow!("No active service worker"))
+}
+
 pub fn new_image() -> Result<HtmlImageElement> {
     HtmlImageElement::new().map_err(|err| anyhow!("Could not create HtmlImageElement: {:#?}", err))
 }
@@ -101,6 +296,12 @@ pub fn request_animation_frame(callback: &LoopClosure) -> Result<i32> {
         .map_err(|err| anyhow!("Cannot request animation frame {:#?}", err))
 }
 
+pub fn cancel_animation_frame(id: i32) -> Result<()> {
+    window()?
+        .cancel_animation_frame(id)
+        .map_err(|err| anyhow!("Cannot cancel animation frame {:#?}", err))
+}
+
 pub fn create_raf_closure(f: impl FnMut(f64) + 'static) -> LoopClosure {
     closure_wrap(Box::new(f))
 }
@@ -109,42 +310,814 @@ pub fn closure_wrap<T: WasmClosure + ?Sized>(data: Box<T>) -> Closure<T> {
     Closure::wrap(data)
 }
 
-pub fn now() -> Result<f64> {
-    Ok(window()?
+/// Leaves `closure` for the page to call for as long as it's open. Counted
+/// in [`live_closures`], so leaks show up in a long session.
+pub fn forget<T: WasmClosure + ?Sized>(closure: Closure<T>) {
+    LIVE_CLOSURES.with(|count| count.set(count.get() + 1));
+    closure.forget();
+}
+
+/// How many closures the page can still call, from [`EventListener`]s and
+/// [`forget`].
+pub fn live_closures() -> usize {
+    LIVE_CLOSURES.with(Cell::get)
+}
+
+/// Calls a function on each of an event on a target, until it's dropped.
+pub struct EventListener<E> {
+    target: EventTarget,
+    event: &'static str,
+    callback: Closure<dyn FnMut(E)>,
+}
+
+impl<E: FromWasmAbi + 'static> EventListener<E> {
+    pub fn listen(
+        target: &EventTarget,
+        event: &'static str,
+        callback: impl FnMut(E) + 'static,
+    ) -> Result<Self> {
+        let callback = closure_wrap(Box::new(callback) as Box<dyn FnMut(E)>);
+        target
+            .add_event_listener_with_callback(event, callback.as_ref().unchecked_ref())
+            .map_err(|err| anyhow!("Could not listen for {} {:#?}", event, err))?;
+        LIVE_CLOSURES.with(|count| count.set(count.get() + 1));
+        Ok(EventListener {
+            target: target.clone(),
+            event,
+            callback,
+        })
+    }
+}
+
+impl<E> Drop for EventListener<E> {
+    fn drop(&mut self) {
+        LIVE_CLOSURES.with(|count| count.set(count.get() - 1));
+        if let Err(err) = self
+            .target
+            .remove_event_listener_with_callback(self.event, self.callback.as_ref().unchecked_ref())
+        {
+            log::error!("Could not stop listening for {} {:#?}", self.event, err);
+        }
+    }
+}
+
+/// Calls `callback` with whether the page is hidden, whenever that changes,
+/// as when the player switches tabs.
+pub fn on_visibility_change(
+    callback: impl Fn(bool) + 'static,
+) -> Result<EventListener<web_sys::Event>> {
+    let document = document()?;
+    let hidden = document.clone();
+    EventListener::listen(&document, "visibilitychange", move |_event| {
+        callback(hidden.hidden())
+    })
+}
+
+/// Calls `callback` when the window changes size or the screen is turned.
+pub fn on_resize(callback: impl Fn() + 'static) -> Result<[EventListener<web_sys::Event>; 2]> {
+    let window = window()?;
+    let callback = Rc::new(callback);
+    let turned = Rc::clone(&callback);
+    Ok([
+        EventListener::listen(&window, "resize", move |_event| callback())?,
+        EventListener::listen(&window, "orientationchange", move |_event| turned())?,
+    ])
+}
+
+/// How much room the window has, across and down, in CSS pixels.
+pub fn window_size() -> Result<(f64, f64)> {
+    let window = window()?;
+    let size = |size: std::result::Result<JsValue, JsValue>| {
+        size.ok()
+            .and_then(|size| size.as_f64())
+            .ok_or_else(|| anyhow!("Could not read the window size"))
+    };
+    Ok((size(window.inner_width())?, size(window.inner_height())?))
+}
+
+/// Calls `callback` as the page goes away, when there is still time to
+/// send a beacon.
+pub fn on_page_hide(callback: impl Fn() + 'static) -> Result<EventListener<web_sys::Event>> {
+    let window = window()?;
+    EventListener::listen(&window, "pagehide", move |_event| callback())
+}
+
+/// Marks the history entry pushed for the guard, so Back has somewhere to
+/// go without leaving the page.
+const GUARD_STATE: &str = "walk_the_dog_guard";
+
+/// Asks before the page is left while it's guarding, as in the middle of a
+/// run: closing or reloading the tab, and pressing Back. Back is caught by
+/// keeping an extra entry in the history, which it goes back to first.
+pub struct NavigationGuard {
+    guarding: Rc<Cell<bool>>,
+    /// Only listened for while guarding, since the browser keeps pages
+    /// with the listener out of its back/forward cache.
+    before_unload: Option<EventListener<BeforeUnloadEvent>>,
+    _pop_state: EventListener<web_sys::Event>,
+}
+
+impl NavigationGuard {
+    /// Asks `question` when Back is pressed while guarding.
+    pub fn new(question: &'static str) -> Result<Self> {
+        let window = window()?;
+        push_guard_state()?;
+        let guarding = Rc::new(Cell::new(false));
+        let asking = guarding.clone();
+        let popped = window.clone();
+        let pop_state = EventListener::listen(&window, "popstate", move |_event| {
+            let leave = !asking.get() || popped.confirm_with_message(question).unwrap_or(true);
+            let kept = if leave {
+                popped
+                    .history()
+                    .and_then(|history| history.back())
+                    .map_err(|err| anyhow!("Could not go back {:#?}", err))
+            } else {
+                push_guard_state()
+            };
+            if let Err(err) = kept {
+                log::error!("Could not guard the page {:#?}", err);
+            }
+        })?;
+        Ok(NavigationGuard {
+            guarding,
+            before_unload: None,
+            _pop_state: pop_state,
+        })
+    }
+
+    pub fn set_guarding(&mut self, guarding: bool) -> Result<()> {
+        if guarding == self.guarding.get() {
+            return Ok(());
+        }
+        self.guarding.set(guarding);
+        self.before_unload = if guarding {
+            let window = window()?;
+            Some(EventListener::listen(
+                &window,
+                "beforeunload",
+                |event: BeforeUnloadEvent| {
+                    // Older browsers only ask when a return value is set.
+                    event.prevent_default();
+                    event.set_return_value(" ");
+                },
+            )?)
+        } else {
+            None
+        };
+        Ok(())
+    }
+}
+
+fn push_guard_state() -> Result<()> {
+    window()?
+        .history()
+        .and_then(|history| history.push_state(&JsValue::from_str(GUARD_STATE), ""))
+        .map_err(|err| anyhow!("Could not add to the history {:#?}", err))
+}
+
+/// Sends `body` to `url` in the background, even if the page is closing.
+pub fn send_beacon(url: &str, body: &str) -> Result<()> {
+    let queued = window()?
+        .navigator()
+        .send_beacon_with_opt_str(url, Some(body))
+        .map_err(|err| anyhow!("Could not send a beacon to {} {:#?}", url, err))?;
+    if queued {
+        Ok(())
+    } else {
+        Err(anyhow!("The browser would not queue a beacon to {}", url))
+    }
+}
+
+fn performance() -> Result<Performance> {
+    window()?
         .performance()
-        .ok_or_else(|| anyhow!("Performance object not found"))?
-        .now())
-}
-
-pub fn draw_ui(html: &str) -> Result<()> {
-    find_ui()?
-        .insert_adjacent_html("afterBegin", html)
-        .map_err(|err| anyhow!("Could not insert html {:#?}", err))
-}
-
-pub fn hide_ui() -> Result<()> {
-    let ui = find_ui()?;
-    if let Some(child) = ui.first_child() {
-        ui.remove_child(&child)
-            .map(|_removed_child| ())
-            .map_err(|err| anyhow!("Failed to remove child {:#?}", err))
-            .and_then(|_| {
-                canvas()?
-                    .focus()
-                    .map_err(|err| anyhow!("Could not set focus to canvas! {:#?}", err))
+        .ok_or_else(|| anyhow!("Performance object not found"))
+}
+
+/// The page, and the scripts, styles and wasm it loaded from its own origin,
+/// which it needs to start again offline.
+#[cfg_attr(feature = "dev", allow(dead_code))]
+pub fn app_shell() -> Result<Vec<String>> {
+    let location = window()?.location();
+    let origin = location
+        .origin()
+        .map_err(|err| anyhow!("Could not read the origin {:#?}", err))?;
+    let page = location
+        .pathname()
+        .map_err(|err| anyhow!("Could not read the path {:#?}", err))?;
+    let mut shell = vec![format!("{}{}", origin, page)];
+    shell.extend(
+        performance()?
+            .get_entries_by_type("resource")
+            .iter()
+            .filter_map(|entry| {
+                Reflect::get(&entry, &JsValue::from_str("name"))
+                    .ok()?
+                    .as_string()
             })
-    } else {
+            .filter(|url| url.starts_with(&origin))
+            .filter(|url| {
+                let path = url.split(['?', '#']).next().unwrap_or_default();
+                [".js", ".wasm", ".css"]
+                    .iter()
+                    .any(|extension| path.ends_with(extension))
+            }),
+    );
+    shell.sort_unstable();
+    shell.dedup();
+    Ok(shell)
+}
+
+pub fn now() -> Result<f64> {
+    Ok(performance()?.now())
+}
+
+#[cfg(feature = "profiling")]
+pub fn mark(name: &str) -> Result<()> {
+    performance()?
+        .mark(name)
+        .map_err(|err| anyhow!("Could not create mark {} {:#?}", name, err))
+}
+
+#[cfg(feature = "profiling")]
+pub fn measure(name: &str, start_mark: &str, end_mark: &str) -> Result<()> {
+    let performance = performance()?;
+    performance
+        .measure_with_start_mark_and_end_mark(name, start_mark, end_mark)
+        .map_err(|err| anyhow!("Could not measure {} {:#?}", name, err))?;
+    performance.clear_marks_with_mark_name(start_mark);
+    performance.clear_marks_with_mark_name(end_mark);
+    performance.clear_measures_with_measure_name(name);
+    Ok(())
+}
+
+pub fn local_storage() -> Result<Storage> {
+    window()?
+        .local_storage()
+        .map_err(|err| anyhow!("Could not access local storage {:#?}", err))?
+        .ok_or_else(|| anyhow!("No local storage found"))
+}
+
+/// Storage that lasts as long as the tab does, reloads included.
+pub fn session_storage() -> Result<Storage> {
+    window()?
+        .session_storage()
+        .map_err(|err| anyhow!("Could not access session storage {:#?}", err))?
+        .ok_or_else(|| anyhow!("No session storage found"))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Mode {
+    #[default]
+    Solo,
+    TwoPlayer,
+    Autopilot,
+    /// Runs the autopilot through one segment over and over, with its
+    /// bounding boxes showing.
+    Preview(usize),
+}
+
+/// Settings read from the page URL at startup, so links can set up a game
+/// without a rebuild. Unknown values fall back to the defaults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StartupConfig {
+    /// The course the first run starts on. Runs after it get new ones.
+    pub seed: Option<u64>,
+    pub debug: Option<String>,
+    pub mode: Mode,
+    pub character: Option<String>,
+    /// Left out of the URL, the player's profile decides.
+    pub mute: Option<bool>,
+    pub reduced_motion: bool,
+    pub high_contrast: bool,
+    /// Left out of the URL, the player's profile decides.
+    pub hold_to_slide: Option<bool>,
+    /// Runs the course right to left. Left out of the URL, the player's
+    /// profile decides.
+    pub mirrored: Option<bool>,
+    /// Where the game's files are, when not next to the page.
+    pub asset_base: Option<String>,
+    /// The id of the element the game's panels are drawn in, so each game
+    /// on a page can have its own. Left out, it's `ui`.
+    pub ui: Option<String>,
+    /// Where to send scores. Left out, they stay on the player's device.
+    /// Only an embedding page's config can set it, so a link can't send
+    /// scores and their signatures somewhere else.
+    pub leaderboard: Option<String>,
+    /// Where to send gameplay events. Left out, nothing is collected. Only
+    /// an embedding page's config can set it, so a link can't send a
+    /// player's events somewhere else.
+    pub analytics: Option<String>,
+    /// Starts with developer mode on, in a build with the `dev` feature.
+    pub cheats: bool,
+    /// The size of the world, across and down, when the canvas the page
+    /// made shouldn't decide it.
+    pub resolution: Option<(i16, i16)>,
+    /// The race server to join, where every runner gets the same course.
+    pub race: Option<String>,
+    /// Starts the race instead of waiting for the host to.
+    pub host: bool,
+    /// A replay to watch: an exported code, or `last` for this device's
+    /// last run.
+    pub replay: Option<String>,
+    /// Redraws only what changed each frame, with `render=dirty`, whatever
+    /// the quality.
+    pub dirty_rects: bool,
+    /// Asks before the page is left in the middle of a run. The game's own
+    /// page always does. A page embedding the game has to ask for it, as
+    /// the page isn't the game's to hold.
+    pub guard_navigation: bool,
+    pub callbacks: Callbacks,
+}
+
+/// The game events a page can register a function for. The functions are
+/// passed in as `on_` and the event's name.
+const CALLBACK_EVENTS: [&str; 3] = ["ready", "score_changed", "game_over"];
+
+/// Functions a page embedding the game registers, to hear how the game goes
+/// without patching it. Clones share their functions, so ones registered
+/// after the game starts are called too.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Callbacks {
+    registered: Rc<RefCell<HashMap<&'static str, Function>>>,
+}
+
+impl Callbacks {
+    fn from_js(config: &JsValue) -> Self {
+        let callbacks = Callbacks::default();
+        CALLBACK_EVENTS.iter().for_each(|&event| {
+            let callback = Reflect::get(config, &JsValue::from_str(&format!("on_{}", event)))
+                .ok()
+                .and_then(|value| value.dyn_into::<Function>().ok());
+            if let Some(callback) = callback {
+                callbacks.registered.borrow_mut().insert(event, callback);
+            }
+        });
+        callbacks
+    }
+
+    /// Calls `callback` on `event` from now on, instead of any function
+    /// registered for it before.
+    pub fn register(&self, event: &str, callback: Function) -> Result<()> {
+        let event = CALLBACK_EVENTS
+            .iter()
+            .find(|&&name| name == event)
+            .ok_or_else(|| anyhow!("No game event called {}", event))?;
+        self.registered.borrow_mut().insert(event, callback);
+        Ok(())
+    }
+
+    /// Tells the page the game has loaded and is running.
+    pub fn ready(&self) {
+        self.call("ready", &JsValue::UNDEFINED);
+    }
+
+    /// Tells the page the distance run so far, whenever it changes.
+    pub fn score_changed(&self, distance: u32) {
+        self.call("score_changed", &JsValue::from(distance));
+    }
+
+    /// Tells the page a run ended, and how far it got.
+    pub fn game_over(&self, distance: u32) {
+        self.call("game_over", &JsValue::from(distance));
+    }
+
+    fn call(&self, event: &str, argument: &JsValue) {
+        let Some(callback) = self.registered.borrow().get(event).cloned() else {
+            return;
+        };
+        if let Err(err) = callback.call1(&JsValue::NULL, argument) {
+            log::error!("The page's {} callback failed {:#?}", event, err);
+        }
+    }
+}
+
+impl StartupConfig {
+    pub fn from_url() -> Result<Self> {
+        let search = window()?
+            .location()
+            .search()
+            .map_err(|err| anyhow!("Could not read location search {:#?}", err))?;
+        let params = UrlSearchParams::new_with_str(&search)
+            .map_err(|err| anyhow!("Could not parse query string {:#?}", err))?;
+        let config = StartupConfig::parse(|name| params.get(name));
+        if config.leaderboard.is_some() {
+            log::warn!("The leaderboard can only be set by the page's config");
+        }
+        if config.analytics.is_some() {
+            log::warn!("Analytics can only be set by the page's config");
+        }
+        Ok(StartupConfig {
+            leaderboard: None,
+            analytics: None,
+            guard_navigation: true,
+            ..config
+        }
+        .with_preferences()
+        .with_orientation())
+    }
+
+    /// Reads the settings from a JavaScript object instead of the URL, with
+    /// the same names, along with the page's callbacks.
+    pub fn from_js(config: &JsValue) -> Self {
+        let mut startup = StartupConfig::parse(|name| {
+            let value = Reflect::get(config, &JsValue::from_str(name)).ok()?;
+            value
+                .as_string()
+                .or_else(|| value.as_f64().map(|number| number.to_string()))
+                .or_else(|| value.as_bool().map(|flag| flag.to_string()))
+        });
+        startup.callbacks = Callbacks::from_js(config);
+        startup.with_preferences()
+    }
+
+    /// Turns on what the player has asked their system for.
+    fn with_preferences(mut self) -> Self {
+        self.reduced_motion |=
+            matches_media("(prefers-reduced-motion: reduce)").unwrap_or_else(|err| {
+                log::error!("Could not read the reduced motion preference {:#?}", err);
+                false
+            });
+        self.high_contrast |= matches_media("(prefers-contrast: more)").unwrap_or_else(|err| {
+            log::error!("Could not read the contrast preference {:#?}", err);
+            false
+        });
+        self
+    }
+
+    /// Lays the game's own page out tall on a portrait screen, unless a
+    /// resolution was asked for. Two players can't share a screen that
+    /// narrow, so they are asked to turn it instead.
+    fn with_orientation(mut self) -> Self {
+        if self.resolution.is_some() || self.mode == Mode::TwoPlayer {
+            return self;
+        }
+        let portrait = matches_media("(orientation: portrait)").unwrap_or_else(|err| {
+            log::error!("Could not read the screen orientation {:#?}", err);
+            false
+        });
+        if portrait {
+            self.resolution = Some(PORTRAIT);
+        }
+        self
+    }
+
+    fn parse(param: impl Fn(&str) -> Option<String>) -> Self {
+        let mode = match param("mode").as_deref() {
+            Some("2p") | Some("two_player") => Mode::TwoPlayer,
+            Some("autopilot") => Mode::Autopilot,
+            Some("preview") => Mode::Preview(
+                param("segment")
+                    .and_then(|index| index.parse().ok())
+                    .unwrap_or_default(),
+            ),
+            _ => Mode::Solo,
+        };
+        let debug = match mode {
+            Mode::Preview(_) => param("debug").or_else(|| Some("boxes".to_string())),
+            _ => param("debug"),
+        };
+        StartupConfig {
+            seed: param("seed").and_then(|seed| seed.parse().ok()),
+            debug,
+            mode,
+            character: param("character").filter(|character| !character.is_empty()),
+            mute: setting(param("mute")),
+            reduced_motion: flag(param("reduced_motion")),
+            high_contrast: flag(param("high_contrast")),
+            hold_to_slide: param("slide").map(|slide| slide == "hold"),
+            mirrored: setting(param("mirror")),
+            asset_base: param("asset_base").filter(|base| !base.is_empty()),
+            ui: param("ui").filter(|ui| !ui.is_empty()),
+            leaderboard: param("leaderboard").filter(|url| !url.is_empty()),
+            analytics: param("analytics").filter(|url| !url.is_empty()),
+            cheats: flag(param("cheats")),
+            resolution: resolution(param("resolution")),
+            race: param("race").filter(|url| !url.is_empty()),
+            host: flag(param("host")),
+            replay: param("replay").filter(|replay| !replay.is_empty()),
+            dirty_rects: param("render").as_deref() == Some("dirty"),
+            guard_navigation: flag(param("guard_navigation")),
+            callbacks: Callbacks::default(),
+        }
+    }
+}
+
+/// 16:9 at the game's usual height.
+pub const WIDESCREEN: (i16, i16) = (1067, 600);
+/// 9:16, as wide as the game's ground is usually long.
+pub const PORTRAIT: (i16, i16) = (450, 800);
+
+/// `wide` for [`WIDESCREEN`], `portrait` for [`PORTRAIT`], or a width and
+/// height like `800x600`.
+fn resolution(value: Option<String>) -> Option<(i16, i16)> {
+    let value = value?;
+    match value.as_str() {
+        "wide" => return Some(WIDESCREEN),
+        "portrait" => return Some(PORTRAIT),
+        _ => {}
+    }
+    let (width, height) = value.split_once('x')?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// A parameter that is on when present, unless it is set to `0` or `false`.
+fn flag(value: Option<String>) -> bool {
+    setting(value).unwrap_or(false)
+}
+
+/// Like [`flag`], but `None` when the parameter is left out.
+fn setting(value: Option<String>) -> Option<bool> {
+    value.map(|value| value != "0" && value != "false")
+}
+
+pub fn matches_media(query: &str) -> Result<bool> {
+    Ok(window()?
+        .match_media(query)
+        .map_err(|err| anyhow!("Could not match media query {:#?}", err))?
+        .is_some_and(|query| query.matches()))
+}
+
+const ANNOUNCER_ID: &str = "announcer";
+
+/// Reads `message` out to screen readers through a polite live region, which
+/// is created hidden the first time something is announced.
+pub fn announce(message: &str) -> Result<()> {
+    let announcer = match document()?.get_element_by_id(ANNOUNCER_ID) {
+        Some(announcer) => announcer,
+        None => create_announcer()?,
+    };
+    // Clearing first makes the region announce a repeated message again.
+    announcer.set_text_content(None);
+    announcer.set_text_content(Some(message));
+    Ok(())
+}
+
+/// Has the page show its prompt to turn the device over the game while the
+/// screen is portrait, for a game that can't be played that way round.
+pub fn require_landscape() -> Result<()> {
+    document()?
+        .body()
+        .ok_or_else(|| anyhow!("No body found"))?
+        .set_attribute("data-landscape-only", "")
+        .map_err(|err| anyhow!("Could not ask for landscape {:#?}", err))
+}
+
+fn create_announcer() -> Result<Element> {
+    let document = document()?;
+    let announcer = document
+        .create_element("div")
+        .map_err(|err| anyhow!("Could not create announcer {:#?}", err))?;
+    announcer.set_id(ANNOUNCER_ID);
+    [
+        ("role", "status"),
+        ("aria-live", "polite"),
+        ("class", "visually-hidden"),
+    ]
+    .into_iter()
+    .try_for_each(|(name, value)| announcer.set_attribute(name, value))
+    .map_err(|err| anyhow!("Could not set announcer attributes {:#?}", err))?;
+    document
+        .body()
+        .ok_or_else(|| anyhow!("No body found"))?
+        .append_child(&announcer)
+        .map_err(|err| anyhow!("Could not add announcer {:#?}", err))?;
+    Ok(announcer)
+}
+
+/// Encodes what is drawn on `canvas` as a PNG.
+pub async fn canvas_to_blob(canvas: &HtmlCanvasElement) -> Result<Blob> {
+    let (sender, receiver) = oneshot::channel::<Option<Blob>>();
+    let callback = closure_once(move |blob: Option<Blob>| {
+        let _ = sender.send(blob);
+    });
+    canvas
+        .to_blob_with_type(callback.as_ref().unchecked_ref(), "image/png")
+        .map_err(|err| anyhow!("Could not encode the canvas {:#?}", err))?;
+    receiver
+        .await
+        .map_err(|err| anyhow!("The canvas was never encoded {:#?}", err))?
+        .ok_or_else(|| anyhow!("The canvas could not be encoded as a PNG"))
+}
+
+/// Saves `blob` to the player's downloads as `file_name`.
+pub fn download(blob: &Blob, file_name: &str) -> Result<()> {
+    let url = Url::create_object_url_with_blob(blob)
+        .map_err(|err| anyhow!("Could not create an object URL {:#?}", err))?;
+    let link = document()?
+        .create_element("a")
+        .map_err(|err| anyhow!("Could not create a download link {:#?}", err))?
+        .dyn_into::<HtmlAnchorElement>()
+        .map_err(|err| anyhow!("Could not cast into HtmlAnchorElement {:#?}", err))?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    Url::revoke_object_url(&url).map_err(|err| anyhow!("Could not revoke {} {:#?}", url, err))
+}
+
+/// Offers `blob` to the device's share sheet as `file_name`. Returns false
+/// without sharing when the browser can't share files of its type.
+pub async fn share_file(blob: &Blob, file_name: &str) -> Result<bool> {
+    let options = FilePropertyBag::new();
+    options.set_type(&blob.type_());
+    let file = File::new_with_blob_sequence_and_options(&Array::of1(blob), file_name, &options)
+        .map_err(|err| anyhow!("Could not create {} {:#?}", file_name, err))?;
+    let data = Object::new();
+    Reflect::set(&data, &JsValue::from_str("files"), &Array::of1(&file))
+        .map_err(|err| anyhow!("Could not set the files to share {:#?}", err))?;
+
+    // The Web Share API is still unstable in web-sys, so it is looked up by name.
+    let navigator = window()?.navigator();
+    let Ok(can_share) = Reflect::get(&navigator, &JsValue::from_str("canShare"))
+        .and_then(|can_share| can_share.dyn_into::<Function>().map_err(JsValue::from))
+    else {
+        return Ok(false);
+    };
+    if !can_share
+        .call1(&navigator, &data)
+        .map_err(|err| anyhow!("Could not check whether files can be shared {:#?}", err))?
+        .is_truthy()
+    {
+        return Ok(false);
+    }
+    let share = Reflect::get(&navigator, &JsValue::from_str("share"))
+        .and_then(|share| share.dyn_into::<Function>().map_err(JsValue::from))
+        .map_err(|err| anyhow!("Could not find navigator.share {:#?}", err))?;
+    let promise = share
+        .call1(&navigator, &data)
+        .and_then(|promise| promise.dyn_into::<Promise>().map_err(JsValue::from))
+        .map_err(|err| anyhow!("Could not share {} {:#?}", file_name, err))?;
+    JsFuture::from(promise)
+        .await
+        .map_err(|err| anyhow!("Sharing {} failed {:#?}", file_name, err))?;
+    Ok(true)
+}
+
+pub fn btoa(data: &str) -> Result<String> {
+    window()?
+        .btoa(data)
+        .map_err(|err| anyhow!("Could not encode base64 {:#?}", err))
+}
+
+pub fn atob(data: &str) -> Result<String> {
+    window()?
+        .atob(data)
+        .map_err(|err| anyhow!("Could not decode base64 {:#?}", err))
+}
+
+/// What can take focus on an overlay, in the order Tab visits it.
+const FOCUSABLE: &str = "button, input, select, textarea";
+
+/// Where one game is on the page: the canvas it draws on, and the element
+/// its panels are drawn in over the canvas. Each game on a page has its own,
+/// so they never draw panels over, or take screenshots of, each other.
+/// Copies share the canvas, so moving one moves them all.
+#[derive(Clone)]
+pub struct Screen {
+    canvas: Rc<RefCell<HtmlCanvasElement>>,
+    ui: HtmlElement,
+}
+
+impl Screen {
+    /// The game on `canvas`, with its panels in the element with the id `ui`.
+    pub fn new(canvas: HtmlCanvasElement, ui: &str) -> Result<Self> {
+        Ok(Screen {
+            canvas: Rc::new(RefCell::new(canvas)),
+            ui: find_html_element_by_id(ui)?,
+        })
+    }
+
+    pub fn canvas(&self) -> HtmlCanvasElement {
+        self.canvas.borrow().clone()
+    }
+
+    /// Moves the game onto `canvas`. Its panels stay where they are.
+    pub fn set_canvas(&self, canvas: HtmlCanvasElement) {
+        *self.canvas.borrow_mut() = canvas;
+    }
+
+    /// Draws `html` over the canvas. The first thing on it that can take
+    /// focus does, and the up and down arrows move between them, so overlays
+    /// work from the keyboard as well as the mouse.
+    pub fn draw_ui(&self, html: &str) -> Result<()> {
+        self.ui
+            .insert_adjacent_html("afterBegin", html)
+            .map_err(|err| anyhow!("Could not insert html {:#?}", err))?;
+        let Some(overlay) = self.ui.first_element_child() else {
+            return Ok(());
+        };
+        let keys = navigate_with_arrows(&overlay)?;
+        OVERLAY_KEYS.with(|overlays| overlays.borrow_mut().push((overlay.clone(), keys)));
+        if let Some(first) = overlay
+            .query_selector(FOCUSABLE)
+            .map_err(|err| anyhow!("Could not find {} {:#?}", FOCUSABLE, err))?
+        {
+            focus(&first)?;
+        }
         Ok(())
     }
+
+    /// Removes the overlay drawn last, so one drawn over another closes
+    /// before it, and gives the focus back to the canvas.
+    pub fn hide_ui(&self) -> Result<()> {
+        let shown = OVERLAY_KEYS.with(|overlays| {
+            let mut overlays = overlays.borrow_mut();
+            overlays.retain(|(overlay, _keys)| overlay.is_connected());
+            overlays
+                .iter()
+                .rposition(|(overlay, _keys)| self.ui.contains(Some(overlay)))
+                .map(|index| overlays.remove(index))
+        });
+        let Some((overlay, _keys)) = shown else {
+            return Ok(());
+        };
+        overlay.remove();
+        self.canvas()
+            .focus()
+            .map_err(|err| anyhow!("Could not set focus to canvas! {:#?}", err))
+    }
+
+    /// Shows the UI `scale` times its size, to stay over a canvas shown at
+    /// that scale.
+    pub fn scale_ui(&self, scale: f64) -> Result<()> {
+        let style = self.ui.style();
+        style
+            .set_property("transform-origin", "0 0")
+            .and_then(|_| style.set_property("transform", &format!("scale({})", scale)))
+            .map_err(|err| anyhow!("Could not scale the UI {:#?}", err))
+    }
+
+    /// The element with the id `id` on this game's panels.
+    pub fn find(&self, id: &str) -> Result<HtmlElement> {
+        self.ui
+            .query_selector(&format!("#{}", id))
+            .map_err(|err| anyhow!("Invalid id {} {:#?}", id, err))?
+            .ok_or_else(|| anyhow!("Element with id {} not found", id))?
+            .dyn_into::<HtmlElement>()
+            .map_err(|err| anyhow!("Could not cast into HtmlElement {:#?}", err))
+    }
+
+    pub fn find_input(&self, id: &str) -> Result<HtmlInputElement> {
+        self.find(id).and_then(|element| {
+            element
+                .dyn_into::<HtmlInputElement>()
+                .map_err(|err| anyhow!("Could not cast into HtmlInputElement {:#?}", err))
+        })
+    }
 }
 
-fn find_ui() -> Result<Element> {
-    document().and_then(|doc| {
-        doc.get_element_by_id("ui")
-            .ok_or_else(|| anyhow!("UI element not found"))
+fn navigate_with_arrows(overlay: &Element) -> Result<EventListener<KeyboardEvent>> {
+    let focusable = overlay.clone();
+    EventListener::listen(overlay, "keydown", move |evt: KeyboardEvent| {
+        let step = match evt.code().as_str() {
+            "ArrowUp" => -1,
+            "ArrowDown" => 1,
+            _ => return,
+        };
+        // Kept from the game, where the arrows move the boy.
+        evt.prevent_default();
+        evt.stop_propagation();
+        if let Err(err) = focus_step(&focusable, step) {
+            log::error!("Could not move the focus {:#?}", err);
+        }
     })
 }
 
+/// Moves the focus `step` places through `overlay`, wrapping around.
+fn focus_step(overlay: &Element, step: i32) -> Result<()> {
+    let elements = overlay
+        .query_selector_all(FOCUSABLE)
+        .map_err(|err| anyhow!("Could not find {} {:#?}", FOCUSABLE, err))?;
+    let count = elements.length() as i32;
+    if count == 0 {
+        return Ok(());
+    }
+    let active: Option<Node> = document()?.active_element().map(Into::into);
+    let current = (0..count).find(|&index| elements.item(index as u32) == active);
+    let next = match current {
+        Some(current) => (current + step).rem_euclid(count),
+        None if step > 0 => 0,
+        None => count - 1,
+    };
+    match elements
+        .item(next as u32)
+        .and_then(|node| node.dyn_into::<Element>().ok())
+    {
+        Some(element) => focus(&element),
+        None => Ok(()),
+    }
+}
+
+fn focus(element: &Element) -> Result<()> {
+    element
+        .dyn_ref::<HtmlElement>()
+        .ok_or_else(|| anyhow!("Could not focus {:#?}", element))?
+        .focus()
+        .map_err(|err| anyhow!("Could not focus {:#?}", err))
+}
+
 pub fn find_html_element_by_id(id: &str) -> Result<HtmlElement> {
     document()
         .and_then(|doc| {
@@ -165,6 +1138,89 @@ mod tests {
     use wasm_bindgen_test::wasm_bindgen_test;
     wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
+    #[test]
+    fn parses_startup_config() {
+        let params = [
+            ("seed", "42"),
+            ("debug", "fps"),
+            ("mode", "2p"),
+            ("mute", ""),
+            ("reduced_motion", "false"),
+            ("slide", "hold"),
+            ("mirror", "1"),
+            ("resolution", "wide"),
+            ("race", "wss://race.example"),
+            ("host", ""),
+            ("render", "dirty"),
+        ];
+        let config = StartupConfig::parse(|name| {
+            params
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        });
+        assert_eq!(
+            config,
+            StartupConfig {
+                seed: Some(42),
+                debug: Some("fps".to_string()),
+                mode: Mode::TwoPlayer,
+                character: None,
+                mute: Some(true),
+                reduced_motion: false,
+                high_contrast: false,
+                hold_to_slide: Some(true),
+                mirrored: Some(true),
+                asset_base: None,
+                ui: None,
+                leaderboard: None,
+                analytics: None,
+                cheats: false,
+                resolution: Some(WIDESCREEN),
+                race: Some("wss://race.example".to_string()),
+                host: true,
+                replay: None,
+                dirty_rects: true,
+                guard_navigation: false,
+                callbacks: Callbacks::default(),
+            }
+        );
+        assert_eq!(resolution(Some("800x450".to_string())), Some((800, 450)));
+        assert_eq!(resolution(Some("portrait".to_string())), Some(PORTRAIT));
+        assert_eq!(resolution(Some("0x450".to_string())), None);
+        assert_eq!(resolution(Some("huge".to_string())), None);
+        assert_eq!(StartupConfig::parse(|_| None), StartupConfig::default());
+        let unmirrored = StartupConfig::parse(|name| (name == "mirror").then(|| "0".to_string()));
+        assert_eq!(unmirrored.mirrored, Some(false));
+
+        let preview = StartupConfig::parse(|name| match name {
+            "mode" => Some("preview".to_string()),
+            "segment" => Some("5".to_string()),
+            _ => None,
+        });
+        assert_eq!(preview.mode, Mode::Preview(5));
+        assert_eq!(preview.debug.as_deref(), Some("boxes"));
+    }
+
+    #[test]
+    fn hex_pads_every_byte() {
+        assert_eq!(hex(&[0, 10, 255]), "000aff");
+    }
+
+    #[test]
+    fn only_relative_asset_paths_go_under_the_base() {
+        assert_eq!(
+            join_asset_url("https://cdn.example/dog/", "rhb.json"),
+            "https://cdn.example/dog/rhb.json"
+        );
+        assert_eq!(join_asset_url("assets", "rhb.json"), "assets/rhb.json");
+        assert_eq!(join_asset_url("assets", "/rhb.json"), "/rhb.json");
+        assert_eq!(
+            join_asset_url("assets", "https://other.example/rhb.png"),
+            "https://other.example/rhb.png"
+        );
+    }
+
     #[wasm_bindgen_test]
     async fn test_error_loading_json() {
         let json = fetch_json("not_there.json").await;
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use futures::channel::{
+    mpsc::{unbounded, UnboundedReceiver},
+    oneshot::channel,
+};
+use wasm_bindgen::{prelude::Closure, JsCast};
+use web_sys::{
+    js_sys::Array, Blob, BlobEvent, BlobPropertyBag, HtmlCanvasElement, MediaRecorder,
+    MediaRecorderOptions,
+};
+
+use super::{closure_once, closure_wrap};
+
+/// The video formats to record in, best first. Safari only records MP4.
+const VIDEO_TYPES: [&str; 3] = ["video/webm;codecs=vp9", "video/webm", "video/mp4"];
+
+/// Records everything drawn on a canvas as a video, until it is stopped.
+pub struct CanvasRecorder {
+    recorder: MediaRecorder,
+    chunks: UnboundedReceiver<Blob>,
+    _on_data: Closure<dyn FnMut(BlobEvent)>,
+}
+
+impl CanvasRecorder {
+    pub fn start(canvas: &HtmlCanvasElement) -> Result<Self> {
+        let video_type = VIDEO_TYPES
+            .into_iter()
+            .find(|video_type| MediaRecorder::is_type_supported(video_type))
+            .ok_or_else(|| anyhow!("This browser can't record video"))?;
+        let stream = canvas
+            .capture_stream()
+            .map_err(|err| anyhow!("Could not capture the canvas {:#?}", err))?;
+        let options = MediaRecorderOptions::new();
+        options.set_mime_type(video_type);
+        let recorder =
+            MediaRecorder::new_with_media_stream_and_media_recorder_options(&stream, &options)
+                .map_err(|err| anyhow!("Could not create a MediaRecorder {:#?}", err))?;
+
+        let (chunk_tx, chunks) = unbounded();
+        let on_data = closure_wrap(Box::new(move |event: BlobEvent| {
+            if let Some(chunk) = event.data() {
+                let _ = chunk_tx.unbounded_send(chunk);
+            }
+        }) as Box<dyn FnMut(BlobEvent)>);
+        recorder.set_ondataavailable(Some(on_data.as_ref().unchecked_ref()));
+        recorder
+            .start()
+            .map_err(|err| anyhow!("Could not start recording {:#?}", err))?;
+        Ok(CanvasRecorder {
+            recorder,
+            chunks,
+            _on_data: on_data,
+        })
+    }
+
+    /// Stops recording and waits for the video, in whichever format the
+    /// browser recorded it.
+    pub async fn stop(mut self) -> Result<Blob> {
+        let (stop_tx, stop_rx) = channel::<()>();
+        let on_stop = closure_once(move || {
+            let _ = stop_tx.send(());
+        });
+        self.recorder
+            .set_onstop(Some(on_stop.as_ref().unchecked_ref()));
+        self.recorder
+            .stop()
+            .map_err(|err| anyhow!("Could not stop recording {:#?}", err))?;
+        stop_rx
+            .await
+            .map_err(|err| anyhow!("Recording never stopped {:#?}", err))?;
+
+        let chunks = Array::new();
+        while let Ok(Some(chunk)) = self.chunks.try_next() {
+            chunks.push(&chunk);
+        }
+        let options = BlobPropertyBag::new();
+        options.set_type(&self.recorder.mime_type());
+        Blob::new_with_blob_sequence_and_options(&chunks, &options)
+            .map_err(|err| anyhow!("Could not put the recording together {:#?}", err))
+    }
+}
@@ -0,0 +1,128 @@
+use std::{rc::Rc, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use futures::channel::oneshot::channel;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode, Storage};
+
+use super::{closure_once, local_storage, window};
+
+const DATABASE: &str = "walk_the_dog";
+const VERSION: u32 = 1;
+const OBJECT_STORE: &str = "data";
+
+/// Somewhere to keep data too big for local storage's quota, like replays.
+/// Backed by IndexedDB, or by local storage where IndexedDB is unavailable,
+/// as in some private browsing modes.
+pub enum Store {
+    IndexedDb(IdbDatabase),
+    LocalStorage(Storage),
+}
+
+impl Store {
+    pub async fn open() -> Result<Self> {
+        match open_database().await {
+            Ok(database) => Ok(Store::IndexedDb(database)),
+            Err(err) => {
+                log::warn!("IndexedDB is unavailable, using local storage {:#?}", err);
+                Ok(Store::LocalStorage(local_storage()?))
+            }
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<String>> {
+        match self {
+            Store::IndexedDb(database) => {
+                let request = object_store(database, IdbTransactionMode::Readonly)?
+                    .get(&JsValue::from_str(key))
+                    .map_err(|err| anyhow!("Could not read {} {:#?}", key, err))?;
+                Ok(finish(&request).await?.as_string())
+            }
+            Store::LocalStorage(storage) => storage
+                .get_item(key)
+                .map_err(|err| anyhow!("Could not read {} {:#?}", key, err)),
+        }
+    }
+
+    pub async fn set(&self, key: &str, value: &str) -> Result<()> {
+        match self {
+            Store::IndexedDb(database) => {
+                let request = object_store(database, IdbTransactionMode::Readwrite)?
+                    .put_with_key(&JsValue::from_str(value), &JsValue::from_str(key))
+                    .map_err(|err| anyhow!("Could not save {} {:#?}", key, err))?;
+                finish(&request).await.map(|_value| ())
+            }
+            Store::LocalStorage(storage) => storage
+                .set_item(key, value)
+                .map_err(|err| anyhow!("Could not save {} {:#?}", key, err)),
+        }
+    }
+}
+
+async fn open_database() -> Result<IdbDatabase> {
+    let request = window()?
+        .indexed_db()
+        .map_err(|err| anyhow!("Could not access IndexedDB {:#?}", err))?
+        .ok_or_else(|| anyhow!("No IndexedDB found"))?
+        .open_with_u32(DATABASE, VERSION)
+        .map_err(|err| anyhow!("Could not open {} {:#?}", DATABASE, err))?;
+
+    // Only runs when the database is new, which is the only time the object
+    // store needs making.
+    let upgrading = request.clone();
+    let on_upgrade = closure_once(move || {
+        let created = upgrading
+            .result()
+            .and_then(|database| database.dyn_into::<IdbDatabase>().map_err(JsValue::from))
+            .and_then(|database| database.create_object_store(OBJECT_STORE));
+        if let Err(err) = created {
+            log::error!("Could not create the {} store {:#?}", OBJECT_STORE, err);
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+
+    finish(&request)
+        .await?
+        .dyn_into::<IdbDatabase>()
+        .map_err(|err| anyhow!("Could not convert into IdbDatabase {:#?}", err))
+}
+
+fn object_store(database: &IdbDatabase, mode: IdbTransactionMode) -> Result<IdbObjectStore> {
+    database
+        .transaction_with_str_and_mode(OBJECT_STORE, mode)
+        .and_then(|transaction| transaction.object_store(OBJECT_STORE))
+        .map_err(|err| anyhow!("Could not open the {} store {:#?}", OBJECT_STORE, err))
+}
+
+/// Waits for an IndexedDB request to succeed or fail, with its result.
+async fn finish(request: &IdbRequest) -> Result<JsValue> {
+    let (result_tx, result_rx) = channel::<Result<JsValue>>();
+    let success_tx = Rc::new(Mutex::new(Some(result_tx)));
+    let error_tx = Rc::clone(&success_tx);
+
+    let succeeded = request.clone();
+    let on_success = closure_once(move || {
+        if let Some(tx) = success_tx.lock().ok().and_then(|mut opt| opt.take()) {
+            let _ = tx.send(
+                succeeded
+                    .result()
+                    .map_err(|err| anyhow!("Could not get the result {:#?}", err)),
+            );
+        }
+    });
+    let failed = request.clone();
+    let on_error = closure_once(move || {
+        if let Some(tx) = error_tx.lock().ok().and_then(|mut opt| opt.take()) {
+            let _ = tx.send(Err(anyhow!(
+                "IndexedDB request failed {:#?}",
+                failed.error()
+            )));
+        }
+    });
+    request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+    request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+    result_rx
+        .await
+        .map_err(|err| anyhow!("IndexedDB request never finished {:#?}", err))?
+}
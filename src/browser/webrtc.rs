@@ -0,0 +1,243 @@
+use std::{rc::Rc, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use futures::{
+    channel::{
+        mpsc::{unbounded, UnboundedReceiver},
+        oneshot::channel,
+    },
+    StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    js_sys::{Reflect, JSON},
+    MessageEvent, RtcDataChannel, RtcDataChannelEvent, RtcDataChannelState, RtcIceCandidateInit,
+    RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType, RtcSessionDescriptionInit,
+};
+
+use super::{
+    closure_once, closure_wrap, forget, spawn_local,
+    websocket::{WebSocketConnection, WebSocketSender},
+};
+
+const CHANNEL_LABEL: &str = "walk-the-dog";
+
+/// Messages the two peers relay through the signaling server until the data
+/// channel is open.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Signal {
+    Offer {
+        sdp: String,
+    },
+    Answer {
+        sdp: String,
+    },
+    Candidate {
+        candidate: String,
+        sdp_mid: Option<String>,
+        sdp_m_line_index: Option<u16>,
+    },
+}
+
+/// An open peer-to-peer data channel along with the text messages it receives.
+pub struct DataChannel {
+    channel: RtcDataChannel,
+    messages: UnboundedReceiver<String>,
+}
+
+impl DataChannel {
+    pub fn send(&self, message: &str) -> Result<()> {
+        self.channel
+            .send_with_str(message)
+            .map_err(|err| anyhow!("Could not send on data channel {:#?}", err))
+    }
+
+    pub async fn receive(&mut self) -> Option<String> {
+        self.messages.next().await
+    }
+
+    pub fn try_receive(&mut self) -> Option<String> {
+        self.messages.try_next().ok().flatten()
+    }
+
+    pub fn close(&self) {
+        self.channel.close();
+    }
+}
+
+/// Negotiates a data channel with the other peer in the signaling server's
+/// room. Exactly one of the two peers must be the initiator.
+pub async fn connect(signaling: WebSocketConnection, initiator: bool) -> Result<DataChannel> {
+    let peer = RtcPeerConnection::new()
+        .map_err(|err| anyhow!("Could not create peer connection {:#?}", err))?;
+    let signals = signaling.sender();
+
+    let candidate_signals = signals.clone();
+    let on_ice_candidate = closure_wrap(Box::new(move |event: RtcPeerConnectionIceEvent| {
+        if let Some(candidate) = event.candidate() {
+            let signal = Signal::Candidate {
+                candidate: candidate.candidate(),
+                sdp_mid: candidate.sdp_mid(),
+                sdp_m_line_index: candidate.sdp_m_line_index(),
+            };
+            if let Err(err) = send_signal(&candidate_signals, &signal) {
+                log::error!("Could not send ICE candidate {:#?}", err);
+            }
+        }
+    }) as Box<dyn FnMut(RtcPeerConnectionIceEvent)>);
+    peer.set_onicecandidate(Some(on_ice_candidate.as_ref().unchecked_ref()));
+    forget(on_ice_candidate);
+
+    let (channel_tx, channel_rx) = channel::<RtcDataChannel>();
+    if initiator {
+        let _ = channel_tx.send(peer.create_data_channel(CHANNEL_LABEL));
+        let offer = JsFuture::from(peer.create_offer())
+            .await
+            .map_err(|err| anyhow!("Could not create offer {:#?}", err))?;
+        let sdp = description_sdp(&offer)?;
+        set_local_description(&peer, RtcSdpType::Offer, &sdp).await?;
+        send_signal(&signals, &Signal::Offer { sdp })?;
+    } else {
+        let channel_tx = Rc::new(Mutex::new(Some(channel_tx)));
+        let on_data_channel = closure_wrap(Box::new(move |event: RtcDataChannelEvent| {
+            if let Some(tx) = channel_tx.lock().ok().and_then(|mut opt| opt.take()) {
+                let _ = tx.send(event.channel());
+            }
+        }) as Box<dyn FnMut(RtcDataChannelEvent)>);
+        peer.set_ondatachannel(Some(on_data_channel.as_ref().unchecked_ref()));
+        forget(on_data_channel);
+    }
+
+    spawn_local(handle_signals(peer, signaling, signals));
+
+    let channel = channel_rx.await.map_err(|err| {
+        anyhow!(
+            "Peer connection closed before a data channel opened {:#?}",
+            err
+        )
+    })?;
+    open(channel).await
+}
+
+async fn handle_signals(
+    peer: RtcPeerConnection,
+    mut signaling: WebSocketConnection,
+    signals: WebSocketSender,
+) {
+    while let Some(text) = signaling.receive().await {
+        if let Err(err) = handle_signal(&peer, &signals, &text).await {
+            log::error!("Could not handle signal {:#?}", err);
+        }
+    }
+}
+
+async fn handle_signal(
+    peer: &RtcPeerConnection,
+    signals: &WebSocketSender,
+    text: &str,
+) -> Result<()> {
+    let value = JSON::parse(text).map_err(|err| anyhow!("Could not parse signal {:#?}", err))?;
+    let signal: Signal = serde_wasm_bindgen::from_value(value)
+        .map_err(|err| anyhow!("Could not convert signal {:#?}", err))?;
+    match signal {
+        Signal::Offer { sdp } => {
+            set_remote_description(peer, RtcSdpType::Offer, &sdp).await?;
+            let answer = JsFuture::from(peer.create_answer())
+                .await
+                .map_err(|err| anyhow!("Could not create answer {:#?}", err))?;
+            let sdp = description_sdp(&answer)?;
+            set_local_description(peer, RtcSdpType::Answer, &sdp).await?;
+            send_signal(signals, &Signal::Answer { sdp })
+        }
+        Signal::Answer { sdp } => set_remote_description(peer, RtcSdpType::Answer, &sdp).await,
+        Signal::Candidate {
+            candidate,
+            sdp_mid,
+            sdp_m_line_index,
+        } => {
+            let init = RtcIceCandidateInit::new(&candidate);
+            init.set_sdp_mid(sdp_mid.as_deref());
+            init.set_sdp_m_line_index(sdp_m_line_index);
+            JsFuture::from(peer.add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(&init)))
+                .await
+                .map(|_| ())
+                .map_err(|err| anyhow!("Could not add ICE candidate {:#?}", err))
+        }
+    }
+}
+
+async fn open(data_channel: RtcDataChannel) -> Result<DataChannel> {
+    let (message_tx, messages) = unbounded();
+    let on_message = closure_wrap(Box::new(move |event: MessageEvent| {
+        if let Some(text) = event.data().as_string() {
+            let _ = message_tx.unbounded_send(text);
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    data_channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    forget(on_message);
+
+    if data_channel.ready_state() != RtcDataChannelState::Open {
+        let (open_tx, open_rx) = channel::<()>();
+        let on_open = closure_once(move || {
+            let _ = open_tx.send(());
+        });
+        data_channel.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        open_rx
+            .await
+            .map_err(|err| anyhow!("Data channel closed before opening {:#?}", err))?;
+        data_channel.set_onopen(None);
+    }
+
+    Ok(DataChannel {
+        channel: data_channel,
+        messages,
+    })
+}
+
+fn send_signal(signals: &WebSocketSender, signal: &Signal) -> Result<()> {
+    let value = signal
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|err| anyhow!("Could not serialize signal {:#?}", err))?;
+    let json: String = JSON::stringify(&value)
+        .map_err(|err| anyhow!("Could not stringify signal {:#?}", err))?
+        .into();
+    signals.send(&json)
+}
+
+fn description_sdp(description: &JsValue) -> Result<String> {
+    Reflect::get(description, &JsValue::from_str("sdp"))
+        .ok()
+        .and_then(|sdp| sdp.as_string())
+        .ok_or_else(|| anyhow!("Session description has no sdp {:#?}", description))
+}
+
+async fn set_local_description(
+    peer: &RtcPeerConnection,
+    kind: RtcSdpType,
+    sdp: &str,
+) -> Result<()> {
+    JsFuture::from(peer.set_local_description(&session_description(kind, sdp)))
+        .await
+        .map(|_| ())
+        .map_err(|err| anyhow!("Could not set local description {:#?}", err))
+}
+
+async fn set_remote_description(
+    peer: &RtcPeerConnection,
+    kind: RtcSdpType,
+    sdp: &str,
+) -> Result<()> {
+    JsFuture::from(peer.set_remote_description(&session_description(kind, sdp)))
+        .await
+        .map(|_| ())
+        .map_err(|err| anyhow!("Could not set remote description {:#?}", err))
+}
+
+fn session_description(kind: RtcSdpType, sdp: &str) -> RtcSessionDescriptionInit {
+    let description = RtcSessionDescriptionInit::new(kind);
+    description.set_sdp(sdp);
+    description
+}
@@ -0,0 +1,110 @@
+use std::{rc::Rc, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use futures::{
+    channel::{
+        mpsc::{unbounded, UnboundedReceiver},
+        oneshot::channel,
+    },
+    StreamExt,
+};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{MessageEvent, WebSocket};
+
+use super::{closure_once, closure_wrap, forget};
+
+/// An open WebSocket along with the stream of text messages it receives. The
+/// stream ends when the socket closes.
+pub struct WebSocketConnection {
+    socket: WebSocket,
+    messages: UnboundedReceiver<String>,
+}
+
+/// A handle for sending on a connection, for callbacks that can't borrow it.
+#[derive(Clone)]
+pub struct WebSocketSender {
+    socket: WebSocket,
+}
+
+impl WebSocketSender {
+    pub fn send(&self, message: &str) -> Result<()> {
+        self.socket
+            .send_with_str(message)
+            .map_err(|err| anyhow!("Could not send message {:#?}", err))
+    }
+}
+
+pub async fn connect(url: &str) -> Result<WebSocketConnection> {
+    let socket =
+        WebSocket::new(url).map_err(|err| anyhow!("Could not create WebSocket {:#?}", err))?;
+
+    let (message_tx, messages) = unbounded();
+    let message_tx = Rc::new(Mutex::new(Some(message_tx)));
+    let close_tx = Rc::clone(&message_tx);
+    let on_message = closure_wrap(Box::new(move |event: MessageEvent| {
+        if let (Some(text), Ok(tx)) = (event.data().as_string(), message_tx.lock()) {
+            if let Some(tx) = tx.as_ref() {
+                let _ = tx.unbounded_send(text);
+            }
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    let on_close = closure_wrap(Box::new(move || {
+        if let Ok(mut tx) = close_tx.lock() {
+            tx.take();
+        }
+    }) as Box<dyn FnMut()>);
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    forget(on_message);
+    forget(on_close);
+
+    let (open_tx, open_rx) = channel::<Result<()>>();
+    let open_tx = Rc::new(Mutex::new(Some(open_tx)));
+    let error_tx = Rc::clone(&open_tx);
+    let on_open = closure_once(move || {
+        if let Some(tx) = open_tx.lock().ok().and_then(|mut opt| opt.take()) {
+            let _ = tx.send(Ok(()));
+        }
+    });
+    let on_error = closure_once(move |err: JsValue| {
+        if let Some(tx) = error_tx.lock().ok().and_then(|mut opt| opt.take()) {
+            let _ = tx.send(Err(anyhow!("Error connecting to WebSocket {:#?}", err)));
+        }
+    });
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+    let opened = open_rx.await;
+    socket.set_onopen(None);
+    socket.set_onerror(None);
+    opened.map_err(|err| anyhow!("WebSocket dropped before opening {:#?}", err))??;
+
+    Ok(WebSocketConnection { socket, messages })
+}
+
+impl WebSocketConnection {
+    pub fn sender(&self) -> WebSocketSender {
+        WebSocketSender {
+            socket: self.socket.clone(),
+        }
+    }
+
+    /// Waits for the next message, or `None` once the socket has closed.
+    pub async fn receive(&mut self) -> Option<String> {
+        self.messages.next().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_error_connecting_to_invalid_url() {
+        let connection = connect("not a url").await;
+        assert!(connection.is_err());
+    }
+}
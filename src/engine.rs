@@ -1,29 +1,127 @@
-use std::{cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc, sync::Mutex};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    ops::{Add, AddAssign, Mul, Sub},
+    rc::Rc,
+    sync::Mutex,
+};
 
+use self::dirty_rects::{DirtyRects, DrawCommand};
 use crate::{
     browser::{self, LoopClosure},
-    sound,
+    sound::{self, Cue, Looping},
 };
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use futures::channel::{
-    mpsc::{unbounded, UnboundedReceiver},
+    mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     oneshot::channel,
 };
-use serde::Deserialize;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{AudioBuffer, AudioContext, CanvasRenderingContext2d, HtmlElement, HtmlImageElement};
+use web_sys::{
+    AudioBuffer, AudioContext, CanvasRenderingContext2d, EventTarget, HtmlCanvasElement,
+    HtmlElement, HtmlImageElement,
+};
 
 const FRAME_SIZE: f32 = 1.0 / 60.0 * 1000.0;
 type SharedLoopClosure = Rc<RefCell<Option<LoopClosure>>>;
 
-#[derive(Debug, Clone, Copy, Default)]
+mod atlas;
+mod dirty_rects;
+mod font;
+mod physics;
+mod quality;
+mod time;
+
+pub use self::atlas::Atlas;
+use self::font::BitmapFont;
+pub use self::font::{FONT_IMAGE, FONT_METRICS};
+pub use self::physics::{Physics, SubPixel};
+pub use self::quality::Quality;
+use self::quality::QualityController;
+pub use self::time::Timer;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: i16,
     pub y: i16,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+impl Point {
+    /// The point `t` of the way from here to `to`, where 0 is here and 1 is `to`.
+    pub fn lerp(self, to: Point, t: f32) -> Point {
+        Point {
+            x: lerp(self.x, to.x, t),
+            y: lerp(self.y, to.y, t),
+        }
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, other: Point) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl Mul<i16> for Point {
+    type Output = Point;
+
+    fn mul(self, scale: i16) -> Point {
+        Point {
+            x: self.x * scale,
+            y: self.y * scale,
+        }
+    }
+}
+
+/// The value `t` of the way from `from` to `to`, rounded to the nearest pixel.
+pub fn lerp(from: i16, to: i16, t: f32) -> i16 {
+    (f32::from(from) + f32::from(to - from) * t).round() as i16
+}
+
+/// A position or velocity that can fall between pixels. It is only rounded
+/// to a [`Point`] for drawing and collisions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vector {
+    pub fn round(self) -> Point {
+        Point {
+            x: self.x.round() as i16,
+            y: self.y.round() as i16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct Rect {
     pub position: Point,
     pub width: i16,
@@ -59,6 +157,39 @@ impl Rect {
         self.position.x = x;
     }
 
+    pub fn center(&self) -> Point {
+        Point {
+            x: self.x() + self.width / 2,
+            y: self.y() + self.height / 2,
+        }
+    }
+
+    /// Whether `point` is inside. The right and bottom edges are outside,
+    /// like for [`Rect::intersects`].
+    pub fn contains_point(&self, point: Point) -> bool {
+        (self.x()..self.right()).contains(&point.x) && (self.y()..self.bottom()).contains(&point.y)
+    }
+
+    /// The overlap of the two rects, if they overlap at all.
+    pub fn intersection(&self, rect: &Rect) -> Option<Rect> {
+        let x = self.x().max(rect.x());
+        let y = self.y().max(rect.y());
+        let right = self.right().min(rect.right());
+        let bottom = self.bottom().min(rect.bottom());
+        (x < right && y < bottom).then(|| Rect::new_from_x_y(x, y, right - x, bottom - y))
+    }
+
+    /// Grows the rect by `amount` on every side, or shrinks it when
+    /// `amount` is negative.
+    pub fn inflate(&self, amount: i16) -> Rect {
+        Rect::new_from_x_y(
+            self.x() - amount,
+            self.y() - amount,
+            self.width + amount * 2,
+            self.height + amount * 2,
+        )
+    }
+
     pub fn intersects(&self, rect: &Rect) -> bool {
         self.x() < rect.right()
             && self.right() > rect.x()
@@ -115,30 +246,43 @@ impl SpriteSheet {
         self.sheet.frames.get(name)
     }
 
+    /// The same image cut up by a different sheet.
+    #[cfg(feature = "dev")]
+    pub fn with_sheet(&self, sheet: Sheet) -> Self {
+        Self::new(sheet, self.image.clone())
+    }
+
     pub fn draw(&self, renderer: &Renderer, source: &Rect, destination: &Rect) {
         renderer.draw_image(&self.image, source, destination);
     }
 }
 
 pub struct Image {
-    element: HtmlImageElement,
+    atlas: Rc<Atlas>,
+    frame: Rect,
     bounding_box: Rect,
 }
 
 impl Image {
-    pub fn new(element: HtmlImageElement, position: Point) -> Self {
-        let bounding_box = Rect::new(position, element.width() as i16, element.height() as i16);
-        Self {
-            element,
-            bounding_box,
-        }
+    pub fn from_atlas(atlas: Rc<Atlas>, name: &str, position: Point) -> Option<Self> {
+        let frame = atlas.frame(name)?;
+        Some(Self {
+            atlas,
+            frame,
+            bounding_box: Rect::new(position, frame.width, frame.height),
+        })
+    }
+
+    /// Draws the image at `scale` times its size, keeping its top left.
+    pub fn scaled(mut self, scale: f32) -> Self {
+        self.bounding_box.width = (f32::from(self.frame.width) * scale).round() as i16;
+        self.bounding_box.height = (f32::from(self.frame.height) * scale).round() as i16;
+        self
     }
 
     pub fn draw(&self, renderer: &Renderer) {
-        renderer.draw_entire_image(&self.element, &self.bounding_box.position);
-        if cfg!(feature = "draw_debug_info") {
-            renderer.draw_rect(&self.bounding_box);
-        }
+        self.atlas.draw(renderer, &self.frame, &self.bounding_box);
+        renderer.draw_bounding_box(&self.bounding_box);
     }
 
     pub fn bounding_box(&self) -> &Rect {
@@ -158,11 +302,160 @@ impl Image {
     }
 }
 
+/// How big the world the game is laid out in is, in world pixels, so games
+/// can fit the canvas they are given instead of assuming its size. Its
+/// bottom edge is the ground.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldBounds {
+    pub width: i16,
+    pub height: i16,
+}
+
+impl WorldBounds {
+    /// The whole of `canvas`, a world pixel to each of its pixels.
+    pub fn from_canvas(canvas: &HtmlCanvasElement) -> Self {
+        WorldBounds {
+            width: i16::try_from(canvas.width()).unwrap_or(i16::MAX),
+            height: i16::try_from(canvas.height()).unwrap_or(i16::MAX),
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new_from_x_y(0, 0, self.width, self.height)
+    }
+
+    /// How many times its size the world can be shown at to fit in a space
+    /// `width` by `height`, keeping its shape.
+    pub fn scale_to_fit(&self, width: f64, height: f64) -> f64 {
+        if self.width <= 0 || self.height <= 0 {
+            return 1.0;
+        }
+        (width / f64::from(self.width)).min(height / f64::from(self.height))
+    }
+
+    /// Sizes `canvas` to hold the world, a pixel to each world pixel. The
+    /// page's styles decide how big it shows.
+    pub fn resize(&self, canvas: &HtmlCanvasElement) {
+        canvas.set_width(self.width.max(0) as u32);
+        canvas.set_height(self.height.max(0) as u32);
+    }
+}
+
+/// Something that happened to the screen the game is on, passed on to the
+/// renderer and then the game at the start of the next frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineEvent {
+    /// The window changed size or the screen was turned, leaving this much
+    /// room in CSS pixels.
+    Resized { width: f64, height: f64 },
+}
+
+/// Shows part of the world inside a viewport of the canvas. World coordinates
+/// are moved by `offset` and anything outside the viewport is clipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Camera {
+    pub viewport: Rect,
+    pub offset: Point,
+    /// Flips the world left to right inside the viewport, after moving it.
+    pub mirrored: bool,
+}
+
+impl Camera {
+    pub fn project(&self, rect: &Rect) -> Rect {
+        let mut projected = Rect::new(rect.position + self.offset, rect.width, rect.height);
+        if self.mirrored {
+            projected.set_x(self.mirror_line() - projected.right());
+        }
+        projected
+    }
+
+    /// Twice the x of the line the viewport is flipped around.
+    fn mirror_line(&self) -> i16 {
+        self.viewport.x() + self.viewport.right()
+    }
+}
+
+/// A horizontally repeating image pre-rendered twice side by side onto an
+/// offscreen canvas, so each frame only needs to blit one scrolled slice.
+pub struct ScrollingBackground {
+    canvas: HtmlCanvasElement,
+    tile_width: i16,
+    view: Rect,
+    offset: f32,
+    scroll_speed: f32,
+}
+
+impl ScrollingBackground {
+    pub fn new(image: &HtmlImageElement, position: Point, view_width: i16) -> Result<Self> {
+        let tile_width = image.width() as i16;
+        let height = image.height() as i16;
+        let canvas = browser::create_canvas(image.width() * 2, image.height())?;
+        let context = browser::canvas_context(&canvas)?;
+        for x in [0, tile_width] {
+            context
+                .draw_image_with_html_image_element(image, x.into(), 0.0)
+                .map_err(|err| anyhow!("Could not pre-render background {:#?}", err))?;
+        }
+        Ok(ScrollingBackground {
+            canvas,
+            tile_width,
+            view: Rect::new(position, view_width.min(tile_width), height),
+            offset: 0.0,
+            scroll_speed: 1.0,
+        })
+    }
+
+    /// Scrolls the background at `scroll_speed` times the distance it is
+    /// moved, without changing how fast the world itself moves.
+    pub fn with_scroll_speed(mut self, scroll_speed: f32) -> Self {
+        self.scroll_speed = scroll_speed;
+        self
+    }
+
+    pub fn move_horizontally(&mut self, distance: i16) {
+        if self.tile_width > 0 {
+            self.offset = (self.offset - f32::from(distance) * self.scroll_speed)
+                .rem_euclid(f32::from(self.tile_width));
+        }
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        renderer.draw_canvas(
+            &self.canvas,
+            &Rect::new_from_x_y(self.offset as i16, 0, self.view.width, self.view.height),
+            &self.view,
+        );
+    }
+}
+
 #[async_trait(?Send)]
 pub trait Game {
     async fn initialize(&self) -> Result<Box<dyn Game>>;
     fn update(&mut self, keystate: &KeyState);
     fn draw(&self, renderer: &Renderer);
+    /// Lets go of what the game holds outside the page, like its audio, when
+    /// the game loop stops for good.
+    fn shutdown(&mut self) {}
+    /// How many things are in the world, for the debug overlay.
+    fn entity_count(&self) -> usize {
+        0
+    }
+    /// Tells the game which effects the device has time for.
+    fn set_quality(&mut self, _quality: Quality) {}
+    /// How big a world the game asks for, when the canvas the page made
+    /// shouldn't decide it.
+    fn resolution(&self) -> Option<WorldBounds> {
+        None
+    }
+    /// Tells the game how big the world on the canvas is, before it is
+    /// initialized.
+    fn set_bounds(&mut self, _bounds: WorldBounds) {}
+    /// Tells the game which canvas it is drawn on, before it is initialized
+    /// and again whenever it is moved to another.
+    fn set_canvas(&mut self, _canvas: HtmlCanvasElement) {}
+    /// Tells the game what happened to its screen, after the renderer has
+    /// been told.
+    fn handle_event(&mut self, _event: EngineEvent) {}
 }
 
 pub async fn load_image(source: &str) -> Result<HtmlImageElement> {
@@ -183,7 +476,7 @@ pub async fn load_image(source: &str) -> Result<HtmlImageElement> {
 
     image.set_onload(Some(success_callback.as_ref().unchecked_ref()));
     image.set_onerror(Some(error_callback.as_ref().unchecked_ref()));
-    image.set_src(source);
+    image.set_src(&browser::asset_url(source));
     complete_rx.await??;
     Ok(image)
 }
@@ -191,136 +484,918 @@ pub async fn load_image(source: &str) -> Result<HtmlImageElement> {
 pub struct GameLoop {
     last_frame: f64,
     accumulated_delta: f32,
+    frame_stats: FrameStats,
+    quality: QualityController,
 }
 
 impl GameLoop {
-    pub async fn start(game: impl Game + 'static) -> Result<()> {
-        let mut keyevent_receiver = prepare_input()?;
-        let mut game = game.initialize().await?;
+    /// Runs `game` on the page's canvas, with keys pressed anywhere on the
+    /// page.
+    pub async fn start(
+        game: impl Game + 'static,
+        debug: Option<&str>,
+        high_contrast: bool,
+        dirty_rects: bool,
+    ) -> Result<GameLoopHandle> {
+        Self::run(
+            browser::canvas()?,
+            false,
+            game,
+            debug,
+            high_contrast,
+            dirty_rects,
+        )
+        .await
+    }
+
+    /// Runs `game` on `canvas`, with keys pressed while it has the focus, so
+    /// it can share the page with other games.
+    pub async fn attach(
+        canvas: HtmlCanvasElement,
+        game: impl Game + 'static,
+        debug: Option<&str>,
+        high_contrast: bool,
+        dirty_rects: bool,
+    ) -> Result<GameLoopHandle> {
+        // Without a tab index the canvas can't take the focus, or its keys.
+        if !canvas.has_attribute("tabindex") {
+            canvas
+                .set_attribute("tabindex", "0")
+                .map_err(|err| anyhow!("Could not make the canvas focusable {:#?}", err))?;
+        }
+        Self::run(canvas, true, game, debug, high_contrast, dirty_rects).await
+    }
+
+    async fn run(
+        canvas: HtmlCanvasElement,
+        keys_from_canvas: bool,
+        mut game: impl Game + 'static,
+        debug: Option<&str>,
+        high_contrast: bool,
+        dirty_rects: bool,
+    ) -> Result<GameLoopHandle> {
+        if let Some(bounds) = game.resolution() {
+            bounds.resize(&canvas);
+        }
+        game.set_bounds(WorldBounds::from_canvas(&canvas));
+        game.set_canvas(canvas.clone());
+        let (key_sender, mut keyevent_receiver) = unbounded();
+        let keys = KeyListener::listen(key_target(&canvas, keys_from_canvas)?, key_sender.clone())?;
+        let (screen_sender, mut screen_receiver) = unbounded();
+        // The game's own page is the whole window, so the game scales to fit
+        // it. Pages embedding the game size its canvas themselves.
+        let resize_listeners = if keys_from_canvas {
+            vec![]
+        } else {
+            let send_size = move || match browser::window_size() {
+                Ok((width, height)) => {
+                    let _ = screen_sender.unbounded_send(EngineEvent::Resized { width, height });
+                }
+                Err(err) => log::error!("Could not fit the game to the window {:#?}", err),
+            };
+            send_size();
+            Vec::from(browser::on_resize(send_size)?)
+        };
+        let game = Rc::new(RefCell::new(game.initialize().await?));
+        let frame_game = Rc::clone(&game);
+        let font = BitmapFont::load()
+            .await
+            .map_err(|err| log::warn!("Falling back to system text {:#?}", err))
+            .ok();
         let mut game_loop = GameLoop {
             last_frame: browser::now()?,
             accumulated_delta: 0.0,
+            frame_stats: FrameStats::default(),
+            quality: QualityController::default(),
         };
 
-        let renderer = Renderer {
-            context: browser::context()?,
+        let mut renderer = Renderer {
+            context: browser::canvas_context(&canvas)?,
+            debug_settings: debug.map(DebugSettings::parse).unwrap_or_default(),
+            dirty_rects: None,
+            high_contrast,
+            accents: RefCell::new(vec![]),
+            font,
+            mirrored: std::cell::Cell::new(false),
+            bounding_boxes: std::cell::Cell::new(false),
         };
-
+        // Asked for dirty rects stay on whatever the quality.
+        renderer.set_dirty_rect_mode(dirty_rects);
+
+        let attachment = Rc::new(RefCell::new(Attachment {
+            running: true,
+            frame_id: None,
+            canvas: None,
+            keys_from_canvas,
+            key_sender,
+            keys: Some(keys),
+            resize_listeners,
+        }));
         let f: SharedLoopClosure = Rc::new(RefCell::new(None));
         let g = f.clone();
+        let frame_attachment = Rc::clone(&attachment);
 
         let mut keystate = KeyState::new();
         *g.borrow_mut() = Some(browser::create_raf_closure(move |pref: f64| {
+            let canvas = {
+                let mut attachment = frame_attachment.borrow_mut();
+                attachment.frame_id = None;
+                if !attachment.running {
+                    return;
+                }
+                attachment.canvas.take()
+            };
+            if let Some(canvas) = canvas {
+                match browser::canvas_context(&canvas) {
+                    Ok(context) => renderer.attach(context),
+                    Err(err) => log::error!("Could not draw on the new canvas {:#?}", err),
+                }
+                // Keys held when the loop was detached were let go somewhere
+                // it couldn't hear, and the time away isn't caught up on.
+                keystate = KeyState::new();
+                game_loop.last_frame = pref;
+                game_loop.accumulated_delta = 0.0;
+            }
+
             let frame_time = (pref - game_loop.last_frame) as f32;
 
             if game_loop.accumulated_delta + frame_time > FRAME_SIZE {
                 game_loop.accumulated_delta += frame_time;
                 game_loop.last_frame = pref;
-                process_input(&mut keystate, &mut keyevent_receiver);
+                let work_started = browser::now().unwrap_or(pref);
+                process_input(
+                    &mut keystate,
+                    &mut keyevent_receiver,
+                    &mut renderer.debug_settings,
+                );
+                while let Ok(Some(event)) = screen_receiver.try_next() {
+                    renderer.handle_event(&event);
+                    frame_game.borrow_mut().handle_event(event);
+                }
 
+                let mut updates = 0;
                 while game_loop.accumulated_delta > FRAME_SIZE {
-                    game.update(&keystate);
+                    profile("update", || frame_game.borrow_mut().update(&keystate));
+                    keystate.end_update();
                     game_loop.accumulated_delta -= FRAME_SIZE;
+                    updates += 1;
                 }
+                game_loop.frame_stats.record_frame(frame_time, updates);
+
+                profile("draw", || frame_game.borrow().draw(&renderer));
+                renderer.draw_accents();
+
+                if renderer.debug_settings().frame_rate {
+                    let census = Census {
+                        closures: browser::live_closures(),
+                        channels: live_click_channels(),
+                        entities: frame_game.borrow().entity_count(),
+                    };
+                    game_loop
+                        .frame_stats
+                        .draw(&renderer, &census, game_loop.quality.quality());
+                }
+                renderer.end_frame();
 
-                game.draw(&renderer);
-
-                if cfg!(feature = "draw_debug_info") {
-                    unsafe {
-                        draw_frame_rate(&renderer, frame_time);
-                    }
+                let work_time = (browser::now().unwrap_or(pref) - work_started) as f32;
+                if let Some(quality) = game_loop.quality.record(frame_time, work_time) {
+                    log::info!("Quality is now {:?}", quality);
+                    renderer.set_dirty_rect_mode(dirty_rects || quality.dirty_rects());
+                    frame_game.borrow_mut().set_quality(quality);
                 }
             }
 
-            browser::request_animation_frame(f.borrow().as_ref().unwrap()).unwrap();
+            frame_attachment.borrow_mut().frame_id =
+                Some(browser::request_animation_frame(f.borrow().as_ref().unwrap()).unwrap());
         }));
 
-        browser::request_animation_frame(
+        attachment.borrow_mut().frame_id = Some(browser::request_animation_frame(
             g.borrow()
                 .as_ref()
                 .ok_or_else(|| anyhow!("GameLoop: Loop is None"))?,
-        )?;
+        )?);
+        Ok(GameLoopHandle {
+            attachment,
+            frame: g,
+            game,
+        })
+    }
+}
+
+/// What a running game loop is drawing on and listening to.
+struct Attachment {
+    /// Cleared to stop the loop at its next frame.
+    running: bool,
+    /// The frame requested and not run yet, so reattaching doesn't start a
+    /// second loop alongside it, and stopping can cancel it.
+    frame_id: Option<i32>,
+    /// A canvas to move to at the next frame.
+    canvas: Option<HtmlCanvasElement>,
+    keys_from_canvas: bool,
+    key_sender: UnboundedSender<KeyPress>,
+    /// `None` once the loop has stopped for good.
+    keys: Option<KeyListener>,
+    /// Hear the window change size, on the game's own page.
+    resize_listeners: Vec<browser::EventListener<web_sys::Event>>,
+}
+
+/// Stops a running game loop and starts it again, on the same canvas or
+/// another, or shuts it down. Dropping the handle leaves the loop running.
+pub struct GameLoopHandle {
+    attachment: Rc<RefCell<Attachment>>,
+    frame: SharedLoopClosure,
+    game: Rc<RefCell<Box<dyn Game>>>,
+}
+
+impl GameLoopHandle {
+    /// Stops updating and drawing. The game keeps its state, and the canvas
+    /// keeps the last frame drawn.
+    pub fn detach(&self) {
+        self.attachment.borrow_mut().running = false;
+    }
+
+    /// Carries on from where the game was detached, drawing on `canvas`.
+    pub fn reattach(&self, canvas: HtmlCanvasElement) -> Result<()> {
+        let mut attachment = self.attachment.borrow_mut();
+        if attachment.keys.is_none() {
+            return Err(anyhow!("GameLoop: Loop has stopped"));
+        }
+        let target = key_target(&canvas, attachment.keys_from_canvas)?;
+        attachment.keys = Some(KeyListener::listen(target, attachment.key_sender.clone())?);
+        self.game.borrow_mut().set_canvas(canvas.clone());
+        attachment.canvas = Some(canvas);
+        attachment.running = true;
+        if attachment.frame_id.is_none() {
+            attachment.frame_id = Some(browser::request_animation_frame(
+                self.frame
+                    .borrow()
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("GameLoop: Loop is None"))?,
+            )?);
+        }
         Ok(())
     }
+
+    /// Stops the game loop for good: no more frames, no more key listeners,
+    /// and the game lets go of its audio, so the page can take the game off
+    /// it without leaving anything behind.
+    pub fn stop(self) {
+        {
+            let mut attachment = self.attachment.borrow_mut();
+            attachment.running = false;
+            if let Some(id) = attachment.frame_id.take() {
+                if let Err(err) = browser::cancel_animation_frame(id) {
+                    log::error!("{:#?}", err);
+                }
+            }
+            attachment.keys = None;
+            attachment.resize_listeners.clear();
+        }
+        self.game.borrow_mut().shutdown();
+        // The frame closure holds on to itself to request the next frame, so
+        // it's only dropped once it's taken out.
+        self.frame.borrow_mut().take();
+    }
+}
+
+#[cfg(feature = "profiling")]
+pub fn profile<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start_mark = format!("{}-start", name);
+    let end_mark = format!("{}-end", name);
+    if let Err(err) = browser::mark(&start_mark) {
+        log::error!("{:#?}", err);
+    }
+    let result = f();
+    if let Err(err) =
+        browser::mark(&end_mark).and_then(|_| browser::measure(name, &start_mark, &end_mark))
+    {
+        log::error!("{:#?}", err);
+    }
+    result
+}
+
+#[cfg(not(feature = "profiling"))]
+pub fn profile<T>(_name: &str, f: impl FnOnce() -> T) -> T {
+    f()
 }
 
-#[cfg(feature = "draw_debug_info")]
-unsafe fn draw_frame_rate(renderer: &Renderer, frame_time: f32) {
-    static mut FRAMES_COUNTED: i32 = 0;
-    static mut TOTAL_FRAME_TIME: f32 = 0.0;
-    static mut FRAME_RATE: i32 = 0;
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    frames_counted: u32,
+    total_frame_time: f32,
+    longest_frame_counted: f32,
+    frame_rate: u32,
+    longest_frame: f32,
+    updates_per_frame: u32,
+}
+
+impl FrameStats {
+    fn record_frame(&mut self, frame_time: f32, updates: u32) {
+        self.frames_counted += 1;
+        self.total_frame_time += frame_time;
+        self.longest_frame_counted = self.longest_frame_counted.max(frame_time);
+        self.updates_per_frame = updates;
+
+        if self.total_frame_time > 1000.0 {
+            self.frame_rate = self.frames_counted;
+            self.longest_frame = self.longest_frame_counted;
+            self.total_frame_time = 0.0;
+            self.frames_counted = 0;
+            self.longest_frame_counted = 0.0;
+        }
+    }
+
+    pub fn frame_rate(&self) -> u32 {
+        self.frame_rate
+    }
 
-    FRAMES_COUNTED += 1;
-    TOTAL_FRAME_TIME += frame_time;
+    pub fn longest_frame(&self) -> f32 {
+        self.longest_frame
+    }
 
-    if TOTAL_FRAME_TIME > 1000.0 {
-        FRAME_RATE = FRAMES_COUNTED;
-        TOTAL_FRAME_TIME = 0.0;
-        FRAMES_COUNTED = 0;
+    pub fn updates_per_frame(&self) -> u32 {
+        self.updates_per_frame
     }
 
-    let fr = FRAME_RATE;
-    if let Err(err) = renderer.draw_text(&format!("Frame Rate {}", fr), &Point { x: 400, y: 100 }) {
-        log::error!("Could not draw text {:#?}", err);
+    fn draw(&self, renderer: &Renderer, census: &Census, quality: Quality) {
+        let lines = [
+            format!("Frame Rate {}", self.frame_rate()),
+            format!("Longest {:.1}ms", self.longest_frame()),
+            format!("Updates {}", self.updates_per_frame()),
+            format!("Quality {:?}", quality),
+            format!("Closures {}", census.closures),
+            format!("Channels {}", census.channels),
+            format!("Entities {}", census.entities),
+        ];
+        for (row, line) in lines.iter().enumerate() {
+            let location = Point {
+                x: 400,
+                y: 100 + 24 * row as i16,
+            };
+            if let Err(err) = renderer.draw_text(line, &location) {
+                log::error!("Could not draw text {:#?}", err);
+            }
+        }
     }
 }
 
+/// What a session is holding onto, shown with the frame rate, so anything
+/// that keeps growing over many runs stands out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Census {
+    pub closures: usize,
+    pub channels: usize,
+    pub entities: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DebugSettings {
+    pub bounding_boxes: bool,
+    pub frame_rate: bool,
+}
+
+impl DebugSettings {
+    fn parse(value: &str) -> Self {
+        if value.is_empty() {
+            return DebugSettings {
+                bounding_boxes: true,
+                frame_rate: true,
+            };
+        }
+        let flags: Vec<&str> = value.split(',').map(str::trim).collect();
+        DebugSettings {
+            bounding_boxes: flags.contains(&"boxes"),
+            frame_rate: flags.contains(&"fps"),
+        }
+    }
+
+    fn toggle(&mut self, code: &str) {
+        match code {
+            "F8" => self.bounding_boxes = !self.bounding_boxes,
+            "F9" => self.frame_rate = !self.frame_rate,
+            _ => (),
+        }
+    }
+}
+
+/// The high contrast palette: light text on a dark outline, and hazards
+/// hatched in yellow.
+const CONTRAST_LIGHT: &str = "#FFFFFF";
+const CONTRAST_DARK: &str = "#000000";
+const ACCENT_COLOR: &str = "#FFD400";
+const ACCENT_OUTLINE_WIDTH: f64 = 8.0;
+const ACCENT_HATCH_WIDTH: f64 = 3.0;
+const ACCENT_HATCH_SPACING: f64 = 14.0;
+const TEXT_OUTLINE_WIDTH: f64 = 4.0;
+/// Bitmap glyphs can't be stroked, so in high contrast mode they are drawn
+/// dark this far around themselves, then light on top.
+const GLYPH_OUTLINE_OFFSET: i16 = 2;
+const GLYPH_DARK: &str = "brightness(0)";
+const GLYPH_LIGHT: &str = "brightness(0) invert(1)";
+
 pub struct Renderer {
     context: CanvasRenderingContext2d,
+    debug_settings: DebugSettings,
+    dirty_rects: Option<RefCell<DirtyRects>>,
+    high_contrast: bool,
+    accents: RefCell<Vec<DrawCommand>>,
+    /// Text is drawn from this when it loaded, and with the system font if not.
+    font: Option<BitmapFont>,
+    /// Whether drawing is going through a mirrored camera, so text has to be
+    /// flipped back to be read.
+    mirrored: std::cell::Cell<bool>,
+    /// Whether bounding boxes are drawn whatever the debug settings say.
+    bounding_boxes: std::cell::Cell<bool>,
 }
 
 impl Renderer {
+    /// Draws on a canvas of its own that is never shown.
+    pub fn offscreen(width: u32, height: u32) -> Result<Self> {
+        Ok(Renderer {
+            context: browser::canvas_context(&browser::create_canvas(width, height)?)?,
+            debug_settings: DebugSettings::default(),
+            dirty_rects: None,
+            high_contrast: false,
+            accents: RefCell::new(vec![]),
+            font: None,
+            mirrored: std::cell::Cell::new(false),
+            bounding_boxes: std::cell::Cell::new(false),
+        })
+    }
+
+    pub fn debug_settings(&self) -> &DebugSettings {
+        &self.debug_settings
+    }
+
+    pub fn dirty_rect_mode(&self) -> bool {
+        self.dirty_rects.is_some()
+    }
+
+    /// Draws on another canvas from now on.
+    fn attach(&mut self, context: CanvasRenderingContext2d) {
+        self.context = context;
+        // The new canvas has none of what was drawn, so it all has to be.
+        if self.dirty_rect_mode() {
+            self.dirty_rects = Some(RefCell::new(DirtyRects::default()));
+        }
+    }
+
+    pub fn set_dirty_rect_mode(&mut self, enabled: bool) {
+        if enabled != self.dirty_rect_mode() {
+            self.dirty_rects = enabled.then(|| RefCell::new(DirtyRects::default()));
+            if let Some(canvas) = self.context.canvas() {
+                self.context
+                    .clear_rect(0.0, 0.0, canvas.width().into(), canvas.height().into());
+            }
+        }
+    }
+
+    /// Shows the canvas as big as fits the room `event` leaves, keeping
+    /// its shape.
+    pub fn handle_event(&self, event: &EngineEvent) {
+        let EngineEvent::Resized { width, height } = *event;
+        let Some(canvas) = self.context.canvas() else {
+            return;
+        };
+        let bounds = WorldBounds::from_canvas(&canvas);
+        let scale = bounds.scale_to_fit(width, height);
+        let style = canvas.style();
+        let sized = style
+            .set_property("width", &format!("{}px", f64::from(bounds.width) * scale))
+            .and_then(|_| {
+                style.set_property("height", &format!("{}px", f64::from(bounds.height) * scale))
+            });
+        if let Err(err) = sized {
+            log::error!("Could not scale the canvas {:#?}", err);
+        }
+    }
+
+    /// The world the canvas being drawn on holds.
+    pub fn bounds(&self) -> WorldBounds {
+        self.context
+            .canvas()
+            .map(|canvas| WorldBounds::from_canvas(&canvas))
+            .unwrap_or(WorldBounds {
+                width: 0,
+                height: 0,
+            })
+    }
+
     pub fn clear(&self, rect: &Rect) {
-        self.context.clear_rect(
-            rect.x().into(),
-            rect.y().into(),
-            rect.width.into(),
-            rect.height.into(),
-        );
+        // In dirty rect mode only the changed regions are cleared in end_frame.
+        if !self.dirty_rect_mode() {
+            self.context.clear_rect(
+                rect.x().into(),
+                rect.y().into(),
+                rect.width.into(),
+                rect.height.into(),
+            );
+        }
     }
 
     pub fn draw_image(&self, image: &HtmlImageElement, frame: &Rect, destination: &Rect) {
-        self.context
-            .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
-                image,
-                frame.x().into(),
-                frame.y().into(),
-                frame.width.into(),
-                frame.height.into(),
-                destination.x().into(),
-                destination.y().into(),
-                destination.width.into(),
-                destination.height.into(),
-            )
-            .expect("Drawing is throwing exceptions! Unrecoverable error.");
+        self.draw_image_with_alpha(image, frame, destination, 1.0);
     }
 
-    pub fn draw_entire_image(&self, image: &HtmlImageElement, position: &Point) {
-        self.context
-            .draw_image_with_html_image_element(image, position.x.into(), position.y.into())
-            .expect("Drawing is throwing exceptions! Unrecoverable error.");
+    pub fn draw_image_with_alpha(
+        &self,
+        image: &HtmlImageElement,
+        frame: &Rect,
+        destination: &Rect,
+        alpha: f64,
+    ) {
+        self.draw_image_with_filter(image, frame, destination, alpha, None);
+    }
+
+    /// Draws through a CSS `filter`, like `hue-rotate(90deg)`, to recolor a
+    /// sprite without another sprite sheet.
+    pub fn draw_image_with_filter(
+        &self,
+        image: &HtmlImageElement,
+        frame: &Rect,
+        destination: &Rect,
+        alpha: f64,
+        filter: Option<&'static str>,
+    ) {
+        self.submit(DrawCommand::Image {
+            image: image.clone(),
+            frame: *frame,
+            destination: *destination,
+            alpha,
+            flipped: false,
+            filter,
+        });
+    }
+
+    pub fn draw_canvas(&self, canvas: &HtmlCanvasElement, frame: &Rect, destination: &Rect) {
+        self.submit(DrawCommand::Canvas {
+            canvas: canvas.clone(),
+            frame: *frame,
+            destination: *destination,
+        });
+    }
+
+    pub fn draw_bounding_box(&self, bounding_box: &Rect) {
+        if self.debug_settings.bounding_boxes || self.bounding_boxes.get() {
+            self.draw_rect(bounding_box);
+        }
+    }
+
+    /// Draws with bounding boxes showing, even with them turned off.
+    pub fn with_bounding_boxes(&self, draw: impl FnOnce()) {
+        let shown = self.bounding_boxes.replace(true);
+        draw();
+        self.bounding_boxes.set(shown);
     }
 
-    #[cfg(feature = "draw_debug_info")]
     pub fn draw_rect(&self, bounding_box: &Rect) {
-        self.context.set_stroke_style_str("#FF0000");
-        self.context.begin_path();
-        self.context.rect(
-            bounding_box.x().into(),
-            bounding_box.y().into(),
-            bounding_box.width.into(),
-            bounding_box.height.into(),
-        );
-        self.context.stroke();
+        self.submit(DrawCommand::Rect(*bounding_box));
+    }
+
+    /// Covers `rect` in `color`, letting what is under it show through as
+    /// much as `alpha` leaves.
+    pub fn fill_rect(&self, rect: &Rect, color: &'static str, alpha: f64) {
+        self.submit(DrawCommand::Fill {
+            rect: *rect,
+            color,
+            alpha,
+        });
+    }
+
+    /// Outlines and hatches a hazard in high contrast mode. Accents are drawn
+    /// after the rest of the frame so nothing covers them.
+    pub fn draw_hazard(&self, hazard: &Rect) {
+        if self.high_contrast {
+            self.accents.borrow_mut().push(DrawCommand::Accent(*hazard));
+        }
+    }
+
+    fn draw_accents(&self) {
+        let accents = std::mem::take(&mut *self.accents.borrow_mut());
+        if accents
+            .iter()
+            .any(|command| matches!(command, DrawCommand::Accent(_)))
+        {
+            accents.into_iter().for_each(|command| self.submit(command));
+        }
     }
 
-    #[cfg(feature = "draw_debug_info")]
     pub fn draw_text(&self, text: &str, location: &Point) -> Result<()> {
+        self.draw_text_with_alpha(text, location, 1.0)
+    }
+
+    pub fn draw_text_with_alpha(&self, text: &str, location: &Point, alpha: f64) -> Result<()> {
+        let flipped = self.mirrored.get();
+        if let Some(font) = &self.font {
+            let glyphs = font.layout(text, location, 1);
+            // Under a mirrored camera each glyph is flipped back, and put
+            // where the camera will move it back from.
+            let right = glyphs
+                .iter()
+                .map(|(_, destination)| destination.right())
+                .max()
+                .unwrap_or(location.x);
+            for (frame, mut destination) in glyphs {
+                if flipped {
+                    destination.set_x(location.x + right - destination.right());
+                }
+                let glyph = |destination: Rect, filter| DrawCommand::Image {
+                    image: font.image().clone(),
+                    frame,
+                    destination,
+                    alpha,
+                    flipped,
+                    filter,
+                };
+                if self.high_contrast {
+                    outline_offsets().for_each(|(dx, dy)| {
+                        let mut outline = destination;
+                        outline.set_x(destination.x() + dx);
+                        outline.position.y += dy;
+                        self.submit(glyph(outline, Some(GLYPH_DARK)));
+                    });
+                    self.submit(glyph(destination, Some(GLYPH_LIGHT)));
+                } else {
+                    self.submit(glyph(destination, None));
+                }
+            }
+            return Ok(());
+        }
         self.context.set_font("16pt serif");
-        self.context
-            .fill_text(text, location.x.into(), location.y.into())
-            .map_err(|err| anyhow!("Error filling text {:#?}", err))?;
-        Ok(())
+        match &self.dirty_rects {
+            Some(dirty_rects) => {
+                dirty_rects.borrow_mut().record(DrawCommand::Text {
+                    text: text.to_string(),
+                    location: *location,
+                    alpha,
+                    flipped,
+                });
+                Ok(())
+            }
+            None => self.fill_text(text, location, alpha, flipped),
+        }
+    }
+
+    pub fn end_frame(&self) {
+        let Some(dirty_rects) = &self.dirty_rects else {
+            return;
+        };
+        let mut dirty_rects = dirty_rects.borrow_mut();
+        let (regions, commands) = dirty_rects.finish_frame(&self.context);
+        if regions.is_empty() {
+            return;
+        }
+
+        self.context.save();
+        self.context.begin_path();
+        for region in regions.iter() {
+            self.context.rect(
+                region.x().into(),
+                region.y().into(),
+                region.width.into(),
+                region.height.into(),
+            );
+        }
+        self.context.clip();
+        for region in regions.iter() {
+            self.context.clear_rect(
+                region.x().into(),
+                region.y().into(),
+                region.width.into(),
+                region.height.into(),
+            );
+        }
+        commands
+            .iter()
+            .filter(|(command, bounds)| {
+                matches!(command, DrawCommand::PushCamera(_) | DrawCommand::PopCamera)
+                    || regions.iter().any(|region| region.intersects(bounds))
+            })
+            .for_each(|(command, _)| self.execute(command));
+        self.context.restore();
+    }
+
+    /// Draws everything `draw` renders through `camera`.
+    pub fn with_camera(&self, camera: Camera, draw: impl FnOnce()) {
+        self.submit(DrawCommand::PushCamera(camera));
+        if self.high_contrast {
+            self.accents
+                .borrow_mut()
+                .push(DrawCommand::PushCamera(camera));
+        }
+        let mirrored = self.mirrored.get();
+        self.mirrored.set(mirrored != camera.mirrored);
+        draw();
+        self.mirrored.set(mirrored);
+        self.submit(DrawCommand::PopCamera);
+        if self.high_contrast {
+            self.accents.borrow_mut().push(DrawCommand::PopCamera);
+        }
+    }
+
+    fn submit(&self, command: DrawCommand) {
+        match &self.dirty_rects {
+            Some(dirty_rects) => dirty_rects.borrow_mut().record(command),
+            None => self.execute(&command),
+        }
+    }
+
+    fn execute(&self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Image {
+                image,
+                frame,
+                destination,
+                alpha,
+                flipped,
+                filter,
+            } => {
+                if *flipped {
+                    self.flip(destination.x(), destination.width);
+                }
+                if let Some(filter) = filter {
+                    self.context.set_filter(filter);
+                }
+                self.context.set_global_alpha(*alpha);
+                self.context
+                    .draw_image_with_html_image_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                        image,
+                        frame.x().into(),
+                        frame.y().into(),
+                        frame.width.into(),
+                        frame.height.into(),
+                        destination.x().into(),
+                        destination.y().into(),
+                        destination.width.into(),
+                        destination.height.into(),
+                    )
+                    .expect("Drawing is throwing exceptions! Unrecoverable error.");
+                self.context.set_global_alpha(1.0);
+                if filter.is_some() {
+                    self.context.set_filter("none");
+                }
+                if *flipped {
+                    self.context.restore();
+                }
+            }
+            DrawCommand::Canvas {
+                canvas,
+                frame,
+                destination,
+            } => self
+                .context
+                .draw_image_with_html_canvas_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                    canvas,
+                    frame.x().into(),
+                    frame.y().into(),
+                    frame.width.into(),
+                    frame.height.into(),
+                    destination.x().into(),
+                    destination.y().into(),
+                    destination.width.into(),
+                    destination.height.into(),
+                )
+                .expect("Drawing is throwing exceptions! Unrecoverable error."),
+            DrawCommand::Rect(bounding_box) => {
+                self.context.set_stroke_style_str("#FF0000");
+                self.context.begin_path();
+                self.context.rect(
+                    bounding_box.x().into(),
+                    bounding_box.y().into(),
+                    bounding_box.width.into(),
+                    bounding_box.height.into(),
+                );
+                self.context.stroke();
+            }
+            DrawCommand::Fill { rect, color, alpha } => {
+                // Saved so the fill color doesn't carry over to text.
+                self.context.save();
+                self.context.set_global_alpha(*alpha);
+                self.context.set_fill_style_str(color);
+                self.context.fill_rect(
+                    rect.x().into(),
+                    rect.y().into(),
+                    rect.width.into(),
+                    rect.height.into(),
+                );
+                self.context.restore();
+            }
+            DrawCommand::Accent(hazard) => self.draw_accent(hazard),
+            DrawCommand::Text {
+                text,
+                location,
+                alpha,
+                flipped,
+            } => {
+                if let Err(err) = self.fill_text(text, location, *alpha, *flipped) {
+                    log::error!("{:#?}", err);
+                }
+            }
+            DrawCommand::PushCamera(camera) => {
+                self.context.save();
+                self.context.begin_path();
+                self.context.rect(
+                    camera.viewport.x().into(),
+                    camera.viewport.y().into(),
+                    camera.viewport.width.into(),
+                    camera.viewport.height.into(),
+                );
+                self.context.clip();
+                if camera.mirrored {
+                    self.flip(camera.viewport.x(), camera.viewport.width);
+                }
+                if let Err(err) = self
+                    .context
+                    .translate(camera.offset.x.into(), camera.offset.y.into())
+                {
+                    log::error!("Could not move the camera {:#?}", err);
+                }
+            }
+            DrawCommand::PopCamera => self.context.restore(),
+        }
+    }
+
+    fn draw_accent(&self, hazard: &Rect) {
+        let (x, y) = (f64::from(hazard.x()), f64::from(hazard.y()));
+        let (width, height) = (f64::from(hazard.width), f64::from(hazard.height));
+        self.context.save();
+        self.context.begin_path();
+        self.context.rect(x, y, width, height);
+        self.context.clip();
+
+        self.context.set_stroke_style_str(ACCENT_COLOR);
+        self.context.set_line_width(ACCENT_HATCH_WIDTH);
+        self.context.begin_path();
+        let mut hatch_x = x - height;
+        while hatch_x < x + width {
+            self.context.move_to(hatch_x, y + height);
+            self.context.line_to(hatch_x + height, y);
+            hatch_x += ACCENT_HATCH_SPACING;
+        }
+        self.context.stroke();
+
+        // The outline is clipped to the hazard, so only its inner half shows.
+        self.context.set_line_width(ACCENT_OUTLINE_WIDTH);
+        self.context.set_stroke_style_str(CONTRAST_DARK);
+        self.context.stroke_rect(x, y, width, height);
+        self.context.set_line_width(ACCENT_OUTLINE_WIDTH / 2.0);
+        self.context.set_stroke_style_str(ACCENT_COLOR);
+        self.context.stroke_rect(x, y, width, height);
+        self.context.restore();
+    }
+
+    /// Saves the context and flips what is drawn next left to right, inside
+    /// the band `width` wide starting at `x`. Restore the context to stop.
+    fn flip(&self, x: i16, width: i16) {
+        self.context.save();
+        let result = self
+            .context
+            .translate(f64::from(x) * 2.0 + f64::from(width), 0.0)
+            .and_then(|_| self.context.scale(-1.0, 1.0));
+        if let Err(err) = result {
+            log::error!("Could not flip the canvas {:#?}", err);
+        }
+    }
+
+    fn fill_text(&self, text: &str, location: &Point, alpha: f64, flipped: bool) -> Result<()> {
+        if flipped {
+            let width = self
+                .context
+                .measure_text(text)
+                .map_err(|err| anyhow!("Error measuring text {:#?}", err))?
+                .width();
+            self.flip(location.x, width.ceil() as i16);
+            let result = self.fill_text(text, location, alpha, false);
+            self.context.restore();
+            return result;
+        }
+        self.context.set_global_alpha(alpha);
+        let result = if self.high_contrast {
+            self.outline_text(text, location)
+        } else {
+            self.context
+                .fill_text(text, location.x.into(), location.y.into())
+                .map_err(|err| anyhow!("Error filling text {:#?}", err))
+        };
+        self.context.set_global_alpha(1.0);
+        result
+    }
+
+    fn outline_text(&self, text: &str, location: &Point) -> Result<()> {
+        self.context.save();
+        self.context.set_line_width(TEXT_OUTLINE_WIDTH);
+        self.context.set_stroke_style_str(CONTRAST_DARK);
+        self.context.set_fill_style_str(CONTRAST_LIGHT);
+        let result = self
+            .context
+            .stroke_text(text, location.x.into(), location.y.into())
+            .and_then(|_| {
+                self.context
+                    .fill_text(text, location.x.into(), location.y.into())
+            })
+            .map_err(|err| anyhow!("Error filling text {:#?}", err));
+        self.context.restore();
+        result
     }
 }
 
@@ -329,36 +1404,63 @@ enum KeyPress {
     KeyDown(web_sys::KeyboardEvent),
 }
 
-fn prepare_input() -> Result<UnboundedReceiver<KeyPress>> {
-    let (ke_sender, ke_receiver) = unbounded();
-    let kd_sender = Rc::new(RefCell::new(ke_sender));
-    let ku_sender = Rc::clone(&kd_sender);
+/// Where the dark copies of a glyph go around it for its outline.
+fn outline_offsets() -> impl Iterator<Item = (i16, i16)> {
+    [-GLYPH_OUTLINE_OFFSET, 0, GLYPH_OUTLINE_OFFSET]
+        .into_iter()
+        .flat_map(|dx| {
+            [-GLYPH_OUTLINE_OFFSET, 0, GLYPH_OUTLINE_OFFSET]
+                .into_iter()
+                .map(move |dy| (dx, dy))
+        })
+        .filter(|&offset| offset != (0, 0))
+}
 
-    let onkeydown = browser::closure_wrap(Box::new(move |keycode: web_sys::KeyboardEvent| {
-        let _ = kd_sender
-            .borrow_mut()
-            .start_send(KeyPress::KeyDown(keycode));
-    }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
+fn key_target(canvas: &HtmlCanvasElement, keys_from_canvas: bool) -> Result<EventTarget> {
+    Ok(if keys_from_canvas {
+        canvas.clone().into()
+    } else {
+        browser::window()?.into()
+    })
+}
 
-    let onkeyup = browser::closure_wrap(Box::new(move |keycode: web_sys::KeyboardEvent| {
-        let _ = ku_sender.borrow_mut().start_send(KeyPress::KeyUp(keycode));
-    }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
+/// Sends the keys pressed on `target` to a game loop, until it's dropped.
+struct KeyListener {
+    _key_down: browser::EventListener<web_sys::KeyboardEvent>,
+    _key_up: browser::EventListener<web_sys::KeyboardEvent>,
+}
 
-    browser::window()?.set_onkeydown(Some(onkeydown.as_ref().unchecked_ref()));
-    browser::window()?.set_onkeyup(Some(onkeyup.as_ref().unchecked_ref()));
-    onkeydown.forget();
-    onkeyup.forget();
-    Ok(ke_receiver)
+impl KeyListener {
+    fn listen(target: EventTarget, sender: UnboundedSender<KeyPress>) -> Result<Self> {
+        let up_sender = sender.clone();
+        Ok(KeyListener {
+            _key_down: browser::EventListener::listen(&target, "keydown", move |keycode| {
+                let _ = sender.unbounded_send(KeyPress::KeyDown(keycode));
+            })?,
+            _key_up: browser::EventListener::listen(&target, "keyup", move |keycode| {
+                let _ = up_sender.unbounded_send(KeyPress::KeyUp(keycode));
+            })?,
+        })
+    }
 }
 
-fn process_input(state: &mut KeyState, keyevent_receiver: &mut UnboundedReceiver<KeyPress>) {
+fn process_input(
+    state: &mut KeyState,
+    keyevent_receiver: &mut UnboundedReceiver<KeyPress>,
+    debug_settings: &mut DebugSettings,
+) {
     loop {
         match keyevent_receiver.try_next() {
             Ok(None) => break,
             Err(_err) => break,
             Ok(Some(evt)) => match evt {
                 KeyPress::KeyUp(evt) => state.set_released(&evt.code()),
-                KeyPress::KeyDown(evt) => state.set_pressed(&evt.code(), evt),
+                KeyPress::KeyDown(evt) => {
+                    if !state.is_pressed(&evt.code()) {
+                        debug_settings.toggle(&evt.code());
+                    }
+                    state.set_pressed(&evt.code(), evt)
+                }
             },
         };
     }
@@ -366,12 +1468,18 @@ fn process_input(state: &mut KeyState, keyevent_receiver: &mut UnboundedReceiver
 
 pub struct KeyState {
     pressed_keys: HashMap<String, web_sys::KeyboardEvent>,
+    /// Keys that went down or up since the last update. Holding a key
+    /// down only counts as pressing it once.
+    just_pressed: HashSet<String>,
+    just_released: HashSet<String>,
 }
 
 impl KeyState {
     fn new() -> Self {
         KeyState {
             pressed_keys: HashMap::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
         }
     }
 
@@ -379,12 +1487,43 @@ impl KeyState {
         self.pressed_keys.contains_key(code)
     }
 
+    /// Whether `code` went down since the last update, even if it has
+    /// already been let go again.
+    pub fn just_pressed(&self, code: &str) -> bool {
+        self.just_pressed.contains(code)
+    }
+
+    pub fn just_released(&self, code: &str) -> bool {
+        self.just_released.contains(code)
+    }
+
+    /// The keys that went down since the last update.
+    #[cfg(feature = "dev")]
+    pub fn pressed_this_update(&self) -> impl Iterator<Item = &str> {
+        self.just_pressed.iter().map(String::as_str)
+    }
+
+    pub fn any_pressed(&self) -> bool {
+        !self.pressed_keys.is_empty()
+    }
+
     fn set_pressed(&mut self, code: &str, event: web_sys::KeyboardEvent) {
-        self.pressed_keys.insert(code.into(), event);
+        // Held keys repeat their key down, which isn't another press.
+        if self.pressed_keys.insert(code.into(), event).is_none() {
+            self.just_pressed.insert(code.into());
+        }
     }
 
     fn set_released(&mut self, code: &str) {
-        self.pressed_keys.remove(code);
+        if self.pressed_keys.remove(code).is_some() {
+            self.just_released.insert(code.into());
+        }
+    }
+
+    /// Forgets which keys went down and up, once an update has seen them.
+    fn end_update(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
     }
 }
 
@@ -401,47 +1540,359 @@ impl Debug for KeyState {
 #[derive(Clone)]
 pub struct Audio {
     context: AudioContext,
+    mixer: sound::Mixer,
+    music: Rc<RefCell<Option<sound::Track>>>,
+    /// How many things want the audio stopped, like the pause menu and a
+    /// hidden tab. It only starts again once none of them do.
+    suspensions: Rc<std::cell::Cell<u32>>,
 }
 
 #[derive(Clone)]
 pub struct Sound {
     pub(crate) buffer: AudioBuffer,
+    pub(crate) cue: Option<Cue>,
+}
+
+/// Variations of a single sound effect. Playing the group picks one at random
+/// and nudges its pitch, so repeated effects don't sound identical.
+#[derive(Clone)]
+pub struct SoundGroup {
+    sounds: Vec<Sound>,
+    ducks_music: bool,
+}
+
+impl SoundGroup {
+    pub fn new(sounds: Vec<Sound>) -> Self {
+        SoundGroup {
+            sounds,
+            ducks_music: false,
+        }
+    }
+
+    /// Marks the group as a priority effect that briefly lowers the music
+    /// whenever it plays.
+    pub fn ducking_music(mut self) -> Self {
+        self.ducks_music = true;
+        self
+    }
+}
+
+const MUSIC_VOLUME: f32 = 0.001;
+const DUCK_LEVEL: f32 = 0.3;
+const DUCK_HOLD: f64 = 0.3;
+const PLAYBACK_RATE_VARIATION: f32 = 0.05;
+const DETUNE_VARIATION: f32 = 50.0;
+
+#[derive(Deserialize)]
+struct AudioSpriteSheet {
+    src: String,
+    sprites: HashMap<String, Cue>,
 }
 
 impl Audio {
     pub fn new() -> Result<Self> {
+        let context = sound::create_audio_context()?;
+        let mixer = sound::Mixer::new(&context)?;
         Ok(Self {
-            context: sound::create_audio_context()?,
+            context,
+            mixer,
+            music: Rc::new(RefCell::new(None)),
+            suspensions: Rc::new(std::cell::Cell::new(0)),
         })
     }
 
+    pub fn set_muted(&self, muted: bool) {
+        self.mixer.set_muted(muted);
+    }
+
+    /// Holds every sound where it is, music included, until a matching
+    /// [`Audio::resume`].
+    pub fn suspend(&self) {
+        let suspensions = self.suspensions.get();
+        self.suspensions.set(suspensions + 1);
+        if suspensions == 0 {
+            if let Err(err) = self.context.suspend() {
+                log::error!("Could not suspend the audio {:#?}", err);
+            }
+        }
+    }
+
+    /// Lets the sounds carry on, once every [`Audio::suspend`] is resumed.
+    pub fn resume(&self) {
+        let suspensions = self.suspensions.get().saturating_sub(1);
+        self.suspensions.set(suspensions);
+        if suspensions == 0 {
+            if let Err(err) = self.context.resume() {
+                log::error!("Could not resume the audio {:#?}", err);
+            }
+        }
+    }
+
+    /// Stops every sound for good, and frees the audio hardware.
+    pub fn close(&self) {
+        if let Err(err) = self.context.close() {
+            log::error!("Could not close the audio context {:#?}", err);
+        }
+    }
+
     pub async fn load_sound(&self, filename: &str) -> Result<Sound> {
         let array_buffer = browser::fetch_array_buffer(filename).await?;
         let audio_buffer = sound::decode_audio_data(&self.context, &array_buffer).await?;
         Ok(Sound {
             buffer: audio_buffer,
+            cue: None,
         })
     }
 
-    pub fn play_sound(&self, sound: &Sound) -> Result<()> {
-        sound::play_sound(&self.context, &sound.buffer, sound::Looping::No, 1.0)
+    /// Loads every sound of an audio sprite with a single fetch and decode. The
+    /// cue sheet names the audio file and the start and duration of each sound.
+    pub async fn load_audio_sprite(&self, cue_sheet: &str) -> Result<HashMap<String, Sound>> {
+        let json = browser::fetch_json(cue_sheet).await?;
+        let sheet: AudioSpriteSheet = serde_wasm_bindgen::from_value(json).map_err(|err| {
+            anyhow!(
+                "Could not convert {} into a cue sheet {:#?}",
+                cue_sheet,
+                err
+            )
+        })?;
+        let sound = self.load_sound(&sheet.src).await?;
+        Ok(sheet
+            .sprites
+            .into_iter()
+            .map(|(name, cue)| {
+                (
+                    name,
+                    Sound {
+                        buffer: sound.buffer.clone(),
+                        cue: Some(cue),
+                    },
+                )
+            })
+            .collect())
     }
 
-    pub fn play_looping_sound(&self, sound: &Sound) -> Result<()> {
-        sound::play_sound(&self.context, &sound.buffer, sound::Looping::Yes, 0.001)
+    pub fn play_sound_group(&self, group: &SoundGroup) -> Result<()> {
+        let mut rng = thread_rng();
+        let Some(sound) = group.sounds.choose(&mut rng) else {
+            return Ok(());
+        };
+        let pitch = sound::Pitch {
+            playback_rate: 1.0 + rng.gen_range(-PLAYBACK_RATE_VARIATION..=PLAYBACK_RATE_VARIATION),
+            detune: rng.gen_range(-DETUNE_VARIATION..=DETUNE_VARIATION),
+        };
+        sound::play_sound(
+            &self.context,
+            &sound.buffer,
+            sound::Looping::No,
+            1.0,
+            sound.cue.as_ref(),
+            pitch,
+            self.mixer.sfx(),
+        )?;
+        if group.ducks_music {
+            self.mixer.duck(&self.context, DUCK_LEVEL, DUCK_HOLD)?;
+        }
+        Ok(())
     }
+
+    /// Fades the current music out and `sound` in over `duration` seconds,
+    /// stopping the old track once it is silent.
+    pub fn crossfade_to(&self, sound: &Sound, duration: f64, looping: Looping) -> Result<()> {
+        let track = sound::play_sound(
+            &self.context,
+            &sound.buffer,
+            looping,
+            0.0,
+            sound.cue.as_ref(),
+            sound::Pitch::NATURAL,
+            self.mixer.music(),
+        )?;
+        track.fade(&self.context, MUSIC_VOLUME, duration)?;
+        if let Some(previous) = self.music.replace(Some(track)) {
+            previous.fade(&self.context, 0.0, duration)?;
+            previous.stop(&self.context, duration)?;
+        }
+        Ok(())
+    }
+}
+
+/// A button's click and key listeners, with the sender they share so it
+/// can be told when nothing reads its clicks any more.
+struct ClickHandler {
+    sender: UnboundedSender<()>,
+    _click: browser::EventListener<web_sys::Event>,
+    _key_down: browser::EventListener<web_sys::KeyboardEvent>,
 }
 
+thread_local! {
+    static CLICK_HANDLERS: RefCell<Vec<ClickHandler>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Sends on the receiver each time `elem` is clicked, or pressed with Enter
+/// or Space. The listeners stay until the receiver is dropped and
+/// [`release_click_handlers`] runs.
 pub fn add_click_handler(elem: HtmlElement) -> UnboundedReceiver<()> {
-    let (mut click_sender, click_reciever) = unbounded();
-    let on_click = browser::closure_wrap(Box::new(move || {
-        let _ = click_sender.start_send(());
-    }) as Box<dyn FnMut()>);
-    elem.set_onclick(Some(on_click.as_ref().unchecked_ref()));
-    on_click.forget();
+    release_click_handlers();
+    let (click_sender, click_reciever) = unbounded();
+    let key_sender = click_sender.clone();
+    let sender = click_sender.clone();
+    let click = browser::EventListener::listen(&elem, "click", move |_evt: web_sys::Event| {
+        let _ = click_sender.unbounded_send(());
+    });
+    // Kept from the game as well, or Space would jump as it presses the
+    // button. Held keys repeating don't press it again.
+    let key_down =
+        browser::EventListener::listen(&elem, "keydown", move |evt: web_sys::KeyboardEvent| {
+            if !matches!(evt.code().as_str(), "Enter" | "Space") {
+                return;
+            }
+            evt.prevent_default();
+            evt.stop_propagation();
+            if !evt.repeat() {
+                let _ = key_sender.unbounded_send(());
+            }
+        });
+    match click.and_then(|click| Ok((click, key_down?))) {
+        Ok((click, key_down)) => CLICK_HANDLERS.with(|handlers| {
+            handlers.borrow_mut().push(ClickHandler {
+                sender,
+                _click: click,
+                _key_down: key_down,
+            })
+        }),
+        Err(err) => log::error!("Could not listen for clicks {:#?}", err),
+    }
     click_reciever
 }
 
+/// Removes the listeners of every button whose receiver has been dropped.
+pub fn release_click_handlers() {
+    CLICK_HANDLERS.with(|handlers| {
+        handlers
+            .borrow_mut()
+            .retain(|handler| !handler.sender.is_closed())
+    });
+}
+
+/// How many click channels still have a receiver reading them.
+pub fn live_click_channels() -> usize {
+    CLICK_HANDLERS.with(|handlers| {
+        handlers
+            .borrow()
+            .iter()
+            .filter(|handler| !handler.sender.is_closed())
+            .count()
+    })
+}
+
+/// A text box's key listener, held until it's dropped, and the lines
+/// typed into it.
+pub struct SubmitHandler {
+    receiver: UnboundedReceiver<String>,
+    _key_down: browser::EventListener<web_sys::KeyboardEvent>,
+}
+
+impl SubmitHandler {
+    /// The next line entered, if one was entered since last asked.
+    pub fn try_next(&mut self) -> Option<String> {
+        self.receiver.try_next().ok().flatten()
+    }
+}
+
+/// Sends what is in `input` each time Enter is pressed in it, and empties
+/// it. Other keys are kept from the game, except the one that closes the
+/// debug console.
+pub fn add_submit_handler(input: web_sys::HtmlInputElement) -> Result<SubmitHandler> {
+    let (submit_sender, receiver) = unbounded();
+    let element = input.clone();
+    let key_down =
+        browser::EventListener::listen(&input, "keydown", move |evt: web_sys::KeyboardEvent| {
+            match evt.code().as_str() {
+                "Enter" => {
+                    let _ = submit_sender.unbounded_send(element.value());
+                    element.set_value("");
+                }
+                "Backquote" => evt.prevent_default(),
+                _ => (),
+            }
+            if evt.code() != "Backquote" {
+                evt.stop_propagation();
+            }
+        })?;
+    Ok(SubmitHandler {
+        receiver,
+        _key_down: key_down,
+    })
+}
+
+#[cfg(feature = "editor")]
+#[derive(Debug, Clone, Copy)]
+pub enum MouseInput {
+    Down(Point),
+    Move(Point),
+    Up,
+}
+
+/// The editor's mouse listeners on an element, held until it's dropped.
+#[cfg(feature = "editor")]
+pub struct MouseHandler {
+    receiver: UnboundedReceiver<MouseInput>,
+    _down: browser::EventListener<web_sys::MouseEvent>,
+    _move: browser::EventListener<web_sys::MouseEvent>,
+    _up: browser::EventListener<web_sys::MouseEvent>,
+}
+
+#[cfg(feature = "editor")]
+impl MouseHandler {
+    /// The next thing the mouse did, if it did anything since last asked.
+    pub fn try_next(&mut self) -> Option<MouseInput> {
+        self.receiver.try_next().ok().flatten()
+    }
+}
+
+#[cfg(feature = "editor")]
+pub fn add_mouse_handler(elem: HtmlElement) -> Result<MouseHandler> {
+    let (down_sender, receiver) = unbounded();
+    let move_sender = down_sender.clone();
+    let up_sender = down_sender.clone();
+    let down_elem = elem.clone();
+    let move_elem = elem.clone();
+
+    let down =
+        browser::EventListener::listen(&elem, "mousedown", move |evt: web_sys::MouseEvent| {
+            let _ = down_sender.unbounded_send(MouseInput::Down(mouse_position(&evt, &down_elem)));
+        })?;
+    let motion =
+        browser::EventListener::listen(&elem, "mousemove", move |evt: web_sys::MouseEvent| {
+            let _ = move_sender.unbounded_send(MouseInput::Move(mouse_position(&evt, &move_elem)));
+        })?;
+    let up = browser::EventListener::listen(&elem, "mouseup", move |_evt: web_sys::MouseEvent| {
+        let _ = up_sender.unbounded_send(MouseInput::Up);
+    })?;
+    Ok(MouseHandler {
+        receiver,
+        _down: down,
+        _move: motion,
+        _up: up,
+    })
+}
+
+/// Where the mouse is on `elem`. On a canvas shown scaled up or down it's
+/// in the canvas's own pixels.
+#[cfg(feature = "editor")]
+fn mouse_position(evt: &web_sys::MouseEvent, elem: &HtmlElement) -> Point {
+    let scale = elem
+        .dyn_ref::<HtmlCanvasElement>()
+        .filter(|_| elem.client_width() > 0)
+        .map_or(1.0, |canvas| {
+            f64::from(canvas.width()) / f64::from(elem.client_width())
+        });
+    Point {
+        x: (f64::from(evt.offset_x()) * scale) as i16,
+        y: (f64::from(evt.offset_y()) * scale) as i16,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -459,6 +1910,107 @@ mod tests {
             width: 100,
         };
 
-        assert_eq!(rect2.intersects(&rect1), true);
+        assert!(rect2.intersects(&rect1));
+    }
+
+    #[test]
+    fn overlapping_rects_have_an_intersection() {
+        let rect = Rect::new_from_x_y(10, 10, 100, 100);
+        assert_eq!(
+            rect.intersection(&Rect::new_from_x_y(0, 50, 50, 100)),
+            Some(Rect::new_from_x_y(10, 50, 40, 60))
+        );
+        assert_eq!(
+            rect.intersection(&Rect::new_from_x_y(110, 10, 10, 10)),
+            None
+        );
+    }
+
+    #[test]
+    fn rect_points_and_sizes() {
+        let rect = Rect::new_from_x_y(10, 10, 100, 50);
+        assert_eq!(rect.center(), Point { x: 60, y: 35 });
+        assert!(rect.contains_point(Point { x: 10, y: 59 }));
+        assert!(!rect.contains_point(Point { x: 110, y: 20 }));
+        assert_eq!(rect.inflate(2), Rect::new_from_x_y(8, 8, 104, 54));
+        assert_eq!(rect.inflate(-5), Rect::new_from_x_y(15, 15, 90, 40));
+    }
+
+    #[test]
+    fn mirrored_cameras_flip_inside_the_viewport() {
+        let camera = Camera {
+            viewport: Rect::new_from_x_y(0, 300, 600, 300),
+            offset: Point { x: 0, y: 300 },
+            mirrored: true,
+        };
+        assert_eq!(
+            camera.project(&Rect::new_from_x_y(100, 0, 50, 20)),
+            Rect::new_from_x_y(450, 300, 50, 20)
+        );
+    }
+
+    #[test]
+    fn worlds_scale_to_fit_keeping_their_shape() {
+        let wide = WorldBounds {
+            width: 1067,
+            height: 600,
+        };
+        assert_eq!(wide.scale_to_fit(2134.0, 2000.0), 2.0);
+        assert_eq!(wide.scale_to_fit(3000.0, 300.0), 0.5);
+        let empty = WorldBounds {
+            width: 0,
+            height: 0,
+        };
+        assert_eq!(empty.scale_to_fit(800.0, 600.0), 1.0);
+    }
+
+    #[test]
+    fn point_arithmetic_and_lerp() {
+        let point = Point { x: 4, y: -2 };
+        assert_eq!(point + Point { x: 1, y: 1 }, Point { x: 5, y: -1 });
+        assert_eq!(point - Point { x: 1, y: 1 }, Point { x: 3, y: -3 });
+        assert_eq!(point * 3, Point { x: 12, y: -6 });
+        assert_eq!(point.lerp(Point { x: 14, y: 8 }, 0.5), Point { x: 9, y: 3 });
+        assert_eq!(lerp(0, 10, 0.25), 3);
+    }
+
+    #[test]
+    fn frame_stats_roll_over_each_second() {
+        let mut stats = FrameStats::default();
+        for _ in 0..60 {
+            stats.record_frame(16.0, 1);
+        }
+        stats.record_frame(40.0, 2);
+        assert_eq!(stats.frame_rate(), 0);
+
+        stats.record_frame(16.0, 1);
+        assert_eq!(stats.frame_rate(), 62);
+        assert_eq!(stats.longest_frame(), 40.0);
+        assert_eq!(stats.updates_per_frame(), 1);
+    }
+
+    #[test]
+    fn debug_settings_from_query_value() {
+        assert_eq!(
+            DebugSettings::parse(""),
+            DebugSettings {
+                bounding_boxes: true,
+                frame_rate: true
+            }
+        );
+        assert_eq!(
+            DebugSettings::parse("fps"),
+            DebugSettings {
+                bounding_boxes: false,
+                frame_rate: true
+            }
+        );
+        assert_eq!(
+            DebugSettings::parse("boxes, fps"),
+            DebugSettings {
+                bounding_boxes: true,
+                frame_rate: true
+            }
+        );
     }
 }
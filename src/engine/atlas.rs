@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use web_sys::{HtmlCanvasElement, HtmlImageElement};
+
+use super::{Point, Rect, Renderer};
+use crate::browser;
+
+const ATLAS_WIDTH: i16 = 1024;
+const PADDING: i16 = 1;
+
+/// Several loaded images composited onto a single canvas at load time, so the
+/// renderer can draw all of them from one source.
+pub struct Atlas {
+    canvas: HtmlCanvasElement,
+    frames: HashMap<String, Rect>,
+}
+
+impl Atlas {
+    pub fn pack(images: &[(&str, &HtmlImageElement)]) -> Result<Self> {
+        let sizes: Vec<(i16, i16)> = images
+            .iter()
+            .map(|(_, image)| (image.width() as i16, image.height() as i16))
+            .collect();
+        let (positions, width, height) = pack_shelves(&sizes, ATLAS_WIDTH);
+
+        let canvas = browser::create_canvas(width as u32, height as u32)?;
+        let context = browser::canvas_context(&canvas)?;
+        let mut frames = HashMap::new();
+        for (((name, image), position), (w, h)) in images.iter().zip(positions).zip(sizes) {
+            context
+                .draw_image_with_html_image_element(image, position.x.into(), position.y.into())
+                .map_err(|err| anyhow!("Could not pack {} into the atlas {:#?}", name, err))?;
+            frames.insert(name.to_string(), Rect::new(position, w, h));
+        }
+        Ok(Atlas { canvas, frames })
+    }
+
+    pub fn frame(&self, name: &str) -> Option<Rect> {
+        self.frames.get(name).copied()
+    }
+
+    pub fn draw(&self, renderer: &Renderer, frame: &Rect, destination: &Rect) {
+        renderer.draw_canvas(&self.canvas, frame, destination);
+    }
+}
+
+/// Places rectangles on shelves ordered from tallest to shortest, returning each
+/// rectangle's position in input order together with the packed width and height.
+fn pack_shelves(sizes: &[(i16, i16)], max_width: i16) -> (Vec<Point>, i16, i16) {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(sizes[index].1));
+
+    let mut positions = vec![Point::default(); sizes.len()];
+    let (mut x, mut y, mut shelf_height, mut width) = (0, 0, 0, 0);
+    for index in order {
+        let (w, h) = sizes[index];
+        if x > 0 && x + w > max_width {
+            y += shelf_height + PADDING;
+            x = 0;
+            shelf_height = 0;
+        }
+        positions[index] = Point { x, y };
+        x += w + PADDING;
+        width = width.max(x - PADDING);
+        shelf_height = shelf_height.max(h);
+    }
+    (positions, width, y + shelf_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_tallest_first_and_wraps_shelves() {
+        let (positions, width, height) = pack_shelves(&[(40, 10), (50, 30), (30, 20)], 100);
+        assert_eq!(
+            positions,
+            [
+                Point { x: 0, y: 31 },
+                Point { x: 0, y: 0 },
+                Point { x: 51, y: 0 }
+            ]
+        );
+        assert_eq!(width, 81);
+        assert_eq!(height, 41);
+    }
+}
@@ -0,0 +1,133 @@
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+use super::{Camera, Point, Rect};
+
+const TEXT_ASCENT: i16 = 22;
+const TEXT_HEIGHT: i16 = 28;
+
+#[derive(Clone, PartialEq)]
+pub enum DrawCommand {
+    Image {
+        image: HtmlImageElement,
+        frame: Rect,
+        destination: Rect,
+        alpha: f64,
+        /// Flipped left to right inside the destination.
+        flipped: bool,
+        /// A CSS filter to draw through, like `hue-rotate(90deg)`.
+        filter: Option<&'static str>,
+    },
+    Canvas {
+        canvas: HtmlCanvasElement,
+        frame: Rect,
+        destination: Rect,
+    },
+    Rect(Rect),
+    /// A rectangle filled with a see-through color.
+    Fill {
+        rect: Rect,
+        color: &'static str,
+        alpha: f64,
+    },
+    Accent(Rect),
+    Text {
+        text: String,
+        location: Point,
+        alpha: f64,
+        flipped: bool,
+    },
+    PushCamera(Camera),
+    PopCamera,
+}
+
+impl DrawCommand {
+    fn bounds(&self, context: &CanvasRenderingContext2d) -> Rect {
+        match self {
+            DrawCommand::PushCamera(camera) => camera.viewport,
+            DrawCommand::PopCamera => Rect::default(),
+            DrawCommand::Image { destination, .. } | DrawCommand::Canvas { destination, .. } => {
+                *destination
+            }
+            DrawCommand::Accent(rect) | DrawCommand::Fill { rect, .. } => *rect,
+            DrawCommand::Rect(rect) => rect.inflate(1),
+            DrawCommand::Text { text, location, .. } => {
+                let width = context
+                    .measure_text(text)
+                    .map(|metrics| metrics.width().ceil() as i16)
+                    .unwrap_or_default();
+                Rect::new_from_x_y(location.x, location.y - TEXT_ASCENT, width, TEXT_HEIGHT)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DirtyRects {
+    previous: Vec<(DrawCommand, Rect)>,
+    current: Vec<DrawCommand>,
+}
+
+impl DirtyRects {
+    pub fn record(&mut self, command: DrawCommand) {
+        self.current.push(command);
+    }
+
+    /// Finishes the frame, returning the regions that changed since the previous
+    /// frame along with every command of this frame and its bounds.
+    pub fn finish_frame(
+        &mut self,
+        context: &CanvasRenderingContext2d,
+    ) -> (Vec<Rect>, &[(DrawCommand, Rect)]) {
+        let mut camera = None;
+        let current: Vec<(DrawCommand, Rect)> = self
+            .current
+            .drain(..)
+            .map(|command| {
+                let bounds = match (&command, camera) {
+                    (DrawCommand::PushCamera(pushed), _) => {
+                        camera = Some(*pushed);
+                        pushed.viewport
+                    }
+                    (DrawCommand::PopCamera, _) => camera.take().unwrap_or_default().viewport,
+                    // Drawing is clipped to the camera's viewport, so nothing
+                    // outside it changes.
+                    (_, Some(camera)) => camera
+                        .project(&command.bounds(context))
+                        .intersection(&camera.viewport)
+                        .unwrap_or_default(),
+                    (_, None) => command.bounds(context),
+                };
+                (command, bounds)
+            })
+            .collect();
+        let regions = changed(&self.previous, &current)
+            .map(|(_, bounds)| *bounds)
+            .collect();
+        self.previous = current;
+        (regions, &self.previous)
+    }
+}
+
+fn changed<'a, T: PartialEq>(previous: &'a [T], current: &'a [T]) -> impl Iterator<Item = &'a T> {
+    let added = current.iter().filter(|item| !previous.contains(item));
+    let removed = previous.iter().filter(|item| !current.contains(item));
+    added.chain(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_items_include_added_and_removed() {
+        let previous = [1, 2, 3];
+        let current = [2, 3, 4];
+        assert_eq!(changed(&previous, &current).collect::<Vec<_>>(), [&4, &1]);
+    }
+
+    #[test]
+    fn unchanged_frames_have_no_changes() {
+        let frame = [1, 2, 3];
+        assert_eq!(changed(&frame, &frame).count(), 0);
+    }
+}
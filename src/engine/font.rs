@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use web_sys::HtmlImageElement;
+
+use super::{load_image, Point, Rect};
+use crate::browser;
+
+pub const FONT_IMAGE: &str = "font.png";
+pub const FONT_METRICS: &str = "font.json";
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+struct Glyph {
+    x: i16,
+    y: i16,
+    w: i16,
+    h: i16,
+    advance: i16,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct FontMetrics {
+    /// How far the top of a glyph is above the baseline.
+    ascent: i16,
+    line_height: i16,
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl FontMetrics {
+    /// Where each glyph of `text` comes from in the font image and where it
+    /// goes, for text with its baseline starting at `location`. Letters the
+    /// font lacks fall back to upper case, and anything else leaves a space.
+    fn layout(&self, text: &str, location: &Point, scale: i16) -> Vec<(Rect, Rect)> {
+        let space = self.glyphs.get(&' ').map_or(0, |glyph| glyph.advance);
+        let mut x = location.x;
+        let mut y = location.y - self.ascent * scale;
+        let mut glyphs = vec![];
+        for character in text.chars() {
+            if character == '\n' {
+                x = location.x;
+                y += self.line_height * scale;
+                continue;
+            }
+            match self
+                .glyphs
+                .get(&character)
+                .or_else(|| self.glyphs.get(&character.to_ascii_uppercase()))
+            {
+                Some(glyph) => {
+                    glyphs.push((
+                        Rect::new_from_x_y(glyph.x, glyph.y, glyph.w, glyph.h),
+                        Rect::new_from_x_y(x, y, glyph.w * scale, glyph.h * scale),
+                    ));
+                    x += glyph.advance * scale;
+                }
+                None => x += space * scale,
+            }
+        }
+        glyphs
+    }
+}
+
+/// Text drawn from a glyph image, so it looks the same in every browser.
+pub struct BitmapFont {
+    image: HtmlImageElement,
+    metrics: FontMetrics,
+}
+
+impl BitmapFont {
+    pub async fn load() -> Result<Self> {
+        let metrics = browser::fetch_json(FONT_METRICS).await?;
+        let metrics: FontMetrics = serde_wasm_bindgen::from_value(metrics)
+            .map_err(|err| anyhow!("Could not convert {} into metrics {:#?}", FONT_METRICS, err))?;
+        let image = load_image(FONT_IMAGE).await?;
+        Ok(BitmapFont { image, metrics })
+    }
+
+    pub fn image(&self) -> &HtmlImageElement {
+        &self.image
+    }
+
+    pub fn layout(&self, text: &str, location: &Point, scale: i16) -> Vec<(Rect, Rect)> {
+        self.metrics.layout(text, location, scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> FontMetrics {
+        let glyph = |x| Glyph {
+            x,
+            y: 0,
+            w: 12,
+            h: 16,
+            advance: 12,
+        };
+        FontMetrics {
+            ascent: 15,
+            line_height: 18,
+            glyphs: HashMap::from([(' ', glyph(0)), ('A', glyph(12)), ('1', glyph(24))]),
+        }
+    }
+
+    #[test]
+    fn lays_glyphs_out_along_the_baseline() {
+        let glyphs = metrics().layout("A1", &Point { x: 10, y: 40 }, 1);
+        assert_eq!(
+            glyphs,
+            [
+                (
+                    Rect::new_from_x_y(12, 0, 12, 16),
+                    Rect::new_from_x_y(10, 25, 12, 16)
+                ),
+                (
+                    Rect::new_from_x_y(24, 0, 12, 16),
+                    Rect::new_from_x_y(22, 25, 12, 16)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_upper_case_and_spaces() {
+        let glyphs = metrics().layout("a?A", &Point { x: 0, y: 15 }, 2);
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!(glyphs[0].0, Rect::new_from_x_y(12, 0, 12, 16));
+        assert_eq!(glyphs[1].1, Rect::new_from_x_y(48, -15, 24, 32));
+    }
+
+    #[test]
+    fn breaks_lines() {
+        let glyphs = metrics().layout("A\nA", &Point { x: 5, y: 15 }, 1);
+        assert_eq!(glyphs[1].1.position, Point { x: 5, y: 18 });
+    }
+}
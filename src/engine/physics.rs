@@ -0,0 +1,100 @@
+use super::Vector;
+
+/// How one thing moves under gravity. Each entity has its own, so a heavy
+/// enemy can fall faster than a floaty particle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Physics {
+    /// Added to the vertical velocity every frame, before scaling.
+    pub gravity: f32,
+    pub gravity_scale: f32,
+    /// Gravity stops speeding a fall up past this.
+    pub max_fall_speed: f32,
+    /// The fastest it can move across the screen either way.
+    pub max_speed: f32,
+}
+
+impl Physics {
+    /// Pulls `velocity` down by a frame of gravity and caps its speed.
+    pub fn accelerate(&self, mut velocity: Vector) -> Vector {
+        if velocity.y < self.max_fall_speed {
+            velocity.y = (velocity.y + self.gravity * self.gravity_scale).min(self.max_fall_speed);
+        }
+        velocity.x = velocity.x.clamp(-self.max_speed, self.max_speed);
+        velocity
+    }
+
+    /// Moves on by one frame, accelerating first so a fall starts straight
+    /// away. Returns the new position and velocity.
+    pub fn step(&self, position: Vector, velocity: Vector) -> (Vector, Vector) {
+        let velocity = self.accelerate(velocity);
+        let position = Vector {
+            x: position.x + velocity.x,
+            y: position.y + velocity.y,
+        };
+        (position, velocity)
+    }
+}
+
+/// Turns a speed that can fall between pixels into whole-pixel moves for
+/// things placed on the pixel grid. The fraction left over each frame is
+/// carried into the next, so on average they move at the speed asked for.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SubPixel {
+    remainder: f32,
+}
+
+impl SubPixel {
+    pub fn advance(&mut self, speed: f32) -> i16 {
+        let distance = self.remainder + speed;
+        let whole = distance.round();
+        self.remainder = distance - whole;
+        whole as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHYSICS: Physics = Physics {
+        gravity: 2.0,
+        gravity_scale: 1.0,
+        max_fall_speed: 5.0,
+        max_speed: 3.0,
+    };
+
+    #[test]
+    fn falls_faster_every_frame_up_to_the_limit() {
+        let (position, velocity) = PHYSICS.step(Vector::default(), Vector::default());
+        assert_eq!(position, Vector { x: 0.0, y: 2.0 });
+        assert_eq!(velocity, Vector { x: 0.0, y: 2.0 });
+        assert_eq!(
+            PHYSICS.accelerate(Vector { x: 0.0, y: 4.0 }),
+            Vector { x: 0.0, y: 5.0 }
+        );
+        assert_eq!(
+            PHYSICS.accelerate(Vector { x: 0.0, y: 8.0 }),
+            Vector { x: 0.0, y: 8.0 }
+        );
+    }
+
+    #[test]
+    fn scales_gravity_and_caps_speed_across() {
+        let floaty = Physics {
+            gravity_scale: 0.25,
+            ..PHYSICS
+        };
+        assert_eq!(
+            floaty.accelerate(Vector { x: -10.0, y: -4.0 }),
+            Vector { x: -3.0, y: -3.5 }
+        );
+    }
+
+    #[test]
+    fn sub_pixel_speeds_move_whole_pixels_that_add_up() {
+        let mut scroll = SubPixel::default();
+        let moves: Vec<i16> = (0..4).map(|_| scroll.advance(-4.25)).collect();
+        assert_eq!(moves.iter().sum::<i16>(), -17);
+        assert!(moves.iter().all(|step| (-5..=-4).contains(step)));
+    }
+}
@@ -0,0 +1,141 @@
+use super::FRAME_SIZE;
+
+/// How much the game spends on looks. Each level drops more of the effects
+/// to keep a slow device at full speed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quality {
+    /// Dirty rect mode, and the background holds still.
+    Low,
+    /// No particles or afterimages.
+    Reduced,
+    #[default]
+    Full,
+}
+
+impl Quality {
+    fn lower(self) -> Self {
+        match self {
+            Quality::Full => Quality::Reduced,
+            Quality::Reduced | Quality::Low => Quality::Low,
+        }
+    }
+
+    fn higher(self) -> Self {
+        match self {
+            Quality::Low => Quality::Reduced,
+            Quality::Reduced | Quality::Full => Quality::Full,
+        }
+    }
+
+    pub fn particles(self) -> bool {
+        self == Quality::Full
+    }
+
+    pub fn scrolling_background(self) -> bool {
+        self != Quality::Low
+    }
+
+    pub fn dirty_rects(self) -> bool {
+        self == Quality::Low
+    }
+}
+
+/// Frames are looked at in windows this long before the quality changes.
+const WINDOW_FRAMES: u32 = 120;
+/// A frame that takes this long missed 60fps by more than the odd hiccup.
+const SLOW_FRAME: f32 = FRAME_SIZE * 1.25;
+/// Longer gaps are the tab being hidden or the loop being detached, not
+/// the device being slow.
+const STALLED_FRAME: f32 = 250.0;
+/// The quality drops once this many frames of a window are slow.
+const SLOW_FRAMES_TO_DROP: u32 = WINDOW_FRAMES / 4;
+/// Updating and drawing under this much of a frame leaves room for more.
+const HEADROOM: f32 = FRAME_SIZE * 0.5;
+/// Windows in a row with room to spare before the quality goes back up.
+/// Longer than a drop takes, so it doesn't flip back and forth.
+const QUIET_WINDOWS_TO_RAISE: u32 = 5;
+
+/// Watches how long frames take and picks the quality the device can keep
+/// up with, dropping it when frames run slow and raising it again once
+/// there has been headroom for a while.
+#[derive(Debug, Default)]
+pub struct QualityController {
+    quality: Quality,
+    frames: u32,
+    slow_frames: u32,
+    longest_work: f32,
+    quiet_windows: u32,
+}
+
+impl QualityController {
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// Records a frame `frame_time` after the last one, `work_time` of which
+    /// went on updating and drawing. Hands back the new quality when it
+    /// changes.
+    pub fn record(&mut self, frame_time: f32, work_time: f32) -> Option<Quality> {
+        if frame_time > STALLED_FRAME {
+            return None;
+        }
+        self.frames += 1;
+        if frame_time > SLOW_FRAME || work_time > FRAME_SIZE {
+            self.slow_frames += 1;
+        }
+        self.longest_work = self.longest_work.max(work_time);
+
+        let previous = self.quality;
+        if self.slow_frames >= SLOW_FRAMES_TO_DROP {
+            self.quality = self.quality.lower();
+            self.quiet_windows = 0;
+            self.start_window();
+        } else if self.frames >= WINDOW_FRAMES {
+            if self.slow_frames == 0 && self.longest_work < HEADROOM {
+                self.quiet_windows += 1;
+            } else {
+                self.quiet_windows = 0;
+            }
+            if self.quiet_windows >= QUIET_WINDOWS_TO_RAISE {
+                self.quality = self.quality.higher();
+                self.quiet_windows = 0;
+            }
+            self.start_window();
+        }
+        (self.quality != previous).then_some(self.quality)
+    }
+
+    fn start_window(&mut self) {
+        self.frames = 0;
+        self.slow_frames = 0;
+        self.longest_work = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_when_frames_run_slow_and_comes_back_with_headroom() {
+        let mut controller = QualityController::default();
+        let changes = (0..SLOW_FRAMES_TO_DROP)
+            .filter_map(|_| controller.record(33.0, 20.0))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [Quality::Reduced]);
+
+        // Hiding the tab isn't the device being slow.
+        assert_eq!(controller.record(1000.0, 1.0), None);
+
+        // One quiet window isn't enough to go back up.
+        let quiet = (0..WINDOW_FRAMES)
+            .filter_map(|_| controller.record(FRAME_SIZE, 2.0))
+            .count();
+        assert_eq!(quiet, 0);
+        let changes = (0..WINDOW_FRAMES * QUIET_WINDOWS_TO_RAISE)
+            .filter_map(|_| controller.record(FRAME_SIZE, 2.0))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [Quality::Full]);
+        assert_eq!(controller.quality(), Quality::Full);
+    }
+}
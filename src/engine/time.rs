@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+/// Counts down a number of updates. The game updates at a fixed step, so
+/// the frames a timer lasts are the same on every machine. Also used as a
+/// cooldown, restarted every time whatever it holds back happens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timer {
+    duration: u16,
+    remaining: u16,
+}
+
+impl Timer {
+    pub fn new(frames: u16) -> Self {
+        Timer {
+            duration: frames,
+            remaining: frames,
+        }
+    }
+
+    /// Moves on by one fixed step. A finished timer stays finished.
+    pub fn tick(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    /// Starts counting down from the beginning again.
+    pub fn restart(&mut self) {
+        self.remaining = self.duration;
+    }
+
+    pub fn remaining(&self) -> u16 {
+        self.remaining
+    }
+
+    pub fn finished(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// How much of the timer is left, from 1 when it starts down to 0 when
+    /// it finishes. Handy for fading things out.
+    pub fn fraction_left(&self) -> f64 {
+        if self.duration == 0 {
+            return 0.0;
+        }
+        f64::from(self.remaining) / f64::from(self.duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_down_to_finished_and_restarts() {
+        let mut timer = Timer::new(2);
+        assert_eq!(timer.fraction_left(), 1.0);
+        timer.tick();
+        assert_eq!(timer.remaining(), 1);
+        assert_eq!(timer.fraction_left(), 0.5);
+        timer.tick();
+        timer.tick();
+        assert!(timer.finished());
+        timer.restart();
+        assert_eq!(timer.remaining(), 2);
+        assert!(Timer::default().finished());
+    }
+}
@@ -1,28 +1,154 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
-use self::red_hat_boy_states::*;
+use self::{
+    config::GameConfig,
+    music::{Mood, Music},
+    red_hat_boy_states::*,
+    stats::{RunStats, Stats},
+};
+pub use self::{
+    config::Reach,
+    zones::{Modifier, ZoneKind},
+};
 use crate::{
-    browser,
+    browser::{self, recorder::CanvasRecorder, Callbacks, Mode, StartupConfig},
     engine::{
-        self, Audio, Cell, Game, Image, KeyState, Point, Rect, Renderer, Sheet, Sound, SpriteSheet,
+        self, Atlas, Audio, Camera, Cell, EngineEvent, Game, Image, KeyState, Point, Quality, Rect,
+        Renderer, ScrollingBackground, Sheet, Sound, SoundGroup, SpriteSheet, Timer, WorldBounds,
     },
-    segments::{platform_and_stone, stone_and_platform},
+    obstacles::ObstacleRegistry,
+    segments::{self, build_segment, stone_and_platform, SegmentData, SEGMENT_COUNT, STONE},
 };
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures::channel::mpsc::UnboundedReceiver;
 use rand::prelude::*;
-use web_sys::HtmlImageElement;
-
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use web_sys::{HtmlCanvasElement, HtmlImageElement};
+
+mod afterimage;
+mod ambience;
+mod analytics;
+pub mod assets;
+mod attract;
+mod autopilot;
+mod biomes;
+mod boss;
+mod celebration;
+#[cfg(feature = "dev")]
+mod cheats;
+mod clip;
+mod coins;
+mod config;
+#[cfg(feature = "dev")]
+mod console;
+mod cosmetics;
+mod countdown;
+mod course;
+#[cfg(feature = "editor")]
+mod editor;
+#[cfg(feature = "dev")]
+mod hot_reload;
+mod layout;
+mod leaderboard;
+mod music;
+mod particles;
+mod pause;
+mod popups;
+mod preview;
+mod prewarm;
+mod profiles;
+mod race;
+mod replay;
+mod rewind;
+mod save;
+mod screenshot;
+mod snapshot;
+mod stats;
+mod suspend;
+mod tutorial;
+mod two_player;
+mod ui;
+mod wind;
+mod window;
+mod zones;
+
+pub const WIDTH: i16 = 600;
 pub const HEIGHT: i16 = 600;
-const TIMELINE_MINIMUM: i16 = 1000;
-const OBSTACLE_BUFFER: i16 = 20;
+/// The world the game is laid out for, used until the canvas says otherwise.
+const DESIGN_BOUNDS: WorldBounds = WorldBounds {
+    width: WIDTH,
+    height: HEIGHT,
+};
+/// How far the boy can run into a platform's side or jump into its underside
+/// and get away with a stumble or a bumped head rather than a knockout.
+const SHALLOW_COLLISION: i16 = 16;
+/// How far below a platform's top the boy's feet can clip its side as he
+/// falls past and still be put up on it, since he looks like he made it.
+const LANDING_FORGIVENESS: i16 = 20;
+/// How much faster than his running speed the boy has to go, as with a
+/// speed boost or a tailwind, for the tense music to play.
+const TENSE_SPEED_SCALE: f32 = 1.25;
+const REDUCED_MOTION_SCROLL_SPEED: f32 = 0.25;
+const DISTANCE_MILESTONE: u32 = 5000;
+/// How far below a ramp's surface the boy's feet can be and still be put
+/// back on it. Covers a frame of falling at terminal velocity.
+const RAMP_SNAP: i16 = 32;
+const RAMP_STRIP: i16 = 8;
+const RAMP_TILE: &str = "2.png";
+const SPRING_TILE: &str = "2.png";
+/// The grassy top of the spring tile, squashed down into the spring's pad.
+const SPRING_PAD_HEIGHT: i16 = 40;
+const PIT_TILE: &str = "5.png";
+const BEAM_TILE: &str = "5.png";
+/// How far below the top of the boy's sprite the top of his bounding box
+/// is at least while he slides or crouches, so he fits under anything he
+/// hits running, whichever frame of the slide he is on.
+const SLIDING_TOP: i16 = 40;
+/// How far above a segment's first obstacle its row of coins floats.
+const COIN_HEIGHT: i16 = 100;
+const COIN_ROW: i16 = 5;
+/// How many of the next segment's obstacles are built each frame ahead of
+/// it being needed.
+const OBSTACLES_PER_FRAME: usize = 2;
+/// The stream of the course seed that power-ups are rolled from, apart
+/// from the one that picks the segments.
+const POWER_UP_STREAM: u64 = 1;
+/// One segment in this many is followed by a magnet.
+const MAGNET_CHANCE: u32 = 8;
+/// Of the segments without a magnet, one in this many has a shield.
+const SHIELD_CHANCE: u32 = 10;
+/// Of the segments with neither, one in this many has a speed boost.
+const SPEED_CHANCE: u32 = 10;
+/// How long a speed boost lasts.
+const SPEED_BOOST_FRAMES: u16 = 300;
+/// How many times faster the boy runs while boosted.
+const SPEED_BOOST: f32 = 1.5;
+const POWER_UP_Y: i16 = 520;
+/// How long the boy can't be hurt after his shield breaks.
+const SHIELD_BREAK_FRAMES: u16 = 60;
+const SHIELD_MARGIN: i16 = 12;
+const COIN_POINTS: u32 = 100;
+/// Near misses in a row multiply their points, up to this many times.
+const MAX_COMBO: u32 = 5;
+/// How long the boy can run through whatever tripped him.
+const TRIP_FRAMES: u16 = 45;
+#[derive(Clone, Copy)]
 pub enum Event {
     Run,
     Jump,
     Slide,
-    KnockOut,
+    SlideHeld,
+    Crouch,
+    Stumble,
+    Trip,
+    BumpHead(i16),
+    Bounce,
+    ShieldBreak,
+    FastFall,
+    KnockOut(Cause),
     Land(i16),
+    Fidget(Fidget),
     Update,
 }
 
@@ -31,6 +157,31 @@ pub trait Obstacle {
     fn draw(&self, renderer: &Renderer);
     fn move_horizontally(&mut self, x: i16);
     fn right(&self) -> i16;
+    /// What to blame a knockout on.
+    fn name(&self) -> &str;
+    fn bounding_box(&self) -> Rect;
+
+    /// The height of the surface the boy can run along at `x`, for obstacles
+    /// whose top isn't a flat edge of their bounding box.
+    fn surface_at(&self, _x: i16) -> Option<i16> {
+        None
+    }
+
+    /// Whether the boy is meant to run over this rather than avoid it.
+    fn walkable(&self) -> bool {
+        self.surface_at(self.bounding_box().x()).is_some()
+    }
+
+    /// Scored for only just clearing this, before the combo multiplies them.
+    fn near_miss_points(&self) -> u32 {
+        0
+    }
+
+    /// What to build this again from, for snapshots. Only obstacles built
+    /// from the registry have it.
+    fn data(&self) -> Option<segments::ObstacleData> {
+        None
+    }
 }
 
 pub struct Platform {
@@ -92,10 +243,9 @@ impl Obstacle for Platform {
             x += sprite.frame.w;
         });
 
-        if cfg!(feature = "draw_debug_info") {
-            for bbox in self.bounding_boxes().iter() {
-                renderer.draw_rect(bbox);
-            }
+        for bbox in self.bounding_boxes().iter() {
+            renderer.draw_hazard(bbox);
+            renderer.draw_bounding_box(bbox);
         }
     }
 
@@ -107,15 +257,35 @@ impl Obstacle for Platform {
     }
 
     fn check_intersection(&self, boy: &mut RedHatBoy) {
-        if let Some(box_to_land_on) = self
+        // Falling, he is checked against all he fell through this frame, so
+        // he can't drop past a narrow box between one frame and the next.
+        if boy.velocity_y() >= 0.0 {
+            let fall_box = boy.fall_box();
+            if let Some(top) = self
+                .bounding_boxes()
+                .iter()
+                .filter(|&bounding_box| fall_box.intersects(bounding_box))
+                .map(Rect::y)
+                .find(|&top| boy.came_down_onto(top))
+            {
+                boy.land_on(top);
+                return;
+            }
+        }
+        let boy_box = boy.bounding_box();
+        if let Some(hit) = self
             .bounding_boxes()
             .iter()
-            .find(|&bounding_box| boy.bounding_box().intersects(bounding_box))
+            .find(|&bounding_box| boy_box.intersects(bounding_box))
         {
-            if boy.velocity_y() > 0 && boy.pos_y() < self.position.y {
-                boy.land_on(box_to_land_on.y());
+            if boy.velocity_y() >= 0.0 && boy.came_down_onto(hit.y() + LANDING_FORGIVENESS) {
+                boy.land_on(hit.y());
+            } else if boy.velocity_y() < 0.0 && hit.bottom() - boy_box.y() <= SHALLOW_COLLISION {
+                boy.bump_head(hit.bottom());
+            } else if boy_box.right() - hit.x() <= SHALLOW_COLLISION {
+                boy.stumble();
             } else {
-                boy.knock_out();
+                boy.knock_out(Cause::Bonked);
             }
         }
     }
@@ -126,27 +296,362 @@ impl Obstacle for Platform {
             .map(|b| b.right())
             .unwrap_or_default()
     }
+
+    fn name(&self) -> &str {
+        "platform"
+    }
+
+    fn bounding_box(&self) -> Rect {
+        let bottom = self
+            .bounding_boxes()
+            .iter()
+            .map(|b| b.bottom())
+            .max()
+            .unwrap_or(self.position.y);
+        Rect::new(
+            self.position,
+            self.right() - self.position.x,
+            bottom - self.position.y,
+        )
+    }
+}
+
+/// A sloped piece of ground. The boy runs up or down it with his feet on the
+/// slope, and can never be knocked out by it.
+pub struct Ramp {
+    sheet: Rc<SpriteSheet>,
+    tile: Option<Cell>,
+    position: Point,
+    width: i16,
+    height: i16,
+    ascending: bool,
+}
+
+impl Ramp {
+    pub fn new(
+        sheet: Rc<SpriteSheet>,
+        position: Point,
+        width: i16,
+        height: i16,
+        ascending: bool,
+    ) -> Self {
+        Ramp {
+            tile: sheet.cell(RAMP_TILE).cloned(),
+            sheet,
+            position,
+            width,
+            height,
+            ascending,
+        }
+    }
+
+    fn bottom(&self) -> i16 {
+        self.position.y + self.height
+    }
+}
+
+impl Obstacle for Ramp {
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        let boy_box = boy.bounding_box();
+        if let Some(surface) = self.surface_at(boy_box.center().x) {
+            let depth = boy_box.bottom() - surface;
+            if boy.velocity_y() >= 0.0 && (0..=RAMP_SNAP).contains(&depth) {
+                boy.land_on(surface);
+            }
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let Some(tile) = &self.tile else {
+            return;
+        };
+        for offset in (0..self.width).step_by(RAMP_STRIP as usize) {
+            let x = self.position.x + offset;
+            let Some(surface) = self.surface_at(x + RAMP_STRIP / 2) else {
+                continue;
+            };
+            let height = (self.bottom() - surface).min(tile.frame.h);
+            if height <= 0 {
+                continue;
+            }
+            self.sheet.draw(
+                renderer,
+                &Rect::new_from_x_y(
+                    tile.frame.x + offset % tile.frame.w,
+                    tile.frame.y,
+                    RAMP_STRIP,
+                    height,
+                ),
+                &Rect::new_from_x_y(x, surface, RAMP_STRIP, height),
+            );
+        }
+        renderer.draw_bounding_box(&self.bounding_box());
+    }
+
+    fn move_horizontally(&mut self, x: i16) {
+        self.position.x += x;
+    }
+
+    fn right(&self) -> i16 {
+        self.position.x + self.width
+    }
+
+    fn name(&self) -> &str {
+        "ramp"
+    }
+
+    fn bounding_box(&self) -> Rect {
+        Rect::new(self.position, self.width, self.height)
+    }
+
+    fn surface_at(&self, x: i16) -> Option<i16> {
+        let along = x - self.position.x;
+        if along < 0 || along >= self.width {
+            return None;
+        }
+        let top_left = self.position;
+        let bottom_left = Point {
+            x: self.position.x,
+            y: self.bottom(),
+        };
+        let (start, end) = if self.ascending {
+            (
+                bottom_left,
+                top_left
+                    + Point {
+                        x: self.width,
+                        y: 0,
+                    },
+            )
+        } else {
+            (
+                top_left,
+                bottom_left
+                    + Point {
+                        x: self.width,
+                        y: 0,
+                    },
+            )
+        };
+        Some(start.lerp(end, f32::from(along) / f32::from(self.width)).y)
+    }
+}
+
+/// A springboard on the ground. Landing on it, or running into it, bounces
+/// the boy up higher than a jump.
+pub struct Spring {
+    sheet: Rc<SpriteSheet>,
+    tile: Option<Cell>,
+    bounding_box: Rect,
+}
+
+impl Spring {
+    pub fn new(sheet: Rc<SpriteSheet>, bounding_box: Rect) -> Self {
+        Spring {
+            tile: sheet.cell(SPRING_TILE).cloned(),
+            sheet,
+            bounding_box,
+        }
+    }
+}
+
+impl Obstacle for Spring {
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if boy.velocity_y() >= 0.0 && boy.bounding_box().intersects(&self.bounding_box) {
+            boy.bounce();
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        if let Some(tile) = &self.tile {
+            self.sheet.draw(
+                renderer,
+                &Rect::new_from_x_y(tile.frame.x, tile.frame.y, tile.frame.w, SPRING_PAD_HEIGHT),
+                &self.bounding_box,
+            );
+        }
+        renderer.draw_bounding_box(&self.bounding_box);
+    }
+
+    fn move_horizontally(&mut self, x: i16) {
+        self.bounding_box.set_x(self.bounding_box.x() + x);
+    }
+
+    fn right(&self) -> i16 {
+        self.bounding_box.right()
+    }
+
+    fn name(&self) -> &str {
+        "spring"
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+}
+
+/// A gap in the ground. The boy falls into it unless he jumps over it or
+/// lands on something, and is knocked out once he drops out of sight.
+pub struct Pit {
+    sheet: Rc<SpriteSheet>,
+    tile: Option<Cell>,
+    bounding_box: Rect,
+}
+
+impl Pit {
+    pub fn new(sheet: Rc<SpriteSheet>, bounding_box: Rect) -> Self {
+        Pit {
+            tile: sheet.cell(PIT_TILE).cloned(),
+            sheet,
+            bounding_box,
+        }
+    }
+}
+
+impl Obstacle for Pit {
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        let boy_box = boy.bounding_box();
+        // The spot on the pit's opening right below the middle of the boy.
+        let below = Point {
+            x: boy_box.center().x,
+            y: self.bounding_box.y(),
+        };
+        if self.bounding_box.contains_point(below) {
+            boy.over_gap();
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        if let Some(tile) = &self.tile {
+            self.sheet.draw(
+                renderer,
+                &Rect::new_from_x_y(
+                    tile.frame.x,
+                    tile.frame.y,
+                    tile.frame.w,
+                    self.bounding_box.height,
+                ),
+                &self.bounding_box,
+            );
+        }
+        renderer.draw_hazard(&self.bounding_box);
+        renderer.draw_bounding_box(&self.bounding_box);
+    }
+
+    fn move_horizontally(&mut self, x: i16) {
+        self.bounding_box.set_x(self.bounding_box.x() + x);
+    }
+
+    fn right(&self) -> i16 {
+        self.bounding_box.right()
+    }
+
+    fn name(&self) -> &str {
+        "pit"
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+}
+
+/// A beam hanging down from out of sight, too low to run under and too tall
+/// to jump over. The boy gets past by sliding under it.
+pub struct Beam {
+    sheet: Rc<SpriteSheet>,
+    tile: Option<Cell>,
+    bounding_box: Rect,
+}
+
+impl Beam {
+    pub fn new(sheet: Rc<SpriteSheet>, bounding_box: Rect) -> Self {
+        Beam {
+            tile: sheet.cell(BEAM_TILE).cloned(),
+            sheet,
+            bounding_box,
+        }
+    }
+}
+
+impl Obstacle for Beam {
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        if boy.bounding_box().intersects(&self.bounding_box) {
+            boy.knock_out(Cause::Bonked);
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        if let Some(tile) = &self.tile {
+            // Stacked up from the bottom, so the end showing is a whole tile.
+            let mut y = self.bounding_box.bottom();
+            while y > self.bounding_box.y() {
+                y -= tile.frame.h;
+                self.sheet.draw(
+                    renderer,
+                    &tile.frame.into(),
+                    &Rect::new_from_x_y(
+                        self.bounding_box.x(),
+                        y,
+                        self.bounding_box.width,
+                        tile.frame.h,
+                    ),
+                );
+            }
+        }
+        renderer.draw_hazard(&self.bounding_box);
+        renderer.draw_bounding_box(&self.bounding_box);
+    }
+
+    fn move_horizontally(&mut self, x: i16) {
+        self.bounding_box.set_x(self.bounding_box.x() + x);
+    }
+
+    fn right(&self) -> i16 {
+        self.bounding_box.right()
+    }
+
+    fn name(&self) -> &str {
+        "beam"
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+}
+
+/// How badly running into an obstacle hurts.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Trips the boy up, slowing him down and breaking his combo.
+    Minor,
+    KnockOut,
 }
 
 pub struct Barrier {
     image: Image,
+    severity: Severity,
 }
 
 impl Barrier {
-    pub fn new(image: Image) -> Self {
-        Self { image }
+    pub fn new(image: Image, severity: Severity) -> Self {
+        Self { image, severity }
     }
 }
 
 impl Obstacle for Barrier {
     fn check_intersection(&self, boy: &mut RedHatBoy) {
         if boy.bounding_box().intersects(self.image.bounding_box()) {
-            boy.knock_out();
+            match self.severity {
+                Severity::Minor => boy.trip(),
+                Severity::KnockOut => boy.knock_out(Cause::Tripped),
+            }
         }
     }
 
     fn draw(&self, renderer: &Renderer) {
         self.image.draw(renderer);
+        renderer.draw_hazard(self.image.bounding_box());
     }
 
     fn move_horizontally(&mut self, x: i16) {
@@ -156,59 +661,269 @@ impl Obstacle for Barrier {
     fn right(&self) -> i16 {
         self.image.right()
     }
+
+    fn name(&self) -> &str {
+        "barrier"
+    }
+
+    fn bounding_box(&self) -> Rect {
+        *self.image.bounding_box()
+    }
+}
+
+/// Power-ups currently working on the boy.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+struct Effects {
+    /// Takes the next knockout in his place.
+    shield: bool,
+    /// Until he can be hurt again.
+    invulnerable: Timer,
+    /// Running faster.
+    boosted: Timer,
 }
 
 pub struct RedHatBoy {
     state_machine: RedHatBoyStateMachine,
     sprite_sheet: Sheet,
     image: HtmlImageElement,
+    /// God mode, from the developer cheats.
+    #[cfg(feature = "dev")]
+    invincible: bool,
+    effects: Effects,
+    events: Vec<GameEvent>,
+    skin: cosmetics::Skin,
 }
 
 impl RedHatBoy {
-    fn new(sheet: Sheet, image: HtmlImageElement, audio: Audio, jump_sound: Sound) -> Self {
+    fn new(
+        sheet: Sheet,
+        image: HtmlImageElement,
+        audio: Audio,
+        jump_sound: SoundGroup,
+        config: GameConfig,
+        ground: i16,
+    ) -> Self {
         RedHatBoy {
-            state_machine: RedHatBoyStateMachine::Idle(RedHatBoyState::new(audio, jump_sound)),
+            state_machine: RedHatBoyStateMachine::Idle(RedHatBoyState::new(
+                audio, jump_sound, config, ground,
+            )),
             sprite_sheet: sheet,
             image,
+            #[cfg(feature = "dev")]
+            invincible: false,
+            effects: Effects::default(),
+            events: vec![],
+            skin: cosmetics::Skin::default(),
+        }
+    }
+
+    fn transition(&mut self, event: Event) {
+        let next = self.state_machine.clone().transition(event);
+        if let Some(game_event) = self.state_machine.game_event(event, &next) {
+            self.events.push(game_event);
         }
+        self.state_machine = next;
+    }
+
+    fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
     }
 
     fn update(&mut self) {
-        self.state_machine = self.state_machine.clone().update();
+        self.effects.invulnerable.tick();
+        self.effects.boosted.tick();
+        self.transition(Event::Update);
+        // Checked here rather than by the gap he fell through, which may
+        // have scrolled away by the time he drops out of the world.
+        if self.bounding_box().y() > self.ground() {
+            self.fall_out();
+        }
     }
     fn run_right(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::Run);
+        self.transition(Event::Run);
+    }
+
+    /// Plays `fidget` once if he is standing still, then goes back to idling.
+    fn fidget(&mut self, fidget: Fidget) {
+        self.transition(Event::Fidget(fidget));
     }
 
     fn slide(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::Slide);
+        self.transition(Event::Slide);
+    }
+
+    fn hold_slide(&mut self) {
+        self.transition(Event::SlideHeld);
+    }
+
+    fn crouch(&mut self) {
+        self.transition(Event::Crouch);
     }
 
     fn jump(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::Jump);
+        self.transition(Event::Jump);
+    }
+
+    /// Drops him faster out of a jump, until he lands.
+    fn fast_fall(&mut self) {
+        self.transition(Event::FastFall);
+    }
+
+    /// Whether god mode is on, which only developer builds have.
+    #[cfg(feature = "dev")]
+    fn invincible(&self) -> bool {
+        self.invincible
+    }
+
+    #[cfg(not(feature = "dev"))]
+    fn invincible(&self) -> bool {
+        false
+    }
+
+    fn knock_out(&mut self, cause: Cause) {
+        if self.invincible() || !self.effects.invulnerable.finished() {
+            return;
+        }
+        if self.effects.shield {
+            self.effects = Effects {
+                shield: false,
+                invulnerable: Timer::new(SHIELD_BREAK_FRAMES),
+                ..self.effects
+            };
+            self.transition(Event::ShieldBreak);
+            self.events.push(GameEvent::ShieldBroken);
+        } else {
+            self.transition(Event::KnockOut(cause));
+        }
+    }
+
+    fn raise_shield(&mut self) {
+        self.effects.shield = true;
+    }
+
+    fn boost(&mut self) {
+        self.effects.boosted = Timer::new(SPEED_BOOST_FRAMES);
+    }
+
+    fn boosted(&self) -> bool {
+        !self.effects.boosted.finished()
+    }
+
+    fn shield_box(&self) -> Rect {
+        self.bounding_box().inflate(SHIELD_MARGIN)
+    }
+
+    fn over_gap(&mut self) {
+        self.state_machine.context_mut().over_gap = true;
+    }
+
+    /// Knocks the boy out for falling off the bottom of the world. Unlike
+    /// other knockouts, being invincible doesn't save him.
+    fn fall_out(&mut self) {
+        self.transition(Event::KnockOut(Cause::Fell));
     }
 
-    fn knock_out(&mut self) {
-        self.state_machine = self.state_machine.clone().transition(Event::KnockOut);
+    /// What knocked him out, once something has.
+    fn knockout_cause(&self) -> Option<Cause> {
+        self.state_machine.knockout_cause()
     }
 
     fn land_on(&mut self, position_y: i16) {
-        self.state_machine = self
-            .state_machine
-            .clone()
-            .transition(Event::Land(position_y));
+        self.transition(Event::Land(position_y));
+    }
+
+    fn stumble(&mut self) {
+        if !self.invincible() && self.effects.invulnerable.finished() {
+            self.transition(Event::Stumble);
+        }
+    }
+
+    /// A stumble that leaves him unhurt for a moment, so he can run on
+    /// through what tripped him instead of tripping over it again.
+    fn trip(&mut self) {
+        if self.invincible() || !self.effects.invulnerable.finished() {
+            return;
+        }
+        self.transition(Event::Trip);
+        if matches!(self.state_machine, RedHatBoyStateMachine::Stumbling(_)) {
+            self.effects.invulnerable = Timer::new(TRIP_FRAMES);
+        }
+    }
+
+    fn bounce(&mut self) {
+        self.transition(Event::Bounce);
+    }
+
+    fn bump_head(&mut self, ceiling: i16) {
+        let dy = ceiling - self.bounding_box().y();
+        self.transition(Event::BumpHead(dy));
+    }
+
+    fn pos_x(&self) -> i16 {
+        self.state_machine.context().position.round().x
     }
 
     fn pos_y(&self) -> i16 {
-        self.state_machine.context().position.y
+        self.state_machine.context().position.round().y
     }
 
-    fn velocity_y(&self) -> i16 {
+    fn velocity_y(&self) -> f32 {
         self.state_machine.context().velocity.y
     }
 
-    fn walking_speed(&self) -> i16 {
-        self.state_machine.context().velocity.x
+    /// His bounding box, stretched up over the distance he fell this frame.
+    fn fall_box(&self) -> Rect {
+        let mut fall_box = self.bounding_box();
+        let fallen = self.velocity_y().max(0.0).ceil() as i16;
+        fall_box.position.y -= fallen;
+        fall_box.height += fallen;
+        fall_box
+    }
+
+    /// Whether his feet were at or above `top` before this frame's fall, so
+    /// he came down onto it rather than running or jumping into its side.
+    fn came_down_onto(&self, top: i16) -> bool {
+        self.state_machine.context().feet_before_fall() <= f32::from(top) + 1.0
+    }
+
+    /// How fast the world scrolls under him, with everything that speeds
+    /// him up or slows him down, but never faster than he can run.
+    fn walking_speed(&self) -> f32 {
+        let context = self.state_machine.context();
+        let boost = if self.boosted() { SPEED_BOOST } else { 1.0 };
+        ((context.velocity.x + context.drift) * (1.0 - context.modifier.drag) * boost)
+            .min(MAX_RUNNING_SPEED)
+    }
+
+    fn set_modifier(&mut self, modifier: zones::Modifier) {
+        self.state_machine.context_mut().modifier = modifier;
+    }
+
+    fn set_force(&mut self, force: f32) {
+        self.state_machine.context_mut().force = force;
+    }
+
+    /// Steers him ahead (1.0) or back (-1.0) while he is in the air.
+    fn steer(&mut self, steering: f32) {
+        self.state_machine.context_mut().steering = steering;
+    }
+
+    fn set_skin(&mut self, skin: cosmetics::Skin) {
+        self.skin = skin;
+    }
+
+    #[cfg(feature = "dev")]
+    fn speed(&self) -> Speed {
+        self.state_machine.context().speed
+    }
+
+    /// Runs him `bonus` faster than the game's running speed, in place of
+    /// whatever was added before.
+    #[cfg(any(feature = "dev", test))]
+    fn set_speed_bonus(&mut self, bonus: f32) {
+        let context = self.state_machine.context_mut();
+        context.speed.bonus = bonus;
+        context.velocity.x = context.speed.value();
     }
 
     fn frame_name(&self) -> String {
@@ -225,7 +940,7 @@ impl RedHatBoy {
 
     fn destination_box(&self) -> Rect {
         let sprite = self.current_sprite().expect("Cell not found");
-        let pos = &self.state_machine.context().position;
+        let pos = self.state_machine.context().position.round();
         Rect::new_from_x_y(
             pos.x + sprite.sprite_source_size.x,
             pos.y + sprite.sprite_source_size.y,
@@ -243,30 +958,92 @@ impl RedHatBoy {
         bounding_box.width -= WIDTH_OFFSET;
         bounding_box.position.y += Y_OFFSET;
         bounding_box.height -= Y_OFFSET;
+        if self.ducking() {
+            let top = self.pos_y() + SLIDING_TOP;
+            if bounding_box.y() < top {
+                bounding_box.height -= top - bounding_box.y();
+                bounding_box.position.y = top;
+            }
+        }
         bounding_box
     }
 
+    fn ducking(&self) -> bool {
+        matches!(
+            self.state_machine,
+            RedHatBoyStateMachine::Sliding(_) | RedHatBoyStateMachine::Crouching(_)
+        )
+    }
+
     fn draw(&self, renderer: &Renderer) {
+        // Flickers while he can't be hurt.
+        if self.effects.invulnerable.remaining() % 8 >= 4 {
+            return;
+        }
         let sprite = self.current_sprite().expect("Cell not found");
 
-        renderer.draw_image(&self.image, &sprite.frame.into(), &self.destination_box());
-        if cfg!(feature = "draw_debug_info") {
-            renderer.draw_rect(&self.bounding_box());
-        }
+        renderer.draw_image_with_filter(
+            &self.image,
+            &sprite.frame.into(),
+            &self.destination_box(),
+            self.state_machine.opacity(),
+            self.skin.filter(),
+        );
+        renderer.draw_bounding_box(&self.bounding_box());
     }
 
-    fn knocked_out(&self) -> bool {
-        self.state_machine.knocked_out()
+    fn draw_ghost(&self, renderer: &Renderer, frame_name: &str, position: Point, alpha: f64) {
+        if let Some(sprite) = self.sprite_sheet.frames.get(frame_name) {
+            let destination = Rect::new_from_x_y(
+                position.x + sprite.sprite_source_size.x,
+                position.y + sprite.sprite_source_size.y,
+                sprite.frame.w,
+                sprite.frame.h,
+            );
+            renderer.draw_image_with_filter(
+                &self.image,
+                &sprite.frame.into(),
+                &destination,
+                alpha,
+                self.skin.filter(),
+            );
+        }
+    }
+
+    fn knocked_out(&self) -> bool {
+        self.state_machine.knocked_out()
     }
 
     fn reset(boy: Self) -> Self {
+        let mut new_boy = boy.twin();
+        #[cfg(feature = "dev")]
+        {
+            new_boy.invincible = boy.invincible;
+        }
+        new_boy.skin = boy.skin;
+        new_boy
+    }
+
+    #[cfg(feature = "dev")]
+    fn set_config(&mut self, config: GameConfig) {
+        self.state_machine.context_mut().config = config;
+    }
+
+    fn twin(&self) -> Self {
         RedHatBoy::new(
-            boy.sprite_sheet,
-            boy.image,
-            boy.state_machine.context().audio.clone(),
-            boy.state_machine.context().jump_sound.clone(),
+            self.sprite_sheet.clone(),
+            self.image.clone(),
+            self.state_machine.context().audio.clone(),
+            self.state_machine.context().jump_sound.clone(),
+            self.state_machine.context().config,
+            self.ground(),
         )
     }
+
+    /// The y of the ground he runs along when there's nothing under him.
+    fn ground(&self) -> i16 {
+        self.state_machine.context().ground
+    }
 }
 
 #[derive(Clone)]
@@ -274,6 +1051,8 @@ enum RedHatBoyStateMachine {
     Idle(RedHatBoyState<Idle>),
     Running(RedHatBoyState<Running>),
     Sliding(RedHatBoyState<Sliding>),
+    Crouching(RedHatBoyState<Crouching>),
+    Stumbling(RedHatBoyState<Stumbling>),
     Jumping(RedHatBoyState<Jumping>),
     Falling(RedHatBoyState<Falling>),
     KnockedOut(RedHatBoyState<KnockedOut>),
@@ -283,11 +1062,57 @@ impl RedHatBoyStateMachine {
     fn transition(self, event: Event) -> Self {
         match (self.clone(), event) {
             (RedHatBoyStateMachine::Idle(state), Event::Run) => state.run().into(),
+            (RedHatBoyStateMachine::Idle(state), Event::Fidget(fidget)) => {
+                state.fidget(fidget).into()
+            }
             (RedHatBoyStateMachine::Running(state), Event::Slide) => state.slide().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::SlideHeld) => state.hold().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::Crouch) => state.crouch().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::Crouch) => state.hold().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::Jump) => state.jump().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::Jump) => state.jump().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::KnockOut(cause)) => {
+                state.knock_out(cause).into()
+            }
+            (RedHatBoyStateMachine::Crouching(state), Event::Land(y)) => state.land_on(y).into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::Update) => state.update().into(),
+            (RedHatBoyStateMachine::Running(state), Event::Stumble) => state.stumble().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::Stumble) => state.stumble().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::Stumble) => state.stumble().into(),
+            (RedHatBoyStateMachine::Running(state), Event::ShieldBreak) => state.stumble().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::ShieldBreak) => state.stumble().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::ShieldBreak) => state.stumble().into(),
+            (RedHatBoyStateMachine::Jumping(state), Event::ShieldBreak) => state.stumble().into(),
+            (RedHatBoyStateMachine::Running(state), Event::Trip) => state.stumble().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::Trip) => state.stumble().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::Trip) => state.stumble().into(),
+            (RedHatBoyStateMachine::Jumping(state), Event::Stumble) => {
+                state.knock_out(Cause::Tripped).into()
+            }
+            (RedHatBoyStateMachine::Running(state), Event::Bounce) => state.bounce().into(),
+            (RedHatBoyStateMachine::Sliding(state), Event::Bounce) => state.bounce().into(),
+            (RedHatBoyStateMachine::Crouching(state), Event::Bounce) => state.bounce().into(),
+            (RedHatBoyStateMachine::Stumbling(state), Event::Bounce) => state.bounce().into(),
+            (RedHatBoyStateMachine::Jumping(state), Event::Bounce) => state.bounce().into(),
+            (RedHatBoyStateMachine::Jumping(state), Event::FastFall) => state.fast_fall().into(),
+            (RedHatBoyStateMachine::Jumping(state), Event::BumpHead(dy)) => {
+                state.bump_head(dy).into()
+            }
+            (RedHatBoyStateMachine::Stumbling(state), Event::KnockOut(cause)) => {
+                state.knock_out(cause).into()
+            }
+            (RedHatBoyStateMachine::Stumbling(state), Event::Land(y)) => state.land_on(y).into(),
+            (RedHatBoyStateMachine::Stumbling(state), Event::Update) => state.update().into(),
             (RedHatBoyStateMachine::Running(state), Event::Jump) => state.jump().into(),
-            (RedHatBoyStateMachine::Running(state), Event::KnockOut) => state.knock_out().into(),
-            (RedHatBoyStateMachine::Jumping(state), Event::KnockOut) => state.knock_out().into(),
-            (RedHatBoyStateMachine::Sliding(state), Event::KnockOut) => state.knock_out().into(),
+            (RedHatBoyStateMachine::Running(state), Event::KnockOut(cause)) => {
+                state.knock_out(cause).into()
+            }
+            (RedHatBoyStateMachine::Jumping(state), Event::KnockOut(cause)) => {
+                state.knock_out(cause).into()
+            }
+            (RedHatBoyStateMachine::Sliding(state), Event::KnockOut(cause)) => {
+                state.knock_out(cause).into()
+            }
             (RedHatBoyStateMachine::Running(state), Event::Land(y)) => state.land_on(y).into(),
             (RedHatBoyStateMachine::Sliding(state), Event::Land(y)) => state.land_on(y).into(),
             (RedHatBoyStateMachine::Jumping(state), Event::Land(y)) => state.land_on(y).into(),
@@ -296,6 +1121,7 @@ impl RedHatBoyStateMachine {
             (RedHatBoyStateMachine::Sliding(state), Event::Update) => state.update().into(),
             (RedHatBoyStateMachine::Jumping(state), Event::Update) => state.update().into(),
             (RedHatBoyStateMachine::Falling(state), Event::Update) => state.update().into(),
+            (RedHatBoyStateMachine::KnockedOut(state), Event::Update) => state.update().into(),
             _ => self,
         }
     }
@@ -304,6 +1130,8 @@ impl RedHatBoyStateMachine {
             RedHatBoyStateMachine::Idle(state) => state.frame_name(),
             RedHatBoyStateMachine::Running(state) => state.frame_name(),
             RedHatBoyStateMachine::Sliding(state) => state.frame_name(),
+            RedHatBoyStateMachine::Crouching(state) => state.frame_name(),
+            RedHatBoyStateMachine::Stumbling(state) => state.frame_name(),
             RedHatBoyStateMachine::Jumping(state) => state.frame_name(),
             RedHatBoyStateMachine::Falling(state) => state.frame_name(),
             RedHatBoyStateMachine::KnockedOut(state) => state.frame_name(),
@@ -315,18 +1143,71 @@ impl RedHatBoyStateMachine {
             RedHatBoyStateMachine::Idle(state) => state.context(),
             RedHatBoyStateMachine::Running(state) => state.context(),
             RedHatBoyStateMachine::Sliding(state) => state.context(),
+            RedHatBoyStateMachine::Crouching(state) => state.context(),
+            RedHatBoyStateMachine::Stumbling(state) => state.context(),
             RedHatBoyStateMachine::Jumping(state) => state.context(),
             RedHatBoyStateMachine::Falling(state) => state.context(),
             RedHatBoyStateMachine::KnockedOut(state) => state.context(),
         }
     }
 
-    fn update(self) -> Self {
-        self.transition(Event::Update)
+    fn context_mut(&mut self) -> &mut RedHatBoyContext {
+        match self {
+            RedHatBoyStateMachine::Idle(state) => state.context_mut(),
+            RedHatBoyStateMachine::Running(state) => state.context_mut(),
+            RedHatBoyStateMachine::Sliding(state) => state.context_mut(),
+            RedHatBoyStateMachine::Crouching(state) => state.context_mut(),
+            RedHatBoyStateMachine::Stumbling(state) => state.context_mut(),
+            RedHatBoyStateMachine::Jumping(state) => state.context_mut(),
+            RedHatBoyStateMachine::Falling(state) => state.context_mut(),
+            RedHatBoyStateMachine::KnockedOut(state) => state.context_mut(),
+        }
     }
 
+    /// Whether he has been down long enough for the run to be over.
     fn knocked_out(&self) -> bool {
-        matches!(self, RedHatBoyStateMachine::KnockedOut(_))
+        matches!(self, RedHatBoyStateMachine::KnockedOut(state) if state.out_for_the_count())
+    }
+
+    fn knockout_cause(&self) -> Option<Cause> {
+        match self {
+            RedHatBoyStateMachine::Falling(state) => Some(state.cause()),
+            RedHatBoyStateMachine::KnockedOut(state) => Some(state.cause()),
+            _ => None,
+        }
+    }
+
+    /// How solidly he is drawn, which drops as he lies knocked out.
+    fn opacity(&self) -> f64 {
+        match self {
+            RedHatBoyStateMachine::KnockedOut(state) => state.opacity(),
+            _ => 1.0,
+        }
+    }
+
+    fn game_event(&self, event: Event, next: &Self) -> Option<GameEvent> {
+        if let (Event::Bounce, RedHatBoyStateMachine::Jumping(_)) = (event, next) {
+            return Some(GameEvent::Bounced);
+        }
+        match (self, next) {
+            (
+                RedHatBoyStateMachine::Running(_)
+                | RedHatBoyStateMachine::Sliding(_)
+                | RedHatBoyStateMachine::Crouching(_),
+                RedHatBoyStateMachine::Jumping(_),
+            ) => Some(GameEvent::Jumped),
+            (RedHatBoyStateMachine::Jumping(_), RedHatBoyStateMachine::Running(_)) => {
+                Some(GameEvent::Landed)
+            }
+            (RedHatBoyStateMachine::Running(_), RedHatBoyStateMachine::Sliding(_)) => {
+                Some(GameEvent::SlideStarted)
+            }
+            (RedHatBoyStateMachine::Stumbling(_), RedHatBoyStateMachine::Stumbling(_)) => None,
+            (_, RedHatBoyStateMachine::Stumbling(_)) => Some(GameEvent::Stumbled),
+            (RedHatBoyStateMachine::Falling(_), _) => None,
+            (_, RedHatBoyStateMachine::Falling(_)) => Some(GameEvent::KnockedOut),
+            _ => None,
+        }
     }
 }
 
@@ -348,6 +1229,18 @@ impl From<RedHatBoyState<Sliding>> for RedHatBoyStateMachine {
     }
 }
 
+impl From<RedHatBoyState<Crouching>> for RedHatBoyStateMachine {
+    fn from(value: RedHatBoyState<Crouching>) -> Self {
+        RedHatBoyStateMachine::Crouching(value)
+    }
+}
+
+impl From<RedHatBoyState<Stumbling>> for RedHatBoyStateMachine {
+    fn from(value: RedHatBoyState<Stumbling>) -> Self {
+        RedHatBoyStateMachine::Stumbling(value)
+    }
+}
+
 impl From<RedHatBoyState<Jumping>> for RedHatBoyStateMachine {
     fn from(value: RedHatBoyState<Jumping>) -> Self {
         RedHatBoyStateMachine::Jumping(value)
@@ -371,6 +1264,16 @@ impl From<SlidingEndState> for RedHatBoyStateMachine {
         match value {
             SlidingEndState::Complete(running_state) => running_state.into(),
             SlidingEndState::Sliding(sliding_state) => sliding_state.into(),
+            SlidingEndState::Crouching(crouching_state) => crouching_state.into(),
+        }
+    }
+}
+
+impl From<CrouchingEndState> for RedHatBoyStateMachine {
+    fn from(value: CrouchingEndState) -> Self {
+        match value {
+            CrouchingEndState::Complete(running_state) => running_state.into(),
+            CrouchingEndState::Crouching(crouching_state) => crouching_state.into(),
         }
     }
 }
@@ -384,6 +1287,15 @@ impl From<JumpingEndState> for RedHatBoyStateMachine {
     }
 }
 
+impl From<StumblingEndState> for RedHatBoyStateMachine {
+    fn from(value: StumblingEndState) -> Self {
+        match value {
+            StumblingEndState::Complete(running_state) => running_state.into(),
+            StumblingEndState::Stumbling(stumbling_state) => stumbling_state.into(),
+        }
+    }
+}
+
 impl From<FallingEndState> for RedHatBoyStateMachine {
     fn from(value: FallingEndState) -> Self {
         match value {
@@ -394,11 +1306,12 @@ impl From<FallingEndState> for RedHatBoyStateMachine {
 }
 
 mod red_hat_boy_states {
-    use super::HEIGHT;
-    use crate::engine::{Audio, Point, Sound};
+    use serde::{Deserialize, Serialize};
 
-    const FLOOR: i16 = 479;
-    const PLAYER_HEIGHT: i16 = HEIGHT - FLOOR;
+    use super::{zones::Modifier, GameConfig, RedHatBoyStateMachine};
+    use crate::engine::{Audio, Physics, SoundGroup, Vector};
+
+    const PLAYER_HEIGHT: i16 = 121;
 
     const IDLE_FRAME_NAME: &str = "Idle";
     const RUN_FRAME_NAME: &str = "Run";
@@ -412,10 +1325,23 @@ mod red_hat_boy_states {
     const SLIDING_FRAMES: u8 = 14;
     const JUMPING_FRAMES: u8 = 35;
     const FALLING_FRAMES: u8 = 29;
-    const RUNNING_SPEED: i16 = 4;
-    const JUMP_SPEED: i16 = -25;
-    const GRAVITY: i16 = 1;
-    const TERMINAL_VELOCITY: i16 = 20;
+    const BOUNCE_SPEED: f32 = -30.0;
+    const STUMBLE_FRAMES: u8 = 8;
+    const STUMBLE_SPEED: f32 = -3.0;
+    /// How much of the speed pushed into him in the air he keeps each frame
+    /// he runs after landing.
+    const DRIFT_FALLOFF: f32 = 0.9;
+    const MIN_DRIFT: f32 = 0.01;
+    /// How much steering in the air changes his speed each frame.
+    const AIR_CONTROL: f32 = 0.15;
+    /// The most speed steering in the air can add or take away.
+    const MAX_AIR_CONTROL: f32 = 1.5;
+    /// The fastest he can run, whatever is added to his speed.
+    pub const MAX_RUNNING_SPEED: f32 = 16.0;
+    /// How fast a fast-fall drops him straight away, at least.
+    const FAST_FALL_SPEED: f32 = 10.0;
+    /// How much harder gravity pulls him down in a fast-fall.
+    const FAST_FALL_GRAVITY: f32 = 2.5;
 
     #[derive(Clone)]
     pub struct RedHatBoyState<S> {
@@ -428,38 +1354,111 @@ mod red_hat_boy_states {
             &self.context
         }
 
+        pub fn context_mut(&mut self) -> &mut RedHatBoyContext {
+            &mut self.context
+        }
+
         pub fn update_context(&mut self, frames: u8) {
             let ctx = self.context.clone().update(frames);
             self.context = ctx;
         }
+
+        /// Launches the boy off a spring, higher than he can jump. The
+        /// transition table decides which states can be bounced from.
+        pub fn bounce(mut self) -> RedHatBoyState<Jumping> {
+            self.context.gravity_scale = 1.0;
+            RedHatBoyState {
+                context: self
+                    .context
+                    .set_vertical_velocity(BOUNCE_SPEED)
+                    .reset_frame(),
+                _state: Jumping {},
+            }
+        }
     }
 
     #[derive(Clone)]
     pub struct RedHatBoyContext {
         pub frame: u8,
-        pub position: Point,
-        pub velocity: Point,
+        pub position: Vector,
+        pub velocity: Vector,
+        /// Set by a gap in the ground the boy is over this frame, so the
+        /// next update doesn't stop him at the floor.
+        pub over_gap: bool,
+        /// Whether he is below the floor, dropping through a gap.
+        pub dropping: bool,
         pub audio: Audio,
-        pub jump_sound: Sound,
+        pub jump_sound: SoundGroup,
+        pub config: GameConfig,
+        /// The y of the bottom of the world, which he runs along when
+        /// there's nothing under him.
+        pub ground: i16,
+        /// How the zone the boy is in changes the way he moves.
+        pub modifier: Modifier,
+        /// A push across the screen, such as a gust of wind, that speeds
+        /// him up or slows him down while he is in the air.
+        pub force: f32,
+        /// The speed across the screen the force has added in this jump.
+        pub drift: f32,
+        /// Which way the player is steering him this frame: 1.0 ahead, -1.0
+        /// back, or 0.0. Only heeded in the air.
+        pub steering: f32,
+        /// How much harder than usual gravity pulls him, on top of the zone
+        /// he is in. Raised by a fast-fall until he lands.
+        pub gravity_scale: f32,
+        /// How fast he runs when nothing is slowing him.
+        pub speed: Speed,
+    }
+
+    /// How fast the boy runs: the game's running speed and whatever is added
+    /// on top. It is kept apart from his velocity, which a stumble or a
+    /// knockout changes for a while, and is set rather than added to, so
+    /// starting a run again can't stack speed up.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct Speed {
+        pub base: f32,
+        pub bonus: f32,
+    }
+
+    impl Speed {
+        pub fn value(self) -> f32 {
+            (self.base + self.bonus).clamp(0.0, MAX_RUNNING_SPEED)
+        }
     }
 
     impl RedHatBoyContext {
         pub fn update(mut self, frame_count: u8) -> Self {
-            if self.velocity.y < TERMINAL_VELOCITY {
-                self.velocity.y += GRAVITY;
-            }
-
             if self.frame < frame_count {
                 self.frame += 1;
             } else {
                 self.frame = 0;
             }
 
-            self.position.y += self.velocity.y;
+            let floor = f32::from(self.floor());
+            let above_floor = self.position.y <= floor;
+            // The boy stays put across the screen. Running scrolls the world
+            // instead, so only his vertical velocity moves him.
+            let physics = Physics {
+                gravity_scale: self.modifier.gravity_scale * self.gravity_scale,
+                ..self.config.physics()
+            };
+            let (position, velocity) = physics.step(
+                self.position,
+                Vector {
+                    x: 0.0,
+                    y: self.velocity.y,
+                },
+            );
+            self.position = position;
+            self.velocity.y = velocity.y;
 
-            if self.position.y > FLOOR {
-                self.position.y = FLOOR;
+            // Once the boy drops below the floor through a gap he keeps
+            // falling, even after the gap has scrolled past him.
+            if self.position.y > floor && above_floor && !self.over_gap {
+                self.position.y = floor;
             }
+            self.dropping = self.position.y > floor;
+            self.over_gap = false;
 
             self
         }
@@ -470,64 +1469,176 @@ mod red_hat_boy_states {
         }
 
         fn run_right(mut self) -> Self {
-            self.velocity.x += RUNNING_SPEED;
+            self.speed.base = self.config.running_speed;
+            self.velocity.x = self.speed.value();
             self
         }
 
-        fn set_vertical_velocity(mut self, y: i16) -> Self {
+        fn set_vertical_velocity(mut self, y: f32) -> Self {
             self.velocity.y = y;
             self
         }
 
+        fn set_horizontal_velocity(mut self, x: f32) -> Self {
+            self.velocity.x = x;
+            self
+        }
+
+        fn move_vertically(mut self, dy: i16) -> Self {
+            self.position.y += f32::from(dy);
+            self
+        }
+
         fn stop(mut self) -> Self {
-            self.velocity.x = 0;
-            self.velocity.y = 0;
+            self.velocity.x = 0.0;
+            self.velocity.y = 0.0;
+            self.drift = 0.0;
+            self.gravity_scale = 1.0;
             self
         }
 
+        /// Puts the boy on the ground at `position`. He keeps the speed
+        /// whatever pushed him along in the air gave him, so a chain of hops
+        /// isn't slowed at every landing, and it wears off as he runs.
         fn set_on(mut self, position: i16) -> Self {
             let position = position - PLAYER_HEIGHT;
-            self.position.y = position;
+            self.position.y = f32::from(position);
+            self.gravity_scale = 1.0;
+            self
+        }
+
+        fn coast(mut self) -> Self {
+            self.drift *= DRIFT_FALLOFF;
+            if self.drift.abs() < MIN_DRIFT {
+                self.drift = 0.0;
+            }
+            self
+        }
+
+        /// Where the top of him is when he stands on the ground.
+        fn floor(&self) -> i16 {
+            self.ground - PLAYER_HEIGHT
+        }
+
+        /// Whether he has come down onto the ground, rather than through a
+        /// gap in it.
+        fn on_floor(&self) -> bool {
+            self.position.y >= f32::from(self.floor()) && !self.dropping
+        }
+
+        /// Where his feet were before this frame moved him.
+        pub fn feet_before_fall(&self) -> f32 {
+            self.position.y + f32::from(PLAYER_HEIGHT) - self.velocity.y
+        }
+
+        fn push(mut self) -> Self {
+            self.drift += self.force;
+            self
+        }
+
+        /// Nudges his speed the way he is being steered, up to a limit. A
+        /// push from the wind past the limit isn't undone by steering with it.
+        fn steer(mut self) -> Self {
+            let drift = self.drift + self.steering * AIR_CONTROL;
+            if drift.abs() <= MAX_AIR_CONTROL || drift.abs() < self.drift.abs() {
+                self.drift = drift;
+            }
             self
         }
 
         fn play_jump_sound(self) -> Self {
-            if let Err(err) = self.audio.play_sound(&self.jump_sound) {
+            if let Err(err) = self.audio.play_sound_group(&self.jump_sound) {
                 log::error!("Error playing jump sound {:#?}", err);
             }
             self
         }
     }
 
-    #[derive(Clone, Copy)]
-    pub struct Idle;
+    /// Something the boy does now and then while he waits to start, so he
+    /// isn't only ever standing still.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Fidget {
+        /// A little hop on the spot.
+        Hop,
+        /// Jogging on the spot.
+        Jog,
+    }
+
+    impl Fidget {
+        fn frame_name(self) -> &'static str {
+            match self {
+                Fidget::Hop => JUMPING_FRAME_NAME,
+                Fidget::Jog => RUN_FRAME_NAME,
+            }
+        }
+
+        fn frames(self) -> u8 {
+            match self {
+                Fidget::Hop => JUMPING_FRAMES,
+                Fidget::Jog => RUNNING_FRAMES,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Default)]
+    pub struct Idle {
+        fidget: Option<Fidget>,
+    }
 
     impl RedHatBoyState<Idle> {
-        pub fn new(audio: Audio, jump_sound: Sound) -> Self {
+        pub fn new(audio: Audio, jump_sound: SoundGroup, config: GameConfig, ground: i16) -> Self {
             RedHatBoyState {
                 context: RedHatBoyContext {
                     frame: 0,
-                    position: Point {
-                        x: STARTING_POINT,
-                        y: FLOOR,
+                    position: Vector {
+                        x: f32::from(STARTING_POINT),
+                        y: f32::from(ground - PLAYER_HEIGHT),
                     },
-                    velocity: Point::default(),
+                    velocity: Vector::default(),
+                    over_gap: false,
+                    dropping: false,
                     audio,
                     jump_sound,
+                    config,
+                    ground,
+                    modifier: Modifier::default(),
+                    force: 0.0,
+                    drift: 0.0,
+                    steering: 0.0,
+                    gravity_scale: 1.0,
+                    speed: Speed::default(),
                 },
-                _state: Idle {},
+                _state: Idle::default(),
             }
         }
 
         pub fn frame_name(&self) -> &str {
-            IDLE_FRAME_NAME
+            self._state
+                .fidget
+                .map_or(IDLE_FRAME_NAME, Fidget::frame_name)
         }
 
         pub fn update(mut self) -> Self {
-            self.update_context(IDLE_FRAMES);
+            self.update_context(self._state.fidget.map_or(IDLE_FRAMES, Fidget::frames));
+            // A fidget plays once, and he idles again when it loops.
+            if self.context.frame == 0 {
+                self._state.fidget = None;
+            }
             self
         }
 
+        pub fn fidget(self, fidget: Fidget) -> Self {
+            if self._state.fidget.is_some() {
+                return self;
+            }
+            RedHatBoyState {
+                context: self.context.reset_frame(),
+                _state: Idle {
+                    fidget: Some(fidget),
+                },
+            }
+        }
+
         pub fn run(self) -> RedHatBoyState<Running> {
             RedHatBoyState {
                 context: self.context.reset_frame().run_right(),
@@ -546,31 +1657,40 @@ mod red_hat_boy_states {
 
         pub fn update(mut self) -> Self {
             self.update_context(RUNNING_FRAMES);
+            self.context = self.context.coast();
             self
         }
 
         pub fn slide(self) -> RedHatBoyState<Sliding> {
             RedHatBoyState {
                 context: self.context.reset_frame(),
-                _state: Sliding {},
+                _state: Sliding {
+                    held: false,
+                    crouching: false,
+                },
             }
         }
 
         pub fn jump(self) -> RedHatBoyState<Jumping> {
+            let jump_speed = self.context.config.jump_speed * self.context.modifier.jump_scale;
             RedHatBoyState {
                 context: self
                     .context
-                    .set_vertical_velocity(JUMP_SPEED)
+                    .set_vertical_velocity(jump_speed)
                     .reset_frame()
                     .play_jump_sound(),
                 _state: Jumping {},
             }
         }
 
-        pub fn knock_out(self) -> RedHatBoyState<Falling> {
+        pub fn knock_out(self, cause: Cause) -> RedHatBoyState<Falling> {
             RedHatBoyState {
-                context: self.context.reset_frame().stop(),
-                _state: Falling {},
+                context: self
+                    .context
+                    .reset_frame()
+                    .stop()
+                    .set_vertical_velocity(cause.recoil()),
+                _state: Falling { cause },
             }
         }
 
@@ -580,14 +1700,25 @@ mod red_hat_boy_states {
                 _state: Running {},
             }
         }
+
+        pub fn stumble(self) -> RedHatBoyState<Stumbling> {
+            RedHatBoyState::stumble_from(self.context)
+        }
     }
 
+    /// `held` is set by a SlideHeld event and `crouching` by a Crouch event,
+    /// and both last for one update. A held slide stays down on its last frame
+    /// once the animation has played, while a crouching one ends in a crouch.
     #[derive(Clone, Copy)]
-    pub struct Sliding;
+    pub struct Sliding {
+        held: bool,
+        crouching: bool,
+    }
 
     pub enum SlidingEndState {
         Complete(RedHatBoyState<Running>),
         Sliding(RedHatBoyState<Sliding>),
+        Crouching(RedHatBoyState<Crouching>),
     }
 
     impl RedHatBoyState<Sliding> {
@@ -595,12 +1726,46 @@ mod red_hat_boy_states {
             SLIDING_FRAME_NAME
         }
         pub fn update(mut self) -> SlidingEndState {
+            let held = std::mem::take(&mut self._state.held);
+            let crouching = std::mem::take(&mut self._state.crouching);
             self.update_context(SLIDING_FRAMES);
 
-            if self.context.frame >= SLIDING_FRAMES {
-                SlidingEndState::Complete(self.stand())
-            } else {
+            if self.context.frame < SLIDING_FRAMES {
                 SlidingEndState::Sliding(self)
+            } else if held {
+                self.context.frame = SLIDING_FRAMES - 1;
+                SlidingEndState::Sliding(self)
+            } else if crouching {
+                SlidingEndState::Crouching(self.crouch_down())
+            } else {
+                SlidingEndState::Complete(self.stand())
+            }
+        }
+
+        pub fn hold(mut self) -> Self {
+            self._state.held = true;
+            self
+        }
+
+        pub fn crouch(mut self) -> Self {
+            self._state.crouching = true;
+            self
+        }
+
+        pub fn stumble(self) -> RedHatBoyState<Stumbling> {
+            RedHatBoyState::stumble_from(self.context)
+        }
+
+        /// Cuts the slide short to jump, standing up to his full height as
+        /// he leaves the ground.
+        pub fn jump(self) -> RedHatBoyState<Jumping> {
+            self.stand().jump()
+        }
+
+        fn crouch_down(self) -> RedHatBoyState<Crouching> {
+            RedHatBoyState {
+                context: self.context,
+                _state: Crouching { held: false },
             }
         }
 
@@ -610,16 +1775,148 @@ mod red_hat_boy_states {
                 _state: Running {},
             }
         }
-        pub fn knock_out(self) -> RedHatBoyState<Falling> {
+        pub fn knock_out(self, cause: Cause) -> RedHatBoyState<Falling> {
             RedHatBoyState {
-                context: self.context.reset_frame().stop(),
-                _state: Falling {},
+                context: self
+                    .context
+                    .reset_frame()
+                    .stop()
+                    .set_vertical_velocity(cause.recoil()),
+                _state: Falling { cause },
             }
         }
         pub fn land_on(self, y: i16) -> RedHatBoyState<Sliding> {
             RedHatBoyState {
                 context: self.context.set_on(y),
-                _state: Sliding {},
+                _state: self._state,
+            }
+        }
+    }
+
+    /// Walking along in the last pose of the slide, which keeps the hitbox as
+    /// low as a slide's. `held` is set by a Crouch event and lasts for one
+    /// update, so the boy stands up as soon as the key is let go.
+    #[derive(Clone, Copy)]
+    pub struct Crouching {
+        held: bool,
+    }
+
+    pub enum CrouchingEndState {
+        Complete(RedHatBoyState<Running>),
+        Crouching(RedHatBoyState<Crouching>),
+    }
+
+    impl RedHatBoyState<Crouching> {
+        pub fn frame_name(&self) -> &str {
+            SLIDING_FRAME_NAME
+        }
+
+        pub fn update(mut self) -> CrouchingEndState {
+            let held = std::mem::take(&mut self._state.held);
+            self.update_context(SLIDING_FRAMES);
+            self.context.frame = SLIDING_FRAMES - 1;
+
+            if held {
+                CrouchingEndState::Crouching(self)
+            } else {
+                CrouchingEndState::Complete(self.stand())
+            }
+        }
+
+        pub fn hold(mut self) -> Self {
+            self._state.held = true;
+            self
+        }
+
+        pub fn stand(self) -> RedHatBoyState<Running> {
+            RedHatBoyState {
+                context: self.context.reset_frame(),
+                _state: Running {},
+            }
+        }
+
+        pub fn jump(self) -> RedHatBoyState<Jumping> {
+            self.stand().jump()
+        }
+
+        pub fn knock_out(self, cause: Cause) -> RedHatBoyState<Falling> {
+            RedHatBoyState {
+                context: self
+                    .context
+                    .reset_frame()
+                    .stop()
+                    .set_vertical_velocity(cause.recoil()),
+                _state: Falling { cause },
+            }
+        }
+
+        pub fn land_on(self, y: i16) -> RedHatBoyState<Crouching> {
+            RedHatBoyState {
+                context: self.context.set_on(y),
+                _state: self._state,
+            }
+        }
+
+        pub fn stumble(self) -> RedHatBoyState<Stumbling> {
+            RedHatBoyState::stumble_from(self.context)
+        }
+    }
+
+    /// Knocked back off a wall. The world scrolls backwards for a moment and
+    /// then the boy runs on at his running speed.
+    #[derive(Clone, Copy)]
+    pub struct Stumbling;
+
+    pub enum StumblingEndState {
+        Complete(RedHatBoyState<Running>),
+        Stumbling(RedHatBoyState<Stumbling>),
+    }
+
+    impl RedHatBoyState<Stumbling> {
+        fn stumble_from(context: RedHatBoyContext) -> Self {
+            RedHatBoyState {
+                context: context.reset_frame().set_horizontal_velocity(STUMBLE_SPEED),
+                _state: Stumbling,
+            }
+        }
+
+        pub fn frame_name(&self) -> &str {
+            FALLING_FRAME_NAME
+        }
+
+        pub fn update(mut self) -> StumblingEndState {
+            self.update_context(STUMBLE_FRAMES);
+
+            if self.context.frame >= STUMBLE_FRAMES {
+                StumblingEndState::Complete(self.recover())
+            } else {
+                StumblingEndState::Stumbling(self)
+            }
+        }
+
+        fn recover(self) -> RedHatBoyState<Running> {
+            let speed = self.context.speed.value();
+            RedHatBoyState {
+                context: self.context.reset_frame().set_horizontal_velocity(speed),
+                _state: Running {},
+            }
+        }
+
+        pub fn knock_out(self, cause: Cause) -> RedHatBoyState<Falling> {
+            RedHatBoyState {
+                context: self
+                    .context
+                    .reset_frame()
+                    .stop()
+                    .set_vertical_velocity(cause.recoil()),
+                _state: Falling { cause },
+            }
+        }
+
+        pub fn land_on(self, y: i16) -> RedHatBoyState<Stumbling> {
+            RedHatBoyState {
+                context: self.context.set_on(y),
+                _state: self._state,
             }
         }
     }
@@ -637,30 +1934,108 @@ mod red_hat_boy_states {
             JUMPING_FRAME_NAME
         }
         pub fn update(mut self) -> JumpingEndState {
+            self.context = self.context.push().steer();
             self.update_context(JUMPING_FRAMES);
 
-            if self.context.position.y >= FLOOR {
-                JumpingEndState::Complete(self.land_on(HEIGHT))
+            if self.context.on_floor() {
+                let ground = self.context.ground;
+                JumpingEndState::Complete(self.land_on(ground))
             } else {
                 JumpingEndState::Jumping(self)
             }
         }
-        pub fn knock_out(self) -> RedHatBoyState<Falling> {
+        pub fn knock_out(self, cause: Cause) -> RedHatBoyState<Falling> {
             RedHatBoyState {
-                context: self.context.reset_frame().stop(),
-                _state: Falling {},
+                context: self
+                    .context
+                    .reset_frame()
+                    .stop()
+                    .set_vertical_velocity(cause.recoil()),
+                _state: Falling { cause },
             }
         }
+        /// Knocked back in the air, when his shield takes a hit. He drops
+        /// as he stumbles, and lands as he would from a jump.
+        pub fn stumble(mut self) -> RedHatBoyState<Stumbling> {
+            self.context.gravity_scale = 1.0;
+            RedHatBoyState::stumble_from(self.context)
+        }
         pub fn land_on(self, y: i16) -> RedHatBoyState<Running> {
             RedHatBoyState {
                 context: self.context.reset_frame().set_on(y),
                 _state: Running {},
             }
         }
+
+        /// Drops him at least as fast as a fast-fall and makes gravity pull
+        /// harder until he lands.
+        pub fn fast_fall(mut self) -> RedHatBoyState<Jumping> {
+            self.context.velocity.y = self.context.velocity.y.max(FAST_FALL_SPEED);
+            self.context.gravity_scale = FAST_FALL_GRAVITY;
+            self
+        }
+
+        /// Moves the boy `dy` down out of whatever he jumped into and stops
+        /// him rising, so he falls back down.
+        pub fn bump_head(self, dy: i16) -> RedHatBoyState<Jumping> {
+            RedHatBoyState {
+                context: self.context.move_vertically(dy).set_vertical_velocity(0.0),
+                _state: Jumping {},
+            }
+        }
+    }
+
+    /// What knocked the boy out, which decides how he goes down.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Cause {
+        /// Ran into something low, like a stone, and went flat on his face.
+        #[default]
+        Tripped,
+        /// Ran head first into the edge of a platform or a beam, and is
+        /// thrown back up off it.
+        Bonked,
+        /// Hit by something thrown at him, hard enough to lift him.
+        Struck,
+        /// Dropped off the bottom of the world.
+        Fell,
+    }
+
+    impl Cause {
+        /// The vertical speed he is knocked out with.
+        fn recoil(self) -> f32 {
+            match self {
+                Cause::Tripped | Cause::Fell => 0.0,
+                Cause::Bonked => -6.0,
+                Cause::Struck => -10.0,
+            }
+        }
+
+        /// What the stats call it.
+        pub fn name(self) -> &'static str {
+            match self {
+                Cause::Tripped => "Tripped",
+                Cause::Bonked => "Bonked",
+                Cause::Struck => "Struck",
+                Cause::Fell => "Fell",
+            }
+        }
+
+        /// Shown over him as he goes down.
+        pub fn exclamation(self) -> &'static str {
+            match self {
+                Cause::Tripped => "Trip!",
+                Cause::Bonked => "Bonk!",
+                Cause::Struck => "Ouch!",
+                Cause::Fell => "Whoa!",
+            }
+        }
     }
 
     #[derive(Clone, Copy)]
-    pub struct Falling;
+    pub struct Falling {
+        cause: Cause,
+    }
 
     pub enum FallingEndState {
         Complete(RedHatBoyState<KnockedOut>),
@@ -682,94 +2057,646 @@ mod red_hat_boy_states {
         pub fn down(self) -> RedHatBoyState<KnockedOut> {
             RedHatBoyState {
                 context: self.context,
-                _state: KnockedOut {},
+                _state: KnockedOut {
+                    cause: self._state.cause,
+                    lying: 0,
+                },
             }
         }
+
+        pub fn cause(&self) -> Cause {
+            self._state.cause
+        }
     }
 
+    /// How long the boy lies knocked out before the run is over, however
+    /// long the fall that put him there took.
+    const KNOCKED_OUT_FRAMES: u16 = 60;
+    /// How faint he has faded by the time the run is over.
+    const KNOCKED_OUT_ALPHA: f64 = 0.4;
+
     #[derive(Clone, Copy)]
-    pub struct KnockedOut;
+    pub struct KnockedOut {
+        cause: Cause,
+        lying: u16,
+    }
 
     impl RedHatBoyState<KnockedOut> {
         pub fn frame_name(&self) -> &str {
             FALLING_FRAME_NAME
         }
+
+        /// Counts down to the end of the run and lets him keep falling if
+        /// he went down over a gap, lying still on the last frame.
+        pub fn update(mut self) -> Self {
+            let frame = self.context.frame;
+            self.update_context(FALLING_FRAMES);
+            self.context.frame = frame;
+            self._state.lying = (self._state.lying + 1).min(KNOCKED_OUT_FRAMES);
+            self
+        }
+
+        pub fn cause(&self) -> Cause {
+            self._state.cause
+        }
+
+        pub fn out_for_the_count(&self) -> bool {
+            self._state.lying >= KNOCKED_OUT_FRAMES
+        }
+
+        pub fn opacity(&self) -> f64 {
+            let faded = f64::from(self._state.lying) / f64::from(KNOCKED_OUT_FRAMES);
+            1.0 - faded * (1.0 - KNOCKED_OUT_ALPHA)
+        }
+    }
+
+    /// Which state the boy is in, with whatever that state keeps, for
+    /// snapshots. An idle fidget isn't kept, so he comes back standing still.
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "state", rename_all = "snake_case")]
+    pub enum StateData {
+        Idle,
+        Running,
+        Sliding {
+            held: bool,
+            crouching: bool,
+        },
+        Crouching {
+            held: bool,
+        },
+        Stumbling,
+        Jumping,
+        Falling {
+            #[serde(default)]
+            cause: Cause,
+        },
+        KnockedOut {
+            #[serde(default)]
+            cause: Cause,
+            #[serde(default)]
+            lying: u16,
+        },
+    }
+
+    /// Where the boy is and how he is moving. The sounds and settings come
+    /// from the game he is restored into.
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct ContextData {
+        frame: u8,
+        position: Vector,
+        velocity: Vector,
+        over_gap: bool,
+        #[serde(default)]
+        dropping: bool,
+        modifier: Modifier,
+        force: f32,
+        drift: f32,
+        #[serde(default = "normal_gravity")]
+        gravity_scale: f32,
+        #[serde(default)]
+        speed: Speed,
+    }
+
+    fn normal_gravity() -> f32 {
+        1.0
+    }
+
+    impl RedHatBoyContext {
+        pub fn data(&self) -> ContextData {
+            ContextData {
+                frame: self.frame,
+                position: self.position,
+                velocity: self.velocity,
+                over_gap: self.over_gap,
+                dropping: self.dropping,
+                modifier: self.modifier,
+                force: self.force,
+                drift: self.drift,
+                gravity_scale: self.gravity_scale,
+                speed: self.speed,
+            }
+        }
+
+        pub fn with_data(self, data: ContextData) -> Self {
+            RedHatBoyContext {
+                frame: data.frame,
+                position: data.position,
+                velocity: data.velocity,
+                over_gap: data.over_gap,
+                dropping: data.dropping,
+                modifier: data.modifier,
+                force: data.force,
+                drift: data.drift,
+                gravity_scale: data.gravity_scale,
+                speed: data.speed,
+                ..self
+            }
+        }
+    }
+
+    impl RedHatBoyStateMachine {
+        pub fn data(&self) -> StateData {
+            match self {
+                RedHatBoyStateMachine::Idle(_) => StateData::Idle,
+                RedHatBoyStateMachine::Running(_) => StateData::Running,
+                RedHatBoyStateMachine::Sliding(state) => StateData::Sliding {
+                    held: state._state.held,
+                    crouching: state._state.crouching,
+                },
+                RedHatBoyStateMachine::Crouching(state) => StateData::Crouching {
+                    held: state._state.held,
+                },
+                RedHatBoyStateMachine::Stumbling(_) => StateData::Stumbling,
+                RedHatBoyStateMachine::Jumping(_) => StateData::Jumping,
+                RedHatBoyStateMachine::Falling(state) => StateData::Falling {
+                    cause: state._state.cause,
+                },
+                RedHatBoyStateMachine::KnockedOut(state) => StateData::KnockedOut {
+                    cause: state._state.cause,
+                    lying: state._state.lying,
+                },
+            }
+        }
+
+        pub fn from_data(context: RedHatBoyContext, data: StateData) -> Self {
+            match data {
+                StateData::Idle => RedHatBoyStateMachine::Idle(RedHatBoyState {
+                    context,
+                    _state: Idle::default(),
+                }),
+                StateData::Running => RedHatBoyStateMachine::Running(RedHatBoyState {
+                    context,
+                    _state: Running,
+                }),
+                StateData::Sliding { held, crouching } => {
+                    RedHatBoyStateMachine::Sliding(RedHatBoyState {
+                        context,
+                        _state: Sliding { held, crouching },
+                    })
+                }
+                StateData::Crouching { held } => RedHatBoyStateMachine::Crouching(RedHatBoyState {
+                    context,
+                    _state: Crouching { held },
+                }),
+                StateData::Stumbling => RedHatBoyStateMachine::Stumbling(RedHatBoyState {
+                    context,
+                    _state: Stumbling,
+                }),
+                StateData::Jumping => RedHatBoyStateMachine::Jumping(RedHatBoyState {
+                    context,
+                    _state: Jumping,
+                }),
+                StateData::Falling { cause } => RedHatBoyStateMachine::Falling(RedHatBoyState {
+                    context,
+                    _state: Falling { cause },
+                }),
+                StateData::KnockedOut { cause, lying } => {
+                    RedHatBoyStateMachine::KnockedOut(RedHatBoyState {
+                        context,
+                        _state: KnockedOut { cause, lying },
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Something that happened in the game this frame. Events are queued on the
+/// walk and dispatched once the frame's update is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum GameEvent {
+    Jumped,
+    Landed,
+    SlideStarted,
+    Stumbled,
+    Bounced,
+    BossDefeated,
+    CoinCollected,
+    MagnetCollected,
+    ShieldCollected,
+    SpeedCollected,
+    ShieldBroken,
+    KnockedOut,
+    NewRecord,
+}
+
+impl GameEvent {
+    fn sound_name(&self) -> Option<&'static str> {
+        match self {
+            GameEvent::Jumped => None,
+            GameEvent::Landed => Some("land"),
+            GameEvent::SlideStarted => Some("slide"),
+            GameEvent::Stumbled => Some("land"),
+            GameEvent::Bounced => Some("boing"),
+            GameEvent::BossDefeated | GameEvent::NewRecord => Some("high_score"),
+            GameEvent::CoinCollected
+            | GameEvent::MagnetCollected
+            | GameEvent::ShieldCollected
+            | GameEvent::SpeedCollected => Some("coin"),
+            GameEvent::ShieldBroken => Some("shatter"),
+            GameEvent::KnockedOut => Some("knock_out"),
+        }
+    }
+}
+
+struct SoundEffects {
+    audio: Audio,
+    sounds: HashMap<GameEvent, SoundGroup>,
+}
+
+impl SoundEffects {
+    fn new(audio: Audio, sprites: HashMap<String, Sound>) -> Self {
+        let sounds = [
+            GameEvent::Landed,
+            GameEvent::SlideStarted,
+            GameEvent::Stumbled,
+            GameEvent::Bounced,
+            GameEvent::BossDefeated,
+            GameEvent::CoinCollected,
+            GameEvent::MagnetCollected,
+            GameEvent::ShieldCollected,
+            GameEvent::SpeedCollected,
+            GameEvent::ShieldBroken,
+            GameEvent::KnockedOut,
+            GameEvent::NewRecord,
+        ]
+        .into_iter()
+        .filter_map(|event| {
+            let sound = event
+                .sound_name()
+                .and_then(|name| sprites.get(name).cloned());
+            sound.map(|sound| {
+                let group = SoundGroup::new(vec![sound]);
+                match event {
+                    GameEvent::KnockedOut => (event, group.ducking_music()),
+                    _ => (event, group),
+                }
+            })
+        })
+        .collect();
+        SoundEffects { audio, sounds }
+    }
+
+    fn play(&self, event: GameEvent) {
+        if let Some(group) = self.sounds.get(&event) {
+            if let Err(err) = self.audio.play_sound_group(group) {
+                log::error!(
+                    "Error playing sound for {:?} {:#?}",
+                    event.sound_name(),
+                    err
+                );
+            }
+        }
     }
 }
 
 pub struct Walk {
     boy: RedHatBoy,
-    backgrounds: [Image; 2],
-    obstacles: Vec<Box<dyn Obstacle>>,
+    course: course::Course,
+    music: Music,
+    sound_effects: SoundEffects,
+    stats: Stats,
+    tutorial: tutorial::Tutorial,
+    profiles: profiles::Profiles,
+    run: RunStats,
     obstacle_sheet: Rc<SpriteSheet>,
-    stone: HtmlImageElement,
-    timeline: i16,
+    registry: Rc<ObstacleRegistry>,
+    atlas: Rc<Atlas>,
+    config: GameConfig,
+    /// A fixed course seed for races and replays. Otherwise every run gets a
+    /// new course.
+    seed: Option<u64>,
+    second: Option<two_player::SecondPlayer>,
+    /// Lets the autopilot play the first runner, for checking that every
+    /// segment can be cleared.
+    autopilot: bool,
+    /// The only segment placed in the segment preview, once per run.
+    preview: Option<usize>,
+    /// Keeps sliding for as long as the slide key is held, instead of for one
+    /// slide animation per press.
+    hold_to_slide: bool,
+    /// Keeps the split-screen cameras still instead of following the runners.
+    reduced_motion: bool,
+    /// Shows the course running right to left. Only the view is flipped, so
+    /// the course and the physics are the same either way.
+    mirrored: bool,
+    replay: replay::Replay,
+    playback: Option<replay::Playback>,
+    wind: wind::Wind,
+    trail: afterimage::Trail,
+    /// The last few seconds of the run, for the rewind after a knockout.
+    history: rewind::History,
+    /// The canvas and panels of this game, among any others on the page.
+    screen: browser::Screen,
+    ui: Box<dyn ui::UiController>,
+    /// The embedding page's functions to call as runs go.
+    callbacks: Callbacks,
+    leaderboard: Option<Rc<leaderboard::Leaderboard>>,
+    analytics: Option<analytics::Analytics>,
+    /// Which effects the device has time for.
+    quality: Quality,
+    prewarm: prewarm::Prewarm,
+    autosave: suspend::Autosave,
+    bounds: WorldBounds,
 }
 
 impl Walk {
-    fn velocity(&self) -> i16 {
-        -self.boy.walking_speed()
+    fn layout(&self) -> layout::Layout {
+        layout::Layout::of(self.bounds)
     }
 
-    fn generate_next_segment(&mut self) {
-        let mut rng = thread_rng();
-        let next_segment = rng.gen_range(0..2);
+    /// What the courses are built from and shown with.
+    fn world(&self) -> course::World<'_> {
+        course::World {
+            registry: &self.registry,
+            atlas: &self.atlas,
+            sheet: &self.obstacle_sheet,
+            config: &self.config,
+            prewarm: &self.prewarm,
+            quality: self.quality,
+            bounds: self.bounds,
+            extending: self.preview.is_none(),
+        }
+    }
 
-        let mut next_obstacles = match next_segment {
-            0 => stone_and_platform(
-                self.stone.clone(),
-                self.obstacle_sheet.clone(),
-                self.timeline + OBSTACLE_BUFFER,
-            ),
-            1 => platform_and_stone(
-                self.stone.clone(),
-                self.obstacle_sheet.clone(),
-                self.timeline + OBSTACLE_BUFFER,
-            ),
-            _ => vec![],
+    /// Each runner with their course and how their run is going, and the
+    /// world they share, borrowed apart so the runners can move while the
+    /// world is read.
+    fn runners(&mut self) -> (course::World<'_>, Vec<course::Runner<'_>>) {
+        let world = course::World {
+            registry: &self.registry,
+            atlas: &self.atlas,
+            sheet: &self.obstacle_sheet,
+            config: &self.config,
+            prewarm: &self.prewarm,
+            quality: self.quality,
+            bounds: self.bounds,
+            extending: self.preview.is_none(),
         };
-        self.timeline = rightmost(&next_obstacles);
-        self.obstacles.append(&mut next_obstacles);
+        let mut runners = vec![(&mut self.course, &mut self.boy, &mut self.run)];
+        if let Some(second) = &mut self.second {
+            runners.push((&mut second.course, &mut second.boy, &mut second.run));
+        }
+        (world, runners)
+    }
+
+    /// Adds segment `index` to the end of the first runner's course.
+    #[cfg(feature = "dev")]
+    fn spawn_segment(&mut self, index: usize) {
+        let (world, mut runners) = self.runners();
+        let (course, ..) = &mut runners[0];
+        course.spawn_segment(index, &world);
     }
 
     fn draw(&self, renderer: &Renderer) {
-        self.backgrounds.iter().for_each(|bg| {
-            bg.draw(renderer);
-        });
-        self.boy.draw(renderer);
-        self.obstacles.iter().for_each(|obstacle| {
-            obstacle.draw(renderer);
-        });
+        match &self.second {
+            Some(second) => {
+                // The backgrounds don't go above the top of the world, so
+                // their cameras stop there when a runner climbs higher.
+                let boy_y = self.boy.pos_y();
+                renderer.with_camera(
+                    self.camera(0, boy_y.max(two_player::CAMERA_HEADROOM)),
+                    || self.course.background.draw(renderer),
+                );
+                renderer.with_camera(self.camera(0, boy_y), || self.draw_course(renderer));
+                let second_y = second.boy.pos_y();
+                renderer.with_camera(
+                    self.camera(1, second_y.max(two_player::CAMERA_HEADROOM)),
+                    || second.course.background.draw(renderer),
+                );
+                renderer.with_camera(self.camera(1, second_y), || {
+                    second.course.draw(renderer, &second.boy, &self.world());
+                    self.wind.draw(renderer);
+                });
+            }
+            None => {
+                self.draw_background(renderer);
+                self.in_view(renderer, || self.draw_course(renderer));
+            }
+        }
+    }
+
+    /// Draws what `draw` renders in the first runner's world the way it is
+    /// shown on a screen of its own: mirrored in mirror mode, and following
+    /// the boy up when he climbs above the top of the screen.
+    fn in_view(&self, renderer: &Renderer, draw: impl FnOnce()) {
+        self.in_camera(renderer, view_top(self.boy.pos_y()), draw);
+    }
+
+    /// Draws the background, which stays where it is when the view follows
+    /// the boy up.
+    fn draw_background(&self, renderer: &Renderer) {
+        self.in_camera(renderer, 0, || self.course.background.draw(renderer));
+    }
+
+    fn in_camera(&self, renderer: &Renderer, top: i16, draw: impl FnOnce()) {
+        if self.mirrored || top != 0 {
+            let camera = Camera {
+                viewport: self.bounds.rect(),
+                offset: Point { x: 0, y: -top },
+                mirrored: self.mirrored,
+            };
+            renderer.with_camera(camera, draw);
+        } else {
+            draw();
+        }
+    }
+
+    fn camera(&self, player: i16, boy_y: i16) -> Camera {
+        let camera = if self.reduced_motion {
+            two_player::camera(player, self.bounds.height, self.bounds)
+        } else {
+            two_player::camera(player, boy_y, self.bounds)
+        };
+        Camera {
+            mirrored: self.mirrored,
+            ..camera
+        }
+    }
+
+    fn draw_course(&self, renderer: &Renderer) {
+        if self.quality.particles() {
+            self.trail.draw(renderer, &self.boy);
+        }
+        self.course.draw(renderer, &self.boy, &self.world());
+        self.wind.draw(renderer);
+    }
+
+    /// Whether the player ran this run themselves. The autopilot's runs and
+    /// replays played back aren't theirs, and cheated runs weren't earned,
+    /// so none of them count towards the stats, scores or analytics.
+    fn players_own_run(&self) -> bool {
+        !self.autopilot && self.playback.is_none() && !self.run.cheated
     }
 
     fn knocked_out(&self) -> bool {
         self.boy.knocked_out()
+            && self
+                .second
+                .as_ref()
+                .map_or(true, |second| second.boy.knocked_out())
+    }
+
+    /// Plays what happened to each runner this frame, once it has been
+    /// settled on their course.
+    fn dispatch_events(&mut self) {
+        let reduced_motion = self.reduced_motion;
+        let (_, runners) = self.runners();
+        let events: Vec<GameEvent> = runners
+            .into_iter()
+            .flat_map(|(course, boy, run)| course.dispatch_events(boy, run, reduced_motion))
+            .collect();
+        for event in events {
+            self.sound_effects.play(event);
+        }
+    }
+
+    /// Moves the world on by one frame once the runners have been steered.
+    fn advance(&mut self) {
+        self.wind.update(self.bounds);
+        self.boy.set_force(self.wind.force());
+        if let Some(second) = &mut self.second {
+            second.boy.set_force(self.wind.force());
+        }
+
+        self.history.cull(&mut self.course.obstacles);
+        let (world, runners) = self.runners();
+        let scrolled: Vec<i16> = runners
+            .into_iter()
+            .map(|(course, boy, run)| course.advance(boy, run, &world))
+            .collect();
+        let walking_speed = scrolled[0];
+
+        if self.boy.walking_speed() >= tense_walking_speed(&self.config) {
+            self.music.set_mood(Mood::Tense);
+        } else {
+            self.music.set_mood(Mood::Ambient);
+        }
+        self.trail.update(walking_speed, &self.boy);
+        self.history
+            .record(&self.boy, self.course.boss.as_ref(), walking_speed);
+
+        self.dispatch_events();
     }
 
     fn reset(walk: Self) -> Self {
-        let starting_obstacles =
-            stone_and_platform(walk.stone.clone(), walk.obstacle_sheet.clone(), 0);
-        let timeline = rightmost(&starting_obstacles);
+        Walk::reset_with(walk, ResetOptions::default())
+    }
+
+    fn reset_with(walk: Self, options: ResetOptions) -> Self {
+        let starting_obstacles = build_segment(
+            &starting_segment(walk.preview, walk.bounds.height),
+            &walk.registry,
+            walk.atlas.clone(),
+            walk.obstacle_sheet.clone(),
+            0,
+        );
 
+        let mut music = walk.music;
+        music.set_biome(biomes::at(0));
+        music.set_mood(Mood::Ambient);
+
+        let course_seed = options.seed.or(walk.seed).unwrap_or_else(random);
+        let second = walk.second.map(|second| {
+            second.reset(
+                &walk.registry,
+                walk.atlas.clone(),
+                walk.obstacle_sheet.clone(),
+                course_seed,
+                walk.bounds,
+            )
+        });
+
+        let boy = RedHatBoy::reset(walk.boy);
+        // God mode carries over, and a run with it on isn't earned.
+        let run = RunStats {
+            cheated: boy.invincible(),
+            ..RunStats::default()
+        };
         Walk {
-            boy: RedHatBoy::reset(walk.boy),
-            backgrounds: walk.backgrounds,
-            obstacles: starting_obstacles,
+            boy,
+            course: course::Course::new(
+                walk.course.background,
+                starting_obstacles,
+                walk.atlas.clone(),
+                course_seed,
+                walk.bounds.width,
+            ),
+            music,
+            sound_effects: walk.sound_effects,
+            stats: walk.stats,
+            tutorial: walk.tutorial,
+            profiles: walk.profiles,
+            run,
+            seed: walk.seed,
+            second,
+            autopilot: walk.autopilot,
+            preview: walk.preview,
+            reduced_motion: walk.reduced_motion,
+            mirrored: walk.mirrored,
+            hold_to_slide: walk.hold_to_slide,
+            replay: replay::Replay::new(course_seed),
+            playback: walk.playback.map(replay::Playback::rewind),
+            wind: wind::Wind::default(),
+            trail: afterimage::Trail::default(),
+            history: rewind::History::default(),
+            screen: walk.screen,
+            ui: walk.ui,
+            callbacks: walk.callbacks,
+            leaderboard: walk.leaderboard,
+            analytics: walk.analytics,
+            quality: walk.quality,
+            prewarm: walk.prewarm,
+            autosave: suspend::Autosave::new(),
+            bounds: walk.bounds,
             obstacle_sheet: walk.obstacle_sheet,
-            stone: walk.stone,
-            timeline,
+            registry: walk.registry,
+            atlas: walk.atlas,
+            config: walk.config,
         }
     }
 }
 
+/// The id of the element the game's panels go in, unless the config says.
+const DEFAULT_UI: &str = "ui";
+/// Asked when Back is pressed during a run.
+const LEAVE_QUESTION: &str = "Leave the page? This run will end.";
+
 pub struct WalkTheDog {
+    config: StartupConfig,
     machine: Option<WalkTheDogStateMachine>,
+    #[cfg(feature = "dev")]
+    console: console::DebugConsole,
+    #[cfg(feature = "dev")]
+    cheats: cheats::Cheats,
+    race: Option<race::Race>,
+    /// Holds the audio while the tab is hidden.
+    visibility: Option<browser::EventListener<web_sys::Event>>,
+    /// Asks before the page is left in the middle of a run.
+    navigation: Option<browser::NavigationGuard>,
+    bounds: WorldBounds,
+    /// The canvas the game loop draws on.
+    canvas: Option<HtmlCanvasElement>,
+    #[cfg(feature = "dev")]
+    hot_reload: hot_reload::HotReload,
 }
 
 enum WalkTheDogStateMachine {
+    ChoosingProfile(WalkTheDogState<profiles::ChoosingProfile>),
     Ready(WalkTheDogState<Ready>),
+    Countdown(WalkTheDogState<countdown::Countdown>),
     Walking(WalkTheDogState<Walking>),
+    Paused(WalkTheDogState<pause::Paused>),
+    Rewinding(WalkTheDogState<rewind::Rewinding>),
+    Celebrating(WalkTheDogState<celebration::Celebrating>),
     GameOver(WalkTheDogState<GameOver>),
+    Attract(WalkTheDogState<attract::Attract>),
+    ShowingStats(WalkTheDogState<stats::ShowingStats>),
+    Wardrobe(WalkTheDogState<cosmetics::Wardrobe>),
+    Previewing(WalkTheDogState<preview::Previewing>),
+    Resuming(WalkTheDogState<suspend::Resuming>),
+    #[cfg(feature = "editor")]
+    Editing(WalkTheDogState<editor::Editing>),
 }
 
 impl WalkTheDogStateMachine {
@@ -779,24 +2706,112 @@ impl WalkTheDogStateMachine {
 
     fn update(self, keystate: &KeyState) -> Self {
         match self {
+            Self::ChoosingProfile(state) => state.update().into(),
             Self::Ready(state) => state.update(keystate).into(),
+            Self::Countdown(state) => state.update().into(),
             Self::Walking(state) => state.update(keystate).into(),
-            Self::GameOver(state) => state.update().into(),
+            Self::Paused(state) => state.update(keystate).into(),
+            Self::Rewinding(state) => state.update(keystate).into(),
+            Self::Celebrating(state) => state.update().into(),
+            Self::GameOver(state) => state.update(keystate).into(),
+            Self::Attract(state) => state.update(keystate).into(),
+            Self::ShowingStats(state) => state.update().into(),
+            Self::Wardrobe(state) => state.update().into(),
+            Self::Previewing(state) => state.update().into(),
+            Self::Resuming(state) => state.update().into(),
+            #[cfg(feature = "editor")]
+            Self::Editing(state) => state.update(keystate).into(),
+        }
+    }
+
+    fn walk(&self) -> &Walk {
+        match self {
+            WalkTheDogStateMachine::ChoosingProfile(state) => &state.walk,
+            WalkTheDogStateMachine::Ready(state) => &state.walk,
+            WalkTheDogStateMachine::Countdown(state) => &state.walk,
+            WalkTheDogStateMachine::Walking(state) => &state.walk,
+            WalkTheDogStateMachine::Paused(state) => &state.walk,
+            WalkTheDogStateMachine::Rewinding(state) => &state.walk,
+            WalkTheDogStateMachine::Celebrating(state) => &state.walk,
+            WalkTheDogStateMachine::GameOver(state) => &state.walk,
+            WalkTheDogStateMachine::Attract(state) => &state.walk,
+            WalkTheDogStateMachine::ShowingStats(state) => &state.walk,
+            WalkTheDogStateMachine::Wardrobe(state) => &state.walk,
+            WalkTheDogStateMachine::Previewing(state) => &state.walk,
+            WalkTheDogStateMachine::Resuming(state) => &state.walk,
+            #[cfg(feature = "editor")]
+            WalkTheDogStateMachine::Editing(state) => &state.walk,
+        }
+    }
+
+    fn walk_mut(&mut self) -> &mut Walk {
+        match self {
+            WalkTheDogStateMachine::ChoosingProfile(state) => &mut state.walk,
+            WalkTheDogStateMachine::Ready(state) => &mut state.walk,
+            WalkTheDogStateMachine::Countdown(state) => &mut state.walk,
+            WalkTheDogStateMachine::Walking(state) => &mut state.walk,
+            WalkTheDogStateMachine::Paused(state) => &mut state.walk,
+            WalkTheDogStateMachine::Rewinding(state) => &mut state.walk,
+            WalkTheDogStateMachine::Celebrating(state) => &mut state.walk,
+            WalkTheDogStateMachine::GameOver(state) => &mut state.walk,
+            WalkTheDogStateMachine::Attract(state) => &mut state.walk,
+            WalkTheDogStateMachine::ShowingStats(state) => &mut state.walk,
+            WalkTheDogStateMachine::Wardrobe(state) => &mut state.walk,
+            WalkTheDogStateMachine::Previewing(state) => &mut state.walk,
+            WalkTheDogStateMachine::Resuming(state) => &mut state.walk,
+            #[cfg(feature = "editor")]
+            WalkTheDogStateMachine::Editing(state) => &mut state.walk,
         }
     }
 
     fn draw(&self, renderer: &Renderer) {
         match self {
-            WalkTheDogStateMachine::Ready(state) => state.draw(renderer),
-            WalkTheDogStateMachine::Walking(state) => state.draw(renderer),
-            WalkTheDogStateMachine::GameOver(state) => state.draw(renderer),
+            WalkTheDogStateMachine::ChoosingProfile(state) => state.draw(renderer),
+            WalkTheDogStateMachine::Ready(state) => {
+                state.draw(renderer);
+                state.draw_ambience(renderer);
+            }
+            WalkTheDogStateMachine::Countdown(state) => {
+                state.draw(renderer);
+                state.draw_count(renderer);
+            }
+            WalkTheDogStateMachine::Walking(state) => {
+                state.draw(renderer);
+                state.walk.tutorial.draw(renderer, state.walk.layout());
+            }
+            WalkTheDogStateMachine::Paused(state) => state.draw_paused(renderer),
+            WalkTheDogStateMachine::Rewinding(state) => state.draw_rewind(renderer),
+            WalkTheDogStateMachine::Celebrating(state) => {
+                state.draw(renderer);
+                state.draw_celebration(renderer);
+            }
+            WalkTheDogStateMachine::GameOver(state) => {
+                state.draw(renderer);
+                state.draw_prompt(renderer);
+            }
+            WalkTheDogStateMachine::Attract(state) => {
+                state.draw(renderer);
+                state.draw_prompt(renderer);
+            }
+            WalkTheDogStateMachine::ShowingStats(state) => state.draw(renderer),
+            WalkTheDogStateMachine::Wardrobe(state) => state.draw(renderer),
+            WalkTheDogStateMachine::Previewing(state) => {
+                state.draw(renderer);
+                state.draw_report(renderer);
+            }
+            WalkTheDogStateMachine::Resuming(state) => state.draw(renderer),
+            #[cfg(feature = "editor")]
+            WalkTheDogStateMachine::Editing(state) => {
+                state.draw(renderer);
+                state.draw_editor(renderer);
+            }
         }
     }
 }
 
 struct WalkTheDogState<T> {
     _state: T,
-    walk: Walk,
+    walk: Box<Walk>,
 }
 
 impl<T> WalkTheDogState<T> {
@@ -805,11 +2820,24 @@ impl<T> WalkTheDogState<T> {
     }
 }
 
-struct Ready;
+#[derive(Default)]
+struct Ready {
+    idle_frames: u16,
+    ambience: ambience::Ambience,
+    /// Whether a run saved before the page was reloaded has been looked
+    /// for, which happens once each time the title screen is shown.
+    looked_for_saved_run: bool,
+}
 
 enum ReadyEndState {
-    Complete(WalkTheDogState<Walking>),
+    Complete(WalkTheDogState<countdown::Countdown>),
     Continue(WalkTheDogState<Ready>),
+    Demo(WalkTheDogState<attract::Attract>),
+    ShowStats(WalkTheDogState<stats::ShowingStats>),
+    Wardrobe(WalkTheDogState<cosmetics::Wardrobe>),
+    Resume(WalkTheDogState<suspend::Resuming>),
+    #[cfg(feature = "editor")]
+    Edit(WalkTheDogState<editor::Editing>),
 }
 
 impl From<ReadyEndState> for WalkTheDogStateMachine {
@@ -817,6 +2845,12 @@ impl From<ReadyEndState> for WalkTheDogStateMachine {
         match state {
             ReadyEndState::Complete(state) => state.into(),
             ReadyEndState::Continue(state) => state.into(),
+            ReadyEndState::Demo(state) => state.into(),
+            ReadyEndState::ShowStats(state) => state.into(),
+            ReadyEndState::Wardrobe(state) => state.into(),
+            ReadyEndState::Resume(state) => state.into(),
+            #[cfg(feature = "editor")]
+            ReadyEndState::Edit(state) => state.into(),
         }
     }
 }
@@ -824,29 +2858,66 @@ impl From<ReadyEndState> for WalkTheDogStateMachine {
 impl WalkTheDogState<Ready> {
     fn new(walk: Walk) -> Self {
         WalkTheDogState {
-            _state: Ready,
-            walk,
+            _state: Ready::default(),
+            walk: Box::new(walk),
         }
     }
     fn update(mut self, keystate: &KeyState) -> ReadyEndState {
+        if !self._state.looked_for_saved_run {
+            self._state.looked_for_saved_run = true;
+            match self.offer_saved_run() {
+                Ok(state) => return ReadyEndState::Resume(state),
+                Err(state) => self = state,
+            }
+        }
+        self._state.ambience.update(
+            &mut self.walk.boy,
+            self.walk.reduced_motion,
+            self.walk.bounds.width,
+        );
         self.walk.boy.update();
-        if keystate.is_pressed("ArrowRight") {
-            ReadyEndState::Complete(self.start_running())
+        if let Some(second) = &mut self.walk.second {
+            second.boy.update();
+        }
+        #[cfg(feature = "editor")]
+        if keystate.is_pressed("KeyE") {
+            return self.edit();
+        }
+        // KeyS is the second player's slide key in two-player mode, and
+        // the wardrobe dresses the first player only.
+        if self.walk.second.is_none() {
+            if keystate.is_pressed("KeyS") {
+                return self.show_stats();
+            }
+            if keystate.is_pressed("KeyC") {
+                return self.open_wardrobe();
+            }
+        }
+        self.toggle_mirror(keystate);
+        let start_keys: &[&str] = if self.walk.mirrored {
+            &["ArrowLeft", "ArrowRight", "KeyA", "KeyD"]
         } else {
-            ReadyEndState::Continue(self)
+            &["ArrowRight", "KeyD"]
+        };
+        if start_keys.iter().any(|key| keystate.is_pressed(key)) {
+            return ReadyEndState::Complete(self.start_countdown());
         }
-    }
 
-    fn start_running(mut self) -> WalkTheDogState<Walking> {
-        self.run_right();
-        WalkTheDogState {
-            _state: Walking,
-            walk: self.walk,
+        if keystate.any_pressed() {
+            self._state.idle_frames = 0;
+        } else {
+            self._state.idle_frames += 1;
+        }
+        // Races and two-player games wait for the players instead of
+        // running the demo.
+        if self._state.idle_frames >= attract::ATTRACT_DELAY
+            && self.walk.seed.is_none()
+            && self.walk.second.is_none()
+        {
+            ReadyEndState::Demo(self.start_demo())
+        } else {
+            ReadyEndState::Continue(self)
         }
-    }
-
-    fn run_right(&mut self) {
-        self.walk.boy.run_right();
     }
 }
 
@@ -858,8 +2929,25 @@ impl From<WalkTheDogState<Ready>> for WalkTheDogStateMachine {
 
 struct Walking;
 
+/// Starts a new run straight away, from a run or the game over panel.
+const RESTART_KEY: &str = "KeyR";
+
+/// How fast the world has to scroll for the tense music, which follows the
+/// running speed in the config but can always be reached.
+fn tense_walking_speed(config: &GameConfig) -> f32 {
+    (config.running_speed * TENSE_SPEED_SCALE).min(MAX_RUNNING_SPEED)
+}
+
+/// Races can't be restarted, since both runners start together.
+fn restart_pressed(walk: &Walk, keystate: &KeyState) -> bool {
+    walk.seed.is_none() && keystate.just_pressed(RESTART_KEY)
+}
+
 enum WalkingEndState {
-    Complete(WalkTheDogState<GameOver>),
+    Complete(RunEnd),
+    Rewind(WalkTheDogState<rewind::Rewinding>),
+    Pause(WalkTheDogState<pause::Paused>),
+    Restart(WalkTheDogState<countdown::Countdown>),
     Continue(WalkTheDogState<Walking>),
 }
 
@@ -867,6 +2955,9 @@ impl From<WalkingEndState> for WalkTheDogStateMachine {
     fn from(state: WalkingEndState) -> Self {
         match state {
             WalkingEndState::Complete(state) => state.into(),
+            WalkingEndState::Rewind(state) => state.into(),
+            WalkingEndState::Pause(state) => state.into(),
+            WalkingEndState::Restart(state) => state.into(),
             WalkingEndState::Continue(state) => state.into(),
         }
     }
@@ -874,56 +2965,168 @@ impl From<WalkingEndState> for WalkTheDogStateMachine {
 
 impl WalkTheDogState<Walking> {
     fn update(mut self, keystate: &KeyState) -> WalkingEndState {
-        if keystate.is_pressed("ArrowDown") {
+        if restart_pressed(&self.walk, keystate) {
+            return WalkingEndState::Restart(self.restart());
+        }
+        if self.pause_pressed(keystate) {
+            match self.pause() {
+                Ok(state) => return WalkingEndState::Pause(state),
+                Err(state) => self = state,
+            }
+        }
+        let input = match self.walk.playback.as_mut() {
+            Some(playback) => playback.next_input(),
+            None => {
+                let action = if self.walk.autopilot {
+                    autopilot::decide(&self.walk.boy, self.walk.course.obstacles.active())
+                } else {
+                    None
+                };
+                let sliding = action == Some(autopilot::Action::Slide);
+                // Ahead is to the left on a mirrored screen.
+                let (ahead, back) = if self.walk.mirrored {
+                    ("ArrowLeft", "ArrowRight")
+                } else {
+                    ("ArrowRight", "ArrowLeft")
+                };
+                replay::Input {
+                    jump: keystate.just_pressed("Space") || action == Some(autopilot::Action::Jump),
+                    slide: keystate.just_pressed("ArrowDown") || sliding,
+                    slide_held: keystate.is_pressed("ArrowDown") || sliding,
+                    ahead: keystate.is_pressed(ahead),
+                    back: keystate.is_pressed(back),
+                    fast_fall: keystate.just_pressed("ArrowDown"),
+                }
+            }
+        };
+        self.walk.replay.record(input);
+
+        if input.slide {
             self.walk.boy.slide();
         }
+        if input.fast_fall {
+            self.walk.boy.fast_fall();
+        }
+        if input.slide_held {
+            if self.walk.hold_to_slide {
+                self.walk.boy.hold_slide();
+            } else {
+                self.walk.boy.crouch();
+            }
+        }
 
-        if keystate.is_pressed("Space") {
+        if input.jump {
             self.walk.boy.jump();
         }
+        self.walk.boy.steer(input.steering());
 
-        self.walk.boy.update();
+        if let Some(second) = &mut self.walk.second {
+            second.steer(keystate, self.walk.hold_to_slide);
+        }
 
-        let walking_speed = self.walk.velocity();
+        let distance = self.walk.run.distance;
+        let bosses = self.walk.run.bosses;
+        self.walk.advance();
+        if self.walk.run.distance != distance {
+            self.walk.callbacks.score_changed(self.walk.run.distance);
+        }
+        if self.walk.run.distance / DISTANCE_MILESTONE > distance / DISTANCE_MILESTONE {
+            announce(&format!("Distance {}", self.walk.run.distance));
+        }
+        let boy_right = self.walk.boy.bounding_box().right();
+        let ahead = self
+            .walk
+            .course
+            .obstacles
+            .active()
+            .iter()
+            .map(|obstacle| (obstacle.name(), obstacle.bounding_box().x() - boy_right));
+        if let Some(prompt) = self.walk.tutorial.update(ahead) {
+            announce(prompt);
+        }
+        if let Some(biome) = biomes::entered(distance, self.walk.run.distance) {
+            announce(biome.name);
+            self.walk.music.set_biome(biome);
+            self.walk.course.popups.spawn_text(
+                biome.name.to_string(),
+                self.walk.boy.bounding_box().position,
+            );
+        }
+        if self.walk.run.bosses > bosses {
+            announce(&format!("Boss survived. Bonus {}", boss::BOSS_BONUS));
+        }
 
-        self.walk.backgrounds.iter_mut().for_each(|bg| {
-            bg.move_horizontally(walking_speed);
-            if bg.right() < 0 {
-                bg.move_horizontally(bg.bounding_box().width * 2);
+        if self.walk.knocked_out() {
+            match self.rewind() {
+                Ok(state) => WalkingEndState::Rewind(state),
+                Err(state) => WalkingEndState::Complete(state.end_game(None)),
             }
-        });
+        } else {
+            suspend::update(&mut self.walk);
+            WalkingEndState::Continue(self)
+        }
+    }
 
-        self.walk.obstacles.retain(|obstacle| obstacle.right() > 0);
+    /// Throws the run away for a new one, straight into the countdown. The
+    /// abandoned run doesn't count towards the stats.
+    fn restart(self) -> WalkTheDogState<countdown::Countdown> {
+        suspend::discard();
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: Box::new(Walk::reset(*self.walk)),
+        }
+        .start_countdown()
+    }
 
-        self.walk.obstacles.iter_mut().for_each(|obstacle| {
-            obstacle.move_horizontally(walking_speed);
-            obstacle.check_intersection(&mut self.walk.boy);
+    /// Ends the run, offering to share a clip of the rewind if `recorder`
+    /// has been recording it. A new best is celebrated first.
+    fn end_game(mut self, recorder: Option<CanvasRecorder>) -> RunEnd {
+        suspend::discard();
+        self.walk.music.set_mood(Mood::Somber);
+        announce(&format!(
+            "Game over. Final distance {}",
+            self.walk.run.distance
+        ));
+        self.walk.callbacks.game_over(self.walk.run.distance);
+        self.walk.record(analytics::AnalyticsEvent::RunEnded {
+            distance: self.walk.run.distance,
+            knocked_out_by: self.walk.run.knocked_out_by.clone(),
+            cause: self.walk.run.cause,
         });
-
-        if self.walk.timeline < TIMELINE_MINIMUM {
-            self.walk.generate_next_segment();
-        } else {
-            self.walk.timeline += walking_speed;
+        // Only the player's own runs count towards the stats and what they
+        // unlock.
+        let new_best = self.walk.players_own_run() && {
+            let new_best = self.walk.stats.record(self.walk.run.clone());
+            if let Err(err) = self.walk.stats.save() {
+                log::error!("Could not save stats {:#?}", err);
+            }
+            self.walk.profiles.earn(self.walk.run.coins);
+            self.walk.profiles.unlock(&self.walk.stats);
+            new_best
+        };
+        if let Err(err) = self.walk.tutorial.save() {
+            log::error!("Could not save the tutorial progress {:#?}", err);
         }
-
-        if self.walk.knocked_out() {
-            WalkingEndState::Complete(self.end_game())
+        if new_best {
+            celebration::celebrate(self.walk, recorder)
         } else {
-            WalkingEndState::Continue(self)
+            RunEnd::GameOver(GameOver::show(self.walk, recorder))
         }
     }
+}
 
-    fn end_game(self) -> WalkTheDogState<GameOver> {
-        let receiver = browser::draw_ui("<button id='new_game'>New Game</button>")
-            .and_then(|_unit| browser::find_html_element_by_id("new_game"))
-            .map(engine::add_click_handler)
-            .unwrap();
+/// How a run ends: straight to the game over panel, or with a celebration
+/// first.
+enum RunEnd {
+    GameOver(WalkTheDogState<GameOver>),
+    Celebrate(WalkTheDogState<celebration::Celebrating>),
+}
 
-        WalkTheDogState {
-            _state: GameOver {
-                new_game_event: receiver,
-            },
-            walk: self.walk,
+impl From<RunEnd> for WalkTheDogStateMachine {
+    fn from(state: RunEnd) -> Self {
+        match state {
+            RunEnd::GameOver(state) => state.into(),
+            RunEnd::Celebrate(state) => state.into(),
         }
     }
 }
@@ -934,18 +3137,89 @@ impl From<WalkTheDogState<Walking>> for WalkTheDogStateMachine {
     }
 }
 
+/// How the next run starts when a run is started again.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ResetOptions {
+    /// Runs the course from this seed instead of a new one. Races keep
+    /// their own seed whatever this says.
+    seed: Option<u64>,
+}
+
+/// What the game over panel needs to know about the run that ended.
+#[derive(Debug, Clone, PartialEq)]
+struct FinishedRun {
+    /// The course's seed, to run it again.
+    seed: u64,
+    distance: u32,
+    replay_code: Option<String>,
+}
+
 struct GameOver {
-    new_game_event: UnboundedReceiver<()>,
+    clip: Option<clip::Clip>,
+    /// Whether the panel could be shown. Without it, the keyboard starts a
+    /// new game instead of the panel's button.
+    panel_shown: bool,
+    run: FinishedRun,
 }
 
+/// Starts a new game when the game over panel can't be shown.
+const NEW_GAME_KEY: &str = "Enter";
+const NEW_GAME_PROMPT: &str = "Press Enter for a new game";
+/// Half the width of the prompt, to centre it.
+const NEW_GAME_PROMPT_HALF_WIDTH: i16 = 150;
+const NEW_GAME_PROMPT_TOP: i16 = 200;
+
 impl GameOver {
-    fn new_game_pressed(&mut self) -> bool {
-        matches!(self.new_game_event.try_next(), Ok(Some(())))
+    /// Shows the game over panel.
+    fn show(walk: Box<Walk>, recorder: Option<CanvasRecorder>) -> WalkTheDogState<GameOver> {
+        let replay_code = match walk.replay.export() {
+            Ok(code) => {
+                let saved = code.clone();
+                browser::spawn_local(async move {
+                    if let Err(err) = replay::save_last(&saved).await {
+                        log::error!("Could not save the replay {:#?}", err);
+                    }
+                });
+                if let Some(leaderboard) =
+                    walk.leaderboard.as_ref().filter(|_| walk.players_own_run())
+                {
+                    leaderboard.submit(walk.run.distance, walk.replay.seed(), &code);
+                }
+                Some(code)
+            }
+            Err(err) => {
+                log::error!("Could not export replay {:#?}", err);
+                None
+            }
+        };
+        let run = FinishedRun {
+            seed: walk.replay.seed(),
+            distance: walk.run.distance,
+            replay_code,
+        };
+        let mut state = WalkTheDogState {
+            _state: GameOver {
+                clip: recorder.map(clip::Clip::new),
+                panel_shown: false,
+                run,
+            },
+            walk,
+        };
+        state._state.panel_shown = match state.show_panel() {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Could not show the game over panel {:#?}", err);
+                announce(NEW_GAME_PROMPT);
+                false
+            }
+        };
+        state
     }
 }
 
 enum GameOverEndState {
     Complete(WalkTheDogState<Ready>),
+    Restart(WalkTheDogState<countdown::Countdown>),
     Continue(WalkTheDogState<GameOver>),
 }
 
@@ -953,27 +3227,115 @@ impl From<GameOverEndState> for WalkTheDogStateMachine {
     fn from(state: GameOverEndState) -> Self {
         match state {
             GameOverEndState::Complete(state) => state.into(),
+            GameOverEndState::Restart(state) => state.into(),
             GameOverEndState::Continue(state) => state.into(),
         }
     }
 }
 
 impl WalkTheDogState<GameOver> {
-    fn update(mut self) -> GameOverEndState {
-        if self._state.new_game_pressed() {
-            GameOverEndState::Complete(self.new_game())
-        } else {
-            GameOverEndState::Continue(self)
+    fn update(mut self, keystate: &KeyState) -> GameOverEndState {
+        if restart_pressed(&self.walk, keystate) {
+            return GameOverEndState::Restart(self.new_game().start_countdown());
+        }
+        if let Some(clip) = &mut self._state.clip {
+            clip.update();
+        }
+        if !self._state.panel_shown && keystate.is_pressed(NEW_GAME_KEY) {
+            return GameOverEndState::Complete(self.new_game());
+        }
+        match self.walk.ui.poll() {
+            Some(ui::UiEvent::NewGame) => GameOverEndState::Complete(self.new_game()),
+            Some(ui::UiEvent::RetrySeed) => {
+                let seed = self._state.run.seed;
+                GameOverEndState::Complete(self.new_game_with(ResetOptions { seed: Some(seed) }))
+            }
+            Some(ui::UiEvent::ToggleSound) => {
+                self.toggle_sound();
+                GameOverEndState::Continue(self)
+            }
+            Some(ui::UiEvent::NextCharacter) => {
+                self.next_character();
+                GameOverEndState::Continue(self)
+            }
+            Some(ui::UiEvent::Screenshot) => {
+                screenshot::capture(&self.walk.screen, self._state.run.distance);
+                GameOverEndState::Continue(self)
+            }
+            Some(ui::UiEvent::ShareClip) => {
+                if let Some(clip) = &self._state.clip {
+                    clip.share(self._state.run.distance);
+                }
+                GameOverEndState::Continue(self)
+            }
+            Some(ui::UiEvent::ContinueRun) | None => GameOverEndState::Continue(self),
         }
     }
+
     fn new_game(self) -> WalkTheDogState<Ready> {
-        if let Err(err) = browser::hide_ui() {
-            log::error!("Error hiding the browser {:#?}", err);
-        }
+        self.new_game_with(ResetOptions::default())
+    }
+
+    fn new_game_with(mut self, options: ResetOptions) -> WalkTheDogState<Ready> {
+        self.walk.ui.hide();
         WalkTheDogState {
-            _state: Ready,
-            walk: Walk::reset(self.walk),
+            _state: Ready::default(),
+            walk: Box::new(Walk::reset_with(*self.walk, options)),
+        }
+    }
+
+    fn show_panel(&mut self) -> Result<()> {
+        let settings = self.walk.profiles.current().settings;
+        let panel = ui::GameOverPanel {
+            replay_code: self._state.run.replay_code.clone(),
+            share_clip: self._state.clip.is_some(),
+            muted: settings.mute,
+            skin: settings.skin.name(),
+        };
+        self.walk.ui.hide();
+        self.walk.ui.show_game_over(&panel)
+    }
+
+    fn toggle_sound(&mut self) {
+        let settings = &mut self.walk.profiles.current_mut().settings;
+        settings.mute = !settings.mute;
+        let muted = settings.mute;
+        self.walk.sound_effects.audio.set_muted(muted);
+        announce(if muted { "Sound off" } else { "Sound on" });
+        self.settings_changed();
+    }
+
+    /// Puts the boy in the next skin the profile owns.
+    fn next_character(&mut self) {
+        let profile = self.walk.profiles.current_mut();
+        let skin = profile.settings.skin.next_owned(|skin| profile.owns(skin));
+        profile.settings.skin = skin;
+        self.walk.boy.set_skin(skin);
+        announce(&format!("Wearing {}", skin.name()));
+        self.settings_changed();
+    }
+
+    /// Saves the profile and shows the panel again with the toggles changed.
+    fn settings_changed(&mut self) {
+        if let Err(err) = self.walk.profiles.save() {
+            log::error!("Could not save profiles {:#?}", err);
+        }
+        if let Err(err) = self.show_panel() {
+            log::error!("Could not show the game over panel {:#?}", err);
+        }
+    }
+
+    fn draw_prompt(&self, renderer: &Renderer) {
+        if self._state.panel_shown {
+            return;
         }
+        let location = Point {
+            x: self.walk.bounds.width / 2 - NEW_GAME_PROMPT_HALF_WIDTH,
+            y: NEW_GAME_PROMPT_TOP,
+        };
+        self.walk
+            .layout()
+            .draw_prompt(renderer, NEW_GAME_PROMPT, location);
     }
 }
 impl From<WalkTheDogState<GameOver>> for WalkTheDogStateMachine {
@@ -982,8 +3344,60 @@ impl From<WalkTheDogState<GameOver>> for WalkTheDogStateMachine {
     }
 }
 impl WalkTheDog {
-    pub fn new() -> Self {
-        WalkTheDog { machine: None }
+    pub fn new(config: StartupConfig) -> Self {
+        WalkTheDog {
+            #[cfg(feature = "dev")]
+            hot_reload: hot_reload::HotReload::new(assets::character(config.character.as_deref())),
+            #[cfg(feature = "dev")]
+            cheats: cheats::Cheats::new(config.cheats),
+            config,
+            machine: None,
+            #[cfg(feature = "dev")]
+            console: console::DebugConsole::new(),
+            race: None,
+            visibility: None,
+            navigation: None,
+            bounds: DESIGN_BOUNDS,
+            canvas: None,
+        }
+    }
+
+    /// Suspends `audio` while the tab is hidden, so the music doesn't keep
+    /// playing in the background.
+    fn suspend_when_hidden(audio: Audio) -> Option<browser::EventListener<web_sys::Event>> {
+        let suspended = std::cell::Cell::new(false);
+        let listener = browser::on_visibility_change(move |hidden| {
+            if hidden == suspended.get() {
+                return;
+            }
+            suspended.set(hidden);
+            if hidden {
+                audio.suspend();
+            } else {
+                audio.resume();
+            }
+        });
+        listener
+            .map_err(|err| log::warn!("The audio will play in a hidden tab {:#?}", err))
+            .ok()
+    }
+
+    /// The background, standing on the ground of a world `bounds` big.
+    fn background(
+        image: &HtmlImageElement,
+        bounds: WorldBounds,
+        reduced_motion: bool,
+    ) -> Result<ScrollingBackground> {
+        let position = Point {
+            x: 0,
+            y: bounds.height - HEIGHT,
+        };
+        let background = ScrollingBackground::new(image, position, bounds.width)?;
+        Ok(if reduced_motion {
+            background.with_scroll_speed(REDUCED_MOTION_SCROLL_SPEED)
+        } else {
+            background
+        })
     }
 }
 
@@ -992,70 +3406,398 @@ impl Game for WalkTheDog {
     async fn initialize(&self) -> Result<Box<dyn Game>> {
         match self.machine {
             None => {
-                let json = browser::fetch_json("rhb.json").await?;
-                let sheet: Sheet = serde_wasm_bindgen::from_value(json)
-                    .map_err(|_| anyhow!("Could not convert rhb.json into a Sheet structure"))?;
-                let image = engine::load_image("rhb.png").await?;
+                let (race, seed) = match &self.config.race {
+                    Some(url) => {
+                        let (race, seed) = race::Race::connect(url, self.config.host).await?;
+                        (Some(race), Some(u64::from(seed)))
+                    }
+                    None => (None, None),
+                };
+                // A replay that can't be read shouldn't keep the game from
+                // starting, so it starts a normal run instead.
+                let replay = match self.config.replay.as_deref() {
+                    Some(replay::LAST) => replay::load_last().await.map_err(|err| {
+                        log::error!("Could not load the last replay {:#?}", err);
+                    }),
+                    Some(code) => replay::Replay::import(code).map_err(|err| {
+                        log::error!("Could not read the replay {:#?}", err);
+                    }),
+                    None => Err(()),
+                }
+                .ok();
+                // Races and replays hold every run to their course. A seed
+                // from the config only picks the first run's.
+                let seed = replay.as_ref().map(replay::Replay::seed).or(seed);
+
+                let character = assets::character(self.config.character.as_deref());
+                let json = browser::fetch_json(&assets::character_sheet(character)).await?;
+                let sheet: Sheet = serde_wasm_bindgen::from_value(json).map_err(|_| {
+                    anyhow!(
+                        "Could not convert {} sheet into a Sheet structure",
+                        character
+                    )
+                })?;
+                let image = engine::load_image(&assets::character_image(character)).await?;
+                let canvas = self
+                    .canvas
+                    .clone()
+                    .ok_or_else(|| anyhow!("No canvas to draw the game on"))?;
+                let screen =
+                    browser::Screen::new(canvas, self.config.ui.as_deref().unwrap_or(DEFAULT_UI))?;
+                let leaderboard = self
+                    .config
+                    .leaderboard
+                    .clone()
+                    .map(|url| Rc::new(leaderboard::Leaderboard::connect(url)));
+                let analytics = self.config.analytics.clone().and_then(|endpoint| {
+                    analytics::Analytics::new(endpoint)
+                        .map_err(|err| log::warn!("Analytics are off {:#?}", err))
+                        .ok()
+                });
                 let audio = Audio::new()?;
-                let sound = audio.load_sound("SFX_Jump_23.mp3").await?;
-                let background_music = audio.load_sound("background_song.mp3").await?;
-                audio.play_looping_sound(&background_music)?;
-                let boy = RedHatBoy::new(sheet, image, audio, sound);
+                let visibility = Self::suspend_when_hidden(audio.clone());
+                let profiles = profiles::Profiles::load();
+                let settings = profiles.current().settings.with(&self.config);
+                audio.set_muted(settings.mute);
+                let sounds = audio.load_audio_sprite(assets::SFX_SPRITE).await?;
+                let jump_sounds: Vec<Sound> = sounds
+                    .into_iter()
+                    .filter(|(name, _)| name.starts_with("jump"))
+                    .map(|(_, sound)| sound)
+                    .collect();
+                if jump_sounds.is_empty() {
+                    return Err(anyhow!("No jump sound in sfx.json"));
+                }
+                let music = Music::new(audio.clone(), HashMap::new());
+                let sound_effects = SoundEffects::new(
+                    audio.clone(),
+                    audio.load_audio_sprite(assets::SFX_EVENTS_SPRITE).await?,
+                );
+                let config = GameConfig::load().await;
+                let mut boy = RedHatBoy::new(
+                    sheet,
+                    image,
+                    audio,
+                    SoundGroup::new(jump_sounds).ducking_music(),
+                    config,
+                    self.bounds.height,
+                );
+                boy.set_skin(settings.skin);
 
-                let json = browser::fetch_json("tiles.json").await?;
+                let json = browser::fetch_json(assets::TILES_SHEET).await?;
                 let sheet: Sheet = serde_wasm_bindgen::from_value(json)
                     .map_err(|_| anyhow!("Could not convert tiles.json into a Sheet structure"))?;
-                let image = engine::load_image("tiles.png").await?;
+                let image = engine::load_image(assets::TILES_IMAGE).await?;
                 let sprite_sheet = Rc::new(SpriteSheet::new(sheet, image));
 
-                let background = engine::load_image("BG.png").await?;
-                let stone = engine::load_image("Stone.png").await?;
-
-                let background_width = background.width() as i16;
-
-                let starting_obstacles = stone_and_platform(stone.clone(), sprite_sheet.clone(), 0);
-                let timeline = rightmost(&starting_obstacles);
-                let machine = Some(WalkTheDogStateMachine::new(Walk {
+                let background = engine::load_image(assets::BACKGROUND).await?;
+                let registry = Rc::new(ObstacleRegistry::load().await?);
+                let mut sprites = vec![
+                    STONE,
+                    assets::COIN,
+                    assets::MAGNET,
+                    assets::SHIELD,
+                    assets::SPEED,
+                ];
+                for sprite in registry.atlas_sprites() {
+                    if !sprites.contains(&sprite) {
+                        sprites.push(sprite);
+                    }
+                }
+                let mut images = Vec::with_capacity(sprites.len());
+                for sprite in &sprites {
+                    images.push(engine::load_image(sprite).await?);
+                }
+                let packed: Vec<(&str, &HtmlImageElement)> =
+                    sprites.iter().copied().zip(&images).collect();
+                let atlas = Rc::new(Atlas::pack(&packed)?);
+                registry.check(&atlas)?;
+                for index in 0..SEGMENT_COUNT {
+                    for problem in preview::problems(index, &registry, &config) {
+                        log::warn!("{}", problem);
+                    }
+                }
+                let preview = match self.config.mode {
+                    Mode::Preview(index) => Some(index),
+                    _ => None,
+                };
+
+                let course_seed = seed.or(self.config.seed).unwrap_or_else(random);
+                if self.config.mode == Mode::TwoPlayer {
+                    if let Err(err) = browser::require_landscape() {
+                        log::warn!("Could not ask for a landscape screen {:#?}", err);
+                    }
+                }
+                let second = match self.config.mode {
+                    Mode::TwoPlayer => Some(two_player::SecondPlayer::new(
+                        boy.twin(),
+                        Self::background(&background, self.bounds, self.config.reduced_motion)?,
+                        &registry,
+                        atlas.clone(),
+                        sprite_sheet.clone(),
+                        course_seed,
+                        self.bounds,
+                    )),
+                    _ => None,
+                };
+                let background =
+                    Self::background(&background, self.bounds, self.config.reduced_motion)?;
+
+                let starting_obstacles = build_segment(
+                    &starting_segment(preview, self.bounds.height),
+                    &registry,
+                    atlas.clone(),
+                    sprite_sheet.clone(),
+                    0,
+                );
+                let walk = Walk {
                     boy,
-                    backgrounds: [
-                        Image::new(background.clone(), Point { x: 0, y: 0 }),
-                        Image::new(
-                            background,
-                            Point {
-                                x: background_width,
-                                y: 0,
-                            },
-                        ),
-                    ],
-                    obstacles: starting_obstacles,
+                    course: course::Course::new(
+                        background,
+                        starting_obstacles,
+                        atlas.clone(),
+                        course_seed,
+                        self.bounds.width,
+                    ),
+                    music,
+                    sound_effects,
+                    stats: Stats::load(profiles.storage_key(stats::STORAGE_KEY)),
+                    tutorial: tutorial::Tutorial::load(profiles.storage_key(tutorial::STORAGE_KEY)),
+                    profiles,
+                    run: RunStats::default(),
+                    seed,
+                    second,
+                    autopilot: self.config.mode == Mode::Autopilot,
+                    preview,
+                    reduced_motion: self.config.reduced_motion,
+                    mirrored: settings.mirrored,
+                    hold_to_slide: settings.hold_to_slide,
+                    replay: replay::Replay::new(course_seed),
+                    playback: replay.as_ref().map(replay::Replay::playback),
+                    wind: wind::Wind::default(),
+                    trail: afterimage::Trail::default(),
+                    history: rewind::History::default(),
+                    ui: Box::new(ui::DomUi::new(screen.clone())),
+                    screen,
+                    callbacks: self.config.callbacks.clone(),
+                    leaderboard,
+                    analytics,
+                    quality: Quality::Full,
+                    prewarm: prewarm::Prewarm::new(),
+                    autosave: suspend::Autosave::new(),
+                    bounds: self.bounds,
                     obstacle_sheet: sprite_sheet,
-                    stone,
-                    timeline,
-                }));
-
-                Ok(Box::new(WalkTheDog { machine }))
+                    registry,
+                    atlas,
+                    config,
+                };
+                let machine = Some(match preview {
+                    Some(index) => WalkTheDogState::preview(walk, index).into(),
+                    None if self.config.mode == Mode::Autopilot => {
+                        WalkTheDogStateMachine::new(walk)
+                    }
+                    None => WalkTheDogState::<profiles::ChoosingProfile>::start(
+                        walk,
+                        self.config.clone(),
+                    ),
+                });
+                self.config.callbacks.ready();
+
+                Ok(Box::new(WalkTheDog {
+                    config: self.config.clone(),
+                    machine,
+                    #[cfg(feature = "dev")]
+                    console: console::DebugConsole::new(),
+                    #[cfg(feature = "dev")]
+                    cheats: cheats::Cheats::new(self.config.cheats),
+                    race,
+                    visibility,
+                    navigation: self
+                        .config
+                        .guard_navigation
+                        .then(|| {
+                            browser::NavigationGuard::new(LEAVE_QUESTION)
+                                .map_err(|err| log::warn!("Could not guard the page {:#?}", err))
+                        })
+                        .and_then(Result::ok),
+                    bounds: self.bounds,
+                    canvas: self.canvas.clone(),
+                    #[cfg(feature = "dev")]
+                    hot_reload: hot_reload::HotReload::new(character),
+                }))
             }
             Some(_) => Err(anyhow!("Error: Game is already initialized!")),
         }
     }
 
+    fn entity_count(&self) -> usize {
+        self.machine.as_ref().map_or(0, |machine| {
+            let walk = machine.walk();
+            walk.course.obstacles.len()
+                + walk.course.coins.count()
+                + walk.course.particles.count()
+                + walk.course.popups.count()
+                + walk.trail.count()
+        })
+    }
+
+    fn set_quality(&mut self, quality: Quality) {
+        if let Some(machine) = &mut self.machine {
+            machine.walk_mut().quality = quality;
+        }
+    }
+
+    fn resolution(&self) -> Option<WorldBounds> {
+        self.config
+            .resolution
+            .map(|(width, height)| WorldBounds { width, height })
+    }
+
+    fn set_bounds(&mut self, bounds: WorldBounds) {
+        self.bounds = bounds;
+    }
+
+    fn set_canvas(&mut self, canvas: HtmlCanvasElement) {
+        if let Some(machine) = &self.machine {
+            machine.walk().screen.set_canvas(canvas.clone());
+        }
+        self.canvas = Some(canvas);
+    }
+
+    fn handle_event(&mut self, event: EngineEvent) {
+        let EngineEvent::Resized { width, height } = event;
+        let scale = self.bounds.scale_to_fit(width, height);
+        if let Some(machine) = &mut self.machine {
+            machine.walk_mut().ui.reflow(scale);
+        }
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(race) = self.race.take() {
+            race.leave();
+        }
+        self.visibility = None;
+        self.navigation = None;
+        if let Some(machine) = &self.machine {
+            if let Some(analytics) = &machine.walk().analytics {
+                analytics.end_session();
+            }
+            machine.walk().sound_effects.audio.close();
+        }
+    }
+
     fn update(&mut self, keystate: &KeyState) {
+        #[cfg(feature = "dev")]
+        let running = match self.machine.as_mut() {
+            Some(machine) => self.cheats.update(keystate, machine.walk_mut()),
+            None => true,
+        };
+        #[cfg(not(feature = "dev"))]
+        let running = true;
         if let Some(machine) = self.machine.take() {
-            self.machine.replace(machine.update(keystate));
+            self.machine.replace(if running {
+                machine.update(keystate)
+            } else {
+                machine
+            });
+        }
+
+        if let Some(machine) = self.machine.as_mut() {
+            machine.walk_mut().music.update();
+            #[cfg(feature = "dev")]
+            self.console.update(keystate, machine.walk_mut());
+            let walk = machine.walk();
+            screenshot::update(keystate, &walk.screen, walk.run.distance);
+            if let Some(race) = self.race.as_mut() {
+                race.update(machine.walk());
+            }
+            #[cfg(feature = "dev")]
+            for reload in self.hot_reload.update() {
+                hot_reload::apply(machine.walk_mut(), reload);
+            }
+            if let Some(navigation) = &mut self.navigation {
+                let running = matches!(
+                    machine,
+                    WalkTheDogStateMachine::Walking(_) | WalkTheDogStateMachine::Paused(_)
+                );
+                if let Err(err) = navigation.set_guarding(running) {
+                    log::error!("Could not guard the page {:#?}", err);
+                }
+            }
         }
 
         assert!(self.machine.is_some());
     }
     fn draw(&self, renderer: &Renderer) {
-        renderer.clear(&Rect::new_from_x_y(0, 0, 600, 600));
+        renderer.clear(&self.bounds.rect());
 
         if let Some(machine) = &self.machine {
             machine.draw(renderer);
+            if let Some(race) = &self.race {
+                let walk = machine.walk();
+                walk.in_view(renderer, || race.draw(renderer, walk));
+            }
+            #[cfg(feature = "dev")]
+            self.console.draw(renderer, machine.walk());
+            #[cfg(feature = "dev")]
+            self.cheats.draw(renderer, machine.walk());
         }
     }
 }
 
-fn rightmost(obstacle_list: &Vec<Box<dyn Obstacle>>) -> i16 {
+fn announce(message: &str) {
+    if let Err(err) = browser::announce(message) {
+        log::error!("Could not announce {:#?}", err);
+    }
+}
+
+/// What a run starts with: the previewed segment, or else the usual opening.
+fn starting_segment(preview: Option<usize>, ground: i16) -> SegmentData {
+    preview
+        .map_or_else(stone_and_platform, segments::segment)
+        .on_ground(ground)
+}
+
+/// Picks the next segment at random from the `candidates` the boy can get
+/// through at `speed`. He is back up to his running speed by the time he
+/// reaches it, however slow he is now. The same number is drawn from `rng`
+/// whatever the speed, so runners sharing a seed stay on the same course.
+fn next_segment(
+    rng: &mut ChaCha12Rng,
+    candidates: &[usize],
+    registry: &ObstacleRegistry,
+    config: &GameConfig,
+    speed: f32,
+) -> usize {
+    let speed = speed.max(config.running_speed);
+    let drawn = rng.gen_range(0..candidates.len());
+    (0..candidates.len())
+        .map(|offset| candidates[(drawn + offset) % candidates.len()])
+        .find(|&index| {
+            segments::problems(&segments::segment(index), registry, |modifier| {
+                config.reach_in(speed, modifier)
+            })
+            .is_empty()
+        })
+        .unwrap_or_else(|| {
+            log::warn!("No segment can be cleared at speed {}", speed);
+            candidates[drawn]
+        })
+}
+
+fn power_up_rng(course_seed: u64) -> ChaCha12Rng {
+    let mut rng = ChaCha12Rng::seed_from_u64(course_seed);
+    rng.set_stream(POWER_UP_STREAM);
+    rng
+}
+
+/// The top of the view on a screen of its own, above the top of the world
+/// while the boy at `boy_y` is climbing up a tower.
+fn view_top(boy_y: i16) -> i16 {
+    (boy_y - two_player::CAMERA_HEADROOM).min(0)
+}
+
+fn rightmost(obstacle_list: &[Box<dyn Obstacle>]) -> i16 {
     obstacle_list
         .iter()
         .map(|obstacle| obstacle.right())
@@ -1073,25 +3815,309 @@ mod tests {
     use web_sys::{AudioBuffer, AudioBufferOptions};
 
     use super::*;
+    use crate::engine::SheetRect;
 
     wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
+    const BOY_WIDTH: i16 = 160;
+    const BOY_HEIGHT: i16 = 136;
+    /// How far down its cell the sliding sprite is drawn.
+    const SLIDE_DROP: i16 = 41;
+
+    /// A boy on the ground, with a cell of the same size for every frame,
+    /// the sliding ones lower down.
+    fn test_boy() -> RedHatBoy {
+        boy_sliding_lower_by(SLIDE_DROP)
+    }
+
+    /// A boy whose sliding frames are drawn `slide_drop` down their cells.
+    fn boy_sliding_lower_by(slide_drop: i16) -> RedHatBoy {
+        let frames = ["Idle", "Run", "Slide", "Jump", "Dead"]
+            .iter()
+            .flat_map(|name| (1..=12).map(move |frame| (*name, frame)))
+            .map(|(name, frame)| {
+                let drop = if name == "Slide" { slide_drop } else { 0 };
+                let cell = Cell {
+                    frame: SheetRect {
+                        x: 0,
+                        y: 0,
+                        w: BOY_WIDTH,
+                        h: BOY_HEIGHT - drop,
+                    },
+                    sprite_source_size: SheetRect {
+                        x: 0,
+                        y: drop,
+                        w: BOY_WIDTH,
+                        h: BOY_HEIGHT - drop,
+                    },
+                };
+                (format!("{} ({}).png", name, frame), cell)
+            })
+            .collect();
+        let audio = Audio::new().unwrap();
+        let sound = Sound {
+            buffer: AudioBuffer::new(&AudioBufferOptions::new(1, 3000.0)).unwrap(),
+            cue: None,
+        };
+        RedHatBoy::new(
+            Sheet { frames },
+            HtmlImageElement::new().unwrap(),
+            audio,
+            SoundGroup::new(vec![sound]),
+            GameConfig::default(),
+            HEIGHT,
+        )
+    }
+
+    #[wasm_bindgen_test]
+    fn jumping_out_of_a_slide_stands_him_up_first() {
+        let mut standing = test_boy();
+        standing.run_right();
+        standing.jump();
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.slide();
+        assert!(boy.bounding_box().y() > standing.bounding_box().y());
+        boy.jump();
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Jumping(_)
+        ));
+        assert_eq!(boy.bounding_box(), standing.bounding_box());
+    }
+
+    #[wasm_bindgen_test]
+    fn a_fast_fall_lasts_until_he_lands() {
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.jump();
+        assert!(boy.velocity_y() < 0.0);
+        boy.fast_fall();
+        let context = boy.state_machine.context();
+        assert!(context.velocity.y >= 10.0);
+        assert_eq!(context.gravity_scale, 2.5);
+        boy.land_on(HEIGHT);
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Running(_)
+        ));
+        assert_eq!(boy.state_machine.context().gravity_scale, 1.0);
+    }
+
+    fn empty_sheet() -> Rc<SpriteSheet> {
+        Rc::new(SpriteSheet::new(
+            Sheet {
+                frames: HashMap::new(),
+            },
+            HtmlImageElement::new().unwrap(),
+        ))
+    }
+
+    /// A platform `height` high with its top left at `x`, `y`.
+    fn test_platform(x: i16, y: i16, width: i16, height: i16) -> Platform {
+        Platform::new(
+            empty_sheet(),
+            Point { x, y },
+            &[],
+            &[Rect::new_from_x_y(x, y, width, height)],
+        )
+    }
+
+    #[wasm_bindgen_test]
+    fn a_fast_fall_onto_a_platform_lands_instead_of_passing_through() {
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.jump();
+        boy.fast_fall();
+        let context = boy.state_machine.context_mut();
+        context.config.terminal_velocity = 200.0;
+        context.position.y = 0.0;
+        context.velocity.y = 150.0;
+        boy.update();
+        let top = boy.state_machine.context().feet_before_fall().round() as i16;
+        let platform = test_platform(0, top, WIDTH, 20);
+        // He fell past the whole of it in one frame.
+        assert!(boy.bounding_box().y() > top + 20);
+        assert!(boy.fall_box().intersects(&platform.bounding_box()));
+        assert!(boy.came_down_onto(top));
+        platform.check_intersection(&mut boy);
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Running(_)
+        ));
+        // Standing on it, as high above its top as he is above the ground.
+        assert_eq!(boy.pos_y(), top - (HEIGHT - test_boy().pos_y()));
+    }
+
+    /// A boy coming down with his feet `below` the top of a platform, with
+    /// the platform.
+    fn boy_landing_below(below: i16) -> (RedHatBoy, Platform) {
+        const TOP: i16 = 300;
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.jump();
+        let standing = HEIGHT - test_boy().pos_y();
+        let context = boy.state_machine.context_mut();
+        context.position.y = f32::from(TOP + below - standing);
+        context.velocity.y = 0.0;
+        let platform = test_platform(boy.bounding_box().x(), TOP, WIDTH, 93);
+        (boy, platform)
+    }
+
+    #[wasm_bindgen_test]
+    fn a_landing_a_little_below_the_top_of_a_platform_still_counts() {
+        let (mut boy, platform) = boy_landing_below(LANDING_FORGIVENESS - 10);
+        platform.check_intersection(&mut boy);
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Running(_)
+        ));
+
+        let (mut boy, platform) = boy_landing_below(LANDING_FORGIVENESS + 10);
+        platform.check_intersection(&mut boy);
+        assert_eq!(boy.knockout_cause(), Some(Cause::Bonked));
+    }
+
+    #[wasm_bindgen_test]
+    fn sliding_clears_a_beam_that_running_hits() {
+        // As low as the beams in obstacles.json hang.
+        const BEAM_BOTTOM: i16 = 510;
+        let mut running = boy_sliding_lower_by(0);
+        running.run_right();
+        let boy_box = running.bounding_box();
+        let beam = Beam::new(
+            empty_sheet(),
+            Rect::new_from_x_y(boy_box.x(), 0, boy_box.width, BEAM_BOTTOM),
+        );
+        let mut sliding = boy_sliding_lower_by(0);
+        sliding.run_right();
+        sliding.slide();
+        assert_eq!(sliding.bounding_box().y(), sliding.pos_y() + SLIDING_TOP);
+        assert_eq!(sliding.bounding_box().y(), 519);
+        beam.check_intersection(&mut sliding);
+        assert!(matches!(
+            sliding.state_machine,
+            RedHatBoyStateMachine::Sliding(_)
+        ));
+        assert!(running.bounding_box().y() < BEAM_BOTTOM);
+        beam.check_intersection(&mut running);
+        assert_eq!(running.knockout_cause(), Some(Cause::Bonked));
+    }
+
+    #[wasm_bindgen_test]
+    fn running_speed_is_set_rather_than_added() {
+        let mut boy = test_boy();
+        boy.run_right();
+        let speed = boy.walking_speed();
+        boy.run_right();
+        assert_eq!(boy.walking_speed(), speed);
+        boy.stumble();
+        assert!(boy.walking_speed() < 0.0);
+        (0..60).for_each(|_| boy.update());
+        assert_eq!(boy.walking_speed(), speed);
+        boy.set_speed_bonus(MAX_RUNNING_SPEED);
+        boy.boost();
+        assert_eq!(boy.walking_speed(), MAX_RUNNING_SPEED);
+    }
+
+    #[wasm_bindgen_test]
+    fn a_stumble_knocks_him_back_and_he_runs_on() {
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.stumble();
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Stumbling(_)
+        ));
+        assert!(boy.walking_speed() < 0.0);
+        (0..60).for_each(|_| boy.update());
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Running(_)
+        ));
+
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.stumble();
+        boy.knock_out(Cause::Bonked);
+        assert_eq!(boy.knockout_cause(), Some(Cause::Bonked));
+    }
+
+    #[wasm_bindgen_test]
+    fn stumbling_in_the_air_knocks_him_out() {
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.jump();
+        boy.stumble();
+        assert_eq!(boy.knockout_cause(), Some(Cause::Tripped));
+    }
+
+    #[wasm_bindgen_test]
+    fn bumping_his_head_stops_him_rising_under_the_ceiling() {
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.jump();
+        boy.update();
+        assert!(boy.velocity_y() < 0.0);
+        let ceiling = boy.bounding_box().y() + 10;
+        boy.bump_head(ceiling);
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Jumping(_)
+        ));
+        assert_eq!(boy.velocity_y(), 0.0);
+        assert_eq!(boy.bounding_box().y(), ceiling);
+    }
+
+    #[wasm_bindgen_test]
+    fn a_speed_boost_makes_the_music_tense() {
+        let mut boy = test_boy();
+        boy.run_right();
+        let tense = tense_walking_speed(&GameConfig::default());
+        assert!(boy.walking_speed() < tense);
+        boy.boost();
+        assert!(boy.walking_speed() >= tense);
+    }
+
+    #[wasm_bindgen_test]
+    fn a_shield_broken_in_the_air_stumbles_him() {
+        let mut boy = test_boy();
+        boy.run_right();
+        boy.raise_shield();
+        boy.jump();
+        boy.knock_out(Cause::Struck);
+        assert!(matches!(
+            boy.state_machine,
+            RedHatBoyStateMachine::Stumbling(_)
+        ));
+        assert!(!boy.effects.shield);
+    }
+
     #[wasm_bindgen_test]
     fn test_transition_from_game_over_to_new_game() {
-        let (_, receiver) = unbounded();
+        let document = browser::document().unwrap();
+        document
+            .body()
+            .unwrap()
+            .insert_adjacent_html("afterbegin", "<div id='ui'></div>")
+            .unwrap();
+        let screen = browser::Screen::new(browser::create_canvas(600, 600).unwrap(), "ui").unwrap();
         let image = HtmlImageElement::new().unwrap();
         let audio = Audio::new().unwrap();
         let options = AudioBufferOptions::new(1, 3000.0);
         let sound = Sound {
             buffer: AudioBuffer::new(&options).unwrap(),
+            cue: None,
         };
         let rhb = RedHatBoy::new(
             Sheet {
                 frames: HashMap::new(),
             },
             image.clone(),
-            audio,
-            sound,
+            audio.clone(),
+            SoundGroup::new(vec![sound.clone()]),
+            GameConfig::default(),
+            HEIGHT,
         );
         let sprite_sheet = SpriteSheet::new(
             Sheet {
@@ -1101,29 +4127,65 @@ mod tests {
         );
         let walk = Walk {
             boy: rhb,
-            backgrounds: [
-                Image::new(image.clone(), Point { x: 0, y: 0 }),
-                Image::new(image.clone(), Point { x: 0, y: 0 }),
-            ],
-            obstacles: vec![],
+            course: course::Course::new(
+                ScrollingBackground::new(&image, Point { x: 0, y: 0 }, WIDTH).unwrap(),
+                vec![],
+                Rc::new(Atlas::pack(&[]).unwrap()),
+                0,
+                WIDTH,
+            ),
+            music: Music::new(
+                audio.clone(),
+                Music::first_tracks()
+                    .into_iter()
+                    .map(|track| (track, sound.clone()))
+                    .collect(),
+            ),
+            sound_effects: SoundEffects::new(audio, HashMap::new()),
+            stats: Stats::default(),
+            tutorial: tutorial::Tutorial::default(),
+            profiles: profiles::Profiles::default(),
+            run: RunStats::default(),
+            seed: None,
+            second: None,
+            autopilot: false,
+            preview: None,
+            reduced_motion: false,
+            mirrored: false,
+            hold_to_slide: false,
+            replay: replay::Replay::default(),
+            playback: None,
+            wind: wind::Wind::default(),
+            trail: afterimage::Trail::default(),
+            history: rewind::History::default(),
+            ui: Box::new(ui::DomUi::new(screen.clone())),
+            screen: screen.clone(),
+            callbacks: Callbacks::default(),
+            leaderboard: None,
+            analytics: None,
+            quality: Quality::Full,
+            prewarm: prewarm::Prewarm::default(),
+            autosave: suspend::Autosave::new(),
+            bounds: DESIGN_BOUNDS,
             obstacle_sheet: Rc::new(sprite_sheet),
-            stone: image.clone(),
-            timeline: 0,
+            registry: Rc::new(ObstacleRegistry::default()),
+            atlas: Rc::new(Atlas::pack(&[]).unwrap()),
+            config: GameConfig::default(),
         };
 
-        let document = browser::document().unwrap();
-        document
-            .body()
-            .unwrap()
-            .insert_adjacent_html("afterbegin", "<div id='ui'></div>")
-            .unwrap();
-        browser::draw_ui("<p>This is the UI</p>").unwrap();
+        screen.draw_ui("<p>This is the UI</p>").unwrap();
 
         let state = WalkTheDogState {
             _state: GameOver {
-                new_game_event: receiver,
+                clip: None,
+                panel_shown: true,
+                run: FinishedRun {
+                    seed: 0,
+                    distance: 0,
+                    replay_code: None,
+                },
             },
-            walk,
+            walk: Box::new(walk),
         };
 
         state.new_game();
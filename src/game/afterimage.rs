@@ -0,0 +1,89 @@
+use super::RedHatBoy;
+use crate::engine::{Point, Renderer, Timer};
+
+/// A new afterimage is left behind every this many frames.
+const SPACING: u8 = 3;
+/// How long an afterimage takes to fade away.
+const FADE_FRAMES: u16 = 15;
+/// How see-through a new afterimage is.
+const ALPHA: f64 = 0.5;
+
+struct Afterimage {
+    frame_name: String,
+    position: Point,
+    fade: Timer,
+}
+
+/// Fading copies of the boy's recent frames, left behind him while a speed
+/// boost is on.
+#[derive(Default)]
+pub struct Trail {
+    afterimages: Vec<Afterimage>,
+    frame: u8,
+}
+
+impl Trail {
+    pub fn count(&self) -> usize {
+        self.afterimages.len()
+    }
+
+    /// Scrolls the afterimages by `x` with the world, and leaves a new one
+    /// where the boy is now if he is boosted.
+    pub fn update(&mut self, x: i16, boy: &RedHatBoy) {
+        self.fade(x);
+        self.frame = (self.frame + 1) % SPACING;
+        if boy.boosted() && self.frame == 0 {
+            self.leave(
+                boy.frame_name(),
+                Point {
+                    x: boy.pos_x(),
+                    y: boy.pos_y(),
+                },
+            );
+        }
+    }
+
+    pub fn draw(&self, renderer: &Renderer, boy: &RedHatBoy) {
+        self.afterimages.iter().for_each(|afterimage| {
+            boy.draw_ghost(
+                renderer,
+                &afterimage.frame_name,
+                afterimage.position,
+                ALPHA * afterimage.fade.fraction_left(),
+            );
+        });
+    }
+
+    fn leave(&mut self, frame_name: String, position: Point) {
+        self.afterimages.push(Afterimage {
+            frame_name,
+            position,
+            fade: Timer::new(FADE_FRAMES),
+        });
+    }
+
+    fn fade(&mut self, x: i16) {
+        self.afterimages.iter_mut().for_each(|afterimage| {
+            afterimage.position.x += x;
+            afterimage.fade.tick();
+        });
+        self.afterimages
+            .retain(|afterimage| !afterimage.fade.finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn afterimages_scroll_away_and_fade_out() {
+        let mut trail = Trail::default();
+        trail.leave("Run (1).png".to_string(), Point { x: 100, y: 400 });
+        trail.fade(-4);
+        assert_eq!(trail.afterimages[0].position, Point { x: 96, y: 400 });
+        assert!(trail.afterimages[0].fade.fraction_left() < 1.0);
+        (1..FADE_FRAMES).for_each(|_| trail.fade(-4));
+        assert!(trail.afterimages.is_empty());
+    }
+}
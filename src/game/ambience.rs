@@ -0,0 +1,218 @@
+use std::ops::Range;
+
+use rand::prelude::*;
+
+use super::{Fidget, Ready, RedHatBoy, WalkTheDogState};
+use crate::engine::{Rect, Renderer, Timer};
+
+/// Frames between the boy's fidgets, picked at random from this range.
+const FIDGET_DELAY: Range<u16> = 180..480;
+const CLOUD_COUNT: usize = 4;
+const CLOUD_HEIGHTS: Range<i16> = 20..160;
+const CLOUD_WIDTHS: Range<i16> = 60..140;
+const CLOUD_SPEEDS: Range<f32> = 0.2..0.6;
+const CLOUD_COLOR: &str = "rgb(255 255 255)";
+const CLOUD_ALPHA: f64 = 0.7;
+/// How far in front of the boy the dog wanders, from his left edge.
+const DOG_RANGE: Range<i16> = 90..320;
+const DOG_SPEED: i16 = 2;
+/// How long the dog stays put before wandering off again.
+const DOG_REST: Range<u16> = 60..240;
+const DOG_COLOR: &str = "rgb(150 100 60)";
+const DOG_WIDTH: i16 = 36;
+const DOG_HEIGHT: i16 = 14;
+const DOG_LEG_HEIGHT: i16 = 10;
+/// Legs swap every this many frames while the dog trots.
+const DOG_STEP_FRAMES: u16 = 6;
+
+struct Cloud {
+    x: f32,
+    y: i16,
+    width: i16,
+    speed: f32,
+}
+
+impl Cloud {
+    fn new(rng: &mut ThreadRng, x: f32) -> Self {
+        Cloud {
+            x,
+            y: rng.gen_range(CLOUD_HEIGHTS),
+            width: rng.gen_range(CLOUD_WIDTHS),
+            speed: rng.gen_range(CLOUD_SPEEDS),
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let x = self.x.round() as i16;
+        let puff = self.width / 2;
+        renderer.fill_rect(
+            &Rect::new_from_x_y(x, self.y, self.width, 20),
+            CLOUD_COLOR,
+            CLOUD_ALPHA,
+        );
+        renderer.fill_rect(
+            &Rect::new_from_x_y(x + puff / 2, self.y - 12, puff, 12),
+            CLOUD_COLOR,
+            CLOUD_ALPHA,
+        );
+    }
+}
+
+/// The dog, trotting back and forth in front of the boy and stopping now
+/// and then. Drawn from plain shapes until there is a dog sprite.
+#[derive(Default)]
+struct Dog {
+    /// How far in front of the boy's left edge the dog is.
+    x: i16,
+    target: i16,
+    rest: Timer,
+    frame: u16,
+}
+
+impl Dog {
+    fn update(&mut self, rng: &mut ThreadRng) {
+        self.frame = self.frame.wrapping_add(1);
+        if self.x != self.target {
+            let step = (self.target - self.x).clamp(-DOG_SPEED, DOG_SPEED);
+            self.x += step;
+            if self.x == self.target {
+                self.rest = Timer::new(rng.gen_range(DOG_REST));
+            }
+            return;
+        }
+        self.rest.tick();
+        if self.rest.finished() {
+            self.target = rng.gen_range(DOG_RANGE);
+        }
+    }
+
+    fn draw(&self, renderer: &Renderer, boy: &Rect) {
+        let x = boy.x() + self.x;
+        let ground = boy.bottom();
+        let trotting = self.x != self.target;
+        let facing_left = self.target < self.x;
+        let body_y = ground - DOG_LEG_HEIGHT - DOG_HEIGHT;
+        renderer.fill_rect(
+            &Rect::new_from_x_y(x, body_y, DOG_WIDTH, DOG_HEIGHT),
+            DOG_COLOR,
+            1.0,
+        );
+        let (head_x, tail_x) = if facing_left {
+            (x - 10, x + DOG_WIDTH)
+        } else {
+            (x + DOG_WIDTH - 6, x - 6)
+        };
+        renderer.fill_rect(
+            &Rect::new_from_x_y(head_x, body_y - 10, 16, 14),
+            DOG_COLOR,
+            1.0,
+        );
+        renderer.fill_rect(
+            &Rect::new_from_x_y(tail_x, body_y - 6, 6, 8),
+            DOG_COLOR,
+            1.0,
+        );
+        // Legs swap as it trots, and stay planted while it stands.
+        let stride = trotting && (self.frame / DOG_STEP_FRAMES) % 2 == 0;
+        [2, 10, DOG_WIDTH - 12, DOG_WIDTH - 4]
+            .into_iter()
+            .enumerate()
+            .for_each(|(leg, leg_x)| {
+                let lifted = stride == (leg % 2 == 0);
+                let height = if trotting && lifted {
+                    DOG_LEG_HEIGHT - 3
+                } else {
+                    DOG_LEG_HEIGHT
+                };
+                renderer.fill_rect(
+                    &Rect::new_from_x_y(x + leg_x, ground - DOG_LEG_HEIGHT, 4, height),
+                    DOG_COLOR,
+                    1.0,
+                );
+            });
+    }
+}
+
+/// What goes on around the boy while he waits to start: he fidgets now and
+/// then, the dog wanders about, and clouds drift across the sky.
+#[derive(Default)]
+pub struct Ambience {
+    /// Until the boy next fidgets, once it has been picked.
+    next_fidget: Option<Timer>,
+    clouds: Vec<Cloud>,
+    dog: Dog,
+}
+
+impl Ambience {
+    /// The clouds drift across a sky `width` wide, and hold still when
+    /// `reduced_motion` is on.
+    pub fn update(&mut self, boy: &mut RedHatBoy, reduced_motion: bool, width: i16) {
+        let mut rng = thread_rng();
+        if self.clouds.is_empty() {
+            self.clouds = (0..CLOUD_COUNT)
+                .map(|_| {
+                    let x = rng.gen_range(0.0..f32::from(width));
+                    Cloud::new(&mut rng, x)
+                })
+                .collect();
+        }
+        if !reduced_motion {
+            self.clouds.iter_mut().for_each(|cloud| {
+                cloud.x -= cloud.speed;
+                if cloud.x < -f32::from(cloud.width) {
+                    *cloud = Cloud::new(&mut rng, f32::from(width));
+                }
+            });
+        }
+
+        let next_fidget = self
+            .next_fidget
+            .get_or_insert_with(|| Timer::new(rng.gen_range(FIDGET_DELAY)));
+        next_fidget.tick();
+        if next_fidget.finished() {
+            boy.fidget(if rng.gen_bool(0.5) {
+                Fidget::Hop
+            } else {
+                Fidget::Jog
+            });
+            self.next_fidget = None;
+        }
+
+        self.dog.update(&mut rng);
+    }
+
+    fn draw(&self, renderer: &Renderer, boy: &RedHatBoy) {
+        self.clouds.iter().for_each(|cloud| cloud.draw(renderer));
+        self.dog.draw(renderer, &boy.bounding_box());
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    pub(super) fn draw_ambience(&self, renderer: &Renderer) {
+        self.walk.in_view(renderer, || {
+            self._state.ambience.draw(renderer, &self.walk.boy)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_dog_trots_to_where_it_is_going_and_stays_a_while() {
+        let mut rng = thread_rng();
+        let mut dog = Dog {
+            target: 5,
+            ..Dog::default()
+        };
+        dog.update(&mut rng);
+        assert_eq!(dog.x, DOG_SPEED);
+        dog.update(&mut rng);
+        dog.update(&mut rng);
+        assert_eq!(dog.x, 5);
+        assert!(!dog.rest.finished());
+        dog.update(&mut rng);
+        assert_eq!(dog.target, 5);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{cell::RefCell, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use web_sys::js_sys::JSON;
+
+use super::{Cause, Walk};
+use crate::browser::{self, EventListener};
+
+/// Sent early once this many events are waiting, so a long session doesn't
+/// build up one huge beacon.
+const MAX_BATCH: usize = 50;
+
+/// Something that happened in a session, as the endpoint gets it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AnalyticsEvent {
+    RunStarted,
+    RunEnded {
+        distance: u32,
+        knocked_out_by: Option<String>,
+        cause: Option<Cause>,
+    },
+    SessionEnded {
+        seconds: u32,
+    },
+}
+
+#[derive(Debug, Default)]
+struct Batch {
+    events: Vec<AnalyticsEvent>,
+    /// When the session started, in milliseconds since the page loaded.
+    started: f64,
+    ended: bool,
+}
+
+impl Batch {
+    /// Adds `event`, and says whether the batch should be sent now.
+    fn push(&mut self, event: AnalyticsEvent) -> bool {
+        self.events.push(event);
+        self.events.len() >= MAX_BATCH
+    }
+}
+
+/// Collects what players do, for tuning the difficulty from real runs. Only
+/// made when the page opts in with an endpoint. Events are sent in batches
+/// with `sendBeacon`, the last as the page goes away, so sending never holds
+/// up the game.
+pub struct Analytics {
+    endpoint: Rc<str>,
+    batch: Rc<RefCell<Batch>>,
+    _page_hide: EventListener<web_sys::Event>,
+}
+
+impl Analytics {
+    pub fn new(endpoint: String) -> Result<Self> {
+        let endpoint: Rc<str> = endpoint.into();
+        let batch = Rc::new(RefCell::new(Batch {
+            started: browser::now()?,
+            ..Batch::default()
+        }));
+        let page_hide = {
+            let endpoint = endpoint.clone();
+            let batch = batch.clone();
+            browser::on_page_hide(move || end_session(&endpoint, &batch))?
+        };
+        Ok(Analytics {
+            endpoint,
+            batch,
+            _page_hide: page_hide,
+        })
+    }
+
+    pub fn record(&self, event: AnalyticsEvent) {
+        let full = self.batch.borrow_mut().push(event);
+        if full {
+            flush(&self.endpoint, &self.batch);
+        }
+    }
+
+    /// Sends what is left, along with how long the session lasted, as when
+    /// the game is stopped without the page going away.
+    pub fn end_session(&self) {
+        end_session(&self.endpoint, &self.batch);
+    }
+}
+
+fn end_session(endpoint: &str, batch: &RefCell<Batch>) {
+    {
+        let mut batch = batch.borrow_mut();
+        if batch.ended {
+            return;
+        }
+        batch.ended = true;
+        let elapsed = browser::now().map_or(0.0, |now| now - batch.started);
+        batch.push(AnalyticsEvent::SessionEnded {
+            seconds: (elapsed / 1000.0) as u32,
+        });
+    }
+    flush(endpoint, batch);
+}
+
+fn flush(endpoint: &str, batch: &RefCell<Batch>) {
+    let events = std::mem::take(&mut batch.borrow_mut().events);
+    if events.is_empty() {
+        return;
+    }
+    if let Err(err) = send(endpoint, &events) {
+        log::warn!(
+            "Could not send {} analytics events {:#?}",
+            events.len(),
+            err
+        );
+    }
+}
+
+fn send(endpoint: &str, events: &[AnalyticsEvent]) -> Result<()> {
+    let value = events
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|err| anyhow!("Could not serialize analytics events {:#?}", err))?;
+    let json: String = JSON::stringify(&value)
+        .map_err(|err| anyhow!("Could not stringify analytics events {:#?}", err))?
+        .into();
+    browser::send_beacon(endpoint, &json)
+}
+
+impl Walk {
+    /// Records `event` if the page collects analytics, for the player's own
+    /// runs only.
+    pub(super) fn record(&self, event: AnalyticsEvent) {
+        if !self.players_own_run() {
+            return;
+        }
+        if let Some(analytics) = &self.analytics {
+            analytics.record(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_full_batch_asks_to_be_sent() {
+        let mut batch = Batch::default();
+        (1..MAX_BATCH).for_each(|_| assert!(!batch.push(AnalyticsEvent::RunStarted)));
+        assert!(batch.push(AnalyticsEvent::RunStarted));
+    }
+}
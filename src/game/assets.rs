@@ -0,0 +1,89 @@
+//! Every file the game loads. The offline cache manifest is built from
+//! [`all`], so new files belong here rather than inline at the call site.
+
+use crate::{
+    engine::{FONT_IMAGE, FONT_METRICS},
+    segments::STONE,
+};
+
+/// Runners picked with `?character=`. Each has a `<name>.json` sprite sheet
+/// and a `<name>.png` image.
+const CHARACTERS: [&str; 1] = ["rhb"];
+pub const SFX_SPRITE: &str = "sfx.json";
+pub const SFX_EVENTS_SPRITE: &str = "sfx_events.json";
+pub const AMBIENT_SONG: &str = "background_song.mp3";
+pub const TENSE_SONG: &str = "tense_song.wav";
+pub const GAME_OVER_STING: &str = "game_over_sting.wav";
+pub const TILES_SHEET: &str = "tiles.json";
+pub const TILES_IMAGE: &str = "tiles.png";
+pub const BACKGROUND: &str = "BG.png";
+pub const COIN: &str = "Coin.png";
+pub const MAGNET: &str = "Magnet.png";
+pub const SHIELD: &str = "Shield.png";
+pub const SPEED: &str = "Speed.png";
+pub const CONFIG: &str = "config.json";
+pub const OBSTACLES: &str = "obstacles.json";
+
+/// Files referenced from the sprite sheets and the page rather than loaded
+/// directly by the game.
+const REFERENCED: [&str; 5] = [
+    "SFX_Jump_23.mp3",
+    "sfx_events.wav",
+    "style.css",
+    "Button.svg",
+    "kenney_future_narrow-webfont.woff2",
+];
+
+/// The requested character, or the Red Hat Boy if it is unknown.
+pub fn character(name: Option<&str>) -> &'static str {
+    match name {
+        Some(name) => CHARACTERS
+            .into_iter()
+            .find(|character| *character == name)
+            .unwrap_or_else(|| {
+                log::warn!("Unknown character {}", name);
+                CHARACTERS[0]
+            }),
+        None => CHARACTERS[0],
+    }
+}
+
+pub fn character_sheet(character: &str) -> String {
+    format!("{}.json", character)
+}
+
+pub fn character_image(character: &str) -> String {
+    format!("{}.png", character)
+}
+
+pub fn all() -> Vec<String> {
+    let mut assets: Vec<String> = CHARACTERS
+        .into_iter()
+        .flat_map(|character| [character_sheet(character), character_image(character)])
+        .collect();
+    assets.extend(
+        [
+            SFX_SPRITE,
+            SFX_EVENTS_SPRITE,
+            AMBIENT_SONG,
+            TENSE_SONG,
+            GAME_OVER_STING,
+            TILES_SHEET,
+            TILES_IMAGE,
+            BACKGROUND,
+            STONE,
+            COIN,
+            MAGNET,
+            SHIELD,
+            SPEED,
+            FONT_IMAGE,
+            FONT_METRICS,
+            CONFIG,
+            OBSTACLES,
+        ]
+        .into_iter()
+        .chain(REFERENCED)
+        .map(str::to_string),
+    );
+    assets
+}
@@ -0,0 +1,77 @@
+use super::{
+    autopilot::{self, Action},
+    Ready, Walk, WalkTheDogState, WalkTheDogStateMachine,
+};
+use crate::engine::{KeyState, Point, Renderer};
+
+/// Frames the title screen waits without input before the demo starts.
+pub const ATTRACT_DELAY: u16 = 600;
+
+const PROMPT: &str = "DEMO - press any key";
+/// Half the width of the prompt, to centre it.
+const PROMPT_HALF_WIDTH: i16 = 110;
+const PROMPT_TOP: i16 = 40;
+
+/// The attract demo shown on the title screen, with the autopilot running.
+pub struct Attract;
+
+pub enum AttractEndState {
+    Complete(WalkTheDogState<Ready>),
+    Continue(WalkTheDogState<Attract>),
+}
+
+impl From<AttractEndState> for WalkTheDogStateMachine {
+    fn from(state: AttractEndState) -> Self {
+        match state {
+            AttractEndState::Complete(state) => state.into(),
+            AttractEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Attract>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Attract>) -> Self {
+        WalkTheDogStateMachine::Attract(state)
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    pub(super) fn start_demo(mut self) -> WalkTheDogState<Attract> {
+        self.walk.boy.run_right();
+        WalkTheDogState {
+            _state: Attract,
+            walk: self.walk,
+        }
+    }
+}
+
+impl WalkTheDogState<Attract> {
+    pub fn update(mut self, keystate: &KeyState) -> AttractEndState {
+        if keystate.any_pressed() || self.walk.boy.knocked_out() {
+            return AttractEndState::Complete(self.stop());
+        }
+
+        match autopilot::decide(&self.walk.boy, self.walk.course.obstacles.active()) {
+            Some(Action::Jump) => self.walk.boy.jump(),
+            Some(Action::Slide) => self.walk.boy.slide(),
+            None => {}
+        }
+        self.walk.advance();
+        AttractEndState::Continue(self)
+    }
+
+    pub fn draw_prompt(&self, renderer: &Renderer) {
+        let location = Point {
+            x: self.walk.bounds.width / 2 - PROMPT_HALF_WIDTH,
+            y: PROMPT_TOP,
+        };
+        self.walk.layout().draw_prompt(renderer, PROMPT, location);
+    }
+
+    fn stop(self) -> WalkTheDogState<Ready> {
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: Box::new(Walk::reset(*self.walk)),
+        }
+    }
+}
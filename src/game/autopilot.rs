@@ -0,0 +1,36 @@
+use super::{Obstacle, RedHatBoy};
+
+/// How many frames ahead of an obstacle the autopilot reacts.
+const JUMP_LEAD_FRAMES: i16 = 10;
+const SLIDE_LEAD_FRAMES: i16 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Jump,
+    Slide,
+}
+
+/// Picks an action by looking at the nearest obstacle ahead of the boy that
+/// isn't below his feet. Obstacles sitting low in his path are jumped over,
+/// and anything hanging higher up is slid under. Ramps are run over.
+pub fn decide(boy: &RedHatBoy, obstacles: &[Box<dyn Obstacle>]) -> Option<Action> {
+    let bounding_box = boy.bounding_box();
+    let lead = (boy.walking_speed().round() as i16).max(1);
+    let middle = bounding_box.y() + bounding_box.height / 2;
+    obstacles
+        .iter()
+        .filter(|obstacle| !obstacle.walkable())
+        .map(|obstacle| obstacle.bounding_box())
+        .filter(|obstacle| {
+            obstacle.right() > bounding_box.x() && obstacle.y() < bounding_box.bottom()
+        })
+        .min_by_key(|obstacle| obstacle.x())
+        .and_then(|obstacle| {
+            let distance = obstacle.x() - bounding_box.right();
+            if obstacle.y() > middle {
+                (distance <= lead * JUMP_LEAD_FRAMES).then_some(Action::Jump)
+            } else {
+                (distance <= lead * SLIDE_LEAD_FRAMES).then_some(Action::Slide)
+            }
+        })
+}
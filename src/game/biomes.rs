@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use super::assets;
+
+/// How much of the course each biome lasts for.
+pub const BIOME_LENGTH: u32 = 10_000;
+/// The segment placed where one biome gives way to the next. Hills are only
+/// found there, so they mark the change.
+const TRANSITION: usize = 3;
+
+/// A stretch of the course built from its own mix of segments.
+pub struct Biome {
+    pub name: &'static str,
+    /// The music while the boy is running through it.
+    pub track: &'static str,
+    segments: &'static [usize],
+}
+
+/// The biomes in the order the course runs through them, starting again
+/// from the first after the last.
+static BIOMES: [Biome; 3] = [
+    Biome {
+        name: "Meadow",
+        track: assets::AMBIENT_SONG,
+        segments: &[0, 1, 6, 8],
+    },
+    Biome {
+        name: "Cliffs",
+        // A placeholder until Cliffs has its own song. While it shares the
+        // tense one, speeding up here doesn't change the music.
+        track: assets::TENSE_SONG,
+        segments: &[0, 4, 5, 7, 10],
+    },
+    Biome {
+        name: "Woods",
+        track: assets::AMBIENT_SONG,
+        segments: &[1, 2, 6, 9, 11],
+    },
+];
+
+fn biome(number: u32) -> &'static Biome {
+    &BIOMES[number as usize % BIOMES.len()]
+}
+
+/// The biome the boy is in once he has run `distance`.
+pub fn at(distance: u32) -> &'static Biome {
+    biome(distance / BIOME_LENGTH)
+}
+
+/// The biome that comes after `current`.
+pub fn after(current: &Biome) -> &'static Biome {
+    let index = BIOMES
+        .iter()
+        .position(|biome| std::ptr::eq(biome, current))
+        .unwrap_or_default();
+    &BIOMES[(index + 1) % BIOMES.len()]
+}
+
+/// The biome the boy runs into on getting from `from` to `to`, if he
+/// crossed into one.
+pub fn entered(from: u32, to: u32) -> Option<&'static Biome> {
+    (to / BIOME_LENGTH > from / BIOME_LENGTH).then(|| biome(to / BIOME_LENGTH))
+}
+
+/// Decides which segments the course is built from as it grows. It goes by
+/// how much course has been placed rather than how far the boy has run, so
+/// runners sharing a seed go through the same biomes at the same places.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BiomeSchedule {
+    placed: u32,
+    current: u32,
+}
+
+impl BiomeSchedule {
+    /// The segments to pick the next one from: the transition once the
+    /// course has reached the next biome, and the current biome's otherwise.
+    pub fn next(&mut self) -> &'static [usize] {
+        let due = self.placed / BIOME_LENGTH;
+        if due != self.current {
+            self.current = due;
+            &[TRANSITION]
+        } else {
+            biome(self.current).segments
+        }
+    }
+
+    /// Records that the course has grown by `width`.
+    pub fn extend(&mut self, width: i16) {
+        self.placed += u32::from(width.max(0).unsigned_abs());
+    }
+
+    /// Counts `distance` more course as placed without building it, straight
+    /// into whichever biome that lands in. There's no transition when the
+    /// course didn't run through the change.
+    #[cfg(feature = "dev")]
+    pub fn skip(&mut self, distance: i64) {
+        self.placed = (i64::from(self.placed) + distance).clamp(0, i64::from(u32::MAX)) as u32;
+        self.current = self.placed / BIOME_LENGTH;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_on_to_the_next_biome_through_a_transition() {
+        let mut schedule = BiomeSchedule::default();
+        assert_eq!(schedule.next(), BIOMES[0].segments);
+        schedule.extend(6000);
+        assert_eq!(schedule.next(), BIOMES[0].segments);
+        schedule.extend(4000);
+        assert_eq!(schedule.next(), [TRANSITION]);
+        assert_eq!(schedule.next(), BIOMES[1].segments);
+        assert!(BIOMES
+            .iter()
+            .all(|biome| !biome.segments.contains(&TRANSITION)));
+    }
+
+    #[test]
+    fn names_the_biome_the_boy_runs_into() {
+        assert!(entered(0, BIOME_LENGTH - 1).is_none());
+        assert_eq!(
+            entered(BIOME_LENGTH - 1, BIOME_LENGTH).map(|biome| biome.name),
+            Some("Cliffs")
+        );
+        assert_eq!(
+            entered(3 * BIOME_LENGTH - 1, 3 * BIOME_LENGTH).map(|biome| biome.name),
+            Some("Meadow")
+        );
+        assert_eq!(after(at(2 * BIOME_LENGTH)).name, "Meadow");
+    }
+}
@@ -0,0 +1,241 @@
+use std::rc::Rc;
+
+use super::{Cause, RedHatBoy};
+use crate::{
+    engine::{Atlas, Rect, Renderer, Timer, WorldBounds},
+    segments::STONE,
+};
+
+/// How far apart boss fights are, and where the first one is.
+pub const BOSS_DISTANCE: u32 = 10000;
+/// Added to the run's score for surviving a boss.
+pub const BOSS_BONUS: u32 = 2000;
+
+const BOSS_SIZE: i16 = 160;
+/// How far in from the right edge of the screen he stands to throw.
+const BOSS_MARGIN: i16 = 20;
+const BOSS_SPEED: i16 = 4;
+const ATTACKS: u8 = 6;
+const ATTACK_INTERVAL: u16 = 70;
+const PROJECTILE_SIZE: i16 = 54;
+const PROJECTILE_SPEED: i16 = 7;
+/// How far above the ground the top of a throw along the ground is, to be
+/// jumped over.
+const LOW_ATTACK: i16 = PROJECTILE_SIZE;
+/// How far above the ground the top of a throw at head height is, to be
+/// slid under.
+const HIGH_ATTACK: i16 = 141;
+
+/// Where he stands to throw, on a screen `width` wide.
+fn home(width: i16) -> i16 {
+    width - BOSS_SIZE - BOSS_MARGIN
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Waiting for the last obstacles to scroll away before the scroll stops.
+    Waiting,
+    Entering,
+    Attacking {
+        thrown: u8,
+        /// Until the next throw.
+        cooldown: Timer,
+    },
+    Retreating,
+    Defeated,
+}
+
+impl Phase {
+    /// Moves the fight on by one frame. `clear` is whether the road ahead has
+    /// no obstacles left, `x` is where the boss is and `projectiles` is how
+    /// many of his throws are still flying, on a screen `width` wide.
+    fn next(self, clear: bool, x: i16, projectiles: usize, width: i16) -> Self {
+        match self {
+            Phase::Waiting if clear => Phase::Entering,
+            Phase::Entering if x <= home(width) => Phase::Attacking {
+                thrown: 0,
+                cooldown: Timer::new(ATTACK_INTERVAL),
+            },
+            Phase::Attacking { thrown, .. } if thrown == ATTACKS => Phase::Retreating,
+            Phase::Attacking {
+                thrown,
+                mut cooldown,
+            } => {
+                cooldown.tick();
+                Phase::Attacking { thrown, cooldown }
+            }
+            Phase::Retreating if x >= width && projectiles == 0 => Phase::Defeated,
+            phase => phase,
+        }
+    }
+
+    /// The boss fight pauses the run from when he appears until he is gone.
+    fn holds_scroll(self) -> bool {
+        !matches!(self, Phase::Waiting | Phase::Defeated)
+    }
+}
+
+/// A scripted fight that stops the run. The boss walks in, throws stones
+/// low and high in turn, and leaves once he has thrown them all.
+#[derive(Clone)]
+pub struct Boss {
+    atlas: Rc<Atlas>,
+    frame: Rect,
+    x: i16,
+    phase: Phase,
+    projectiles: Vec<Rect>,
+    bounds: WorldBounds,
+}
+
+impl Boss {
+    /// A boss coming on from the right of a world `bounds` big.
+    pub fn new(atlas: Rc<Atlas>, bounds: WorldBounds) -> Option<Self> {
+        let frame = atlas.frame(STONE)?;
+        Some(Boss {
+            atlas,
+            frame,
+            x: bounds.width,
+            phase: Phase::Waiting,
+            projectiles: vec![],
+            bounds,
+        })
+    }
+
+    pub fn update(&mut self, clear: bool, boy: &mut RedHatBoy) {
+        self.phase = self
+            .phase
+            .next(clear, self.x, self.projectiles.len(), self.bounds.width);
+        match self.phase {
+            Phase::Entering => self.x -= BOSS_SPEED,
+            Phase::Attacking {
+                thrown,
+                mut cooldown,
+            } if cooldown.finished() => {
+                let height = if thrown % 2 == 0 {
+                    LOW_ATTACK
+                } else {
+                    HIGH_ATTACK
+                };
+                let y = self.bounds.height - height;
+                self.projectiles.push(Rect::new_from_x_y(
+                    self.x,
+                    y,
+                    PROJECTILE_SIZE,
+                    PROJECTILE_SIZE,
+                ));
+                cooldown.restart();
+                self.phase = Phase::Attacking {
+                    thrown: thrown + 1,
+                    cooldown,
+                };
+            }
+            Phase::Retreating => self.x += BOSS_SPEED,
+            _ => {}
+        }
+
+        self.projectiles.retain(|projectile| projectile.right() > 0);
+        for projectile in self.projectiles.iter_mut() {
+            projectile.set_x(projectile.x() - PROJECTILE_SPEED);
+        }
+        if self
+            .projectiles
+            .iter()
+            .any(|projectile| boy.bounding_box().intersects(projectile))
+        {
+            boy.knock_out(Cause::Struck);
+        }
+    }
+
+    pub fn holds_scroll(&self) -> bool {
+        self.phase.holds_scroll()
+    }
+
+    pub fn defeated(&self) -> bool {
+        self.phase == Phase::Defeated
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        if self.x < self.bounds.width {
+            let body =
+                Rect::new_from_x_y(self.x, self.bounds.height - BOSS_SIZE, BOSS_SIZE, BOSS_SIZE);
+            self.atlas.draw(renderer, &self.frame, &body);
+            renderer.draw_bounding_box(&body);
+        }
+        for projectile in self.projectiles.iter() {
+            self.atlas.draw(renderer, &self.frame, projectile);
+            renderer.draw_hazard(projectile);
+            renderer.draw_bounding_box(projectile);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::WIDTH, *};
+
+    #[test]
+    fn waits_for_a_clear_road_before_entering() {
+        assert_eq!(Phase::Waiting.next(false, WIDTH, 0, WIDTH), Phase::Waiting);
+        assert_eq!(Phase::Waiting.next(true, WIDTH, 0, WIDTH), Phase::Entering);
+        assert!(!Phase::Waiting.holds_scroll());
+        assert!(Phase::Entering.holds_scroll());
+    }
+
+    #[test]
+    fn attacks_once_in_place_and_retreats_after_the_last_throw() {
+        assert_eq!(
+            Phase::Entering.next(true, home(WIDTH) + 1, 0, WIDTH),
+            Phase::Entering
+        );
+        assert_eq!(
+            Phase::Entering.next(true, home(WIDTH), 0, WIDTH),
+            Phase::Attacking {
+                thrown: 0,
+                cooldown: Timer::new(ATTACK_INTERVAL)
+            }
+        );
+        let cooldown = Timer::new(ATTACK_INTERVAL);
+        let mut ticked = cooldown;
+        ticked.tick();
+        assert_eq!(
+            Phase::Attacking {
+                thrown: 2,
+                cooldown
+            }
+            .next(true, home(WIDTH), 1, WIDTH),
+            Phase::Attacking {
+                thrown: 2,
+                cooldown: ticked
+            }
+        );
+        assert_eq!(
+            Phase::Attacking {
+                thrown: ATTACKS,
+                cooldown
+            }
+            .next(true, home(WIDTH), 1, WIDTH),
+            Phase::Retreating
+        );
+    }
+
+    #[test]
+    fn is_defeated_once_gone_and_every_throw_has_landed() {
+        assert_eq!(
+            Phase::Retreating.next(true, WIDTH, 1, WIDTH),
+            Phase::Retreating
+        );
+        assert_eq!(
+            Phase::Retreating.next(true, WIDTH - 1, 0, WIDTH),
+            Phase::Retreating
+        );
+        assert_eq!(
+            Phase::Retreating.next(true, WIDTH, 0, WIDTH),
+            Phase::Defeated
+        );
+        assert_eq!(
+            Phase::Retreating.next(true, WIDTH, 0, 1067),
+            Phase::Retreating
+        );
+        assert!(!Phase::Defeated.holds_scroll());
+    }
+}
@@ -0,0 +1,150 @@
+use rand::prelude::*;
+
+use super::{announce, GameEvent, GameOver, RunEnd, Walk, WalkTheDogState, WalkTheDogStateMachine};
+use crate::{
+    browser::recorder::CanvasRecorder,
+    engine::{Physics, Point, Rect, Renderer, Timer, Vector, WorldBounds},
+};
+
+/// How long the celebration plays before the game over panel shows.
+const CELEBRATION_FRAMES: u16 = 180;
+const CONFETTI_COUNT: usize = 80;
+const CONFETTI_SIZE: i16 = 6;
+const CONFETTI_COLORS: [&str; 5] = [
+    "rgb(230 60 60)",
+    "rgb(250 200 40)",
+    "rgb(60 180 90)",
+    "rgb(60 120 230)",
+    "rgb(200 90 210)",
+];
+/// Confetti feels this much of the world's gravity.
+const CONFETTI_GRAVITY_SCALE: f32 = 0.08;
+/// Confetti can't fall faster than this, so it flutters down.
+const CONFETTI_MAX_FALL: f32 = 2.5;
+const TEXT: &str = "NEW RECORD";
+/// Half the width of the text, to centre it.
+const TEXT_HALF_WIDTH: i16 = 70;
+const TEXT_TOP: i16 = 160;
+/// Frames for the text to fade down and back up again.
+const PULSE_FRAMES: f64 = 40.0;
+
+struct Confetti {
+    position: Vector,
+    velocity: Vector,
+    color: &'static str,
+}
+
+impl Confetti {
+    /// Thrown up from somewhere along the bottom of a world `bounds` big.
+    fn thrown(rng: &mut ThreadRng, bounds: WorldBounds) -> Self {
+        Confetti {
+            position: Vector {
+                x: rng.gen_range(0.0..f32::from(bounds.width)),
+                y: f32::from(bounds.height),
+            },
+            velocity: Vector {
+                x: rng.gen_range(-1.5..1.5),
+                y: rng.gen_range(-9.0..-5.0),
+            },
+            color: CONFETTI_COLORS[rng.gen_range(0..CONFETTI_COLORS.len())],
+        }
+    }
+
+    fn update(&mut self, physics: &Physics) {
+        (self.position, self.velocity) = physics.step(self.position, self.velocity);
+    }
+}
+
+/// Confetti and a fanfare after a run beats the best distance, before the
+/// game over panel shows. There's no confetti when `reduced_motion` is on.
+pub struct Celebrating {
+    timer: Timer,
+    confetti: Vec<Confetti>,
+    /// How the confetti falls: slowly, under a little of the world's gravity.
+    physics: Physics,
+    /// Kept recording through the celebration, for the clip on the panel.
+    recorder: Option<CanvasRecorder>,
+}
+
+pub enum CelebratingEndState {
+    Complete(WalkTheDogState<GameOver>),
+    Continue(WalkTheDogState<Celebrating>),
+}
+
+impl From<CelebratingEndState> for WalkTheDogStateMachine {
+    fn from(state: CelebratingEndState) -> Self {
+        match state {
+            CelebratingEndState::Complete(state) => state.into(),
+            CelebratingEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Celebrating>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Celebrating>) -> Self {
+        WalkTheDogStateMachine::Celebrating(state)
+    }
+}
+
+/// Celebrates the new best, then shows the game over panel.
+pub(super) fn celebrate(walk: Box<Walk>, recorder: Option<CanvasRecorder>) -> RunEnd {
+    announce(&format!("New record! {}", walk.run.distance));
+    walk.sound_effects.play(GameEvent::NewRecord);
+    let mut rng = thread_rng();
+    let count = if walk.reduced_motion {
+        0
+    } else {
+        CONFETTI_COUNT
+    };
+    RunEnd::Celebrate(WalkTheDogState {
+        _state: Celebrating {
+            timer: Timer::new(CELEBRATION_FRAMES),
+            confetti: (0..count)
+                .map(|_| Confetti::thrown(&mut rng, walk.bounds))
+                .collect(),
+            physics: Physics {
+                gravity_scale: CONFETTI_GRAVITY_SCALE,
+                max_fall_speed: CONFETTI_MAX_FALL,
+                ..walk.config.physics()
+            },
+            recorder,
+        },
+        walk,
+    })
+}
+
+impl WalkTheDogState<Celebrating> {
+    pub fn update(mut self) -> CelebratingEndState {
+        self._state.timer.tick();
+        let physics = self._state.physics;
+        self._state
+            .confetti
+            .iter_mut()
+            .for_each(|confetti| confetti.update(&physics));
+        if self._state.timer.finished() {
+            let recorder = self._state.recorder.take();
+            CelebratingEndState::Complete(GameOver::show(self.walk, recorder))
+        } else {
+            CelebratingEndState::Continue(self)
+        }
+    }
+
+    pub fn draw_celebration(&self, renderer: &Renderer) {
+        self._state.confetti.iter().for_each(|confetti| {
+            renderer.fill_rect(
+                &Rect::new(confetti.position.round(), CONFETTI_SIZE, CONFETTI_SIZE),
+                confetti.color,
+                1.0,
+            );
+        });
+        let elapsed = f64::from(CELEBRATION_FRAMES - self._state.timer.remaining());
+        let alpha = 0.6 + 0.4 * (elapsed * std::f64::consts::TAU / PULSE_FRAMES).cos();
+        let location = Point {
+            x: self.walk.bounds.width / 2 - TEXT_HALF_WIDTH,
+            y: TEXT_TOP,
+        };
+        if let Err(err) = renderer.draw_text_with_alpha(TEXT, &location, alpha) {
+            log::error!("Could not draw the new record {:#?}", err);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use super::{biomes, pause::STEP_KEY, Walk};
+use crate::engine::{KeyState, Point, Renderer};
+
+/// Typed anywhere, turns developer mode on.
+const SEQUENCE: [&str; 5] = ["KeyI", "KeyD", "KeyD", "KeyQ", "KeyD"];
+const GOD_KEY: &str = "KeyG";
+const FASTER_KEY: &str = "Equal";
+const SLOWER_KEY: &str = "Minus";
+/// Stops the game updating, until it's pressed again.
+const FREEZE_KEY: &str = "KeyF";
+/// Teleports to the start of the next biome.
+const TELEPORT_KEY: &str = "KeyT";
+const CHEAT_KEYS: [&str; 6] = [
+    GOD_KEY,
+    FASTER_KEY,
+    SLOWER_KEY,
+    FREEZE_KEY,
+    STEP_KEY,
+    TELEPORT_KEY,
+];
+const SPEED_STEP: f32 = 1.0;
+/// How far in from the right edge of the screen the label starts.
+const LABEL_INSET: i16 = 200;
+const LABEL_TOP: i16 = 30;
+
+/// Where typing [`SEQUENCE`] has got to.
+#[derive(Debug, Default)]
+struct Sequence {
+    typed: usize,
+}
+
+impl Sequence {
+    /// Whether `key` finishes the sequence.
+    fn press(&mut self, key: &str) -> bool {
+        self.typed = if key == SEQUENCE[self.typed] {
+            self.typed + 1
+        } else if key == SEQUENCE[0] {
+            1
+        } else {
+            0
+        };
+        let finished = self.typed == SEQUENCE.len();
+        if finished {
+            self.typed = 0;
+        }
+        finished
+    }
+}
+
+/// Developer mode, for getting to the parts of a run worth testing
+/// quickly: god mode, speed up and down, freezing and stepping one update
+/// at a time, and teleporting ahead. Turned on with `?cheats` or by typing
+/// IDDQD.
+#[derive(Debug, Default)]
+pub struct Cheats {
+    enabled: bool,
+    sequence: Sequence,
+    frozen: bool,
+}
+
+impl Cheats {
+    pub fn new(enabled: bool) -> Self {
+        Cheats {
+            enabled,
+            ..Cheats::default()
+        }
+    }
+
+    /// Handles the cheat keys, and says whether the game should update
+    /// this time round.
+    pub fn update(&mut self, keystate: &KeyState, walk: &mut Walk) -> bool {
+        if !self.enabled {
+            let typed = keystate
+                .pressed_this_update()
+                .any(|key| self.sequence.press(key));
+            if typed {
+                self.enabled = true;
+                super::announce("Developer mode on");
+            }
+            return true;
+        }
+
+        if CHEAT_KEYS.iter().any(|key| keystate.just_pressed(key)) {
+            walk.run.cheated = true;
+        }
+        if keystate.just_pressed(GOD_KEY) {
+            walk.boy.invincible = !walk.boy.invincible;
+        }
+        let speed = walk.boy.speed();
+        if keystate.just_pressed(FASTER_KEY) {
+            walk.boy.set_speed_bonus(speed.bonus + SPEED_STEP);
+        } else if keystate.just_pressed(SLOWER_KEY) {
+            walk.boy
+                .set_speed_bonus((speed.bonus - SPEED_STEP).max(-speed.base));
+        }
+        if keystate.just_pressed(TELEPORT_KEY) {
+            let next_biome = (walk.run.distance / biomes::BIOME_LENGTH + 1) * biomes::BIOME_LENGTH;
+            walk.teleport(next_biome);
+        }
+        if keystate.just_pressed(FREEZE_KEY) {
+            self.frozen = !self.frozen;
+        }
+        !self.frozen || keystate.just_pressed(STEP_KEY)
+    }
+
+    pub fn draw(&self, renderer: &Renderer, walk: &Walk) {
+        if !self.enabled {
+            return;
+        }
+        let label = format!(
+            "DEV{}{} {:.0}",
+            if walk.boy.invincible { " GOD" } else { "" },
+            if self.frozen { " FROZEN" } else { "" },
+            walk.boy.state_machine.context().velocity.x
+        );
+        let location = Point {
+            x: walk.bounds.width - LABEL_INSET,
+            y: LABEL_TOP,
+        };
+        if let Err(err) = renderer.draw_text(&label, &location) {
+            log::error!("Could not draw text {:#?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_the_sequence_turns_developer_mode_on() {
+        let mut sequence = Sequence::default();
+        let typed = |sequence: &mut Sequence, keys: &[&str]| {
+            keys.iter().filter(|key| sequence.press(key)).count()
+        };
+        assert_eq!(typed(&mut sequence, &["KeyI", "KeyD", "Space"]), 0);
+        assert_eq!(typed(&mut sequence, &["KeyD", "KeyQ", "KeyD"]), 0);
+        assert_eq!(typed(&mut sequence, &["KeyI", "KeyI"]), 0);
+        assert_eq!(typed(&mut sequence, &["KeyD", "KeyD", "KeyQ", "KeyD"]), 1);
+        assert_eq!(typed(&mut sequence, &SEQUENCE), 1);
+    }
+}
@@ -0,0 +1,90 @@
+use futures::channel::oneshot::{channel, Receiver};
+use web_sys::Blob;
+
+use super::screenshot;
+use crate::browser::{self, recorder::CanvasRecorder, Screen};
+
+/// Starts recording `screen`'s canvas for a clip, or `None` if the browser
+/// can't.
+pub fn record(screen: &Screen) -> Option<CanvasRecorder> {
+    match CanvasRecorder::start(&screen.canvas()) {
+        Ok(recorder) => Some(recorder),
+        Err(err) => {
+            log::warn!("Not recording a clip {:#?}", err);
+            None
+        }
+    }
+}
+
+/// A video of the rewind after a knockout, shared from the game over screen.
+pub struct Clip {
+    /// The video while the browser is still putting it together.
+    pending: Option<Receiver<Blob>>,
+    video: Option<Blob>,
+}
+
+impl Clip {
+    /// Stops `recorder`, for the video to be shared once it's ready.
+    pub fn new(recorder: CanvasRecorder) -> Self {
+        let (video_tx, pending) = channel();
+        browser::spawn_local(async move {
+            match recorder.stop().await {
+                Ok(video) => {
+                    let _ = video_tx.send(video);
+                }
+                Err(err) => log::error!("Could not record a clip {:#?}", err),
+            }
+        });
+        Clip {
+            pending: Some(pending),
+            video: None,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if let Some(pending) = &mut self.pending {
+            match pending.try_recv() {
+                Ok(Some(video)) => {
+                    self.video = Some(video);
+                    self.pending = None;
+                }
+                Ok(None) => {}
+                Err(_canceled) => self.pending = None,
+            }
+        }
+    }
+
+    /// `distance` goes in the file name, as for screenshots.
+    pub fn share(&self, distance: u32) {
+        let Some(video) = self.video.clone() else {
+            log::warn!("The clip isn't ready to share");
+            return;
+        };
+        browser::spawn_local(async move {
+            let file_name = format!("walk-the-dog-{}.{}", distance, extension(&video.type_()));
+            if let Err(err) = screenshot::share(&video, &file_name).await {
+                log::error!("Could not share the clip {:#?}", err);
+            }
+        });
+    }
+}
+
+fn extension(video_type: &str) -> &'static str {
+    if video_type.starts_with("video/mp4") {
+        "mp4"
+    } else {
+        "webm"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_the_file_after_the_recorded_format() {
+        assert_eq!(extension("video/webm;codecs=vp9"), "webm");
+        assert_eq!(extension("video/mp4"), "mp4");
+        assert_eq!(extension(""), "webm");
+    }
+}
@@ -0,0 +1,185 @@
+use std::rc::Rc;
+
+use super::{assets, GameEvent, RedHatBoy};
+use crate::engine::{Atlas, Point, Rect, Renderer, Timer};
+
+const COIN_SIZE: i16 = 32;
+const POWER_UP_SIZE: i16 = 40;
+const COIN_SPACING: i16 = 48;
+/// How long a magnet keeps pulling coins in.
+const MAGNET_FRAMES: u16 = 600;
+/// Coins closer than this to the boy are pulled in while the magnet is on.
+const MAGNET_RANGE: i16 = 250;
+/// The share of the remaining distance to the boy a coin covers each frame.
+const MAGNET_PULL: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerUp {
+    Magnet,
+    Shield,
+    Speed,
+}
+
+impl PowerUp {
+    fn image(self) -> &'static str {
+        match self {
+            PowerUp::Magnet => assets::MAGNET,
+            PowerUp::Shield => assets::SHIELD,
+            PowerUp::Speed => assets::SPEED,
+        }
+    }
+}
+
+/// Coins and power-ups. Unlike obstacles they can move by themselves, so
+/// they are updated every frame rather than only scrolled.
+pub struct Coins {
+    atlas: Rc<Atlas>,
+    coins: Vec<Point>,
+    power_ups: Vec<(PowerUp, Point)>,
+    magnet: Timer,
+}
+
+impl Coins {
+    pub fn new(atlas: Rc<Atlas>) -> Self {
+        Coins {
+            atlas,
+            coins: vec![],
+            power_ups: vec![],
+            magnet: Timer::default(),
+        }
+    }
+
+    /// The coins and power ups still out in the world.
+    pub fn count(&self) -> usize {
+        self.coins.len() + self.power_ups.len()
+    }
+
+    /// Lays a row of `count` coins out to the right of `position`.
+    pub fn spawn_row(&mut self, position: Point, count: i16) {
+        self.coins.extend((0..count).map(|index| Point {
+            x: position.x + index * COIN_SPACING,
+            y: position.y,
+        }));
+    }
+
+    pub fn spawn_power_up(&mut self, power_up: PowerUp, position: Point) {
+        self.power_ups.push((power_up, position));
+    }
+
+    pub fn magnet_active(&self) -> bool {
+        !self.magnet.finished()
+    }
+
+    /// Scrolls everything by `x`, pulls coins towards the boy while a magnet
+    /// is on and picks up whatever he touches.
+    pub fn update(&mut self, x: i16, boy: &RedHatBoy) -> Vec<GameEvent> {
+        let boy_box = boy.bounding_box();
+        let target = boy_box.center()
+            - Point {
+                x: COIN_SIZE / 2,
+                y: COIN_SIZE / 2,
+            };
+        self.magnet.tick();
+
+        for position in self
+            .coins
+            .iter_mut()
+            .chain(self.power_ups.iter_mut().map(|(_, position)| position))
+        {
+            position.x += x;
+        }
+        if self.magnet_active() {
+            for coin in self.coins.iter_mut() {
+                *coin = attract(*coin, target);
+            }
+        }
+
+        let mut events = vec![];
+        self.coins.retain(|coin| {
+            let collected = boy_box.intersects(&Rect::new(*coin, COIN_SIZE, COIN_SIZE));
+            if collected {
+                events.push(GameEvent::CoinCollected);
+            }
+            !collected && coin.x + COIN_SIZE > 0
+        });
+        let mut collected = vec![];
+        self.power_ups.retain(|&(power_up, position)| {
+            let touched = boy_box.intersects(&Rect::new(position, POWER_UP_SIZE, POWER_UP_SIZE));
+            if touched {
+                collected.push(power_up);
+            }
+            !touched && position.x + POWER_UP_SIZE > 0
+        });
+        for power_up in collected {
+            match power_up {
+                PowerUp::Magnet => {
+                    self.magnet = Timer::new(MAGNET_FRAMES);
+                    events.push(GameEvent::MagnetCollected);
+                }
+                PowerUp::Shield => events.push(GameEvent::ShieldCollected),
+                PowerUp::Speed => events.push(GameEvent::SpeedCollected),
+            }
+        }
+        events
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        if let Some(frame) = self.atlas.frame(assets::COIN) {
+            for coin in self.coins.iter() {
+                let destination = Rect::new(*coin, COIN_SIZE, COIN_SIZE);
+                self.atlas.draw(renderer, &frame, &destination);
+                renderer.draw_bounding_box(&destination);
+            }
+        }
+        for &(power_up, position) in self.power_ups.iter() {
+            if let Some(frame) = self.atlas.frame(power_up.image()) {
+                let destination = Rect::new(position, POWER_UP_SIZE, POWER_UP_SIZE);
+                self.atlas.draw(renderer, &frame, &destination);
+                renderer.draw_bounding_box(&destination);
+            }
+        }
+    }
+}
+
+/// Moves a coin part of the way towards `target`, if it is in range.
+fn attract(coin: Point, target: Point) -> Point {
+    let offset = target - coin;
+    if offset.x.abs() > MAGNET_RANGE || offset.y.abs() > MAGNET_RANGE {
+        return coin;
+    }
+    coin + Point {
+        x: step(offset.x),
+        y: step(offset.y),
+    }
+}
+
+/// Rounds away from zero so a coin never stalls just short of the boy.
+fn step(distance: i16) -> i16 {
+    let step = f32::from(distance) * MAGNET_PULL;
+    if step > 0.0 {
+        step.ceil() as i16
+    } else {
+        step.floor() as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coins_in_range_move_towards_the_target() {
+        let target = Point { x: 100, y: 100 };
+        assert_eq!(
+            attract(Point { x: 200, y: 50 }, target),
+            Point { x: 180, y: 60 }
+        );
+        assert_eq!(attract(Point { x: 101, y: 100 }, target), target);
+    }
+
+    #[test]
+    fn coins_out_of_range_stay_put() {
+        let coin = Point { x: 400, y: 100 };
+        assert_eq!(attract(coin, Point { x: 100, y: 100 }), coin);
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use super::{assets, zones::Modifier};
+use crate::{
+    browser,
+    engine::{Physics, Vector},
+};
+
+const MAX_AIR_FRAMES: u16 = 600;
+
+/// The furthest the boy can jump, from [`GameConfig::reach`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reach {
+    pub distance: i16,
+    pub height: i16,
+}
+
+/// Balance settings read from `config.json` at startup, so they can be tuned
+/// without rebuilding the wasm. Settings missing from the file keep their
+/// defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub gravity: f32,
+    pub jump_speed: f32,
+    pub running_speed: f32,
+    pub terminal_velocity: f32,
+    /// The space left before each new segment.
+    pub obstacle_buffer: i16,
+    /// A new segment is added once the last one ends closer than this.
+    pub timeline_minimum: i16,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            gravity: 1.0,
+            jump_speed: -25.0,
+            running_speed: 4.0,
+            terminal_velocity: 20.0,
+            obstacle_buffer: 20,
+            timeline_minimum: 1000,
+        }
+    }
+}
+
+impl GameConfig {
+    /// How the boy falls.
+    pub fn physics(&self) -> Physics {
+        Physics {
+            gravity: self.gravity,
+            gravity_scale: 1.0,
+            max_fall_speed: self.terminal_velocity,
+            max_speed: f32::MAX,
+        }
+    }
+
+    /// How far across and how high up the boy gets in a jump while running
+    /// at `speed`. The distance is to where he is back at the height he left from.
+    pub fn reach(&self, speed: f32) -> Reach {
+        self.reach_in(speed, Modifier::default())
+    }
+
+    /// How far across and how high up the boy gets in a jump from inside a
+    /// zone with `modifier`, where he is held back or pushed along as well.
+    pub fn reach_in(&self, speed: f32, modifier: Modifier) -> Reach {
+        let physics = Physics {
+            gravity_scale: modifier.gravity_scale,
+            ..self.physics()
+        };
+        let mut position = Vector::default();
+        let mut velocity = Vector {
+            x: speed * (1.0 - modifier.drag),
+            y: self.jump_speed * modifier.jump_scale,
+        };
+        let mut top: f32 = 0.0;
+        // Without gravity he never comes down, so give up after a while.
+        for _ in 0..MAX_AIR_FRAMES {
+            (position, velocity) = physics.step(position, velocity);
+            top = top.min(position.y);
+            if position.y >= 0.0 {
+                break;
+            }
+        }
+        Reach {
+            distance: position.x.round() as i16,
+            height: -top.round() as i16,
+        }
+    }
+
+    /// The config file, or the defaults if it can't be read.
+    pub async fn load() -> Self {
+        GameConfig::fetch().await.unwrap_or_else(|err| {
+            log::warn!("Using the default game config {:#?}", err);
+            GameConfig::default()
+        })
+    }
+
+    async fn fetch() -> Result<Self> {
+        let json = browser::fetch_json(assets::CONFIG).await?;
+        serde_wasm_bindgen::from_value(json).map_err(|err| {
+            anyhow!(
+                "Could not convert {} into a GameConfig {:#?}",
+                assets::CONFIG,
+                err
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jumps_further_when_running_faster() {
+        let config = GameConfig::default();
+        // The fall is held to the terminal velocity, so coming down takes a
+        // frame longer than going up: 50 frames at 4 pixels a frame.
+        assert_eq!(
+            config.reach(4.0),
+            Reach {
+                distance: 200,
+                height: 300
+            }
+        );
+        assert_eq!(config.reach(8.0).distance, 400);
+        let floaty = GameConfig {
+            gravity: 0.5,
+            ..config
+        };
+        assert_eq!(
+            floaty.reach(4.0),
+            Reach {
+                distance: 404,
+                height: 613
+            }
+        );
+        let dragging = Modifier {
+            drag: 0.5,
+            ..Modifier::default()
+        };
+        assert_eq!(config.reach_in(4.0, dragging).distance, 100);
+        let weak = Modifier {
+            jump_scale: 0.5,
+            ..Modifier::default()
+        };
+        assert!(config.reach_in(4.0, weak).height < config.reach(4.0).height);
+    }
+}
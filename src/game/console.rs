@@ -0,0 +1,238 @@
+use super::{biomes, boss, snapshot::Snapshot, Walk};
+use crate::{
+    browser::Screen,
+    engine::{self, KeyState, Point, Renderer},
+    segments::SEGMENT_COUNT,
+};
+
+const LINE_HEIGHT: i16 = 22;
+const MAX_LISTED_OBSTACLES: usize = 8;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Spawn(usize),
+    Speed(f32),
+    Teleport(u32),
+    ToggleInvincibility,
+    ToggleAutopilot,
+    /// Logs the world as JSON, to load again later.
+    Snapshot,
+    Load(String),
+}
+
+impl Command {
+    fn parse(line: &str) -> Option<Self> {
+        // Snapshots are JSON, which is taken whole rather than split up.
+        if let Some(json) = line.trim().strip_prefix("load ") {
+            return Some(Command::Load(json.trim().to_string()));
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["spawn", index] | ["spawn", "segment", index] => {
+                index.parse().ok().map(Command::Spawn)
+            }
+            ["speed", speed] | ["set", "speed", speed] => speed.parse().ok().map(Command::Speed),
+            ["teleport", distance] | ["teleport", "to", distance] => {
+                distance.parse().ok().map(Command::Teleport)
+            }
+            ["god"] | ["invincible"] | ["toggle", "invincibility"] => {
+                Some(Command::ToggleInvincibility)
+            }
+            ["autopilot"] | ["toggle", "autopilot"] => Some(Command::ToggleAutopilot),
+            ["snapshot"] | ["dump"] => Some(Command::Snapshot),
+            _ => None,
+        }
+    }
+
+    fn execute(self, walk: &mut Walk) -> String {
+        match self {
+            Command::Spawn(index) if index >= SEGMENT_COUNT => {
+                format!("No segment {}, there are {}", index, SEGMENT_COUNT)
+            }
+            Command::Spawn(index) => {
+                walk.spawn_segment(index);
+                format!("Spawned segment {}", index)
+            }
+            Command::Speed(speed) => {
+                walk.boy.set_speed_bonus(speed - walk.boy.speed().base);
+                format!("Speed set to {}", walk.boy.speed().value())
+            }
+            Command::Teleport(distance) => {
+                walk.teleport(distance);
+                format!("Teleported to {}", distance)
+            }
+            Command::ToggleInvincibility => {
+                walk.boy.invincible = !walk.boy.invincible;
+                format!("Invincibility {}", walk.boy.invincible)
+            }
+            Command::ToggleAutopilot => {
+                walk.autopilot = !walk.autopilot;
+                format!("Autopilot {}", walk.autopilot)
+            }
+            Command::Snapshot => match walk.snapshot().to_json() {
+                Ok(json) => {
+                    log::info!("{}", json);
+                    format!("Snapshot of {} logged", walk.run.distance)
+                }
+                Err(err) => format!("Could not take a snapshot {:#?}", err),
+            },
+            Command::Load(json) => match Snapshot::from_json(&json) {
+                Ok(snapshot) => match walk.restore(snapshot) {
+                    Ok(()) => format!("Loaded snapshot of {}", walk.run.distance),
+                    Err(err) => format!("Could not load the snapshot {:#?}", err),
+                },
+                Err(err) => format!("Could not load the snapshot {:#?}", err),
+            },
+        }
+    }
+}
+
+pub struct DebugConsole {
+    command_event: Option<engine::SubmitHandler>,
+    message: String,
+}
+
+impl DebugConsole {
+    pub fn new() -> Self {
+        DebugConsole {
+            command_event: None,
+            message: String::new(),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.command_event.is_some()
+    }
+
+    pub fn update(&mut self, keystate: &KeyState, walk: &mut Walk) {
+        if keystate.just_pressed("Backquote") {
+            self.toggle(&walk.screen);
+        }
+
+        let mut commands = vec![];
+        if let Some(command_event) = self.command_event.as_mut() {
+            while let Some(line) = command_event.try_next() {
+                commands.push(line);
+            }
+        }
+        for line in commands {
+            self.message = match Command::parse(&line) {
+                Some(command) => {
+                    walk.run.cheated = true;
+                    command.execute(walk)
+                }
+                None => format!("Unknown command: {}", line),
+            };
+        }
+    }
+
+    pub fn draw(&self, renderer: &Renderer, walk: &Walk) {
+        if !self.is_open() {
+            return;
+        }
+
+        let context = walk.boy.state_machine.context();
+        let mut lines = vec![
+            format!("Obstacles: {}", walk.course.obstacles.len()),
+            format!("Timeline: {}", walk.course.timeline),
+            format!(
+                "Boy: {} pos ({}, {}) vel ({}, {}){}",
+                walk.boy.state_machine.frame_name(),
+                context.position.x,
+                context.position.y,
+                context.velocity.x,
+                context.velocity.y,
+                if walk.boy.invincible {
+                    " invincible"
+                } else {
+                    ""
+                }
+            ),
+        ];
+        lines.extend(
+            walk.course
+                .obstacles
+                .active()
+                .iter()
+                .take(MAX_LISTED_OBSTACLES)
+                .enumerate()
+                .map(|(index, obstacle)| format!("  #{} right {}", index, obstacle.right())),
+        );
+        lines.push(self.message.clone());
+
+        for (row, line) in lines.iter().enumerate() {
+            let location = Point {
+                x: 10,
+                y: LINE_HEIGHT * (row as i16 + 2),
+            };
+            if let Err(err) = renderer.draw_text(line, &location) {
+                log::error!("Could not draw text {:#?}", err);
+            }
+        }
+    }
+
+    fn toggle(&mut self, screen: &Screen) {
+        if self.is_open() {
+            self.command_event = None;
+            if let Err(err) = screen.hide_ui() {
+                log::error!("Error hiding the console {:#?}", err);
+            }
+        } else {
+            self.command_event = screen
+                .draw_ui("<input id='debug_console' autocomplete='off'>")
+                .and_then(|_unit| screen.find_input("debug_console"))
+                .and_then(|input| {
+                    let _ = input.focus();
+                    engine::add_submit_handler(input)
+                })
+                .map_err(|err| log::error!("Could not open the console {:#?}", err))
+                .ok();
+        }
+    }
+}
+
+impl Walk {
+    /// Moves the run on to `distance` without running there: the biome,
+    /// its music, and the next boss are the ones due there.
+    pub(super) fn teleport(&mut self, distance: u32) {
+        let from = self.run.distance;
+        self.run.distance = distance;
+        self.course
+            .biomes
+            .skip(i64::from(distance) - i64::from(from));
+        self.course.next_boss = (distance / boss::BOSS_DISTANCE + 1) * boss::BOSS_DISTANCE;
+        let biome = biomes::at(distance);
+        self.music.set_biome(biome);
+        super::announce(&format!("Teleported to {} in {}", distance, biome.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_console_commands() {
+        assert_eq!(Command::parse("spawn 1"), Some(Command::Spawn(1)));
+        assert_eq!(Command::parse("spawn segment 0"), Some(Command::Spawn(0)));
+        assert_eq!(Command::parse("set speed 8"), Some(Command::Speed(8.0)));
+        assert_eq!(
+            Command::parse("teleport to 20000"),
+            Some(Command::Teleport(20000))
+        );
+        assert_eq!(
+            Command::parse("toggle invincibility"),
+            Some(Command::ToggleInvincibility)
+        );
+        assert_eq!(
+            Command::parse("toggle autopilot"),
+            Some(Command::ToggleAutopilot)
+        );
+        assert_eq!(Command::parse("dump"), Some(Command::Snapshot));
+        assert_eq!(
+            Command::parse("load {\"version\": 1}"),
+            Some(Command::Load("{\"version\": 1}".to_string()))
+        );
+        assert_eq!(Command::parse("speed fast"), None);
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::Result;
+use futures::channel::mpsc::UnboundedReceiver;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    announce, profiles::Profile, Ready, ReadyEndState, Walk, WalkTheDogState,
+    WalkTheDogStateMachine,
+};
+use crate::engine;
+
+/// Every skin, in the order the wardrobe shows them.
+const SKINS: [Skin; 5] = [
+    Skin::Classic,
+    Skin::Ocean,
+    Skin::Forest,
+    Skin::Gold,
+    Skin::Shadow,
+];
+
+/// A recoloring of the character, bought with coins and worn per profile.
+/// Skins are drawn through a canvas filter, so they work for every
+/// character without a sprite sheet of their own.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Skin {
+    #[default]
+    Classic,
+    Ocean,
+    Forest,
+    Gold,
+    Shadow,
+}
+
+impl Skin {
+    pub fn name(self) -> &'static str {
+        match self {
+            Skin::Classic => "Classic",
+            Skin::Ocean => "Ocean",
+            Skin::Forest => "Forest",
+            Skin::Gold => "Gold",
+            Skin::Shadow => "Shadow",
+        }
+    }
+
+    /// What the skin is called in a profile's unlocks.
+    pub fn id(self) -> &'static str {
+        match self {
+            Skin::Classic => "classic",
+            Skin::Ocean => "ocean",
+            Skin::Forest => "forest",
+            Skin::Gold => "gold",
+            Skin::Shadow => "shadow",
+        }
+    }
+
+    /// How many coins the skin costs.
+    pub fn price(self) -> u32 {
+        match self {
+            Skin::Classic => 0,
+            Skin::Ocean | Skin::Forest => 50,
+            Skin::Gold => 150,
+            Skin::Shadow => 300,
+        }
+    }
+
+    /// The next skin after this one that `owned` says is owned, going back
+    /// round to the start.
+    pub fn next_owned(self, owned: impl Fn(Skin) -> bool) -> Skin {
+        let index = SKINS.iter().position(|&skin| skin == self).unwrap_or(0);
+        (1..=SKINS.len())
+            .map(|step| SKINS[(index + step) % SKINS.len()])
+            .find(|&skin| owned(skin))
+            .unwrap_or(self)
+    }
+
+    pub fn filter(self) -> Option<&'static str> {
+        match self {
+            Skin::Classic => None,
+            Skin::Ocean => Some("hue-rotate(200deg)"),
+            Skin::Forest => Some("hue-rotate(100deg)"),
+            Skin::Gold => Some("sepia(1) saturate(3)"),
+            Skin::Shadow => Some("grayscale(1) brightness(0.5)"),
+        }
+    }
+}
+
+/// The wardrobe, where coins buy skins and the profile picks one to wear.
+pub struct Wardrobe {
+    /// One for each skin's button, in the order of `SKINS`.
+    clicks: Vec<UnboundedReceiver<()>>,
+    back_event: UnboundedReceiver<()>,
+}
+
+pub enum WardrobeEndState {
+    Complete(WalkTheDogState<Ready>),
+    Continue(WalkTheDogState<Wardrobe>),
+}
+
+impl From<WardrobeEndState> for WalkTheDogStateMachine {
+    fn from(state: WardrobeEndState) -> Self {
+        match state {
+            WardrobeEndState::Complete(state) => state.into(),
+            WardrobeEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Wardrobe>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Wardrobe>) -> Self {
+        WalkTheDogStateMachine::Wardrobe(state)
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    pub(super) fn open_wardrobe(self) -> ReadyEndState {
+        match show(&self.walk) {
+            Ok(wardrobe) => ReadyEndState::Wardrobe(WalkTheDogState {
+                _state: wardrobe,
+                walk: self.walk,
+            }),
+            Err(err) => {
+                log::error!("Could not show the wardrobe {:#?}", err);
+                ReadyEndState::Continue(self)
+            }
+        }
+    }
+}
+
+impl WalkTheDogState<Wardrobe> {
+    pub fn update(mut self) -> WardrobeEndState {
+        if matches!(self._state.back_event.try_next(), Ok(Some(()))) {
+            return WardrobeEndState::Complete(self.back());
+        }
+        let Some(skin) = self
+            ._state
+            .clicks
+            .iter_mut()
+            .position(|click| matches!(click.try_next(), Ok(Some(()))))
+            .map(|index| SKINS[index])
+        else {
+            return WardrobeEndState::Continue(self);
+        };
+        let profile = self.walk.profiles.current_mut();
+        if !profile.owns(skin) && !profile.buy(skin) {
+            announce(&format!("{} costs {} coins", skin.name(), skin.price()));
+            return WardrobeEndState::Continue(self);
+        }
+        profile.settings.skin = skin;
+        self.walk.boy.set_skin(skin);
+        announce(&format!("Wearing {}", skin.name()));
+        if let Err(err) = self.walk.profiles.save() {
+            log::error!("Could not save profiles {:#?}", err);
+        }
+        // Shown again so the buttons say what is owned and worn now.
+        match show(&self.walk) {
+            Ok(wardrobe) => WardrobeEndState::Continue(WalkTheDogState {
+                _state: wardrobe,
+                walk: self.walk,
+            }),
+            Err(err) => {
+                log::error!("Could not show the wardrobe {:#?}", err);
+                WardrobeEndState::Complete(self.back())
+            }
+        }
+    }
+
+    fn back(self) -> WalkTheDogState<Ready> {
+        if let Err(err) = self.walk.screen.hide_ui() {
+            log::error!("Error hiding the wardrobe {:#?}", err);
+        }
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: self.walk,
+        }
+    }
+}
+
+fn show(walk: &Walk) -> Result<Wardrobe> {
+    let profile = walk.profiles.current();
+    let buttons = SKINS
+        .iter()
+        .enumerate()
+        .map(|(index, &skin)| {
+            format!(
+                "<button id='skin_{}'>{} - {}</button>",
+                index,
+                skin.name(),
+                label(profile, skin)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+    walk.screen.draw_ui(&format!(
+        "<div id='wardrobe'><p>Coins: {}</p>{}<button id='wardrobe_back'>Back</button></div>",
+        profile.coins, buttons
+    ))?;
+    let clicks = (0..SKINS.len())
+        .map(|index| {
+            walk.screen
+                .find(&format!("skin_{}", index))
+                .map(engine::add_click_handler)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let back_event = walk
+        .screen
+        .find("wardrobe_back")
+        .map(engine::add_click_handler)?;
+    Ok(Wardrobe { clicks, back_event })
+}
+
+fn label(profile: &Profile, skin: Skin) -> String {
+    if profile.settings.skin == skin {
+        "Wearing".to_string()
+    } else if profile.owns(skin) {
+        "Wear".to_string()
+    } else {
+        format!("{} coins", skin.price())
+    }
+}
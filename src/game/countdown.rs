@@ -0,0 +1,133 @@
+use super::{
+    analytics::AnalyticsEvent, announce, Ready, WalkTheDogState, WalkTheDogStateMachine, Walking,
+};
+use crate::engine::{Point, Renderer, Timer};
+
+/// Frames each number of the countdown stays up for.
+const FRAMES_PER_COUNT: u16 = 60;
+const COUNT_FROM: u16 = 3;
+/// Half the width of a number, to centre it.
+const COUNT_HALF_WIDTH: i16 = 8;
+const COUNT_TOP: i16 = 200;
+
+/// The "3, 2, 1" before a run starts. The world is drawn but stands still,
+/// so the player can see what's coming before the boy sets off.
+pub struct Countdown {
+    timer: Timer,
+    /// Whether it's counting back into a paused run, which is already
+    /// running, rather than starting a new one.
+    resuming: bool,
+}
+
+impl Countdown {
+    fn new(resuming: bool) -> Self {
+        announce(&COUNT_FROM.to_string());
+        Countdown {
+            timer: Timer::new(COUNT_FROM * FRAMES_PER_COUNT),
+            resuming,
+        }
+    }
+
+    /// Counts back into a paused run.
+    pub(super) fn resuming() -> Self {
+        Countdown::new(true)
+    }
+}
+
+pub enum CountdownEndState {
+    Complete(WalkTheDogState<Walking>),
+    Continue(WalkTheDogState<Countdown>),
+}
+
+impl From<CountdownEndState> for WalkTheDogStateMachine {
+    fn from(state: CountdownEndState) -> Self {
+        match state {
+            CountdownEndState::Complete(state) => state.into(),
+            CountdownEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Countdown>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Countdown>) -> Self {
+        WalkTheDogStateMachine::Countdown(state)
+    }
+}
+
+/// The number showing with `frames_left` to go.
+fn count(frames_left: u16) -> u16 {
+    frames_left.div_ceil(FRAMES_PER_COUNT)
+}
+
+impl WalkTheDogState<Ready> {
+    pub(super) fn start_countdown(self) -> WalkTheDogState<Countdown> {
+        WalkTheDogState {
+            _state: Countdown::new(false),
+            walk: self.walk,
+        }
+    }
+}
+
+impl WalkTheDogState<Countdown> {
+    pub fn update(mut self) -> CountdownEndState {
+        let before = count(self._state.timer.remaining());
+        self._state.timer.tick();
+        if self._state.timer.finished() {
+            return CountdownEndState::Complete(self.start_running());
+        }
+        let after = count(self._state.timer.remaining());
+        if after != before {
+            announce(&after.to_string());
+        }
+        CountdownEndState::Continue(self)
+    }
+
+    /// Draws the number, fading out as its second runs down.
+    pub fn draw_count(&self, renderer: &Renderer) {
+        let frames_left = self._state.timer.remaining();
+        let alpha =
+            f64::from((frames_left - 1) % FRAMES_PER_COUNT + 1) / f64::from(FRAMES_PER_COUNT);
+        let location = Point {
+            x: self.walk.bounds.width / 2 - COUNT_HALF_WIDTH,
+            y: COUNT_TOP,
+        };
+        if let Err(err) =
+            renderer.draw_text_with_alpha(&count(frames_left).to_string(), &location, alpha)
+        {
+            log::error!("Could not draw the countdown {:#?}", err);
+        }
+    }
+
+    fn start_running(mut self) -> WalkTheDogState<Walking> {
+        if self._state.resuming {
+            announce("Resumed");
+            return WalkTheDogState {
+                _state: Walking,
+                walk: self.walk,
+            };
+        }
+        self.walk.boy.run_right();
+        if let Some(second) = &mut self.walk.second {
+            second.boy.run_right();
+        }
+        announce("Game started. Space to jump, down arrow to slide.");
+        self.walk.record(AnalyticsEvent::RunStarted);
+        WalkTheDogState {
+            _state: Walking,
+            walk: self.walk,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_down_a_second_at_a_time() {
+        assert_eq!(count(COUNT_FROM * FRAMES_PER_COUNT), 3);
+        assert_eq!(count(2 * FRAMES_PER_COUNT + 1), 3);
+        assert_eq!(count(2 * FRAMES_PER_COUNT), 2);
+        assert_eq!(count(1), 1);
+    }
+}
@@ -0,0 +1,392 @@
+use std::rc::Rc;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+
+use super::{
+    assets,
+    biomes::BiomeSchedule,
+    boss::{self, Boss},
+    coins::{self, Coins},
+    config::GameConfig,
+    next_segment,
+    particles::Particles,
+    popups::{self, Popups},
+    power_up_rng,
+    prewarm::{self, Prewarm},
+    rightmost,
+    stats::RunStats,
+    window::ObstacleWindow,
+    zones::Zones,
+    GameEvent, Obstacle, RedHatBoy, COIN_HEIGHT, COIN_POINTS, COIN_ROW, MAGNET_CHANCE, MAX_COMBO,
+    OBSTACLES_PER_FRAME, POWER_UP_Y, SHIELD_CHANCE, SPEED_CHANCE,
+};
+use crate::{
+    engine::{
+        self, Atlas, Point, Quality, Renderer, ScrollingBackground, SpriteSheet, SubPixel,
+        WorldBounds,
+    },
+    obstacles::ObstacleRegistry,
+    segments::SegmentBuilder,
+};
+
+/// What every course in a game is built from and shown with.
+pub struct World<'a> {
+    pub registry: &'a ObstacleRegistry,
+    pub atlas: &'a Rc<Atlas>,
+    pub sheet: &'a Rc<SpriteSheet>,
+    pub config: &'a GameConfig,
+    pub prewarm: &'a Prewarm,
+    pub quality: Quality,
+    pub bounds: WorldBounds,
+    /// Whether segments are added as the course runs out. The segment
+    /// preview only ever has the one.
+    pub extending: bool,
+}
+
+/// A runner, with their course and how their run is going.
+pub type Runner<'a> = (&'a mut Course, &'a mut RedHatBoy, &'a mut RunStats);
+
+/// One runner's course: everything in front of the boy that scrolls past
+/// him as he runs, and what he picks up and scores on it. In two-player
+/// mode each runner has their own, from the same seed.
+pub struct Course {
+    pub background: ScrollingBackground,
+    pub obstacles: ObstacleWindow,
+    pub zones: Zones,
+    pub coins: Coins,
+    pub particles: Particles,
+    pub popups: Popups,
+    pub boss: Option<Boss>,
+    /// The distance the next boss fight starts at.
+    pub next_boss: u32,
+    /// Near misses since the boy last stumbled.
+    pub combo: u32,
+    pub timeline: i16,
+    /// Carries the fraction of a pixel the world has scrolled over between frames.
+    pub scroll: SubPixel,
+    pub rng: ChaCha12Rng,
+    /// Rolls for the power-ups, so whether one turns up never changes which
+    /// segments come next.
+    pub power_up_rng: ChaCha12Rng,
+    pub biomes: BiomeSchedule,
+    /// The next segment, while it is built ahead of being needed.
+    pub builder: Option<SegmentBuilder>,
+    /// What happened this frame, to be dispatched at the end of it.
+    pub events: Vec<GameEvent>,
+}
+
+impl Course {
+    /// Starts a course from `obstacles` on a screen `width` wide, with
+    /// `seed` picking the segments after them.
+    pub fn new(
+        background: ScrollingBackground,
+        obstacles: Vec<Box<dyn Obstacle>>,
+        atlas: Rc<Atlas>,
+        seed: u64,
+        width: i16,
+    ) -> Self {
+        Course {
+            background,
+            timeline: rightmost(&obstacles),
+            obstacles: ObstacleWindow::new(obstacles, width),
+            zones: Zones::default(),
+            coins: Coins::new(atlas.clone()),
+            particles: Particles::new(atlas),
+            popups: Popups::default(),
+            boss: None,
+            next_boss: boss::BOSS_DISTANCE,
+            combo: 0,
+            scroll: SubPixel::default(),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            power_up_rng: power_up_rng(seed),
+            biomes: BiomeSchedule::default(),
+            builder: None,
+            events: vec![],
+        }
+    }
+
+    /// Moves the course on by a frame of `boy` running along it, and returns
+    /// how far it scrolled. What happened is kept for `dispatch_events`.
+    pub fn advance(&mut self, boy: &mut RedHatBoy, run: &mut RunStats, world: &World) -> i16 {
+        boy.update();
+
+        let walking_speed = if self.boss.as_ref().is_some_and(Boss::holds_scroll) {
+            0
+        } else {
+            self.scroll.advance(-boy.walking_speed())
+        };
+
+        if world.quality.scrolling_background() {
+            self.background.move_horizontally(walking_speed);
+        }
+
+        // The first runner's obstacles have already been taken off for the
+        // rewind, so this only culls the second runner's.
+        self.obstacles.cull();
+
+        let boy_x = boy.bounding_box().x();
+        let ahead: Vec<bool> = self
+            .obstacles
+            .active()
+            .iter()
+            .map(|obstacle| obstacle.right() > boy_x)
+            .collect();
+        self.obstacles.scroll(walking_speed, world.bounds.width);
+
+        self.zones.update(walking_speed, boy);
+        let mut events = self.coins.update(walking_speed, boy);
+        self.events.append(&mut events);
+        self.particles
+            .update(walking_speed, &world.config.physics());
+        self.popups.update(walking_speed);
+
+        if walking_speed < 0 {
+            run.distance += walking_speed.unsigned_abs() as u32;
+        }
+
+        // Only the obstacle that knocks him out is blamed, not one he meets
+        // after falling into a pit.
+        engine::profile("collision", || {
+            self.obstacles.active().iter().for_each(|obstacle| {
+                let down = boy.knockout_cause().is_some();
+                obstacle.check_intersection(boy);
+                if !down && boy.knockout_cause().is_some() {
+                    run.knocked_out_by = Some(obstacle.name().to_string());
+                }
+            });
+        });
+
+        if !boy.knocked_out() {
+            let boy_box = boy.bounding_box();
+            let near_misses: Vec<u32> = self
+                .obstacles
+                .active()
+                .iter()
+                .zip(ahead)
+                .filter(|(obstacle, was_ahead)| {
+                    *was_ahead
+                        && obstacle.right() <= boy_box.x()
+                        && obstacle.near_miss_points() > 0
+                        && popups::near_miss(&boy_box, &obstacle.bounding_box())
+                })
+                .map(|(obstacle, _)| obstacle.near_miss_points())
+                .collect();
+            for points in near_misses {
+                self.combo = (self.combo + 1).min(MAX_COMBO);
+                let points = points * self.combo;
+                run.bonus += points;
+                self.popups.spawn(points, boy_box.position);
+            }
+        }
+
+        self.update_boss(boy, run, world);
+
+        let spawn_line = prewarm::spawn_line(
+            world.bounds.width,
+            world.config.timeline_minimum,
+            -boy.walking_speed(),
+        );
+        if self.timeline < spawn_line && self.boss.is_none() {
+            self.generate_next_segment(boy, world);
+        } else {
+            self.timeline += walking_speed;
+            let build_line = prewarm::build_line(spawn_line, -boy.walking_speed());
+            if self.timeline < build_line && self.boss.is_none() {
+                self.build_next_segment(boy, world);
+            }
+        }
+
+        walking_speed
+    }
+
+    /// Gives `boy` and `run` what he picked up and scored this frame, and
+    /// hands back everything that happened, to be heard.
+    pub fn dispatch_events(
+        &mut self,
+        boy: &mut RedHatBoy,
+        run: &mut RunStats,
+        reduced_motion: bool,
+    ) -> Vec<GameEvent> {
+        self.events.append(&mut boy.take_events());
+        for event in &self.events {
+            match event {
+                GameEvent::Jumped => run.jumps += 1,
+                GameEvent::CoinCollected => {
+                    run.coins += 1;
+                    run.bonus += COIN_POINTS;
+                    self.popups.spawn(COIN_POINTS, boy.bounding_box().position);
+                }
+                GameEvent::ShieldCollected => boy.raise_shield(),
+                GameEvent::SpeedCollected => boy.boost(),
+                GameEvent::Stumbled => self.combo = 0,
+                GameEvent::KnockedOut => {
+                    if let Some(cause) = boy.knockout_cause() {
+                        run.cause = Some(cause);
+                        self.popups.spawn_text(
+                            cause.exclamation().to_string(),
+                            boy.bounding_box().position,
+                        );
+                    }
+                }
+                GameEvent::ShieldBroken if !reduced_motion => self
+                    .particles
+                    .shatter(assets::SHIELD, boy.shield_box().position),
+                _ => {}
+            }
+        }
+        std::mem::take(&mut self.events)
+    }
+
+    fn update_boss(&mut self, boy: &mut RedHatBoy, run: &mut RunStats, world: &World) {
+        if self.boss.is_none() && run.distance >= self.next_boss {
+            self.boss = Boss::new(world.atlas.clone(), world.bounds);
+            self.next_boss += boss::BOSS_DISTANCE;
+        }
+        let Some(boss) = &mut self.boss else {
+            return;
+        };
+
+        let down = boy.knockout_cause().is_some();
+        boss.update(self.obstacles.is_empty(), boy);
+        if !down && boy.knockout_cause().is_some() {
+            run.knocked_out_by = Some("boss".to_string());
+        }
+        if boss.defeated() {
+            self.boss = None;
+            run.bonus += boss::BOSS_BONUS;
+            run.bosses += 1;
+            // No segments were spawned during the fight, so start the course
+            // again just off screen.
+            self.timeline = self.timeline.max(world.bounds.width);
+            self.events.push(GameEvent::BossDefeated);
+        }
+    }
+
+    fn next_builder(&mut self, boy: &RedHatBoy, world: &World) -> SegmentBuilder {
+        let index = next_segment(
+            &mut self.rng,
+            self.biomes.next(),
+            world.registry,
+            world.config,
+            boy.walking_speed(),
+        );
+        SegmentBuilder::new(index, world.bounds.height)
+    }
+
+    /// Builds a little more of the next segment, starting it if it isn't
+    /// already, so it's ready before it's needed.
+    fn build_next_segment(&mut self, boy: &RedHatBoy, world: &World) {
+        if !world.extending {
+            return;
+        }
+        if self.builder.is_none() {
+            self.builder = Some(self.next_builder(boy, world));
+        }
+        if let Some(builder) = &mut self.builder {
+            let built = builder.step(
+                OBSTACLES_PER_FRAME,
+                world.registry,
+                world.atlas,
+                world.sheet,
+            );
+            world.prewarm.draw(built);
+        }
+    }
+
+    /// Adds the next segment to the end of the course, finishing whatever
+    /// of it is still to be built.
+    fn generate_next_segment(&mut self, boy: &RedHatBoy, world: &World) {
+        if !world.extending {
+            return;
+        }
+        let mut builder = match self.builder.take() {
+            Some(builder) => builder,
+            None => self.next_builder(boy, world),
+        };
+        builder.finish(world.registry, world.atlas, world.sheet);
+        self.place_segment(builder, world);
+    }
+
+    #[cfg(feature = "dev")]
+    pub fn spawn_segment(&mut self, index: usize, world: &World) {
+        let mut builder = SegmentBuilder::new(index, world.bounds.height);
+        builder.finish(world.registry, world.atlas, world.sheet);
+        self.place_segment(builder, world);
+    }
+
+    fn place_segment(&mut self, builder: SegmentBuilder, world: &World) {
+        let offset = self.timeline + world.config.obstacle_buffer;
+        let (data, next_obstacles) = builder.place(offset);
+        for coin_row in &data.coins {
+            self.coins.spawn_row(
+                Point {
+                    x: offset + coin_row.x,
+                    y: coin_row.y,
+                },
+                coin_row.count,
+            );
+        }
+        for zone in &data.zones {
+            self.zones.spawn(zone.bounds(offset), zone.kind);
+        }
+        if let Some(first) = next_obstacles
+            .iter()
+            .map(|obstacle| obstacle.bounding_box())
+            .min_by_key(|bounding_box| bounding_box.x())
+        {
+            self.coins.spawn_row(
+                Point {
+                    x: first.x(),
+                    y: first.y() - COIN_HEIGHT,
+                },
+                COIN_ROW,
+            );
+        }
+        let timeline = rightmost(&next_obstacles);
+        self.biomes.extend(timeline - self.timeline);
+        self.timeline = timeline;
+        self.obstacles.extend(next_obstacles, world.bounds.width);
+        let power_up = if self.power_up_rng.gen_ratio(1, MAGNET_CHANCE) {
+            Some(coins::PowerUp::Magnet)
+        } else if self.power_up_rng.gen_ratio(1, SHIELD_CHANCE) {
+            Some(coins::PowerUp::Shield)
+        } else if self.power_up_rng.gen_ratio(1, SPEED_CHANCE) {
+            Some(coins::PowerUp::Speed)
+        } else {
+            None
+        };
+        if let Some(power_up) = power_up {
+            self.coins.spawn_power_up(
+                power_up,
+                Point {
+                    x: self.timeline + world.config.obstacle_buffer,
+                    y: POWER_UP_Y,
+                },
+            );
+        }
+    }
+
+    /// Draws `boy` running along the course.
+    pub fn draw(&self, renderer: &Renderer, boy: &RedHatBoy, world: &World) {
+        boy.draw(renderer);
+        if boy.effects.shield {
+            if let Some(frame) = world.atlas.frame(assets::SHIELD) {
+                world.atlas.draw(renderer, &frame, &boy.shield_box());
+            }
+        }
+        self.obstacles.active().iter().for_each(|obstacle| {
+            obstacle.draw(renderer);
+        });
+        // Over the boy, so he looks to be in the water.
+        self.zones.draw(renderer);
+        self.coins.draw(renderer);
+        if world.quality.particles() {
+            self.particles.draw(renderer);
+        }
+        self.popups.draw(renderer);
+        if let Some(boss) = &self.boss {
+            boss.draw(renderer);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use wasm_bindgen::JsCast;
+use web_sys::{js_sys::JSON, HtmlTextAreaElement};
+
+use super::{
+    rightmost, window::ObstacleWindow, Ready, ReadyEndState, WalkTheDogState,
+    WalkTheDogStateMachine,
+};
+use crate::{
+    engine::{self, KeyState, MouseHandler, MouseInput, Point, Renderer},
+    segments::{ObstacleData, SegmentData},
+};
+
+const HELP_TEXT: &str =
+    "1: Stone  2: Platform  3: Ceiling  4: Up ramp  5: Down ramp  6: Spring  7: Pit  8: Pebble  Del: Remove  X: Export  Esc: Play";
+
+/// The obstacle each number key places, in the order of the help text.
+const KINDS: [&str; 8] = [
+    "stone",
+    "platform",
+    "ceiling",
+    "up_ramp",
+    "down_ramp",
+    "spring",
+    "pit",
+    "pebble",
+];
+
+pub struct Editing {
+    mouse: MouseHandler,
+    segment: SegmentData,
+    kind: &'static str,
+    selected: Option<usize>,
+    grab_offset: Option<Point>,
+}
+
+pub enum EditingEndState {
+    Complete(WalkTheDogState<Ready>),
+    Continue(WalkTheDogState<Editing>),
+}
+
+impl From<EditingEndState> for WalkTheDogStateMachine {
+    fn from(state: EditingEndState) -> Self {
+        match state {
+            EditingEndState::Complete(state) => state.into(),
+            EditingEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Editing>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Editing>) -> Self {
+        WalkTheDogStateMachine::Editing(state)
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    pub(super) fn edit(mut self) -> ReadyEndState {
+        let mouse = match engine::add_mouse_handler(self.walk.screen.canvas().into()) {
+            Ok(mouse) => mouse,
+            Err(err) => {
+                log::error!("Could not listen for the mouse {:#?}", err);
+                return ReadyEndState::Continue(self);
+            }
+        };
+        self.walk.course.obstacles = ObstacleWindow::default();
+        ReadyEndState::Edit(WalkTheDogState {
+            _state: Editing {
+                mouse,
+                segment: SegmentData::default(),
+                kind: KINDS[0],
+                selected: None,
+                grab_offset: None,
+            },
+            walk: self.walk,
+        })
+    }
+}
+
+impl WalkTheDogState<Editing> {
+    pub fn update(mut self, keystate: &KeyState) -> EditingEndState {
+        if keystate.is_pressed("Escape") {
+            return EditingEndState::Complete(self.play());
+        }
+        for (index, kind) in KINDS.into_iter().enumerate() {
+            if keystate.is_pressed(&format!("Digit{}", index + 1)) {
+                self._state.kind = kind;
+            }
+        }
+        if keystate.is_pressed("Delete") || keystate.is_pressed("Backspace") {
+            if let Some(index) = self._state.selected.take() {
+                self._state.segment.obstacles.remove(index);
+                self.rebuild();
+            }
+        }
+
+        if keystate.just_pressed("KeyX") {
+            if let Err(err) = self.export() {
+                log::error!("Could not export segment {:#?}", err);
+            }
+        }
+
+        while let Some(input) = self._state.mouse.try_next() {
+            self.handle_mouse(input);
+        }
+
+        EditingEndState::Continue(self)
+    }
+
+    pub fn draw_editor(&self, renderer: &Renderer) {
+        if let Some(obstacle) = self
+            ._state
+            .selected
+            .and_then(|index| self.walk.course.obstacles.active().get(index))
+        {
+            renderer.draw_rect(&obstacle.bounding_box());
+        }
+        if let Err(err) = renderer.draw_text(HELP_TEXT, &Point { x: 10, y: 30 }) {
+            log::error!("Could not draw text {:#?}", err);
+        }
+    }
+
+    fn handle_mouse(&mut self, input: MouseInput) {
+        match input {
+            MouseInput::Down(point) => {
+                let hit = self
+                    .walk
+                    .course
+                    .obstacles
+                    .active()
+                    .iter()
+                    .rposition(|obstacle| obstacle.bounding_box().contains_point(point));
+                let selected = hit.or_else(|| {
+                    let placed = self._state.segment.obstacles.len();
+                    self._state.segment.obstacles.push(ObstacleData::new(
+                        self._state.kind,
+                        point.x,
+                        point.y,
+                    ));
+                    self.rebuild();
+                    (self._state.segment.obstacles.len() > placed).then_some(placed)
+                });
+                self._state.selected = selected;
+                let Some(obstacle) = selected.map(|index| &self._state.segment.obstacles[index])
+                else {
+                    self._state.grab_offset = None;
+                    return;
+                };
+                self._state.grab_offset = Some(Point {
+                    x: point.x - obstacle.x,
+                    y: point.y - obstacle.y,
+                });
+            }
+            MouseInput::Move(point) => {
+                if let (Some(index), Some(offset)) = (self._state.selected, self._state.grab_offset)
+                {
+                    let obstacle = &mut self._state.segment.obstacles[index];
+                    obstacle.x = point.x - offset.x;
+                    obstacle.y = point.y - offset.y;
+                    self.rebuild();
+                }
+            }
+            MouseInput::Up => {
+                self._state.grab_offset = None;
+            }
+        }
+    }
+
+    /// Builds the segment again. Anything that builds nothing is taken out
+    /// of it, so each obstacle is at the same index as what it was built
+    /// from and selecting one edits the right data.
+    fn rebuild(&mut self) {
+        let walk = &self.walk;
+        let mut built = vec![];
+        self._state.segment.obstacles.retain(|obstacle| {
+            let position = Point {
+                x: obstacle.x,
+                y: obstacle.y,
+            };
+            match walk
+                .registry
+                .build(&obstacle.id, position, &walk.atlas, &walk.obstacle_sheet)
+            {
+                Some(obstacle) => {
+                    built.push(obstacle);
+                    true
+                }
+                None => false,
+            }
+        });
+        self.walk.course.obstacles = ObstacleWindow::showing_all(built);
+    }
+
+    fn export(&self) -> Result<()> {
+        let value = serde_wasm_bindgen::to_value(&self._state.segment)
+            .map_err(|err| anyhow!("Could not serialize segment {:#?}", err))?;
+        let json: String = JSON::stringify(&value)
+            .map_err(|err| anyhow!("Could not stringify segment {:#?}", err))?
+            .into();
+        log::info!("{}", json);
+        self.walk.screen.hide_ui()?;
+        self.walk
+            .screen
+            .draw_ui("<textarea id='segment_json' readonly></textarea>")?;
+        self.walk
+            .screen
+            .find("segment_json")?
+            .dyn_into::<HtmlTextAreaElement>()
+            .map_err(|err| anyhow!("Could not cast into HtmlTextAreaElement {:#?}", err))?
+            .set_value(&json);
+        Ok(())
+    }
+
+    fn play(mut self) -> WalkTheDogState<Ready> {
+        if let Err(err) = self.walk.screen.hide_ui() {
+            log::error!("Error hiding the browser {:#?}", err);
+        }
+        self.walk.course.timeline = rightmost(self.walk.course.obstacles.active());
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: self.walk,
+        }
+    }
+}
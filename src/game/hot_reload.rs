@@ -0,0 +1,163 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use serde::de::DeserializeOwned;
+use web_sys::js_sys::JSON;
+
+use super::{assets, config::GameConfig, Walk};
+use crate::{browser, engine::Sheet, obstacles::ObstacleRegistry};
+
+/// How often the watched files are fetched again, about once a second.
+const POLL_FRAMES: u16 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Watched {
+    Config,
+    Obstacles,
+    CharacterSheet,
+    TilesSheet,
+}
+
+pub enum Reload {
+    Config(GameConfig),
+    Obstacles(ObstacleRegistry),
+    CharacterSheet(Sheet),
+    TilesSheet(Sheet),
+}
+
+/// Watches the game's data files in dev builds and hands back whatever
+/// changed, so sheets and balance can be tweaked without a restart.
+pub struct HotReload {
+    files: Vec<(String, Watched)>,
+    seen: Rc<RefCell<HashMap<String, String>>>,
+    sender: UnboundedSender<Reload>,
+    receiver: UnboundedReceiver<Reload>,
+    frames: u16,
+}
+
+impl HotReload {
+    pub fn new(character: &str) -> Self {
+        let (sender, receiver) = unbounded();
+        HotReload {
+            files: vec![
+                (assets::CONFIG.to_string(), Watched::Config),
+                (assets::OBSTACLES.to_string(), Watched::Obstacles),
+                (assets::character_sheet(character), Watched::CharacterSheet),
+                (assets::TILES_SHEET.to_string(), Watched::TilesSheet),
+            ],
+            seen: Rc::new(RefCell::new(HashMap::new())),
+            sender,
+            receiver,
+            frames: 0,
+        }
+    }
+
+    /// Called every frame. Starts a fetch of the watched files now and then,
+    /// and returns the files that have changed since the last one.
+    pub fn update(&mut self) -> Vec<Reload> {
+        self.frames = (self.frames + 1) % POLL_FRAMES;
+        if self.frames == 0 {
+            let files = self.files.clone();
+            let seen = self.seen.clone();
+            let sender = self.sender.clone();
+            browser::spawn_local(async move {
+                for (file, watched) in files {
+                    match fetch(&file, watched, &seen).await {
+                        Ok(Some(reload)) => {
+                            log::info!("Reloaded {}", file);
+                            let _ = sender.unbounded_send(reload);
+                        }
+                        Ok(None) => {}
+                        Err(err) => log::warn!("Could not reload {} {:#?}", file, err),
+                    }
+                }
+            });
+        }
+
+        let mut reloads = vec![];
+        while let Ok(Some(reload)) = self.receiver.try_next() {
+            reloads.push(reload);
+        }
+        reloads
+    }
+}
+
+/// Puts a changed file into the running game. New obstacles and a new tiles
+/// sheet are used by the segments built after them, while those already on
+/// screen keep the old ones.
+pub fn apply(walk: &mut Walk, reload: Reload) {
+    match reload {
+        Reload::Config(config) => {
+            walk.config = config;
+            walk.boy.set_config(config);
+            if let Some(second) = walk.second.as_mut() {
+                second.boy.set_config(config);
+            }
+        }
+        Reload::Obstacles(registry) => match registry.check(&walk.atlas) {
+            Ok(()) => walk.registry = Rc::new(registry),
+            Err(err) => log::error!("Keeping the old obstacles {:#?}", err),
+        },
+        Reload::CharacterSheet(sheet) => {
+            if let Some(second) = walk.second.as_mut() {
+                second.boy.sprite_sheet = sheet.clone();
+            }
+            walk.boy.sprite_sheet = sheet;
+        }
+        Reload::TilesSheet(sheet) => {
+            walk.obstacle_sheet = Rc::new(walk.obstacle_sheet.with_sheet(sheet));
+        }
+    }
+}
+
+async fn fetch(
+    file: &str,
+    watched: Watched,
+    seen: &RefCell<HashMap<String, String>>,
+) -> Result<Option<Reload>> {
+    // The query keeps the browser from answering with its cached copy.
+    let text = browser::fetch_text(&format!("{}?reload={}", file, browser::now()?)).await?;
+    if !changed(&mut seen.borrow_mut(), file, text.clone()) {
+        return Ok(None);
+    }
+    Ok(Some(match watched {
+        Watched::Config => Reload::Config(parse(file, &text)?),
+        Watched::Obstacles => Reload::Obstacles(parse(file, &text)?),
+        Watched::CharacterSheet => Reload::CharacterSheet(parse(file, &text)?),
+        Watched::TilesSheet => Reload::TilesSheet(parse(file, &text)?),
+    }))
+}
+
+fn parse<T: DeserializeOwned>(file: &str, text: &str) -> Result<T> {
+    let json = JSON::parse(text).map_err(|err| anyhow!("Could not parse {} {:#?}", file, err))?;
+    serde_wasm_bindgen::from_value(json)
+        .map_err(|err| anyhow!("Could not convert {} {:#?}", file, err))
+}
+
+/// Records the latest contents of `file`. The first fetch only sets the
+/// baseline, since the game has just loaded that version.
+fn changed(seen: &mut HashMap<String, String>, file: &str, text: String) -> bool {
+    match seen.insert(file.to_string(), text.clone()) {
+        Some(previous) => previous != text,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_edits_after_the_first_fetch_are_changes() {
+        let mut seen = HashMap::new();
+        assert!(!changed(&mut seen, "config.json", "{}".to_string()));
+        assert!(!changed(&mut seen, "config.json", "{}".to_string()));
+        assert!(changed(
+            &mut seen,
+            "config.json",
+            "{\"gravity\": 2}".to_string()
+        ));
+        assert!(!changed(&mut seen, "tiles.json", "{}".to_string()));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::engine::{Point, Renderer, WorldBounds};
+
+/// The most characters of a prompt on one line of a portrait screen.
+const PORTRAIT_LINE_CHARS: usize = 24;
+const PORTRAIT_MARGIN: i16 = 20;
+const LINE_HEIGHT: i16 = 22;
+
+/// Which way round the screen is, which decides how the HUD is laid out.
+/// The course is the same either way, so a seed or a replay plays the same
+/// on any screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Landscape,
+    Portrait,
+}
+
+impl Layout {
+    pub fn of(bounds: WorldBounds) -> Self {
+        if bounds.height > bounds.width {
+            Layout::Portrait
+        } else {
+            Layout::Landscape
+        }
+    }
+
+    /// Draws a prompt laid out for the square screen at `location`. On a
+    /// portrait screen it is broken into short lines down the left edge
+    /// instead, so it fits.
+    pub fn draw_prompt(self, renderer: &Renderer, text: &str, location: Point) {
+        let drawn = match self {
+            Layout::Landscape => renderer.draw_text(text, &location),
+            Layout::Portrait => wrap(text, PORTRAIT_LINE_CHARS)
+                .iter()
+                .enumerate()
+                .try_for_each(|(row, line)| {
+                    let location = Point {
+                        x: PORTRAIT_MARGIN,
+                        y: location.y + LINE_HEIGHT * row as i16,
+                    };
+                    renderer.draw_text(line, &location)
+                }),
+        };
+        if let Err(err) = drawn {
+            log::error!("Could not draw the prompt {:#?}", err);
+        }
+    }
+}
+
+/// Breaks `text` between words into lines of at most `max_chars`, unless
+/// a single word is longer.
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + word.len() <= max_chars => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::PORTRAIT;
+
+    #[test]
+    fn prompts_wrap_between_words() {
+        assert_eq!(
+            wrap("Press Space to jump onto platforms", 24),
+            ["Press Space to jump onto", "platforms"]
+        );
+        assert_eq!(wrap("DEMO - press any key", 24), ["DEMO - press any key"]);
+        assert_eq!(wrap("unbreakable", 4), ["unbreakable"]);
+        assert_eq!(
+            Layout::of(WorldBounds {
+                width: PORTRAIT.0,
+                height: PORTRAIT.1
+            }),
+            Layout::Portrait
+        );
+    }
+}
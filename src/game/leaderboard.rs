@@ -0,0 +1,122 @@
+use std::{cell::RefCell, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::browser;
+
+/// A session started with the leaderboard, whose key signs the scores sent
+/// in it.
+#[derive(Debug, Clone, Deserialize)]
+struct Session {
+    id: String,
+    key: String,
+}
+
+/// A score as the leaderboard gets it. The replay hash ties the score to the
+/// run that made it, for the leaderboard to check against the replay.
+#[derive(Debug, Serialize)]
+struct Submission {
+    distance: u32,
+    /// A string, since the seed doesn't fit in a JavaScript number.
+    seed: String,
+    replay_hash: String,
+    session: Option<String>,
+    signature: Option<String>,
+}
+
+/// Where scores are sent at the end of each run.
+pub struct Leaderboard {
+    url: String,
+    /// Filled in once the leaderboard answers.
+    session: Rc<RefCell<Option<Session>>>,
+}
+
+impl Leaderboard {
+    /// Starts a session with the leaderboard at `url` in the background, so
+    /// a slow leaderboard doesn't hold up the game. Until it answers, or if
+    /// it hands out no key, scores are sent unsigned, for it to take or leave.
+    pub fn connect(url: String) -> Self {
+        let session = Rc::new(RefCell::new(None));
+        let started = session.clone();
+        let session_url = url.clone();
+        browser::spawn_local(async move {
+            match start_session(&session_url).await {
+                Ok(session) => *started.borrow_mut() = Some(session),
+                Err(err) => log::warn!("Scores will be sent unsigned {:#?}", err),
+            }
+        });
+        Leaderboard { url, session }
+    }
+
+    /// Sends a run's score in the background, along with a hash of its
+    /// replay.
+    pub fn submit(&self, distance: u32, seed: u64, replay_code: &str) {
+        let url = format!("{}/scores", self.url.trim_end_matches('/'));
+        let session = self.session.borrow().clone();
+        let replay_code = replay_code.to_string();
+        browser::spawn_local(async move {
+            if let Err(err) = submit(&url, session, distance, seed, &replay_code).await {
+                log::error!("Could not submit the score {:#?}", err);
+            }
+        });
+    }
+}
+
+async fn start_session(url: &str) -> Result<Session> {
+    let url = format!("{}/session", url.trim_end_matches('/'));
+    let response = browser::post_json(&url, &wasm_bindgen::JsValue::NULL).await?;
+    serde_wasm_bindgen::from_value(browser::response_json(&response).await?)
+        .map_err(|err| anyhow!("Could not read the leaderboard session {:#?}", err))
+}
+
+async fn submit(
+    url: &str,
+    session: Option<Session>,
+    distance: u32,
+    seed: u64,
+    replay_code: &str,
+) -> Result<()> {
+    let replay_hash = browser::sha256(replay_code.as_bytes()).await?;
+    let signature = match &session {
+        Some(session) => Some(
+            browser::hmac_sha256(
+                session.key.as_bytes(),
+                signed_message(distance, seed, &replay_hash).as_bytes(),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+    let submission = Submission {
+        distance,
+        seed: seed.to_string(),
+        replay_hash,
+        session: session.map(|session| session.id),
+        signature,
+    };
+    let body = serde_wasm_bindgen::to_value(&submission)
+        .map_err(|err| anyhow!("Could not serialize the score {:#?}", err))?;
+    browser::post_json(url, &body).await?;
+    Ok(())
+}
+
+/// What a score's signature covers, for the leaderboard to sign the same way
+/// and compare.
+fn signed_message(distance: u32, seed: u64, replay_hash: &str) -> String {
+    format!("{}:{}:{}", distance, seed, replay_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_signature_covers_the_whole_score() {
+        assert_eq!(signed_message(1234, 42, "ab12"), "1234:42:ab12");
+        assert_ne!(
+            signed_message(1234, 42, "ab12"),
+            signed_message(12344, 2, "ab12")
+        );
+    }
+}
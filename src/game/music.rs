@@ -0,0 +1,132 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use super::{
+    assets,
+    biomes::{self, Biome},
+};
+use crate::{
+    browser,
+    engine::{Audio, Sound},
+    sound::Looping,
+};
+
+const MUSIC_CROSSFADE: f64 = 2.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mood {
+    /// The current biome's track.
+    Ambient,
+    Tense,
+    Somber,
+}
+
+/// Plays the track that fits the current biome and mood, crossfading
+/// whenever that changes. Tracks load in the background, so the game doesn't
+/// wait on decoding them: each biome's track is loaded while the one before
+/// it is playing, so it is ready by the time the boy gets there, and the
+/// first tracks start as soon as they're decoded.
+pub struct Music {
+    audio: Audio,
+    tracks: Rc<RefCell<HashMap<&'static str, Sound>>>,
+    /// Tracks already loaded or being loaded.
+    requested: HashSet<&'static str>,
+    playing: Option<&'static str>,
+    mood: Mood,
+    biome: &'static Biome,
+}
+
+impl Music {
+    /// Starts the music for the first biome with `tracks` already loaded,
+    /// loading the rest of [`Music::first_tracks`].
+    pub fn new(audio: Audio, tracks: HashMap<&'static str, Sound>) -> Self {
+        let mut music = Music {
+            audio,
+            requested: tracks.keys().copied().collect(),
+            tracks: Rc::new(RefCell::new(tracks)),
+            playing: None,
+            mood: Mood::Ambient,
+            biome: biomes::at(0),
+        };
+        Self::first_tracks()
+            .into_iter()
+            .for_each(|track| music.preload(track));
+        music.set_biome(biomes::at(0));
+        music
+    }
+
+    /// Starts the track that should be playing once it has loaded.
+    pub fn update(&mut self) {
+        self.play();
+    }
+
+    /// The tracks to load first, to start with and to play straight away
+    /// when the boy speeds up or is knocked out.
+    pub fn first_tracks() -> [&'static str; 3] {
+        [
+            biomes::at(0).track,
+            assets::TENSE_SONG,
+            assets::GAME_OVER_STING,
+        ]
+    }
+
+    pub fn set_mood(&mut self, mood: Mood) {
+        self.mood = mood;
+        self.play();
+    }
+
+    /// Moves the ambient music on to `biome`, and starts loading the track
+    /// for the biome after it.
+    pub fn set_biome(&mut self, biome: &'static Biome) {
+        self.biome = biome;
+        self.preload(biomes::after(biome).track);
+        self.play();
+    }
+
+    /// Crossfades to the track for the current mood and biome, unless it is
+    /// already playing or hasn't loaded yet. Called again every frame from
+    /// [`Music::update`], so a late track starts as soon as it arrives.
+    fn play(&mut self) {
+        let (track, looping) = match self.mood {
+            Mood::Ambient => (self.biome.track, Looping::Yes),
+            Mood::Tense => (assets::TENSE_SONG, Looping::Yes),
+            Mood::Somber => (assets::GAME_OVER_STING, Looping::No),
+        };
+        if self.playing == Some(track) {
+            return;
+        }
+        let tracks = self.tracks.borrow();
+        let Some(sound) = tracks.get(track) else {
+            return;
+        };
+        // The very first track starts at once rather than fading in.
+        let duration = if self.playing.is_some() {
+            MUSIC_CROSSFADE
+        } else {
+            0.0
+        };
+        match self.audio.crossfade_to(sound, duration, looping) {
+            Ok(()) => self.playing = Some(track),
+            Err(err) => log::error!("Error crossfading music {:#?}", err),
+        }
+    }
+
+    fn preload(&mut self, track: &'static str) {
+        if !self.requested.insert(track) {
+            return;
+        }
+        let audio = self.audio.clone();
+        let tracks = self.tracks.clone();
+        browser::spawn_local(async move {
+            match audio.load_sound(track).await {
+                Ok(sound) => {
+                    tracks.borrow_mut().insert(track, sound);
+                }
+                Err(err) => log::warn!("Could not load {} {:#?}", track, err),
+            }
+        });
+    }
+}
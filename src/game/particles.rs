@@ -0,0 +1,159 @@
+use std::rc::Rc;
+
+use crate::engine::{Atlas, Physics, Point, Rect, Renderer, Timer, Vector};
+
+/// Shattered images break into this many pieces across and down.
+const PIECES: i16 = 3;
+const SHARD_SPEED: i16 = 3;
+const SHARD_LIFT: i16 = 4;
+const SHARD_FRAMES: u16 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Shard {
+    frame: Rect,
+    position: Vector,
+    velocity: Vector,
+    timer: Timer,
+}
+
+impl Shard {
+    fn update(mut self, scroll: i16, physics: &Physics) -> Self {
+        let (position, velocity) = physics.step(self.position, self.velocity);
+        self.position = Vector {
+            x: position.x + f32::from(scroll),
+            ..position
+        };
+        self.velocity = velocity;
+        self.timer.tick();
+        self
+    }
+}
+
+/// Short-lived pieces of images flying apart, such as a breaking shield.
+pub struct Particles {
+    atlas: Rc<Atlas>,
+    shards: Vec<Shard>,
+}
+
+impl Particles {
+    pub fn new(atlas: Rc<Atlas>) -> Self {
+        Particles {
+            atlas,
+            shards: vec![],
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Breaks the atlas image `name`, drawn with its top left at `position`,
+    /// into pieces that fly out from its middle.
+    pub fn shatter(&mut self, name: &str, position: Point) {
+        if let Some(frame) = self.atlas.frame(name) {
+            self.shards.extend(shards(frame, position));
+        }
+    }
+
+    /// Moves every piece on by a frame, falling the way the world's
+    /// `physics` pulls and scrolling along with it.
+    pub fn update(&mut self, scroll: i16, physics: &Physics) {
+        self.shards = self
+            .shards
+            .drain(..)
+            .map(|shard| shard.update(scroll, physics))
+            .filter(|shard| !shard.timer.finished())
+            .collect();
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        for shard in self.shards.iter() {
+            self.atlas.draw(
+                renderer,
+                &shard.frame,
+                &Rect::new(
+                    shard.position.round(),
+                    shard.frame.width,
+                    shard.frame.height,
+                ),
+            );
+        }
+    }
+}
+
+fn shards(frame: Rect, position: Point) -> Vec<Shard> {
+    let width = frame.width / PIECES;
+    let height = frame.height / PIECES;
+    (0..PIECES)
+        .flat_map(|row| (0..PIECES).map(move |column| (row, column)))
+        .map(|(row, column)| {
+            let velocity = Point {
+                x: column - PIECES / 2,
+                y: row - PIECES / 2,
+            } * SHARD_SPEED
+                - Point {
+                    x: 0,
+                    y: SHARD_LIFT,
+                };
+            Shard {
+                frame: Rect::new_from_x_y(
+                    frame.x() + column * width,
+                    frame.y() + row * height,
+                    width,
+                    height,
+                ),
+                position: Vector {
+                    x: f32::from(position.x + column * width),
+                    y: f32::from(position.y + row * height),
+                },
+                velocity: Vector {
+                    x: f32::from(velocity.x),
+                    y: f32::from(velocity.y),
+                },
+                timer: Timer::new(SHARD_FRAMES),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shattering_splits_the_frame_into_pieces_flying_outwards() {
+        let pieces = shards(Rect::new_from_x_y(10, 20, 48, 48), Point { x: 100, y: 200 });
+        assert_eq!(pieces.len(), 9);
+        assert_eq!(pieces[0].frame, Rect::new_from_x_y(10, 20, 16, 16));
+        assert_eq!(pieces[8].position, Vector { x: 132.0, y: 232.0 });
+        assert!(pieces[0].velocity.x < 0.0 && pieces[0].velocity.y < 0.0);
+        assert!(pieces[8].velocity.x > 0.0);
+        assert_eq!(
+            pieces[4].velocity,
+            Vector {
+                x: 0.0,
+                y: -f32::from(SHARD_LIFT)
+            }
+        );
+    }
+
+    #[test]
+    fn shards_fall_scroll_and_expire() {
+        let physics = Physics {
+            gravity: 1.0,
+            gravity_scale: 1.0,
+            max_fall_speed: 20.0,
+            max_speed: f32::MAX,
+        };
+        let shard = Shard {
+            frame: Rect::default(),
+            position: Vector::default(),
+            velocity: Vector { x: 2.0, y: -4.0 },
+            timer: Timer::new(1),
+        }
+        .update(-5, &physics);
+        assert_eq!(shard.position, Vector { x: -3.0, y: -3.0 });
+        assert_eq!(shard.velocity, Vector { x: 2.0, y: -3.0 });
+        assert!(shard.timer.finished());
+    }
+}
@@ -0,0 +1,314 @@
+use anyhow::Result;
+use futures::channel::mpsc::UnboundedReceiver;
+
+use super::{
+    announce, countdown::Countdown, suspend, Ready, Walk, WalkTheDogState, WalkTheDogStateMachine,
+    Walking, WalkingEndState,
+};
+use crate::engine::{self, KeyState, Renderer};
+
+/// Pauses a run, and resumes it again from the menu.
+pub const PAUSE_KEY: &str = "Escape";
+/// Runs the paused game on by exactly one update, to look at what happens
+/// frame by frame.
+pub const STEP_KEY: &str = "Period";
+
+/// What each button on the pause menu does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+    ToggleSound,
+    ToggleSlide,
+    Back,
+}
+
+impl Action {
+    fn id(self) -> &'static str {
+        match self {
+            Action::Resume => "pause_resume",
+            Action::Restart => "pause_restart",
+            Action::Settings => "pause_settings",
+            Action::Quit => "pause_quit",
+            Action::ToggleSound => "pause_sound",
+            Action::ToggleSlide => "pause_slide",
+            Action::Back => "pause_back",
+        }
+    }
+
+    fn label(self, walk: &Walk) -> &'static str {
+        let settings = walk.profiles.current().settings;
+        match self {
+            Action::Resume => "Resume",
+            Action::Restart => "Restart Run",
+            Action::Settings => "Settings",
+            Action::Quit => "Quit to Title",
+            Action::ToggleSound if settings.mute => "Sound: Off",
+            Action::ToggleSound => "Sound: On",
+            Action::ToggleSlide if walk.hold_to_slide => "Slide: Hold",
+            Action::ToggleSlide => "Slide: Tap",
+            Action::Back => "Back",
+        }
+    }
+}
+
+const MENU: [Action; 4] = [
+    Action::Resume,
+    Action::Restart,
+    Action::Settings,
+    Action::Quit,
+];
+const SETTINGS: [Action; 3] = [Action::ToggleSound, Action::ToggleSlide, Action::Back];
+
+/// The pause menu. The run stands still underneath it until it's resumed,
+/// restarted, or left for the title screen.
+pub struct Paused {
+    /// The buttons showing, and their clicks.
+    buttons: Vec<(Action, UnboundedReceiver<()>)>,
+    /// Whether the pause key has been pressed again since the menu opened.
+    /// The run resumes once it's let go, so the same press doesn't pause it
+    /// again straight away.
+    resuming: bool,
+    /// Whether the game has been stepped since it was paused. The bounding
+    /// boxes show once it has.
+    stepped: bool,
+}
+
+pub enum PausedEndState {
+    Resume(WalkTheDogState<Countdown>),
+    Restart(WalkTheDogState<Countdown>),
+    Quit(WalkTheDogState<Ready>),
+    /// A step ended the run, or restarted it.
+    Stepped(WalkingEndState),
+    Continue(WalkTheDogState<Paused>),
+}
+
+impl From<PausedEndState> for WalkTheDogStateMachine {
+    fn from(state: PausedEndState) -> Self {
+        match state {
+            PausedEndState::Resume(state) => state.into(),
+            PausedEndState::Restart(state) => state.into(),
+            PausedEndState::Quit(state) => state.into(),
+            PausedEndState::Stepped(state) => state.into(),
+            PausedEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Paused>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Paused>) -> Self {
+        WalkTheDogStateMachine::Paused(state)
+    }
+}
+
+impl WalkTheDogState<Walking> {
+    /// Races can't be paused, since the other runner keeps going.
+    pub(super) fn pause_pressed(&self, keystate: &KeyState) -> bool {
+        self.walk.seed.is_none() && keystate.just_pressed(PAUSE_KEY)
+    }
+
+    pub(super) fn pause(self) -> Result<WalkTheDogState<Paused>, WalkTheDogState<Walking>> {
+        match show(&self.walk, &MENU) {
+            Ok(buttons) => {
+                announce("Paused");
+                self.walk.sound_effects.audio.suspend();
+                Ok(WalkTheDogState {
+                    _state: Paused {
+                        buttons,
+                        resuming: false,
+                        stepped: false,
+                    },
+                    walk: self.walk,
+                })
+            }
+            Err(err) => {
+                log::error!("Could not show the pause menu {:#?}", err);
+                Err(self)
+            }
+        }
+    }
+}
+
+impl WalkTheDogState<Paused> {
+    pub fn update(mut self, keystate: &KeyState) -> PausedEndState {
+        if keystate.just_pressed(PAUSE_KEY) {
+            self._state.resuming = true;
+        }
+        if keystate.just_released(PAUSE_KEY) && self._state.resuming {
+            return PausedEndState::Resume(self.resume());
+        }
+        if keystate.just_pressed(STEP_KEY) {
+            return self.step(keystate);
+        }
+
+        let Some(action) = self._state.buttons.iter_mut().find_map(|(action, click)| {
+            matches!(click.try_next(), Ok(Some(()))).then_some(*action)
+        }) else {
+            return PausedEndState::Continue(self);
+        };
+        match action {
+            Action::Resume => PausedEndState::Resume(self.resume()),
+            Action::Restart => PausedEndState::Restart(self.quit().start_countdown()),
+            Action::Quit => PausedEndState::Quit(self.quit()),
+            Action::Settings | Action::Back => {
+                let actions: &[Action] = if action == Action::Settings {
+                    &SETTINGS
+                } else {
+                    &MENU
+                };
+                self.show(actions)
+            }
+            Action::ToggleSound | Action::ToggleSlide => {
+                self.toggle(action);
+                self.show(&SETTINGS)
+            }
+        }
+    }
+
+    /// Runs one update of the run underneath, and stays paused unless it
+    /// ended the run. Stepping through a hard part frame by frame isn't
+    /// playing it, so the run counts as cheated.
+    fn step(mut self, keystate: &KeyState) -> PausedEndState {
+        self.walk.run.cheated = true;
+        let Paused {
+            buttons, resuming, ..
+        } = self._state;
+        let audio = self.walk.sound_effects.audio.clone();
+        let screen = self.walk.screen.clone();
+        let walking = WalkTheDogState {
+            _state: Walking,
+            walk: self.walk,
+        };
+        match walking.update(keystate) {
+            WalkingEndState::Continue(walking) => PausedEndState::Continue(WalkTheDogState {
+                _state: Paused {
+                    buttons,
+                    resuming,
+                    stepped: true,
+                },
+                walk: walking.walk,
+            }),
+            ended => {
+                if let Err(err) = screen.hide_ui() {
+                    log::error!("Error hiding the pause menu {:#?}", err);
+                }
+                audio.resume();
+                PausedEndState::Stepped(ended)
+            }
+        }
+    }
+
+    pub fn draw_paused(&self, renderer: &Renderer) {
+        if self._state.stepped {
+            renderer.with_bounding_boxes(|| self.draw(renderer));
+        } else {
+            self.draw(renderer);
+        }
+    }
+
+    fn toggle(&mut self, action: Action) {
+        let settings = &mut self.walk.profiles.current_mut().settings;
+        if action == Action::ToggleSound {
+            settings.mute = !settings.mute;
+        } else {
+            settings.hold_to_slide = !settings.hold_to_slide;
+        }
+        let settings = *settings;
+        self.walk.sound_effects.audio.set_muted(settings.mute);
+        self.walk.hold_to_slide = settings.hold_to_slide;
+        if let Err(err) = self.walk.profiles.save() {
+            log::error!("Could not save profiles {:#?}", err);
+        }
+    }
+
+    /// Swaps the buttons showing for `actions`.
+    fn show(mut self, actions: &[Action]) -> PausedEndState {
+        let shown = self
+            .walk
+            .screen
+            .hide_ui()
+            .and_then(|_| show(&self.walk, actions));
+        match shown {
+            Ok(buttons) => {
+                self._state.buttons = buttons;
+                PausedEndState::Continue(self)
+            }
+            Err(err) => {
+                log::error!("Could not show the pause menu {:#?}", err);
+                PausedEndState::Resume(self.resume())
+            }
+        }
+    }
+
+    /// Counts back in, so the player has a moment to see where the boy is
+    /// before the run moves again.
+    fn resume(self) -> WalkTheDogState<Countdown> {
+        if let Err(err) = self.walk.screen.hide_ui() {
+            log::error!("Error hiding the pause menu {:#?}", err);
+        }
+        self.walk.sound_effects.audio.resume();
+        WalkTheDogState {
+            _state: Countdown::resuming(),
+            walk: self.walk,
+        }
+    }
+
+    /// Leaves the run behind for a fresh one on the title screen. An
+    /// abandoned run doesn't count towards the stats.
+    fn quit(self) -> WalkTheDogState<Ready> {
+        suspend::discard();
+        if let Err(err) = self.walk.screen.hide_ui() {
+            log::error!("Error hiding the pause menu {:#?}", err);
+        }
+        self.walk.sound_effects.audio.resume();
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: Box::new(Walk::reset(*self.walk)),
+        }
+    }
+}
+
+/// Draws a button for each of `actions`.
+fn show(walk: &Walk, actions: &[Action]) -> Result<Vec<(Action, UnboundedReceiver<()>)>> {
+    let buttons = actions
+        .iter()
+        .map(|action| {
+            format!(
+                "<button id='{}'>{}</button>",
+                action.id(),
+                action.label(walk)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("");
+    walk.screen
+        .draw_ui(&format!("<div id='pause'><p>Paused</p>{}</div>", buttons))?;
+    actions
+        .iter()
+        .map(|&action| {
+            walk.screen
+                .find(action.id())
+                .map(engine::add_click_handler)
+                .map(|click| (action, click))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_pause_button_has_its_own_id() {
+        let mut ids = MENU
+            .iter()
+            .chain(SETTINGS.iter())
+            .map(|action| action.id())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), MENU.len() + SETTINGS.len());
+    }
+}
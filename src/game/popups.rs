@@ -0,0 +1,83 @@
+use crate::engine::{Point, Rect, Renderer, Timer};
+
+const POPUP_FRAMES: u16 = 45;
+const POPUP_RISE: i16 = 1;
+/// Passing this close over or under an obstacle without touching it scores
+/// a near miss.
+const NEAR_MISS_GAP: i16 = 12;
+
+struct Popup {
+    text: String,
+    position: Point,
+    timer: Timer,
+}
+
+/// Scores that float up from where they were won and fade away.
+#[derive(Default)]
+pub struct Popups {
+    popups: Vec<Popup>,
+}
+
+impl Popups {
+    pub fn count(&self) -> usize {
+        self.popups.len()
+    }
+
+    pub fn spawn(&mut self, points: u32, position: Point) {
+        self.spawn_text(format!("+{}", points), position);
+    }
+
+    /// Floats `text` up from `position`, the same way as a score.
+    pub fn spawn_text(&mut self, text: String, position: Point) {
+        self.popups.push(Popup {
+            text,
+            position,
+            timer: Timer::new(POPUP_FRAMES),
+        });
+    }
+
+    /// Floats every popup up by a frame, scrolling it along with the world.
+    pub fn update(&mut self, scroll: i16) {
+        for popup in self.popups.iter_mut() {
+            popup.position.x += scroll;
+            popup.position.y -= POPUP_RISE;
+            popup.timer.tick();
+        }
+        self.popups.retain(|popup| !popup.timer.finished());
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        for popup in self.popups.iter() {
+            let alpha = popup.timer.fraction_left();
+            if let Err(err) = renderer.draw_text_with_alpha(&popup.text, &popup.position, alpha) {
+                log::error!("Could not draw popup {:#?}", err);
+            }
+        }
+    }
+}
+
+/// Whether the boy got past `obstacle` without touching it, but only just.
+pub fn near_miss(boy: &Rect, obstacle: &Rect) -> bool {
+    let gap = if boy.bottom() <= obstacle.y() {
+        obstacle.y() - boy.bottom()
+    } else {
+        boy.y() - obstacle.bottom()
+    };
+    (0..=NEAR_MISS_GAP).contains(&gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn near_misses_pass_just_over_or_under() {
+        let stone = Rect::new_from_x_y(100, 546, 90, 54);
+        assert!(near_miss(&Rect::new_from_x_y(90, 420, 70, 120), &stone));
+        assert!(!near_miss(&Rect::new_from_x_y(90, 300, 70, 120), &stone));
+
+        let ceiling = Rect::new_from_x_y(100, 420, 640, 93);
+        assert!(near_miss(&Rect::new_from_x_y(90, 520, 70, 80), &ceiling));
+        assert!(!near_miss(&Rect::new_from_x_y(90, 480, 70, 120), &ceiling));
+    }
+}
@@ -0,0 +1,107 @@
+use super::{
+    autopilot::{self, Action},
+    config::GameConfig,
+    Walk, WalkTheDogState, WalkTheDogStateMachine,
+};
+use crate::{
+    engine::{Point, Renderer},
+    obstacles::ObstacleRegistry,
+    segments,
+};
+
+const LINE_HEIGHT: i16 = 22;
+const REPORT_LOCATION: Point = Point { x: 10, y: 30 };
+
+/// The segment preview, opened with `?mode=preview&segment=<index>`. The
+/// autopilot runs through the one segment again and again, counting how
+/// often it gets to the end.
+pub struct Previewing {
+    index: usize,
+    cleared: u32,
+    failed: u32,
+    /// What can be seen to be wrong with the segment without running it.
+    problems: Vec<String>,
+}
+
+/// What the boy can't get past in segment `index` when he is running at the
+/// speed he starts at.
+pub fn problems(index: usize, registry: &ObstacleRegistry, config: &GameConfig) -> Vec<String> {
+    let reach = config.reach(config.running_speed);
+    segments::problems(&segments::segment(index), registry, |modifier| {
+        config.reach_in(config.running_speed, modifier)
+    })
+    .into_iter()
+    .map(|problem| {
+        format!(
+            "Segment {}: {} (a jump out of any zone goes {} across and {} up)",
+            index, problem, reach.distance, reach.height
+        )
+    })
+    .collect()
+}
+
+impl From<WalkTheDogState<Previewing>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Previewing>) -> Self {
+        WalkTheDogStateMachine::Previewing(state)
+    }
+}
+
+impl WalkTheDogState<Previewing> {
+    pub(super) fn preview(walk: Walk, index: usize) -> Self {
+        let problems = problems(index, &walk.registry, &walk.config);
+        let mut state = WalkTheDogState {
+            _state: Previewing {
+                index,
+                cleared: 0,
+                failed: 0,
+                problems,
+            },
+            walk: Box::new(walk),
+        };
+        state.walk.boy.run_right();
+        state
+    }
+
+    pub fn update(mut self) -> Self {
+        match autopilot::decide(&self.walk.boy, self.walk.course.obstacles.active()) {
+            Some(Action::Jump) => self.walk.boy.jump(),
+            Some(Action::Slide) => self.walk.boy.slide(),
+            None => {}
+        }
+        self.walk.advance();
+
+        let boy_x = self.walk.boy.bounding_box().x();
+        if self.walk.knocked_out() {
+            self._state.failed += 1;
+            self.restart()
+        } else if self.walk.course.obstacles.passed(boy_x) {
+            self._state.cleared += 1;
+            self.restart()
+        } else {
+            self
+        }
+    }
+
+    pub fn draw_report(&self, renderer: &Renderer) {
+        let lines = std::iter::once(format!(
+            "Segment {}: cleared {}, failed {}",
+            self._state.index, self._state.cleared, self._state.failed
+        ))
+        .chain(self._state.problems.iter().cloned());
+        for (row, line) in lines.enumerate() {
+            let location = Point {
+                x: REPORT_LOCATION.x,
+                y: REPORT_LOCATION.y + LINE_HEIGHT * row as i16,
+            };
+            if let Err(err) = renderer.draw_text(&line, &location) {
+                log::error!("Could not draw the preview report {:#?}", err);
+            }
+        }
+    }
+
+    fn restart(mut self) -> Self {
+        self.walk = Box::new(Walk::reset(*self.walk));
+        self.walk.boy.run_right();
+        self
+    }
+}
@@ -0,0 +1,76 @@
+use super::{Obstacle, WIDTH};
+use crate::engine::{Camera, Point, Rect, Renderer};
+
+/// Segments are laid out at least this many frames before any of them can
+/// scroll onto the screen.
+const PREWARM_FRAMES: f32 = 2.0;
+/// The next segment starts being built this many frames before it's
+/// needed, a few obstacles each frame.
+const BUILD_LEAD_FRAMES: f32 = 8.0;
+/// Big enough to hold a sprite, small enough to cost nothing to draw on.
+const CANVAS_SIZE: u32 = 64;
+
+/// The course has to reach at least this far right before the next segment
+/// is added, whatever `timeline_minimum` says, so a new segment never
+/// arrives on the frame it could first be seen on a screen `width` wide.
+/// `timeline_minimum` is tuned for the square screen, so a wider one looks
+/// that much further ahead.
+pub fn spawn_line(width: i16, timeline_minimum: i16, walking_speed: f32) -> i16 {
+    let lookahead = timeline_minimum + (width - WIDTH).max(0);
+    lookahead.max(width + (walking_speed.abs() * PREWARM_FRAMES).ceil() as i16)
+}
+
+/// Building the next segment starts once the course ends closer than this.
+pub fn build_line(spawn_line: i16, walking_speed: f32) -> i16 {
+    spawn_line + (walking_speed.abs() * BUILD_LEAD_FRAMES).ceil() as i16
+}
+
+/// Draws new obstacles once where nobody can see them, so the browser has
+/// decoded their images before they scroll on, instead of hitching the
+/// frame they first show.
+#[derive(Default)]
+pub struct Prewarm {
+    renderer: Option<Renderer>,
+}
+
+impl Prewarm {
+    pub fn new() -> Self {
+        let renderer = Renderer::offscreen(CANVAS_SIZE, CANVAS_SIZE)
+            .map_err(|err| log::warn!("Could not prewarm sprites {:#?}", err))
+            .ok();
+        Prewarm { renderer }
+    }
+
+    pub fn draw(&self, obstacles: &[Box<dyn Obstacle>]) {
+        let Some(renderer) = &self.renderer else {
+            return;
+        };
+        obstacles.iter().for_each(|obstacle| {
+            let bounding_box = obstacle.bounding_box();
+            let camera = Camera {
+                viewport: Rect::new_from_x_y(0, 0, CANVAS_SIZE as i16, CANVAS_SIZE as i16),
+                offset: Point {
+                    x: -bounding_box.x(),
+                    y: -bounding_box.y(),
+                },
+                mirrored: false,
+            };
+            renderer.with_camera(camera, || obstacle.draw(renderer));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_are_added_before_they_can_be_seen() {
+        assert_eq!(spawn_line(WIDTH, 1000, 4.0), 1000);
+        assert_eq!(spawn_line(WIDTH, 300, 4.5), WIDTH + 9);
+        assert_eq!(spawn_line(WIDTH, 300, -4.5), WIDTH + 9);
+        assert_eq!(spawn_line(1067, 1000, 4.5), 1467);
+        assert_eq!(spawn_line(1067, 300, 4.5), 1076);
+        assert_eq!(build_line(1000, -4.5), 1036);
+    }
+}
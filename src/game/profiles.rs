@@ -0,0 +1,401 @@
+use anyhow::Result;
+use futures::channel::mpsc::UnboundedReceiver;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    announce,
+    cosmetics::Skin,
+    save::{self, Migration},
+    stats,
+    stats::Stats,
+    tutorial,
+    tutorial::Tutorial,
+    Ready, Walk, WalkTheDogState, WalkTheDogStateMachine,
+};
+use crate::{
+    browser::StartupConfig,
+    engine::{self, KeyState},
+};
+
+const STORAGE_KEY: &str = "walk_the_dog_profiles";
+const MIGRATIONS: [Migration; 0] = [];
+const FIRST_PROFILE: &str = "Player 1";
+const MAX_NAME_LENGTH: usize = 16;
+const MIRROR: &str = "mirror";
+/// Distance run over every run of a profile that unlocks mirror mode.
+const MIRROR_UNLOCK_DISTANCE: u32 = 20000;
+const MIRROR_KEY: &str = "KeyM";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mute: bool,
+    pub hold_to_slide: bool,
+    pub mirrored: bool,
+    pub skin: Skin,
+}
+
+impl Settings {
+    /// The settings with those given in the URL put in their place.
+    pub fn with(self, config: &StartupConfig) -> Self {
+        Settings {
+            mute: config.mute.unwrap_or(self.mute),
+            hold_to_slide: config.hold_to_slide.unwrap_or(self.hold_to_slide),
+            mirrored: config.mirrored.unwrap_or(self.mirrored),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub settings: Settings,
+    /// The extras earned by playing and the skins bought, by name.
+    #[serde(default)]
+    pub unlocks: Vec<String>,
+    /// Coins collected over every run and not spent yet.
+    #[serde(default)]
+    pub coins: u32,
+}
+
+impl Profile {
+    fn new(name: &str) -> Self {
+        Profile {
+            name: name.to_string(),
+            settings: Settings::default(),
+            unlocks: vec![],
+            coins: 0,
+        }
+    }
+
+    fn unlocked(&self, extra: &str) -> bool {
+        self.unlocks.iter().any(|unlock| unlock == extra)
+    }
+
+    pub fn owns(&self, skin: Skin) -> bool {
+        skin.price() == 0 || self.unlocked(skin.id())
+    }
+
+    /// Spends coins on `skin`, unless there aren't enough.
+    pub fn buy(&mut self, skin: Skin) -> bool {
+        if self.coins < skin.price() {
+            return false;
+        }
+        self.coins -= skin.price();
+        self.unlocks.push(skin.id().to_string());
+        true
+    }
+}
+
+/// Everyone who plays on this machine. Each profile keeps its own stats,
+/// tutorial progress, settings and unlocks in local storage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    /// The profile playing now, which is chosen first next time.
+    current: usize,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            profiles: vec![Profile::new(FIRST_PROFILE)],
+            current: 0,
+        }
+    }
+}
+
+impl Profiles {
+    pub fn load() -> Self {
+        match Self::read() {
+            Ok(Some(profiles)) if !profiles.profiles.is_empty() => Profiles {
+                current: profiles.current.min(profiles.profiles.len() - 1),
+                ..profiles
+            },
+            Ok(_) => Profiles::default(),
+            Err(err) => {
+                log::error!("Could not load profiles {:#?}", err);
+                Profiles::default()
+            }
+        }
+    }
+
+    fn read() -> Result<Option<Self>> {
+        save::load(STORAGE_KEY, &MIGRATIONS)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save::save(STORAGE_KEY, self, &MIGRATIONS)
+    }
+
+    pub fn current_mut(&mut self) -> &mut Profile {
+        &mut self.profiles[self.current]
+    }
+
+    /// Adds a profile called `name`, unless there already is one, and
+    /// returns where it is.
+    fn add(&mut self, name: &str) -> usize {
+        let name: String = name.trim().chars().take(MAX_NAME_LENGTH).collect();
+        if let Some(index) = self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == name)
+        {
+            return index;
+        }
+        self.profiles.push(Profile::new(&name));
+        self.profiles.len() - 1
+    }
+
+    /// Where the current profile keeps `key` in local storage.
+    pub fn storage_key(&self, key: &str) -> String {
+        storage_key(key, self.current)
+    }
+
+    pub fn current(&self) -> &Profile {
+        &self.profiles[self.current]
+    }
+
+    /// Puts the coins from a run in the current profile's wallet.
+    pub fn earn(&mut self, coins: u32) {
+        let profile = self.current_mut();
+        profile.coins = profile.coins.saturating_add(coins);
+    }
+
+    /// Unlocks mirror mode once the current profile has run far enough over
+    /// all its runs, and saves it.
+    pub fn unlock(&mut self, stats: &Stats) {
+        let profile = self.current_mut();
+        if profile.unlocked(MIRROR) || stats.total_distance < MIRROR_UNLOCK_DISTANCE {
+            return;
+        }
+        profile.unlocks.push(MIRROR.to_string());
+        announce("Mirror mode unlocked. Press M on the start screen to turn it on");
+        if let Err(err) = self.save() {
+            log::error!("Could not save profiles {:#?}", err);
+        }
+    }
+}
+
+/// The first profile keeps to the keys from before there were profiles, so
+/// progress made then carries over.
+fn storage_key(key: &str, profile: usize) -> String {
+    if profile == 0 {
+        key.to_string()
+    } else {
+        format!("{}_{}", key, profile)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\'', "&#39;")
+}
+
+/// The profile select screen shown at startup.
+pub struct ChoosingProfile {
+    /// One for each profile's button, in order.
+    clicks: Vec<UnboundedReceiver<()>>,
+    new_profile: engine::SubmitHandler,
+    config: StartupConfig,
+}
+
+pub enum ChoosingProfileEndState {
+    Complete(WalkTheDogState<Ready>),
+    Continue(WalkTheDogState<ChoosingProfile>),
+}
+
+impl From<ChoosingProfileEndState> for WalkTheDogStateMachine {
+    fn from(state: ChoosingProfileEndState) -> Self {
+        match state {
+            ChoosingProfileEndState::Complete(state) => state.into(),
+            ChoosingProfileEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<ChoosingProfile>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<ChoosingProfile>) -> Self {
+        WalkTheDogStateMachine::ChoosingProfile(state)
+    }
+}
+
+impl WalkTheDogState<ChoosingProfile> {
+    /// Asks who is playing, or goes on as the last player if the screen
+    /// can't be shown.
+    pub(super) fn start(walk: Walk, config: StartupConfig) -> WalkTheDogStateMachine {
+        let buttons = walk
+            .profiles
+            .profiles
+            .iter()
+            .enumerate()
+            .map(|(index, profile)| {
+                format!(
+                    "<button id='profile_{}'>{}</button>",
+                    index,
+                    escape(&profile.name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("");
+        let html = format!(
+            "<div id='profiles'><p>Who's playing?</p>{}<input id='new_profile' \
+             maxlength='{}' placeholder='New player' autocomplete='off'></div>",
+            buttons, MAX_NAME_LENGTH
+        );
+        let screen = walk.screen.draw_ui(&html).and_then(|_unit| {
+            let clicks = (0..walk.profiles.profiles.len())
+                .map(|index| {
+                    walk.screen
+                        .find(&format!("profile_{}", index))
+                        .map(engine::add_click_handler)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let input = walk.screen.find_input("new_profile")?;
+            Ok((clicks, engine::add_submit_handler(input)?))
+        });
+        let current = walk.profiles.current;
+        match screen {
+            Ok((clicks, new_profile)) => {
+                announce("Who's playing? Choose a profile or type a new name");
+                WalkTheDogState {
+                    _state: ChoosingProfile {
+                        clicks,
+                        new_profile,
+                        config,
+                    },
+                    walk: Box::new(walk),
+                }
+                .into()
+            }
+            Err(err) => {
+                log::error!("Could not show the profiles {:#?}", err);
+                choose(Box::new(walk), current, &config).into()
+            }
+        }
+    }
+
+    pub fn update(mut self) -> ChoosingProfileEndState {
+        let clicked = self
+            ._state
+            .clicks
+            .iter_mut()
+            .position(|click| matches!(click.try_next(), Ok(Some(()))));
+        let added = match self._state.new_profile.try_next() {
+            Some(name) if !name.trim().is_empty() => Some(self.walk.profiles.add(&name)),
+            _ => None,
+        };
+        match clicked.or(added) {
+            Some(index) => {
+                if let Err(err) = self.walk.screen.hide_ui() {
+                    log::error!("Error hiding the profiles {:#?}", err);
+                }
+                ChoosingProfileEndState::Complete(choose(self.walk, index, &self._state.config))
+            }
+            None => ChoosingProfileEndState::Continue(self),
+        }
+    }
+}
+
+/// Plays as profile `index`, with its progress and settings.
+fn choose(mut walk: Box<Walk>, index: usize, config: &StartupConfig) -> WalkTheDogState<Ready> {
+    walk.profiles.current = index;
+    let profile = walk.profiles.current_mut();
+    profile.settings = profile.settings.with(config);
+    let settings = profile.settings;
+    announce(&format!("Playing as {}", profile.name));
+    if let Err(err) = walk.profiles.save() {
+        log::error!("Could not save profiles {:#?}", err);
+    }
+
+    walk.stats = Stats::load(walk.profiles.storage_key(stats::STORAGE_KEY));
+    walk.tutorial = Tutorial::load(walk.profiles.storage_key(tutorial::STORAGE_KEY));
+    walk.sound_effects.audio.set_muted(settings.mute);
+    walk.hold_to_slide = settings.hold_to_slide;
+    walk.mirrored = settings.mirrored;
+    walk.boy.set_skin(settings.skin);
+    WalkTheDogState {
+        _state: Ready::default(),
+        walk,
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    /// Turns mirror mode on and off with M, once the profile has unlocked it.
+    pub(super) fn toggle_mirror(&mut self, keystate: &KeyState) {
+        let profile = self.walk.profiles.current_mut();
+        if !keystate.just_pressed(MIRROR_KEY) || !profile.unlocked(MIRROR) {
+            return;
+        }
+        profile.settings.mirrored = !profile.settings.mirrored;
+        self.walk.mirrored = profile.settings.mirrored;
+        announce(if self.walk.mirrored {
+            "Mirror mode on"
+        } else {
+            "Mirror mode off"
+        });
+        if let Err(err) = self.walk.profiles.save() {
+            log::error!("Could not save profiles {:#?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_after_the_first_keep_their_own_progress() {
+        assert_eq!(storage_key("walk_the_dog_stats", 0), "walk_the_dog_stats");
+        assert_eq!(storage_key("walk_the_dog_stats", 2), "walk_the_dog_stats_2");
+
+        let mut profiles = Profiles::default();
+        assert_eq!(profiles.add(" Sam "), 1);
+        assert_eq!(profiles.add("Sam"), 1);
+        assert_eq!(profiles.profiles[1].name, "Sam");
+    }
+
+    #[test]
+    fn skins_are_bought_with_coins() {
+        let mut profile = Profile::new("Sam");
+        assert!(profile.owns(Skin::Classic));
+        profile.coins = Skin::Ocean.price() + 10;
+        assert!(!profile.buy(Skin::Shadow));
+        assert!(profile.buy(Skin::Ocean));
+        assert!(profile.owns(Skin::Ocean));
+        assert_eq!(profile.coins, 10);
+        assert_eq!(
+            Skin::Classic.next_owned(|skin| profile.owns(skin)),
+            Skin::Ocean
+        );
+        assert_eq!(
+            Skin::Ocean.next_owned(|skin| profile.owns(skin)),
+            Skin::Classic
+        );
+    }
+
+    #[test]
+    fn url_settings_win_over_the_profile() {
+        let settings = Settings {
+            mute: true,
+            hold_to_slide: true,
+            mirrored: false,
+            skin: Skin::Gold,
+        };
+        let config = StartupConfig {
+            mute: Some(false),
+            ..StartupConfig::default()
+        };
+        assert_eq!(
+            settings.with(&config),
+            Settings {
+                mute: false,
+                ..settings
+            }
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use web_sys::js_sys::JSON;
+
+use super::Walk;
+use crate::{
+    browser::{
+        webrtc::{self, DataChannel},
+        websocket,
+    },
+    engine::{Point, Renderer},
+};
+
+const GHOST_ALPHA: f64 = 0.4;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RaceMessage {
+    Seed {
+        seed: u32,
+    },
+    Position {
+        distance: u32,
+        y: i16,
+        frame: String,
+    },
+}
+
+struct Ghost {
+    distance: u32,
+    y: i16,
+    frame: String,
+}
+
+/// A ghost race against another browser. Both players run the same seeded
+/// course and send their position every frame over a WebRTC data channel.
+pub struct Race {
+    channel: DataChannel,
+    ghost: Option<Ghost>,
+}
+
+impl Race {
+    /// Joins the signaling room at `url` and connects to the other player.
+    /// The host picks the course seed and the guest waits to receive it.
+    pub async fn connect(url: &str, host: bool) -> Result<(Self, u32)> {
+        let signaling = websocket::connect(url).await?;
+        let mut channel = webrtc::connect(signaling, host).await?;
+        let seed = if host {
+            let seed = rand::random();
+            send(&channel, &RaceMessage::Seed { seed })?;
+            seed
+        } else {
+            loop {
+                let text = channel
+                    .receive()
+                    .await
+                    .ok_or_else(|| anyhow!("Race closed before the seed arrived"))?;
+                if let RaceMessage::Seed { seed } = parse(&text)? {
+                    break seed;
+                }
+            }
+        };
+        Ok((
+            Race {
+                channel,
+                ghost: None,
+            },
+            seed,
+        ))
+    }
+
+    /// Lets the other runner know the race is over on this side.
+    pub fn leave(&self) {
+        self.channel.close();
+    }
+
+    pub fn update(&mut self, walk: &Walk) {
+        let position = RaceMessage::Position {
+            distance: walk.run.distance,
+            y: walk.boy.pos_y(),
+            frame: walk.boy.frame_name(),
+        };
+        if let Err(err) = send(&self.channel, &position) {
+            log::error!("Could not send race position {:#?}", err);
+        }
+
+        while let Some(text) = self.channel.try_receive() {
+            match parse(&text) {
+                Ok(RaceMessage::Position { distance, y, frame }) => {
+                    self.ghost = Some(Ghost { distance, y, frame });
+                }
+                Ok(RaceMessage::Seed { .. }) => {}
+                Err(err) => log::error!("Could not read race message {:#?}", err),
+            }
+        }
+    }
+
+    pub fn draw(&self, renderer: &Renderer, walk: &Walk) {
+        if let Some(ghost) = &self.ghost {
+            let lead = i64::from(ghost.distance) - i64::from(walk.run.distance);
+            let x = i64::from(walk.boy.pos_x()) + lead;
+            if let Ok(x) = i16::try_from(x) {
+                let position = Point { x, y: ghost.y };
+                walk.boy
+                    .draw_ghost(renderer, &ghost.frame, position, GHOST_ALPHA);
+            }
+        }
+    }
+}
+
+fn send(channel: &DataChannel, message: &RaceMessage) -> Result<()> {
+    let value = serde_wasm_bindgen::to_value(message)
+        .map_err(|err| anyhow!("Could not serialize race message {:#?}", err))?;
+    let json: String = JSON::stringify(&value)
+        .map_err(|err| anyhow!("Could not stringify race message {:#?}", err))?
+        .into();
+    channel.send(&json)
+}
+
+fn parse(text: &str) -> Result<RaceMessage> {
+    let value =
+        JSON::parse(text).map_err(|err| anyhow!("Could not parse race message {:#?}", err))?;
+    serde_wasm_bindgen::from_value(value)
+        .map_err(|err| anyhow!("Could not convert race message {:#?}", err))
+}
@@ -0,0 +1,263 @@
+use anyhow::{anyhow, Result};
+
+use crate::browser::{self, storage::Store};
+
+/// `?replay=last` watches the last run played on this machine.
+pub const LAST: &str = "last";
+const LAST_REPLAY_KEY: &str = "walk_the_dog_last_replay";
+/// Bumped whenever the course is laid out differently for the same seed
+/// and keys, or the same keys move the boy differently, since older
+/// replays would no longer play back the same.
+const VERSION: u8 = 4;
+const HEADER_LENGTH: usize = 9;
+const RUN_LENGTH: usize = 3;
+const JUMP: u8 = 1;
+const SLIDE: u8 = 2;
+const SLIDE_HELD: u8 = 4;
+const AHEAD: u8 = 8;
+const BACK: u8 = 16;
+const FAST_FALL: u8 = 32;
+
+/// What the first runner was told to do on one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Input {
+    /// Jump was pressed this frame.
+    pub jump: bool,
+    /// Slide was pressed this frame.
+    pub slide: bool,
+    /// Slide is held down, to keep a slide going.
+    pub slide_held: bool,
+    /// Steering ahead is held down, which only matters in the air.
+    pub ahead: bool,
+    /// Steering back is held down.
+    pub back: bool,
+    /// Down was pressed this frame, which drops him out of a jump. Unlike
+    /// `slide`, the autopilot never asks for it.
+    pub fast_fall: bool,
+}
+
+impl Input {
+    fn bits(self) -> u8 {
+        (if self.jump { JUMP } else { 0 })
+            | (if self.slide { SLIDE } else { 0 })
+            | (if self.slide_held { SLIDE_HELD } else { 0 })
+            | (if self.ahead { AHEAD } else { 0 })
+            | (if self.back { BACK } else { 0 })
+            | (if self.fast_fall { FAST_FALL } else { 0 })
+    }
+
+    /// Which way the runner is steered: 1.0 ahead, -1.0 back, or 0.0 when
+    /// both or neither are held.
+    pub fn steering(self) -> f32 {
+        f32::from(u8::from(self.ahead)) - f32::from(u8::from(self.back))
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        Input {
+            jump: bits & JUMP != 0,
+            slide: bits & SLIDE != 0,
+            slide_held: bits & SLIDE_HELD != 0,
+            ahead: bits & AHEAD != 0,
+            back: bits & BACK != 0,
+            fast_fall: bits & FAST_FALL != 0,
+        }
+    }
+}
+
+/// A recorded run: the course seed and the input of every frame, stored as
+/// runs of repeated input so it stays short enough to share.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    runs: Vec<(Input, u16)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Replay { seed, runs: vec![] }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn record(&mut self, input: Input) {
+        match self.runs.last_mut() {
+            Some((last, frames)) if *last == input && *frames < u16::MAX => *frames += 1,
+            _ => self.runs.push((input, 1)),
+        }
+    }
+
+    pub fn playback(&self) -> Playback {
+        Playback {
+            runs: self.runs.clone(),
+            run: 0,
+            frame: 0,
+        }
+    }
+
+    /// Encodes the replay as URL safe base64, ready to paste into `?replay=`.
+    pub fn export(&self) -> Result<String> {
+        let binary: String = self.to_bytes().into_iter().map(char::from).collect();
+        Ok(browser::btoa(&binary)?
+            .trim_end_matches('=')
+            .replace('+', "-")
+            .replace('/', "_"))
+    }
+
+    pub fn import(code: &str) -> Result<Self> {
+        let mut base64 = code.trim().replace('-', "+").replace('_', "/");
+        while base64.len() % 4 != 0 {
+            base64.push('=');
+        }
+        let bytes = browser::atob(&base64)?
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| anyhow!("Replay is not binary data")))
+            .collect::<Result<Vec<u8>>>()?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + self.runs.len() * RUN_LENGTH);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        for (input, frames) in &self.runs {
+            bytes.push(input.bits());
+            bytes.extend_from_slice(&frames.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LENGTH || (bytes.len() - HEADER_LENGTH) % RUN_LENGTH != 0 {
+            return Err(anyhow!("Replay has the wrong length {}", bytes.len()));
+        }
+        if bytes[0] != VERSION {
+            return Err(anyhow!("Unknown replay version {}", bytes[0]));
+        }
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&bytes[1..HEADER_LENGTH]);
+        let runs = bytes[HEADER_LENGTH..]
+            .chunks_exact(RUN_LENGTH)
+            .map(|run| {
+                (
+                    Input::from_bits(run[0]),
+                    u16::from_le_bytes([run[1], run[2]]),
+                )
+            })
+            .collect();
+        Ok(Replay {
+            seed: u64::from_le_bytes(seed),
+            runs,
+        })
+    }
+}
+
+/// Keeps `code` as the last run's replay, for `?replay=last`.
+pub async fn save_last(code: &str) -> Result<()> {
+    Store::open().await?.set(LAST_REPLAY_KEY, code).await
+}
+
+pub async fn load_last() -> Result<Replay> {
+    let code = Store::open()
+        .await?
+        .get(LAST_REPLAY_KEY)
+        .await?
+        .ok_or_else(|| anyhow!("No run has been played yet"))?;
+    Replay::import(&code)
+}
+
+/// Feeds a replay's input back one frame at a time. Once the recording runs
+/// out the runner gets no more input.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    runs: Vec<(Input, u16)>,
+    run: usize,
+    frame: u16,
+}
+
+impl Playback {
+    pub fn next_input(&mut self) -> Input {
+        let Some((input, frames)) = self.runs.get(self.run).copied() else {
+            return Input::default();
+        };
+        self.frame += 1;
+        if self.frame >= frames {
+            self.run += 1;
+            self.frame = 0;
+        }
+        input
+    }
+
+    pub fn rewind(self) -> Self {
+        Playback {
+            run: 0,
+            frame: 0,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUMPING: Input = Input {
+        jump: true,
+        slide: false,
+        slide_held: false,
+        ahead: false,
+        back: false,
+        fast_fall: false,
+    };
+    const SLIDING: Input = Input {
+        jump: false,
+        slide: true,
+        slide_held: true,
+        ahead: false,
+        back: false,
+        fast_fall: true,
+    };
+    const STEERING_BACK: Input = Input {
+        jump: false,
+        slide: false,
+        slide_held: false,
+        ahead: false,
+        back: true,
+        fast_fall: false,
+    };
+
+    #[test]
+    fn replays_round_trip_through_bytes() {
+        let mut replay = Replay::new(0xDEAD_BEEF);
+        [
+            Input::default(),
+            Input::default(),
+            JUMPING,
+            Input::default(),
+            SLIDING,
+            STEERING_BACK,
+        ]
+        .into_iter()
+        .for_each(|input| replay.record(input));
+        assert_eq!(replay.runs.len(), 5);
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+        assert!(Replay::from_bytes(&[VERSION, 0, 0]).is_err());
+        assert_eq!(STEERING_BACK.steering(), -1.0);
+        assert_eq!(JUMPING.steering(), 0.0);
+    }
+
+    #[test]
+    fn playback_repeats_the_recorded_frames() {
+        let mut replay = Replay::new(1);
+        [JUMPING, JUMPING, Input::default()]
+            .into_iter()
+            .for_each(|input| replay.record(input));
+        let mut playback = replay.playback();
+        let inputs: Vec<Input> = (0..4).map(|_| playback.next_input()).collect();
+        assert_eq!(
+            inputs,
+            [JUMPING, JUMPING, Input::default(), Input::default()]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+
+use super::{
+    boss::Boss, clip, window::ObstacleWindow, Obstacle, RedHatBoy, RedHatBoyStateMachine, RunEnd,
+    Walk, WalkTheDogState, WalkTheDogStateMachine, Walking,
+};
+use crate::{
+    browser::recorder::CanvasRecorder,
+    engine::{KeyState, Point, Renderer},
+};
+
+/// How many frames of the run are kept for the rewind, about five seconds.
+const HISTORY_FRAMES: usize = 300;
+/// Frames of history gone back through on each frame of the rewind.
+const REWIND_SPEED: usize = 4;
+/// How many frames before the knockout the slow-motion replay starts from.
+const SLOW_MOTION_FRAMES: usize = 90;
+/// How many times slower than the run the replay plays.
+const SLOW_MOTION: u8 = 2;
+const SKIP_KEY: &str = "Escape";
+const LABEL_LOCATION: Point = Point { x: 10, y: 30 };
+
+/// What the boy and the world did on one frame of the run.
+struct Snapshot {
+    boy: RedHatBoyStateMachine,
+    boss: Option<Boss>,
+    /// How far the world scrolled.
+    scroll: i16,
+    /// Obstacles that went off the left of the screen, kept so they can
+    /// come back while rewinding.
+    culled: Vec<Box<dyn Obstacle>>,
+}
+
+/// The last few seconds of the run, recorded every frame for the rewind
+/// shown when the boy is knocked out.
+#[derive(Default)]
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    culled: Vec<Box<dyn Obstacle>>,
+}
+
+impl History {
+    /// Takes the obstacles that have gone off the screen out of `obstacles`,
+    /// holding on to them until they are too old to be rewound to.
+    pub fn cull(&mut self, obstacles: &mut ObstacleWindow) {
+        self.culled.extend(obstacles.cull());
+    }
+
+    /// Records the end of a frame where the world scrolled by `scroll`.
+    pub fn record(&mut self, boy: &RedHatBoy, boss: Option<&Boss>, scroll: i16) {
+        if self.snapshots.len() == HISTORY_FRAMES {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot {
+            boy: boy.state_machine.clone(),
+            boss: boss.cloned(),
+            scroll,
+            culled: std::mem::take(&mut self.culled),
+        });
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Phase {
+    /// Going quickly back to the start of the history.
+    Back,
+    /// Playing the last moments before the knockout again, slowly.
+    Replay { frames: u8 },
+}
+
+/// Runs the last few seconds back after a knockout, then plays the moments
+/// before it again in slow motion to show what the boy ran into.
+pub struct Rewinding {
+    /// Oldest first.
+    snapshots: Vec<Snapshot>,
+    /// The snapshot the world is shown at.
+    shown: usize,
+    phase: Phase,
+    /// Records the rewind as a clip to share.
+    recorder: Option<CanvasRecorder>,
+}
+
+pub enum RewindingEndState {
+    Complete(RunEnd),
+    Continue(WalkTheDogState<Rewinding>),
+}
+
+impl From<RewindingEndState> for WalkTheDogStateMachine {
+    fn from(state: RewindingEndState) -> Self {
+        match state {
+            RewindingEndState::Complete(state) => state.into(),
+            RewindingEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Rewinding>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Rewinding>) -> Self {
+        WalkTheDogStateMachine::Rewinding(state)
+    }
+}
+
+impl WalkTheDogState<Walking> {
+    /// Starts the rewind, or gives the state back if there is nothing to
+    /// rewind. There is no rewind in two-player mode, where the runners have
+    /// their own courses.
+    pub(super) fn rewind(mut self) -> Result<WalkTheDogState<Rewinding>, Self> {
+        if self.walk.second.is_some() || self.walk.history.snapshots.is_empty() {
+            return Err(self);
+        }
+        let snapshots: Vec<Snapshot> = std::mem::take(&mut self.walk.history).snapshots.into();
+        Ok(WalkTheDogState {
+            _state: Rewinding {
+                shown: snapshots.len() - 1,
+                snapshots,
+                phase: Phase::Back,
+                recorder: clip::record(&self.walk.screen),
+            },
+            walk: self.walk,
+        })
+    }
+}
+
+impl WalkTheDogState<Rewinding> {
+    pub fn update(mut self, keystate: &KeyState) -> RewindingEndState {
+        if keystate.is_pressed(SKIP_KEY) {
+            return RewindingEndState::Complete(self.end());
+        }
+        match self._state.phase {
+            Phase::Back => {
+                for _ in 0..REWIND_SPEED {
+                    self.step_back();
+                }
+                if self._state.shown == 0 {
+                    let start = self
+                        ._state
+                        .snapshots
+                        .len()
+                        .saturating_sub(SLOW_MOTION_FRAMES);
+                    while self._state.shown < start {
+                        self.step_forward();
+                    }
+                    self._state.phase = Phase::Replay { frames: 0 };
+                }
+            }
+            Phase::Replay { frames } if frames + 1 < SLOW_MOTION => {
+                self._state.phase = Phase::Replay { frames: frames + 1 };
+            }
+            Phase::Replay { .. } => {
+                if self._state.shown + 1 == self._state.snapshots.len() {
+                    return RewindingEndState::Complete(self.end());
+                }
+                self.step_forward();
+                self._state.phase = Phase::Replay { frames: 0 };
+            }
+        }
+        RewindingEndState::Continue(self)
+    }
+
+    /// Draws the course and the boy without the coins and effects, which
+    /// aren't rewound.
+    pub fn draw_rewind(&self, renderer: &Renderer) {
+        self.walk.draw_background(renderer);
+        self.walk.in_view(renderer, || {
+            self.walk.boy.draw(renderer);
+            self.walk
+                .course
+                .obstacles
+                .active()
+                .iter()
+                .for_each(|obstacle| {
+                    obstacle.draw(renderer);
+                });
+            self.walk.course.zones.draw(renderer);
+            if let Some(boss) = &self.walk.course.boss {
+                boss.draw(renderer);
+            }
+        });
+        let label = match self._state.phase {
+            Phase::Back => "<< REWIND",
+            Phase::Replay { .. } => "SLOW MOTION",
+        };
+        if let Err(err) = renderer.draw_text(label, &LABEL_LOCATION) {
+            log::error!("Could not draw the rewind label {:#?}", err);
+        }
+    }
+
+    /// Undoes the frame shown, going back to the one before it.
+    fn step_back(&mut self) {
+        if self._state.shown == 0 {
+            return;
+        }
+        let snapshot = &mut self._state.snapshots[self._state.shown];
+        scroll(&mut self.walk, -snapshot.scroll);
+        self.walk
+            .course
+            .obstacles
+            .restore(std::mem::take(&mut snapshot.culled));
+        self._state.shown -= 1;
+        self.show();
+    }
+
+    fn step_forward(&mut self) {
+        self._state.shown += 1;
+        scroll(
+            &mut self.walk,
+            self._state.snapshots[self._state.shown].scroll,
+        );
+        self.show();
+    }
+
+    fn show(&mut self) {
+        let snapshot = &self._state.snapshots[self._state.shown];
+        self.walk.boy.state_machine = snapshot.boy.clone();
+        self.walk.course.boss = snapshot.boss.clone();
+    }
+
+    /// Puts the boy back where he was knocked out and ends the game.
+    fn end(mut self) -> RunEnd {
+        while self._state.shown + 1 < self._state.snapshots.len() {
+            self.step_forward();
+        }
+        let recorder = self._state.recorder.take();
+        WalkTheDogState {
+            _state: Walking,
+            walk: self.walk,
+        }
+        .end_game(recorder)
+    }
+}
+
+fn scroll(walk: &mut Walk, x: i16) {
+    walk.course.background.move_horizontally(x);
+    walk.course.obstacles.scroll(x, walk.bounds.width);
+    walk.course.zones.scroll(x);
+}
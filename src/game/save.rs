@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use web_sys::js_sys::JSON;
+
+use crate::browser;
+
+/// Moves a save up from one version to the next.
+pub type Migration = fn(&str) -> Result<String>;
+
+/// Reads the JSON saved under `key` in local storage, migrated up to the
+/// newest version. The migration at index n takes a save from version n to
+/// n + 1, so the newest version is how many migrations there are. Version 0
+/// is anything saved before saves had versions.
+///
+/// A save that can't be read is copied to a backup key first, so starting
+/// over after the error doesn't lose the player's progress for good.
+pub fn load<T: DeserializeOwned>(key: &str, migrations: &[Migration]) -> Result<Option<T>> {
+    let Some(saved) = browser::local_storage()?
+        .get_item(key)
+        .map_err(|err| anyhow!("Could not read {} {:#?}", key, err))?
+    else {
+        return Ok(None);
+    };
+    let loaded = migrate(&saved, migrations).and_then(|json| {
+        let value =
+            JSON::parse(&json).map_err(|err| anyhow!("Could not parse {} {:#?}", key, err))?;
+        serde_wasm_bindgen::from_value(value)
+            .map_err(|err| anyhow!("Could not convert {} {:#?}", key, err))
+    });
+    if loaded.is_err() {
+        back_up(key, &saved);
+    }
+    loaded.map(Some)
+}
+
+/// Saves `value` as JSON under `key`, marked with the newest version.
+pub fn save<T: Serialize>(key: &str, value: &T, migrations: &[Migration]) -> Result<()> {
+    let value = value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|err| anyhow!("Could not serialize {} {:#?}", key, err))?;
+    let json: String = JSON::stringify(&value)
+        .map_err(|err| anyhow!("Could not stringify {} {:#?}", key, err))?
+        .into();
+    browser::local_storage()?
+        .set_item(key, &format!("v{}:{}", migrations.len(), json))
+        .map_err(|err| anyhow!("Could not save {} {:#?}", key, err))
+}
+
+fn migrate(saved: &str, migrations: &[Migration]) -> Result<String> {
+    let (version, data) = split_version(saved);
+    if version > migrations.len() {
+        return Err(anyhow!(
+            "Saved by a newer version of the game ({} is newer than {})",
+            version,
+            migrations.len()
+        ));
+    }
+    migrations[version..]
+        .iter()
+        .try_fold(data.to_string(), |data, migration| migration(&data))
+}
+
+/// Saves are written as `v1:` and then the data. Anything else is from
+/// before there were versions.
+fn split_version(saved: &str) -> (usize, &str) {
+    saved
+        .strip_prefix('v')
+        .and_then(|rest| rest.split_once(':'))
+        .and_then(|(version, data)| version.parse().ok().map(|version| (version, data)))
+        .unwrap_or((0, saved))
+}
+
+fn back_up(key: &str, saved: &str) {
+    let backup = format!("{}_backup", key);
+    let backed_up = browser::local_storage().and_then(|storage| {
+        storage
+            .set_item(&backup, saved)
+            .map_err(|err| anyhow!("{:#?}", err))
+    });
+    match backed_up {
+        Ok(()) => log::warn!("Kept the unreadable {} in {}", key, backup),
+        Err(err) => log::error!("Could not back up {} {:#?}", key, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_one(data: &str) -> Result<String> {
+        Ok(format!("{}1", data))
+    }
+
+    fn add_two(data: &str) -> Result<String> {
+        Ok(format!("{}2", data))
+    }
+
+    #[test]
+    fn saves_are_migrated_from_the_version_they_were_written_at() {
+        let migrations: [Migration; 2] = [add_one, add_two];
+        assert_eq!(migrate("{}", &migrations).unwrap(), "{}12");
+        assert_eq!(migrate("v1:{}", &migrations).unwrap(), "{}2");
+        assert_eq!(migrate("v2:{}", &migrations).unwrap(), "{}");
+        assert!(migrate("v3:{}", &migrations).is_err());
+        assert_eq!(split_version("stone,pit"), (0, "stone,pit"));
+    }
+}
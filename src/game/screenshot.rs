@@ -0,0 +1,44 @@
+use anyhow::Result;
+use web_sys::{Blob, HtmlCanvasElement};
+
+use crate::{
+    browser::{self, Screen},
+    engine::KeyState,
+};
+
+const KEY: &str = "KeyP";
+/// Phones and tablets get the share sheet instead of a download.
+const TOUCH_SCREEN: &str = "(pointer: coarse)";
+
+/// Saves what is on the canvas as a PNG when P is pressed. The screenshot
+/// button on the game over screen calls [`capture`] itself. `distance` goes
+/// in the file name, so a high score shows in it.
+pub fn update(keystate: &KeyState, screen: &Screen, distance: u32) {
+    if keystate.just_pressed(KEY) {
+        capture(screen, distance);
+    }
+}
+
+/// Saves what is on `screen`'s canvas now.
+pub fn capture(screen: &Screen, distance: u32) {
+    let canvas = screen.canvas();
+    browser::spawn_local(async move {
+        let file_name = format!("walk-the-dog-{}.png", distance);
+        if let Err(err) = save(&canvas, &file_name).await {
+            log::error!("Could not save a screenshot {:#?}", err);
+        }
+    });
+}
+
+async fn save(canvas: &HtmlCanvasElement, file_name: &str) -> Result<()> {
+    let blob = browser::canvas_to_blob(canvas).await?;
+    share(&blob, file_name).await
+}
+
+/// Shares `blob` on phones and tablets, and downloads it everywhere else.
+pub async fn share(blob: &Blob, file_name: &str) -> Result<()> {
+    if browser::matches_media(TOUCH_SCREEN)? && browser::share_file(blob, file_name).await? {
+        return Ok(());
+    }
+    browser::download(blob, file_name)
+}
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use web_sys::js_sys::JSON;
+
+use super::{
+    biomes::{self, BiomeSchedule},
+    red_hat_boy_states::{ContextData, StateData},
+    rewind,
+    stats::RunStats,
+    window::ObstacleWindow,
+    zones::Zones,
+    Effects, RedHatBoy, RedHatBoyStateMachine, Walk,
+};
+use crate::{
+    engine::Point,
+    segments::{ObstacleData, SegmentBuilder},
+};
+
+/// Bumped whenever the snapshot changes shape, since older ones would no
+/// longer load.
+const VERSION: u32 = 2;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct BoySnapshot {
+    state: StateData,
+    context: ContextData,
+    #[cfg(feature = "dev")]
+    #[serde(default)]
+    invincible: bool,
+    effects: Effects,
+}
+
+/// How far the course generator has got through its random numbers. The
+/// position can be bigger than a JSON number holds exactly, so it is kept
+/// as a string.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: String,
+}
+
+impl RngState {
+    fn new(rng: &ChaCha12Rng) -> Self {
+        RngState {
+            seed: rng.get_seed(),
+            stream: rng.get_stream(),
+            word_pos: rng.get_word_pos().to_string(),
+        }
+    }
+
+    fn rng(&self) -> Result<ChaCha12Rng> {
+        let word_pos = self
+            .word_pos
+            .parse()
+            .map_err(|err| anyhow!("Could not read the random number position {:#?}", err))?;
+        let mut rng = ChaCha12Rng::from_seed(self.seed);
+        rng.set_stream(self.stream);
+        rng.set_word_pos(word_pos);
+        Ok(rng)
+    }
+}
+
+/// The whole world of a run at one moment, down to the course generator's
+/// random numbers, so a bug can be dumped from the debug console and
+/// loaded again later to look into. Coins, the wind and the boss aren't
+/// kept.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    boy: BoySnapshot,
+    obstacles: Vec<ObstacleData>,
+    zones: Zones,
+    /// The segment being built ahead of being needed. It is built again
+    /// from the start.
+    building: Option<usize>,
+    timeline: i16,
+    run: RunStats,
+    rng: RngState,
+    power_up_rng: RngState,
+    biomes: BiomeSchedule,
+    next_boss: u32,
+    combo: u32,
+}
+
+impl Snapshot {
+    pub fn distance(&self) -> u32 {
+        self.run.distance
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let value = self
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|err| anyhow!("Could not serialize snapshot {:#?}", err))?;
+        let json: String = JSON::stringify(&value)
+            .map_err(|err| anyhow!("Could not stringify snapshot {:#?}", err))?
+            .into();
+        Ok(json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value =
+            JSON::parse(json).map_err(|err| anyhow!("Could not parse snapshot {:#?}", err))?;
+        let snapshot: Snapshot = serde_wasm_bindgen::from_value(value)
+            .map_err(|err| anyhow!("Could not convert snapshot {:#?}", err))?;
+        if snapshot.version != VERSION {
+            return Err(anyhow!("Unknown snapshot version {}", snapshot.version));
+        }
+        Ok(snapshot)
+    }
+}
+
+impl RedHatBoy {
+    fn snapshot(&self) -> BoySnapshot {
+        BoySnapshot {
+            state: self.state_machine.data(),
+            context: self.state_machine.context().data(),
+            #[cfg(feature = "dev")]
+            invincible: self.invincible,
+            effects: self.effects,
+        }
+    }
+
+    fn restore(&mut self, snapshot: BoySnapshot) {
+        let context = self
+            .state_machine
+            .context()
+            .clone()
+            .with_data(snapshot.context);
+        self.state_machine = RedHatBoyStateMachine::from_data(context, snapshot.state);
+        #[cfg(feature = "dev")]
+        {
+            self.invincible = snapshot.invincible;
+        }
+        self.effects = snapshot.effects;
+        self.events.clear();
+    }
+}
+
+impl Walk {
+    pub(super) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: VERSION,
+            boy: self.boy.snapshot(),
+            obstacles: self.course.obstacles.data(),
+            zones: self.course.zones.clone(),
+            building: self.course.builder.as_ref().map(|builder| builder.index()),
+            timeline: self.course.timeline,
+            run: self.run.clone(),
+            rng: RngState::new(&self.course.rng),
+            power_up_rng: RngState::new(&self.course.power_up_rng),
+            biomes: self.course.biomes,
+            next_boss: self.course.next_boss,
+            combo: self.course.combo,
+        }
+    }
+
+    /// Puts the world back the way it was in `snapshot`. A run that was
+    /// cheated before, or in the snapshot, stays cheated. Nothing is changed
+    /// if it can't be.
+    pub(super) fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        self.course.rng = snapshot.rng.rng()?;
+        self.course.power_up_rng = snapshot.power_up_rng.rng()?;
+        self.boy.restore(snapshot.boy);
+        let obstacles: Vec<_> = snapshot
+            .obstacles
+            .iter()
+            .filter_map(|obstacle| {
+                self.registry.build(
+                    &obstacle.id,
+                    Point {
+                        x: obstacle.x,
+                        y: obstacle.y,
+                    },
+                    &self.atlas,
+                    &self.obstacle_sheet,
+                )
+            })
+            .collect();
+        self.prewarm.draw(&obstacles);
+        self.course.obstacles = ObstacleWindow::new(obstacles, self.bounds.width);
+        self.course.zones = snapshot.zones;
+        self.course.builder = snapshot
+            .building
+            .map(|index| SegmentBuilder::new(index, self.bounds.height));
+        self.course.timeline = snapshot.timeline;
+        self.run = RunStats {
+            cheated: self.run.cheated || snapshot.run.cheated,
+            ..snapshot.run
+        };
+        self.course.biomes = snapshot.biomes;
+        self.course.next_boss = snapshot.next_boss;
+        self.course.combo = snapshot.combo;
+        self.history = rewind::History::default();
+        self.music.set_biome(biomes::at(self.run.distance));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn random_numbers_carry_on_from_where_they_were() {
+        let mut rng = ChaCha12Rng::seed_from_u64(7);
+        (0..100).for_each(|_| {
+            rng.gen::<u32>();
+        });
+        let state = RngState::new(&rng);
+        let mut restored = state.rng().unwrap();
+        assert_eq!(restored.gen::<u64>(), rng.gen::<u64>());
+
+        let bad = RngState {
+            word_pos: "far".to_string(),
+            ..state
+        };
+        assert!(bad.rng().is_err());
+    }
+}
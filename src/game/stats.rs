@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use futures::channel::mpsc::UnboundedReceiver;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    save::{self, Migration},
+    Cause, Ready, ReadyEndState, WalkTheDogState, WalkTheDogStateMachine,
+};
+use crate::engine;
+
+pub const STORAGE_KEY: &str = "walk_the_dog_stats";
+/// The stats haven't changed shape since they were first saved.
+const MIGRATIONS: [Migration; 0] = [];
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub distance: u32,
+    pub jumps: u32,
+    pub knocked_out_by: Option<String>,
+    /// How he went down, whatever it was that did it.
+    #[serde(default)]
+    pub cause: Option<Cause>,
+    /// Points for coins, near misses and surviving boss fights, on top of
+    /// the distance.
+    #[serde(default)]
+    pub bonus: u32,
+    #[serde(default)]
+    pub bosses: u32,
+    #[serde(default)]
+    pub coins: u32,
+    /// Whether developer mode or the debug console changed the run, which
+    /// keeps it off the leaderboard. Saved with the run, so a run carried on
+    /// after a reload is still cheated if it was.
+    #[serde(default)]
+    pub cheated: bool,
+}
+
+/// Lifetime statistics across every run, persisted to local storage under
+/// the player's profile.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Where in local storage the stats are kept.
+    #[serde(skip)]
+    key: String,
+    runs_played: u32,
+    pub total_distance: u32,
+    jumps: u32,
+    knockouts: BTreeMap<String, u32>,
+    /// How many runs ended each way, by the cause's name.
+    #[serde(default)]
+    causes: BTreeMap<String, u32>,
+    last_run: Option<RunStats>,
+    /// The longest run so far.
+    #[serde(default)]
+    best_distance: u32,
+}
+
+impl Stats {
+    pub fn load(key: String) -> Self {
+        let stats = match Self::read(&key) {
+            Ok(stats) => stats.unwrap_or_default(),
+            Err(err) => {
+                log::error!("Could not load stats {:#?}", err);
+                Stats::default()
+            }
+        };
+        Stats { key, ..stats }
+    }
+
+    fn read(key: &str) -> Result<Option<Self>> {
+        save::load(key, &MIGRATIONS)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save::save(&self.key, self, &MIGRATIONS)
+    }
+
+    /// Adds `run` to the stats, returning whether it beat the best run before
+    /// it. The first run has nothing to beat.
+    pub fn record(&mut self, run: RunStats) -> bool {
+        let new_best = self.best_distance > 0 && run.distance > self.best_distance;
+        self.best_distance = self.best_distance.max(run.distance);
+        self.runs_played += 1;
+        self.total_distance = self.total_distance.saturating_add(run.distance);
+        self.jumps += run.jumps;
+        if let Some(obstacle) = &run.knocked_out_by {
+            *self.knockouts.entry(obstacle.clone()).or_default() += 1;
+        }
+        if let Some(cause) = run.cause {
+            *self.causes.entry(cause.name().to_string()).or_default() += 1;
+        }
+        self.last_run = Some(run);
+        new_best
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if let Some(run) = &self.last_run {
+            lines.push(format!("Last run: {} distance", run.distance));
+            lines.push(format!("Last run: {} jumps", run.jumps));
+            lines.push(format!("Last run: {} coins", run.coins));
+            if run.bonus > 0 {
+                lines.push(format!("Last run: {} bonus", run.bonus));
+            }
+        }
+        lines.push(format!("Runs played: {}", self.runs_played));
+        lines.push(format!("Best distance: {}", self.best_distance));
+        lines.push(format!("Total distance: {}", self.total_distance));
+        lines.push(format!("Total jumps: {}", self.jumps));
+        lines.extend(
+            self.knockouts
+                .iter()
+                .map(|(obstacle, count)| format!("Knocked out by {}: {}", obstacle, count)),
+        );
+        lines.extend(
+            self.causes
+                .iter()
+                .map(|(cause, count)| format!("{}: {}", cause, count)),
+        );
+        lines
+    }
+}
+
+pub struct ShowingStats {
+    back_event: UnboundedReceiver<()>,
+}
+
+pub enum ShowingStatsEndState {
+    Complete(WalkTheDogState<Ready>),
+    Continue(WalkTheDogState<ShowingStats>),
+}
+
+impl From<ShowingStatsEndState> for WalkTheDogStateMachine {
+    fn from(state: ShowingStatsEndState) -> Self {
+        match state {
+            ShowingStatsEndState::Complete(state) => state.into(),
+            ShowingStatsEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<ShowingStats>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<ShowingStats>) -> Self {
+        WalkTheDogStateMachine::ShowingStats(state)
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    pub(super) fn show_stats(self) -> ReadyEndState {
+        let html = format!(
+            "<div id='stats'><ul><li>{}</li></ul><button id='stats_back'>Back</button></div>",
+            self.walk.stats.lines().join("</li><li>")
+        );
+        match self
+            .walk
+            .screen
+            .draw_ui(&html)
+            .and_then(|_unit| self.walk.screen.find("stats_back"))
+            .map(engine::add_click_handler)
+        {
+            Ok(back_event) => ReadyEndState::ShowStats(WalkTheDogState {
+                _state: ShowingStats { back_event },
+                walk: self.walk,
+            }),
+            Err(err) => {
+                log::error!("Could not show stats {:#?}", err);
+                ReadyEndState::Continue(self)
+            }
+        }
+    }
+}
+
+impl WalkTheDogState<ShowingStats> {
+    pub fn update(mut self) -> ShowingStatsEndState {
+        if matches!(self._state.back_event.try_next(), Ok(Some(()))) {
+            ShowingStatsEndState::Complete(self.back())
+        } else {
+            ShowingStatsEndState::Continue(self)
+        }
+    }
+
+    fn back(self) -> WalkTheDogState<Ready> {
+        if let Err(err) = self.walk.screen.hide_ui() {
+            log::error!("Error hiding the browser {:#?}", err);
+        }
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: self.walk,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_runs_into_lifetime_stats() {
+        let mut stats = Stats::default();
+        let first = stats.record(RunStats {
+            distance: 300,
+            jumps: 4,
+            knocked_out_by: Some("stone".to_string()),
+            cause: Some(Cause::Tripped),
+            bonus: 0,
+            bosses: 0,
+            coins: 0,
+            cheated: false,
+        });
+        let worse = stats.record(RunStats {
+            distance: 200,
+            jumps: 1,
+            knocked_out_by: Some("stone".to_string()),
+            cause: Some(Cause::Bonked),
+            bonus: 0,
+            bosses: 0,
+            coins: 0,
+            cheated: false,
+        });
+        assert_eq!(stats.runs_played, 2);
+        assert_eq!(stats.total_distance, 500);
+        assert_eq!(stats.jumps, 5);
+        assert_eq!(stats.knockouts.get("stone"), Some(&2));
+        assert_eq!(stats.causes.get("Tripped"), Some(&1));
+        assert_eq!(stats.causes.get("Bonked"), Some(&1));
+        assert_eq!(stats.last_run.as_ref().map(|run| run.distance), Some(200));
+        assert!(!first && !worse);
+        assert!(stats.record(RunStats {
+            distance: 400,
+            ..RunStats::default()
+        }));
+        assert_eq!(stats.best_distance, 400);
+    }
+}
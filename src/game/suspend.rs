@@ -0,0 +1,168 @@
+use anyhow::{anyhow, Result};
+
+use super::{
+    announce,
+    countdown::Countdown,
+    snapshot::Snapshot,
+    ui::{ContinuePanel, UiEvent},
+    Ready, Walk, WalkTheDogState, WalkTheDogStateMachine,
+};
+use crate::{browser, engine::Timer};
+
+const SUSPENDED_RUN_KEY: &str = "walk_the_dog_suspended_run";
+/// How often the run is saved, about every five seconds.
+const AUTOSAVE_FRAMES: u16 = 300;
+
+/// Counts down to the next time the run is saved.
+pub struct Autosave {
+    timer: Timer,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Autosave {
+            timer: Timer::new(AUTOSAVE_FRAMES),
+        }
+    }
+}
+
+/// Races, two-player games, replays and the autopilot's runs can't be
+/// picked up again, since they depend on more than the one runner's world.
+fn suspendable(walk: &Walk) -> bool {
+    walk.seed.is_none() && walk.second.is_none() && walk.playback.is_none() && !walk.autopilot
+}
+
+/// Saves the run to session storage every few seconds, so it can be
+/// carried on with if the page is reloaded by accident or crashes.
+pub fn update(walk: &mut Walk) {
+    if !suspendable(walk) {
+        return;
+    }
+    walk.autosave.timer.tick();
+    if !walk.autosave.timer.finished() {
+        return;
+    }
+    walk.autosave.timer.restart();
+    if let Err(err) = save(walk) {
+        log::warn!("Could not save the run {:#?}", err);
+    }
+}
+
+fn save(walk: &Walk) -> Result<()> {
+    let json = walk.snapshot().to_json()?;
+    browser::session_storage()?
+        .set_item(SUSPENDED_RUN_KEY, &json)
+        .map_err(|err| anyhow!("Could not save {} {:#?}", SUSPENDED_RUN_KEY, err))
+}
+
+fn load() -> Result<Option<Snapshot>> {
+    browser::session_storage()?
+        .get_item(SUSPENDED_RUN_KEY)
+        .map_err(|err| anyhow!("Could not read {} {:#?}", SUSPENDED_RUN_KEY, err))?
+        .map(|json| Snapshot::from_json(&json))
+        .transpose()
+}
+
+/// Forgets the saved run, once it has ended or been left behind.
+pub fn discard() {
+    let removed = browser::session_storage().and_then(|storage| {
+        storage
+            .remove_item(SUSPENDED_RUN_KEY)
+            .map_err(|err| anyhow!("Could not remove {} {:#?}", SUSPENDED_RUN_KEY, err))
+    });
+    if let Err(err) = removed {
+        log::warn!("Could not discard the saved run {:#?}", err);
+    }
+}
+
+/// Asking whether to carry on with the run saved before the page was
+/// reloaded.
+pub struct Resuming {
+    snapshot: Snapshot,
+}
+
+pub enum ResumingEndState {
+    Complete(WalkTheDogState<Countdown>),
+    Declined(WalkTheDogState<Ready>),
+    Continue(WalkTheDogState<Resuming>),
+}
+
+impl From<ResumingEndState> for WalkTheDogStateMachine {
+    fn from(state: ResumingEndState) -> Self {
+        match state {
+            ResumingEndState::Complete(state) => state.into(),
+            ResumingEndState::Declined(state) => state.into(),
+            ResumingEndState::Continue(state) => state.into(),
+        }
+    }
+}
+
+impl From<WalkTheDogState<Resuming>> for WalkTheDogStateMachine {
+    fn from(state: WalkTheDogState<Resuming>) -> Self {
+        WalkTheDogStateMachine::Resuming(state)
+    }
+}
+
+impl WalkTheDogState<Ready> {
+    /// Offers to carry on with the saved run, or gives the state back if
+    /// there isn't one to offer.
+    pub(super) fn offer_saved_run(mut self) -> Result<WalkTheDogState<Resuming>, Self> {
+        if !suspendable(&self.walk) {
+            return Err(self);
+        }
+        let snapshot = match load() {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return Err(self),
+            Err(err) => {
+                log::warn!("Could not load the saved run {:#?}", err);
+                discard();
+                return Err(self);
+            }
+        };
+        let panel = ContinuePanel {
+            distance: snapshot.distance(),
+        };
+        if let Err(err) = self.walk.ui.show_continue(&panel) {
+            log::error!("Could not offer the saved run {:#?}", err);
+            return Err(self);
+        }
+        announce(&format!("Continue run? You had got to {}", panel.distance));
+        Ok(WalkTheDogState {
+            _state: Resuming { snapshot },
+            walk: self.walk,
+        })
+    }
+}
+
+impl WalkTheDogState<Resuming> {
+    pub fn update(mut self) -> ResumingEndState {
+        match self.walk.ui.poll() {
+            Some(UiEvent::ContinueRun) => self.resume(),
+            Some(UiEvent::NewGame) => {
+                discard();
+                ResumingEndState::Declined(self.ready())
+            }
+            _ => ResumingEndState::Continue(self),
+        }
+    }
+
+    /// Puts the saved run back, and counts down into it.
+    fn resume(mut self) -> ResumingEndState {
+        match self.walk.restore(self._state.snapshot.clone()) {
+            Ok(()) => ResumingEndState::Complete(self.ready().start_countdown()),
+            Err(err) => {
+                log::error!("Could not continue the saved run {:#?}", err);
+                discard();
+                ResumingEndState::Declined(self.ready())
+            }
+        }
+    }
+
+    fn ready(mut self) -> WalkTheDogState<Ready> {
+        self.walk.ui.hide();
+        WalkTheDogState {
+            _state: Ready::default(),
+            walk: self.walk,
+        }
+    }
+}
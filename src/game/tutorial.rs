@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use super::{
+    layout::Layout,
+    save::{self, Migration},
+};
+use crate::engine::{Point, Renderer, Timer};
+
+pub const STORAGE_KEY: &str = "walk_the_dog_tutorial";
+/// Version 1 saves the obstacles seen as JSON, instead of joined with commas.
+const MIGRATIONS: [Migration; 1] = [from_comma_list];
+/// How close an obstacle comes before its prompt shows.
+const PROMPT_DISTANCE: i16 = 350;
+const PROMPT_FRAMES: u16 = 120;
+const PROMPT_LOCATION: Point = Point { x: 120, y: 120 };
+
+/// What to tell a new player the first time each obstacle comes up.
+const PROMPTS: [(&str, &str); 7] = [
+    ("stone", "Press Space to jump over stones"),
+    ("pebble", "Jump pebbles, or trip over them"),
+    ("platform", "Press Space to jump onto platforms"),
+    ("ceiling", "Hold Down to crouch under ceilings"),
+    ("up_ramp", "Run up and over hills"),
+    ("spring", "Run onto springs to bounce up high"),
+    ("pit", "Press Space to jump over pits"),
+];
+
+/// Prompts shown to new players as each kind of obstacle first comes up.
+/// Which ones they've seen is kept in local storage, so the tutorial carries
+/// on into the next run until every prompt has been shown, and then stays
+/// out of the way.
+#[derive(Default)]
+pub struct Tutorial {
+    /// Where in local storage the progress is kept.
+    key: String,
+    /// The obstacles the player has been told about.
+    seen: Vec<String>,
+    prompt: Option<(&'static str, Timer)>,
+}
+
+impl Tutorial {
+    pub fn load(key: String) -> Self {
+        let seen = Self::read(&key).unwrap_or_else(|err| {
+            log::error!("Could not load the tutorial progress {:#?}", err);
+            vec![]
+        });
+        Tutorial {
+            key,
+            seen,
+            prompt: None,
+        }
+    }
+
+    fn read(key: &str) -> Result<Vec<String>> {
+        Ok(save::load(key, &MIGRATIONS)?.unwrap_or_default())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        save::save(&self.key, &self.seen, &MIGRATIONS)
+    }
+
+    pub fn complete(&self) -> bool {
+        PROMPTS
+            .iter()
+            .all(|(id, _)| self.seen.iter().any(|seen| seen == id))
+    }
+
+    /// Called every frame with the obstacles ahead of the boy and how far
+    /// off each one is. Returns a prompt when one first shows.
+    pub fn update<'a>(
+        &mut self,
+        ahead: impl Iterator<Item = (&'a str, i16)>,
+    ) -> Option<&'static str> {
+        if let Some((_, timer)) = self.prompt.as_mut() {
+            timer.tick();
+            if !timer.finished() {
+                return None;
+            }
+            self.prompt = None;
+        }
+
+        let (id, text) = ahead
+            .filter(|(_, distance)| (0..=PROMPT_DISTANCE).contains(distance))
+            .filter_map(|(id, _)| PROMPTS.into_iter().find(|(prompt, _)| *prompt == id))
+            .find(|(id, _)| !self.seen.iter().any(|seen| seen == id))?;
+        self.seen.push(id.to_string());
+        self.prompt = Some((text, Timer::new(PROMPT_FRAMES)));
+        if self.complete() {
+            if let Err(err) = self.save() {
+                log::error!("Could not save the tutorial progress {:#?}", err);
+            }
+        }
+        Some(text)
+    }
+
+    pub fn draw(&self, renderer: &Renderer, layout: Layout) {
+        if let Some((text, _)) = self.prompt {
+            layout.draw_prompt(renderer, text, PROMPT_LOCATION);
+        }
+    }
+}
+
+fn from_comma_list(seen: &str) -> Result<String> {
+    let seen: Vec<String> = seen
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    Ok(format!("[{}]", seen.join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompts_once_for_each_obstacle_as_it_comes_close() {
+        let mut tutorial = Tutorial::default();
+        assert_eq!(tutorial.update([("stone", 500)].into_iter()), None);
+        assert_eq!(
+            tutorial.update([("stone", 300), ("pit", 320)].into_iter()),
+            Some("Press Space to jump over stones")
+        );
+        // Nothing new shows while a prompt is up.
+        assert_eq!(tutorial.update([("pit", 300)].into_iter()), None);
+        tutorial.prompt = None;
+        assert_eq!(
+            tutorial.update([("stone", 100), ("coin", 200), ("pit", 300)].into_iter()),
+            Some("Press Space to jump over pits")
+        );
+        assert!(!tutorial.complete());
+    }
+
+    #[test]
+    fn progress_joined_with_commas_becomes_json() {
+        assert_eq!(from_comma_list("stone,pit").unwrap(), r#"["stone","pit"]"#);
+        assert_eq!(from_comma_list("").unwrap(), "[]");
+    }
+}
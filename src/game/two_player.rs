@@ -0,0 +1,120 @@
+use std::rc::Rc;
+
+use super::{course::Course, stats::RunStats, RedHatBoy};
+use crate::{
+    engine::{Atlas, Camera, KeyState, Point, Rect, ScrollingBackground, SpriteSheet, WorldBounds},
+    obstacles::ObstacleRegistry,
+    segments::{build_segment, stone_and_platform},
+};
+
+/// How far below the top of the view the runner is kept.
+pub const CAMERA_HEADROOM: i16 = 150;
+
+/// The second runner of local two-player mode. It runs its own copy of the
+/// seeded course, so both players face the same obstacles, coins, power-ups
+/// and bosses.
+pub struct SecondPlayer {
+    pub boy: RedHatBoy,
+    pub course: Course,
+    pub run: RunStats,
+}
+
+impl SecondPlayer {
+    pub fn new(
+        boy: RedHatBoy,
+        background: ScrollingBackground,
+        registry: &ObstacleRegistry,
+        atlas: Rc<Atlas>,
+        sheet: Rc<SpriteSheet>,
+        seed: u64,
+        bounds: WorldBounds,
+    ) -> Self {
+        let obstacles = build_segment(
+            &stone_and_platform().on_ground(bounds.height),
+            registry,
+            atlas.clone(),
+            sheet,
+            0,
+        );
+        SecondPlayer {
+            boy,
+            course: Course::new(background, obstacles, atlas, seed, bounds.width),
+            run: RunStats::default(),
+        }
+    }
+
+    pub fn reset(
+        self,
+        registry: &ObstacleRegistry,
+        atlas: Rc<Atlas>,
+        sheet: Rc<SpriteSheet>,
+        seed: u64,
+        bounds: WorldBounds,
+    ) -> Self {
+        SecondPlayer::new(
+            RedHatBoy::reset(self.boy),
+            self.course.background,
+            registry,
+            atlas,
+            sheet,
+            seed,
+            bounds,
+        )
+    }
+
+    pub fn steer(&mut self, keystate: &KeyState, hold_to_slide: bool) {
+        if keystate.just_pressed("KeyS") {
+            self.boy.slide();
+            self.boy.fast_fall();
+        }
+        if keystate.is_pressed("KeyS") {
+            if hold_to_slide {
+                self.boy.hold_slide();
+            } else {
+                self.boy.crouch();
+            }
+        }
+
+        if keystate.just_pressed("KeyW") {
+            self.boy.jump();
+        }
+    }
+}
+
+/// A camera showing one half of the split screen of a world `bounds` big,
+/// following the runner at `boy_y` up and down, and up above the top of the
+/// world onto towers.
+pub fn camera(player: i16, boy_y: i16, bounds: WorldBounds) -> Camera {
+    let viewport_height = bounds.height / 2;
+    let top = (boy_y - CAMERA_HEADROOM).min(bounds.height - viewport_height);
+    let viewport_y = player * viewport_height;
+    Camera {
+        viewport: Rect::new_from_x_y(0, viewport_y, bounds.width, viewport_height),
+        offset: Point {
+            x: 0,
+            y: viewport_y - top,
+        },
+        mirrored: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::DESIGN_BOUNDS, *};
+
+    #[test]
+    fn cameras_follow_the_runner_inside_the_world() {
+        assert_eq!(
+            camera(0, 479, DESIGN_BOUNDS).offset,
+            Point { x: 0, y: -300 }
+        );
+        assert_eq!(camera(1, 479, DESIGN_BOUNDS).offset, Point { x: 0, y: 0 });
+        assert_eq!(camera(0, 150, DESIGN_BOUNDS).offset, Point { x: 0, y: 0 });
+        assert_eq!(
+            camera(0, -100, DESIGN_BOUNDS).offset,
+            Point { x: 0, y: 250 }
+        );
+        assert_eq!(camera(1, 250, DESIGN_BOUNDS).viewport.y(), 300);
+        assert_eq!(camera(1, 250, DESIGN_BOUNDS).offset, Point { x: 0, y: 200 });
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::Result;
+use futures::channel::mpsc::UnboundedReceiver;
+
+use crate::{browser::Screen, engine};
+
+/// A button clicked on one of the game's panels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiEvent {
+    NewGame,
+    Screenshot,
+    ShareClip,
+    ContinueRun,
+    RetrySeed,
+    ToggleSound,
+    NextCharacter,
+}
+
+impl UiEvent {
+    fn id(self) -> &'static str {
+        match self {
+            UiEvent::NewGame => "new_game",
+            UiEvent::Screenshot => "screenshot",
+            UiEvent::ShareClip => "share_clip",
+            UiEvent::ContinueRun => "continue_run",
+            UiEvent::RetrySeed => "retry_seed",
+            UiEvent::ToggleSound => "toggle_sound",
+            UiEvent::NextCharacter => "next_character",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            UiEvent::NewGame => "New Game",
+            UiEvent::Screenshot => "Screenshot",
+            UiEvent::ShareClip => "Share clip",
+            UiEvent::ContinueRun => "Continue",
+            UiEvent::RetrySeed => "Retry same seed",
+            UiEvent::ToggleSound => "Sound",
+            UiEvent::NextCharacter => "Character",
+        }
+    }
+}
+
+/// What the game over panel offers.
+pub struct GameOverPanel {
+    /// The run's replay, to copy and send to someone.
+    pub replay_code: Option<String>,
+    /// Whether there is a clip of the rewind to share.
+    pub share_clip: bool,
+    pub muted: bool,
+    /// The name of the skin the boy is wearing.
+    pub skin: &'static str,
+}
+
+impl GameOverPanel {
+    fn buttons(&self) -> Vec<UiEvent> {
+        let mut buttons = vec![
+            UiEvent::RetrySeed,
+            UiEvent::NewGame,
+            UiEvent::ToggleSound,
+            UiEvent::NextCharacter,
+            UiEvent::Screenshot,
+        ];
+        if self.share_clip {
+            buttons.push(UiEvent::ShareClip);
+        }
+        buttons
+    }
+
+    /// What `button` says on this panel, with the toggles showing how they
+    /// are set.
+    fn label(&self, button: UiEvent) -> String {
+        match button {
+            UiEvent::NewGame => "New random run".to_string(),
+            UiEvent::ToggleSound if self.muted => "Sound: Off".to_string(),
+            UiEvent::ToggleSound => "Sound: On".to_string(),
+            UiEvent::NextCharacter => format!("Character: {}", self.skin),
+            button => button.label().to_string(),
+        }
+    }
+
+    fn html(&self) -> String {
+        let buttons = self
+            .buttons()
+            .into_iter()
+            .map(|button| labelled_button_html(button, &self.label(button)))
+            .collect::<Vec<_>>()
+            .join("");
+        let replay_code = self
+            .replay_code
+            .as_ref()
+            .map(|code| format!("<input id='replay_code' readonly value='{}'>", code))
+            .unwrap_or_default();
+        format!("<div>{}{}</div>", buttons, replay_code)
+    }
+}
+
+/// Offers to carry on with a run the page was reloaded in the middle of.
+pub struct ContinuePanel {
+    pub distance: u32,
+}
+
+impl ContinuePanel {
+    fn buttons(&self) -> Vec<UiEvent> {
+        vec![UiEvent::ContinueRun, UiEvent::NewGame]
+    }
+
+    fn html(&self) -> String {
+        let buttons = self
+            .buttons()
+            .into_iter()
+            .map(button_html)
+            .collect::<Vec<_>>()
+            .join("");
+        format!(
+            "<div><p>Continue run? You had got to {}</p>{}</div>",
+            self.distance, buttons
+        )
+    }
+}
+
+fn button_html(button: UiEvent) -> String {
+    labelled_button_html(button, button.label())
+}
+
+fn labelled_button_html(button: UiEvent, label: &str) -> String {
+    format!("<button id='{}'>{}</button>", button.id(), label)
+}
+
+/// Shows the game's panels for the state machine, and hands back what the
+/// player clicks, so the states never build HTML or touch the DOM
+/// themselves.
+pub trait UiController {
+    fn show_game_over(&mut self, panel: &GameOverPanel) -> Result<()>;
+    fn show_continue(&mut self, panel: &ContinuePanel) -> Result<()>;
+    fn hide(&mut self);
+    /// The next click since the last poll, if any.
+    fn poll(&mut self) -> Option<UiEvent>;
+    /// Lays the panels out again over a canvas shown `scale` times its size.
+    fn reflow(&mut self, scale: f64);
+}
+
+/// Panels drawn as HTML over the canvas.
+pub struct DomUi {
+    screen: Screen,
+    clicks: Vec<(UiEvent, UnboundedReceiver<()>)>,
+}
+
+impl DomUi {
+    pub fn new(screen: Screen) -> Self {
+        DomUi {
+            screen,
+            clicks: vec![],
+        }
+    }
+
+    fn show(&mut self, html: &str, buttons: Vec<UiEvent>) -> Result<()> {
+        self.screen.draw_ui(html)?;
+        self.clicks = buttons
+            .into_iter()
+            .map(|button| {
+                self.screen
+                    .find(button.id())
+                    .map(engine::add_click_handler)
+                    .map(|click| (button, click))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+impl UiController for DomUi {
+    fn show_game_over(&mut self, panel: &GameOverPanel) -> Result<()> {
+        self.show(&panel.html(), panel.buttons())
+    }
+
+    fn show_continue(&mut self, panel: &ContinuePanel) -> Result<()> {
+        self.show(&panel.html(), panel.buttons())
+    }
+
+    fn reflow(&mut self, scale: f64) {
+        if let Err(err) = self.screen.scale_ui(scale) {
+            log::error!("Could not lay the UI out again {:#?}", err);
+        }
+    }
+
+    fn hide(&mut self) {
+        // Dropping the receivers lets their buttons' listeners go, so new
+        // games don't pile them up.
+        self.clicks.clear();
+        engine::release_click_handlers();
+        if let Err(err) = self.screen.hide_ui() {
+            log::error!("Error hiding the browser {:#?}", err);
+        }
+    }
+
+    fn poll(&mut self) -> Option<UiEvent> {
+        self.clicks
+            .iter_mut()
+            .find_map(|(button, click)| matches!(click.try_next(), Ok(Some(()))).then_some(*button))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_share_button_only_shows_with_a_clip() {
+        let mut panel = GameOverPanel {
+            replay_code: None,
+            share_clip: false,
+            muted: true,
+            skin: "Gold",
+        };
+        assert!(!panel.html().contains("share_clip"));
+        assert!(!panel.html().contains("replay_code"));
+        panel.share_clip = true;
+        panel.replay_code = Some("abc".to_string());
+        assert!(panel
+            .html()
+            .contains("<button id='share_clip'>Share clip</button>"));
+        assert!(panel.html().contains("value='abc'"));
+        assert!(panel
+            .html()
+            .contains("<button id='toggle_sound'>Sound: Off</button>"));
+        assert!(panel
+            .html()
+            .contains("<button id='next_character'>Character: Gold</button>"));
+        assert!(panel
+            .html()
+            .contains("<button id='new_game'>New random run</button>"));
+    }
+
+    #[test]
+    fn the_continue_panel_says_how_far_the_run_got() {
+        let html = ContinuePanel { distance: 1234 }.html();
+        assert!(html.contains("got to 1234"));
+        assert!(html.contains("<button id='continue_run'>Continue</button>"));
+        assert!(html.contains("<button id='new_game'>New Game</button>"));
+    }
+}
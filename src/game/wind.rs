@@ -0,0 +1,106 @@
+use crate::engine::{Rect, Renderer, WorldBounds};
+
+/// Frames from the start of one gust to the start of the next. Each gust
+/// blows at the end of its interval, so a run starts calm.
+const GUST_INTERVAL: u32 = 900;
+/// How long the streaks show before a gust starts blowing.
+const WARNING_FRAMES: u32 = 120;
+const GUST_FRAMES: u32 = 150;
+/// Speed across the screen a gust adds each frame the boy is in the air.
+const GUST_FORCE: f32 = 0.04;
+/// A new streak blows across every this many frames.
+const STREAK_EVERY: u32 = 4;
+const STREAK_SPEED: i16 = 24;
+const STREAK_LENGTH: i16 = 48;
+const STREAK_COLOR: &str = "rgb(255 255 255)";
+const STREAK_ALPHA: f64 = 0.6;
+
+/// Gusts of wind that blow now and then, pushing the boy along or holding
+/// him back while he is in the air. Streaks blow across the screen the
+/// gust's way for a while before it starts, so it can be seen coming.
+#[derive(Default)]
+pub struct Wind {
+    frame: u32,
+    /// Where each streak is, across and down the screen.
+    streaks: Vec<(i16, i16)>,
+}
+
+impl Wind {
+    /// Streaks blow across the whole of a world `bounds` big.
+    pub fn update(&mut self, bounds: WorldBounds) {
+        self.frame += 1;
+        let direction = self.direction();
+        self.streaks.iter_mut().for_each(|(x, _)| {
+            *x += direction * STREAK_SPEED;
+        });
+        self.streaks
+            .retain(|(x, _)| *x > -STREAK_LENGTH && *x < bounds.width + STREAK_LENGTH);
+        if self.blowing_or_coming() && self.frame % STREAK_EVERY == 0 {
+            let start = if direction > 0 {
+                -STREAK_LENGTH
+            } else {
+                bounds.width
+            };
+            // Spread down the screen without needing a random number.
+            let y = ((self.frame / STREAK_EVERY) * 97 % bounds.height as u32) as i16;
+            self.streaks.push((start, y));
+        }
+    }
+
+    /// The push across the screen this frame: forwards in a tailwind, and
+    /// backwards in a headwind.
+    pub fn force(&self) -> f32 {
+        if self.frame % GUST_INTERVAL >= GUST_INTERVAL - GUST_FRAMES {
+            GUST_FORCE * f32::from(self.direction())
+        } else {
+            0.0
+        }
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        self.streaks.iter().for_each(|&(x, y)| {
+            renderer.fill_rect(
+                &Rect::new_from_x_y(x, y, STREAK_LENGTH, 2),
+                STREAK_COLOR,
+                STREAK_ALPHA,
+            );
+        });
+    }
+
+    fn blowing_or_coming(&self) -> bool {
+        self.frame % GUST_INTERVAL >= GUST_INTERVAL - GUST_FRAMES - WARNING_FRAMES
+    }
+
+    /// Gusts take turns being tailwinds and headwinds, starting with a
+    /// tailwind. Streaks blow right in a tailwind, the way the boy runs.
+    fn direction(&self) -> i16 {
+        if (self.frame / GUST_INTERVAL) % 2 == 0 {
+            1
+        } else {
+            -1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::DESIGN_BOUNDS, *};
+
+    fn after(frames: u32) -> Wind {
+        let mut wind = Wind::default();
+        (0..frames).for_each(|_| wind.update(DESIGN_BOUNDS));
+        wind
+    }
+
+    #[test]
+    fn gusts_are_signposted_before_they_blow_and_take_turns() {
+        let gust = GUST_INTERVAL - GUST_FRAMES;
+        assert!(after(gust - WARNING_FRAMES - 1).streaks.is_empty());
+        let warning = after(gust - WARNING_FRAMES / 2);
+        assert_eq!(warning.force(), 0.0);
+        assert!(!warning.streaks.is_empty());
+        assert_eq!(after(gust).force(), GUST_FORCE);
+        assert_eq!(after(GUST_INTERVAL).force(), 0.0);
+        assert_eq!(after(GUST_INTERVAL + gust).force(), -GUST_FORCE);
+    }
+}
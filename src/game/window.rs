@@ -0,0 +1,198 @@
+use super::Obstacle;
+use crate::segments::ObstacleData;
+
+/// How far past the right edge of the screen obstacles are kept moving,
+/// so nothing is brought in already on screen.
+const WINDOW_MARGIN: i16 = 200;
+
+/// The obstacles of the course, of which only the ones in or near the
+/// camera are moved each frame. The rest wait, sorted from left to right,
+/// beyond the window, and all owe the same scroll, which is paid as each one
+/// comes into the window. Everything that checks or draws obstacles only
+/// needs the ones in the window.
+#[derive(Default)]
+pub struct ObstacleWindow {
+    obstacles: Vec<Box<dyn Obstacle>>,
+    /// How many obstacles, from the front, are in the window.
+    active: usize,
+    /// How far the obstacles beyond the window are yet to be moved.
+    owed: i16,
+}
+
+impl ObstacleWindow {
+    /// Puts `obstacles` on a screen `width` wide.
+    pub fn new(obstacles: Vec<Box<dyn Obstacle>>, width: i16) -> Self {
+        let mut window = ObstacleWindow::default();
+        window.extend(obstacles, width);
+        window
+    }
+
+    /// Keeps every one of `obstacles` in the window, in the order given, for
+    /// the editor, which picks them by where they are in its segment.
+    #[cfg(feature = "editor")]
+    pub fn showing_all(obstacles: Vec<Box<dyn Obstacle>>) -> Self {
+        ObstacleWindow {
+            active: obstacles.len(),
+            obstacles,
+            owed: 0,
+        }
+    }
+
+    /// The obstacles in or near the camera.
+    pub fn active(&self) -> &[Box<dyn Obstacle>] {
+        &self.obstacles[..self.active]
+    }
+
+    pub fn len(&self) -> usize {
+        self.obstacles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.obstacles.is_empty()
+    }
+
+    /// Adds the obstacles of a new segment. Those already waiting are moved
+    /// to where they should be first, so all that wait owe the same scroll.
+    pub fn extend(&mut self, obstacles: Vec<Box<dyn Obstacle>>, width: i16) {
+        self.settle();
+        self.obstacles.extend(obstacles);
+        self.obstacles[self.active..].sort_by_key(|obstacle| obstacle.bounding_box().x());
+        self.open(width);
+    }
+
+    /// Moves the course `x` across a screen `width` wide.
+    pub fn scroll(&mut self, x: i16, width: i16) {
+        self.obstacles[..self.active]
+            .iter_mut()
+            .for_each(|obstacle| obstacle.move_horizontally(x));
+        if self.active < self.obstacles.len() {
+            self.owed += x;
+        }
+        self.open(width);
+    }
+
+    /// Takes out the obstacles that have gone off the left of the screen.
+    pub fn cull(&mut self) -> Vec<Box<dyn Obstacle>> {
+        if self.active().iter().all(|obstacle| obstacle.right() > 0) {
+            return vec![];
+        }
+        let waiting = self.obstacles.split_off(self.active);
+        let (culled, kept): (Vec<_>, Vec<_>) = self
+            .obstacles
+            .drain(..)
+            .partition(|obstacle| obstacle.right() <= 0);
+        self.active = kept.len();
+        self.obstacles = kept;
+        self.obstacles.extend(waiting);
+        culled
+    }
+
+    /// Brings back obstacles taken out by `cull`, as when rewinding.
+    pub fn restore(&mut self, mut obstacles: Vec<Box<dyn Obstacle>>) {
+        self.active += obstacles.len();
+        obstacles.append(&mut self.obstacles);
+        self.obstacles = obstacles;
+    }
+
+    /// Whether every obstacle is to the left of `x`.
+    pub fn passed(&self, x: i16) -> bool {
+        self.active == self.obstacles.len()
+            && self.active().iter().all(|obstacle| obstacle.right() < x)
+    }
+
+    /// What to build every obstacle again from, where it is now.
+    pub fn data(&self) -> Vec<ObstacleData> {
+        self.obstacles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, obstacle)| {
+                let owed = if index < self.active { 0 } else { self.owed };
+                obstacle.data().map(|data| ObstacleData {
+                    x: data.x + owed,
+                    ..data
+                })
+            })
+            .collect()
+    }
+
+    /// Brings obstacles into the window as they come within reach of it.
+    fn open(&mut self, width: i16) {
+        while let Some(obstacle) = self.obstacles.get_mut(self.active) {
+            if obstacle.bounding_box().x() + self.owed > width + WINDOW_MARGIN {
+                break;
+            }
+            obstacle.move_horizontally(self.owed);
+            self.active += 1;
+        }
+        if self.active == self.obstacles.len() {
+            self.owed = 0;
+        }
+    }
+
+    /// Moves every obstacle waiting beyond the window to where it should be.
+    fn settle(&mut self) {
+        let owed = std::mem::take(&mut self.owed);
+        self.obstacles[self.active..]
+            .iter_mut()
+            .for_each(|obstacle| obstacle.move_horizontally(owed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::{Rect, Renderer},
+        game::RedHatBoy,
+    };
+
+    struct Block(Rect);
+
+    impl Obstacle for Block {
+        fn check_intersection(&self, _boy: &mut RedHatBoy) {}
+        fn draw(&self, _renderer: &Renderer) {}
+        fn move_horizontally(&mut self, x: i16) {
+            self.0.set_x(self.0.x() + x);
+        }
+        fn right(&self) -> i16 {
+            self.0.right()
+        }
+        fn name(&self) -> &str {
+            "block"
+        }
+        fn bounding_box(&self) -> Rect {
+            self.0
+        }
+    }
+
+    fn blocks(xs: &[i16]) -> Vec<Box<dyn Obstacle>> {
+        xs.iter()
+            .map(|&x| Box::new(Block(Rect::new_from_x_y(x, 0, 50, 50))) as Box<dyn Obstacle>)
+            .collect()
+    }
+
+    fn xs(window: &ObstacleWindow) -> Vec<i16> {
+        window
+            .active()
+            .iter()
+            .map(|obstacle| obstacle.bounding_box().x())
+            .collect()
+    }
+
+    #[test]
+    fn only_obstacles_near_the_screen_are_moved() {
+        let mut window = ObstacleWindow::new(blocks(&[1500, 300, 900]), 600);
+        assert_eq!(xs(&window), [300]);
+        window.scroll(-200, 600);
+        assert_eq!(xs(&window), [100, 700]);
+        window.extend(blocks(&[1000]), 600);
+        window.scroll(-200, 600);
+        assert_eq!(xs(&window), [-100, 500, 800]);
+        assert_eq!(window.cull().len(), 1);
+        window.scroll(-500, 600);
+        assert_eq!(xs(&window), [0, 300, 600]);
+        assert!(!window.passed(600));
+        window.scroll(-300, 600);
+        assert!(window.passed(400));
+    }
+}
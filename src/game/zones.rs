@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use super::RedHatBoy;
+use crate::engine::{Point, Rect, Renderer};
+
+const WATER_TINT: &str = "rgb(40 120 220)";
+const WIND_TINT: &str = "rgb(255 255 255)";
+
+/// How a zone changes the way the boy moves while he is in it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Modifier {
+    /// Multiplies gravity, so below 1 he floats down.
+    pub gravity_scale: f32,
+    /// Multiplies how fast he leaves the ground in a jump.
+    pub jump_scale: f32,
+    /// The share of his running speed he loses. Below 0 he is pushed along.
+    pub drag: f32,
+}
+
+impl Default for Modifier {
+    fn default() -> Self {
+        Modifier {
+            gravity_scale: 1.0,
+            jump_scale: 1.0,
+            drag: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneKind {
+    /// Slow and floaty, with a weaker jump that stays up longer.
+    Water,
+    /// A tailwind that carries the boy along faster.
+    Wind,
+}
+
+impl ZoneKind {
+    pub fn modifier(self) -> Modifier {
+        match self {
+            ZoneKind::Water => Modifier {
+                gravity_scale: 0.4,
+                jump_scale: 0.6,
+                drag: 0.3,
+            },
+            ZoneKind::Wind => Modifier {
+                drag: -0.3,
+                ..Modifier::default()
+            },
+        }
+    }
+
+    fn tint(self) -> (&'static str, f64) {
+        match self {
+            ZoneKind::Water => (WATER_TINT, 0.35),
+            ZoneKind::Wind => (WIND_TINT, 0.15),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Zone {
+    bounds: Rect,
+    kind: ZoneKind,
+}
+
+/// Stretches of the course where the boy moves differently, like water or
+/// wind. They scroll with the obstacles.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Zones {
+    zones: Vec<Zone>,
+}
+
+impl Zones {
+    pub fn spawn(&mut self, bounds: Rect, kind: ZoneKind) {
+        self.zones.push(Zone { bounds, kind });
+    }
+
+    pub fn scroll(&mut self, x: i16) {
+        self.zones.iter_mut().for_each(|zone| {
+            zone.bounds.set_x(zone.bounds.x() + x);
+        });
+    }
+
+    /// Scrolls the zones by `x`, and has the boy move the way the zone he is
+    /// in says to from now on.
+    pub fn update(&mut self, x: i16, boy: &mut RedHatBoy) {
+        self.zones.retain(|zone| zone.bounds.right() > 0);
+        self.scroll(x);
+        boy.set_modifier(self.modifier_at(boy.bounding_box().center()));
+    }
+
+    fn modifier_at(&self, point: Point) -> Modifier {
+        self.zones
+            .iter()
+            .find(|zone| zone.bounds.contains_point(point))
+            .map_or_else(Modifier::default, |zone| zone.kind.modifier())
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        self.zones.iter().for_each(|zone| {
+            let (color, alpha) = zone.kind.tint();
+            renderer.fill_rect(&zone.bounds, color, alpha);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_boy_moves_the_way_the_zone_he_is_in_says() {
+        let mut zones = Zones::default();
+        zones.spawn(Rect::new_from_x_y(300, 400, 200, 200), ZoneKind::Water);
+        let boy = Point { x: 250, y: 500 };
+        assert_eq!(zones.modifier_at(boy), Modifier::default());
+        zones.scroll(-100);
+        assert_eq!(zones.modifier_at(boy), ZoneKind::Water.modifier());
+        assert_eq!(
+            zones.modifier_at(Point { x: 250, y: 300 }),
+            Modifier::default()
+        );
+    }
+}
@@ -3,13 +3,16 @@ mod browser;
 mod engine;
 mod game;
 mod miya;
+mod obstacles;
+mod offline;
 mod segments;
 mod sound;
 mod utils;
-use engine::GameLoop;
+use engine::{GameLoop, GameLoopHandle};
 use game::WalkTheDog;
 use utils::set_logs;
 use wasm_bindgen::prelude::*;
+use web_sys::js_sys::Function;
 
 #[wasm_bindgen]
 extern "C" {
@@ -19,13 +22,98 @@ extern "C" {
 #[wasm_bindgen(start)]
 pub fn main_js() -> Result<(), JsValue> {
     set_logs();
+    Ok(())
+}
 
+/// Starts the game on the game's own page, which has a `#canvas` and a `#ui`
+/// and takes its options from the URL.
+#[wasm_bindgen]
+pub fn start_page() {
+    // The offline cache would serve stale files to the hot reloader.
+    #[cfg(not(feature = "dev"))]
     browser::spawn_local(async move {
-        let game = WalkTheDog::new();
-        GameLoop::start(game)
+        if let Err(err) = offline::enable().await {
+            log::warn!("Offline support is unavailable {:#?}", err);
+        }
+    });
+
+    browser::spawn_local(async move {
+        let config = browser::StartupConfig::from_url().unwrap_or_else(|err| {
+            log::error!("Could not read startup config {:#?}", err);
+            browser::StartupConfig::default()
+        });
+        browser::set_asset_base(config.asset_base.clone());
+        let debug = config.debug.clone();
+        let high_contrast = config.high_contrast;
+        let dirty_rects = config.dirty_rects;
+        let game = WalkTheDog::new(config);
+        GameLoop::start(game, debug.as_deref(), high_contrast, dirty_rects)
             .await
             .expect("Could not start game loop");
     });
+}
 
-    Ok(())
+/// A game running on a canvas picked by the page embedding it.
+#[wasm_bindgen]
+pub struct MountedGame {
+    handle: GameLoopHandle,
+    callbacks: browser::Callbacks,
+}
+
+#[wasm_bindgen]
+impl MountedGame {
+    /// Calls `callback` on the game's `event`: `ready`, `score_changed` with
+    /// the distance run, or `game_over` with the final distance.
+    pub fn on(&self, event: &str, callback: Function) -> Result<(), JsValue> {
+        self.callbacks
+            .register(event, callback)
+            .map_err(|err| JsValue::from_str(&format!("{:#?}", err)))
+    }
+
+    /// Stops the game where it is, to carry on later.
+    pub fn detach(&self) {
+        self.handle.detach();
+    }
+
+    /// Takes the game off the page for good.
+    pub fn stop(self) {
+        self.handle.stop();
+    }
+
+    /// Carries on with the game on the canvas matching `selector`.
+    pub fn reattach(&self, selector: &str) -> Result<(), JsValue> {
+        browser::find_canvas(selector)
+            .and_then(|canvas| self.handle.reattach(canvas))
+            .map_err(|err| JsValue::from_str(&format!("{:#?}", err)))
+    }
+}
+
+/// Starts a game on the canvas with the id `canvas_id`, for pages that embed
+/// the game. `config` takes the same options as the game's URL, like `mode`
+/// and `seed`, along with `asset_base` for where the game's files are and
+/// functions for the game's events, like `on_game_over`, as [`MountedGame::on`]
+/// takes them. The game takes keys only while its canvas has the focus.
+#[wasm_bindgen]
+pub async fn start_game(canvas_id: String, config: JsValue) -> Result<MountedGame, JsValue> {
+    let started = async {
+        let canvas = browser::canvas_by_id(&canvas_id)?;
+        let config = browser::StartupConfig::from_js(&config);
+        browser::set_asset_base(config.asset_base.clone());
+        let debug = config.debug.clone();
+        let high_contrast = config.high_contrast;
+        let dirty_rects = config.dirty_rects;
+        let callbacks = config.callbacks.clone();
+        let handle = GameLoop::attach(
+            canvas,
+            WalkTheDog::new(config),
+            debug.as_deref(),
+            high_contrast,
+            dirty_rects,
+        )
+        .await?;
+        Ok::<_, anyhow::Error>(MountedGame { handle, callbacks })
+    };
+    started
+        .await
+        .map_err(|err| JsValue::from_str(&format!("{:#?}", err)))
 }
@@ -232,7 +232,7 @@ impl Game for WalkTheDog {
         }
     }
     fn draw(&self, renderer: &Renderer) {
-        renderer.clear(&Rect::new_from_x_y(0, 0, 600, 600));
+        renderer.clear(&renderer.bounds().rect());
         if let WalkTheDog::Loaded(rhb) = self {
             rhb.draw(renderer);
         }
@@ -0,0 +1,241 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::{
+    browser,
+    engine::{Atlas, Image, Point, Rect, Renderer, SheetRect, SpriteSheet},
+    game::{assets, Barrier, Beam, Obstacle, Pit, Platform, Ramp, RedHatBoy, Severity, Spring},
+    segments::ObstacleData,
+};
+
+/// Which obstacle type a prototype is built as.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Behavior {
+    Barrier,
+    Platform,
+    UpRamp,
+    DownRamp,
+    Spring,
+    Pit,
+    Beam,
+}
+
+/// One kind of obstacle, as described in `obstacles.json`. Only the fields
+/// its behavior uses need to be given.
+#[derive(Clone, Deserialize)]
+struct ObstaclePrototype {
+    behavior: Behavior,
+    /// Atlas frames for barriers, or tiles drawn left to right for platforms.
+    #[serde(default)]
+    sprites: Vec<String>,
+    /// The parts of a platform that can be landed on, from its top left.
+    #[serde(default)]
+    hitboxes: Vec<SheetRect>,
+    /// The size of ramps, springs, pits and beams.
+    #[serde(default)]
+    width: i16,
+    #[serde(default)]
+    height: i16,
+    /// How big a barrier is drawn.
+    #[serde(default = "full_size")]
+    scale: f32,
+    #[serde(default = "knock_out")]
+    severity: Severity,
+    /// Scored for only just clearing it, before the combo multiplies them.
+    #[serde(default)]
+    points: u32,
+}
+
+fn full_size() -> f32 {
+    1.0
+}
+
+fn knock_out() -> Severity {
+    Severity::KnockOut
+}
+
+/// Every obstacle segments can place, keyed by the id they refer to it by.
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct ObstacleRegistry {
+    prototypes: HashMap<String, ObstaclePrototype>,
+}
+
+impl ObstacleRegistry {
+    pub async fn load() -> Result<Self> {
+        let json = browser::fetch_json(assets::OBSTACLES).await?;
+        serde_wasm_bindgen::from_value(json).map_err(|err| {
+            anyhow!(
+                "Could not convert {} into obstacles {:#?}",
+                assets::OBSTACLES,
+                err
+            )
+        })
+    }
+
+    /// How wide obstacle `id` is, if it is a pit the boy has to jump across.
+    pub fn gap_width(&self, id: &str) -> Option<i16> {
+        self.prototypes
+            .get(id)
+            .filter(|prototype| prototype.behavior == Behavior::Pit)
+            .map(|prototype| prototype.width)
+    }
+
+    /// The images barriers are drawn from, which have to be packed into the
+    /// atlas.
+    pub fn atlas_sprites(&self) -> Vec<&str> {
+        let mut sprites: Vec<&str> = self
+            .prototypes
+            .values()
+            .filter(|prototype| prototype.behavior == Behavior::Barrier)
+            .filter_map(|prototype| prototype.sprites.first())
+            .map(String::as_str)
+            .collect();
+        sprites.sort_unstable();
+        sprites.dedup();
+        sprites
+    }
+
+    /// Fails if a barrier has no sprite, or one that isn't in `atlas`, since
+    /// segments would otherwise be built without it.
+    pub fn check(&self, atlas: &Atlas) -> Result<()> {
+        let mut missing: Vec<&str> = self
+            .prototypes
+            .iter()
+            .filter(|(_, prototype)| {
+                prototype.behavior == Behavior::Barrier
+                    && prototype
+                        .sprites
+                        .first()
+                        .is_none_or(|sprite| atlas.frame(sprite).is_none())
+            })
+            .map(|(id, _)| id.as_str())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        missing.sort_unstable();
+        Err(anyhow!(
+            "No sprite in the atlas for obstacles {}",
+            missing.join(", ")
+        ))
+    }
+
+    /// Whether obstacle `id` knocks the boy out unless he jumps over it.
+    pub fn is_hurdle(&self, id: &str) -> bool {
+        self.prototypes.get(id).is_some_and(|prototype| {
+            prototype.behavior == Behavior::Barrier && prototype.severity == Severity::KnockOut
+        })
+    }
+
+    /// Builds obstacle `id` with its top left at `position`. Unknown ids and
+    /// barriers whose frame isn't in the atlas build nothing, though `check`
+    /// rules the latter out when the obstacles are loaded.
+    pub fn build(
+        &self,
+        id: &str,
+        position: Point,
+        atlas: &Rc<Atlas>,
+        sheet: &Rc<SpriteSheet>,
+    ) -> Option<Box<dyn Obstacle>> {
+        let Some(prototype) = self.prototypes.get(id) else {
+            log::warn!("No obstacle called {}", id);
+            return None;
+        };
+        let size = Rect::new(position, prototype.width, prototype.height);
+        let obstacle: Box<dyn Obstacle> = match prototype.behavior {
+            Behavior::Barrier => {
+                let image = Image::from_atlas(atlas.clone(), prototype.sprites.first()?, position)?;
+                Box::new(Barrier::new(
+                    image.scaled(prototype.scale),
+                    prototype.severity,
+                ))
+            }
+            Behavior::Platform => {
+                let sprites: Vec<&str> = prototype.sprites.iter().map(String::as_str).collect();
+                let hitboxes: Vec<Rect> = prototype
+                    .hitboxes
+                    .iter()
+                    .map(|&hitbox| hitbox.into())
+                    .collect();
+                Box::new(Platform::new(sheet.clone(), position, &sprites, &hitboxes))
+            }
+            Behavior::UpRamp | Behavior::DownRamp => Box::new(Ramp::new(
+                sheet.clone(),
+                position,
+                prototype.width,
+                prototype.height,
+                prototype.behavior == Behavior::UpRamp,
+            )),
+            Behavior::Spring => Box::new(Spring::new(sheet.clone(), size)),
+            Behavior::Pit => Box::new(Pit::new(sheet.clone(), size)),
+            Behavior::Beam => Box::new(Beam::new(sheet.clone(), size)),
+        };
+        Some(Box::new(Prototyped {
+            id: id.to_string(),
+            position,
+            points: prototype.points,
+            obstacle,
+        }))
+    }
+}
+
+/// An obstacle that remembers the prototype it was built from, so knockouts
+/// and near misses can be put down to it.
+struct Prototyped {
+    id: String,
+    /// Where it was built, moved along with it, so it can be built again.
+    position: Point,
+    points: u32,
+    obstacle: Box<dyn Obstacle>,
+}
+
+impl Obstacle for Prototyped {
+    fn check_intersection(&self, boy: &mut RedHatBoy) {
+        self.obstacle.check_intersection(boy);
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        self.obstacle.draw(renderer);
+    }
+
+    fn move_horizontally(&mut self, x: i16) {
+        self.position.x += x;
+        self.obstacle.move_horizontally(x);
+    }
+
+    fn right(&self) -> i16 {
+        self.obstacle.right()
+    }
+
+    fn name(&self) -> &str {
+        &self.id
+    }
+
+    fn bounding_box(&self) -> Rect {
+        self.obstacle.bounding_box()
+    }
+
+    fn surface_at(&self, x: i16) -> Option<i16> {
+        self.obstacle.surface_at(x)
+    }
+
+    fn walkable(&self) -> bool {
+        self.obstacle.walkable()
+    }
+
+    fn near_miss_points(&self) -> u32 {
+        self.points
+    }
+
+    fn data(&self) -> Option<ObstacleData> {
+        Some(ObstacleData::new(
+            &self.id,
+            self.position.x,
+            self.position.y,
+        ))
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{browser, game::assets};
+
+/// The worker names its cache after the version it is registered with, so a
+/// new build installs a new worker with a cache of its own. The version is
+/// a hash of the code and assets, made by the build script.
+const SERVICE_WORKER: &str = concat!("sw.js?version=", env!("BUILD_HASH"));
+
+/// What the service worker caches. Any change to the code or the assets
+/// gives the cache a new name, so stale assets are dropped on the next visit.
+#[derive(Debug, Serialize)]
+pub struct CacheManifest {
+    version: &'static str,
+    assets: Vec<String>,
+}
+
+impl CacheManifest {
+    pub fn new() -> Self {
+        CacheManifest {
+            version: env!("BUILD_HASH"),
+            assets: assets::all(),
+        }
+    }
+}
+
+/// The cache manifest as JSON, for build scripts that want to write it out
+/// next to the service worker.
+#[wasm_bindgen]
+pub fn cache_manifest() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&CacheManifest::new()).map_err(JsValue::from)
+}
+
+/// Registers the service worker and hands it the asset list, along with the
+/// page, scripts and wasm this load used, so the game can be played offline
+/// after the first load. Dev builds don't, so edited files are always
+/// fetched fresh.
+#[cfg_attr(feature = "dev", allow(dead_code))]
+pub async fn enable() -> Result<()> {
+    let worker = browser::register_service_worker(SERVICE_WORKER).await?;
+    let mut manifest = CacheManifest::new();
+    manifest.assets.extend(browser::app_shell()?);
+    let manifest = serde_wasm_bindgen::to_value(&manifest)
+        .map_err(|err| anyhow!("Could not serialize cache manifest {:#?}", err))?;
+    worker
+        .post_message(&manifest)
+        .map_err(|err| anyhow!("Could not send cache manifest {:#?}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_lists_each_asset_once() {
+        let mut assets = CacheManifest::new().assets;
+        let count = assets.len();
+        assets.sort_unstable();
+        assets.dedup();
+        assert_eq!(assets.len(), count);
+    }
+}
@@ -1,78 +1,676 @@
-use std::rc::Rc;
+use std::{fmt, rc::Rc};
 
-use web_sys::HtmlImageElement;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    engine::{Image, Point, Rect, SpriteSheet},
-    game::{Barrier, Obstacle, Platform},
+    engine::{Atlas, Point, Rect, SpriteSheet},
+    game::{Modifier, Obstacle, Reach, ZoneKind, HEIGHT},
+    obstacles::ObstacleRegistry,
 };
 
 const LOW_PLATFORM: i16 = 420;
 const HIGH_PLATFORM: i16 = 375;
 const FIRST_PLATFORM: i16 = 370;
 const STONE_ON_GROUND: i16 = 546;
+const PEBBLE_ON_GROUND: i16 = 600 - 27;
+const PEBBLE_SPACING: i16 = 220;
+/// Low enough to hit a running boy, but high enough to crouch under.
+const CEILING: i16 = 420;
+/// Beams hang from the top of the screen, down to just above a sliding boy.
+const BEAM_TOP: i16 = 0;
+const BEAM_SPACING: i16 = 360;
+const RAMP_WIDTH: i16 = 256;
+const RAMP_HEIGHT: i16 = 96;
+const RAMP_TOP: i16 = 600 - RAMP_HEIGHT;
+const SPRING_HEIGHT: i16 = 24;
+const SPRING_ON_GROUND: i16 = 600 - SPRING_HEIGHT;
+/// Only reachable by bouncing off a spring.
+const SKY_PLATFORM: i16 = 220;
+/// How much of the pit's opening shows at the bottom of the screen.
+const PIT_DEPTH: i16 = 24;
+const PIT_TOP: i16 = 600 - PIT_DEPTH;
+/// How much higher each platform of a tower is than the one before, a
+/// little less than the boy can jump.
+const TOWER_STEP: i16 = 250;
+/// The space between the end of one tower platform and the start of the
+/// next, so the boy doesn't hit his head on the next one going up.
+const TOWER_GAP: i16 = 60;
+const PLATFORM_WIDTH: i16 = 384;
+const TOWER_COIN_HEIGHT: i16 = 80;
+const TOWER_COIN_ROW: i16 = 5;
+/// A ledge is two platform tiles wide.
+const LEDGE_WIDTH: i16 = 256;
+/// The space between one ledge of a chain and the next.
+const HOP_GAP: i16 = 120;
+/// Low enough that running into it knocks the boy out.
+const LOW_LEDGE: i16 = 460;
+/// How much higher every other ledge of a chain is.
+const HOP_STEP: i16 = 40;
+const HOP_COIN_HEIGHT: i16 = 60;
+const HOP_COIN_ROW: i16 = 2;
 
-const FLOATING_PLATFORM_SPRITES: [&str; 3] = ["13.png", "14.png", "15.png"];
-const FLOATING_PLATFOPRM_BOUNDING_BOXES: [Rect; 3] = [
-    Rect::new_from_x_y(0, 0, 60, 54),
-    Rect::new_from_x_y(60, 0, 384 - (60 * 2), 93),
-    Rect::new_from_x_y(384 - 60, 0, 60, 54),
-];
+pub const STONE: &str = "Stone.png";
 
-pub fn stone_and_platform(
-    stone: HtmlImageElement,
+/// How many segments the course picks from.
+pub const SEGMENT_COUNT: usize = 12;
+
+/// An obstacle placed in a segment. `kind` is its id in the obstacle
+/// registry, so segments exported before the registry still load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObstacleData {
+    #[serde(rename = "kind")]
+    pub id: String,
+    pub x: i16,
+    pub y: i16,
+}
+
+impl ObstacleData {
+    pub fn new(id: &str, x: i16, y: i16) -> Self {
+        ObstacleData {
+            id: id.to_string(),
+            x,
+            y,
+        }
+    }
+}
+
+/// A row of `count` coins placed in a segment, on top of the row every
+/// segment gets over its first obstacle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinData {
+    pub x: i16,
+    pub y: i16,
+    pub count: i16,
+}
+
+/// A stretch of a segment where the boy moves differently, like water.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneData {
+    pub kind: ZoneKind,
+    pub x: i16,
+    pub y: i16,
+    pub width: i16,
+    pub height: i16,
+}
+
+impl ZoneData {
+    pub fn bounds(&self, offset_x: i16) -> Rect {
+        Rect::new_from_x_y(offset_x + self.x, self.y, self.width, self.height)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SegmentData {
+    pub obstacles: Vec<ObstacleData>,
+    #[serde(default)]
+    pub coins: Vec<CoinData>,
+    #[serde(default)]
+    pub zones: Vec<ZoneData>,
+}
+
+impl SegmentData {
+    /// The segment moved down onto `ground`, since segments are laid out on
+    /// the ground of a world `HEIGHT` high.
+    pub fn on_ground(mut self, ground: i16) -> Self {
+        let shift = ground - HEIGHT;
+        self.obstacles
+            .iter_mut()
+            .for_each(|obstacle| obstacle.y += shift);
+        self.coins.iter_mut().for_each(|coins| coins.y += shift);
+        self.zones.iter_mut().for_each(|zone| zone.y += shift);
+        self
+    }
+}
+
+pub fn build_segment(
+    segment: &SegmentData,
+    registry: &ObstacleRegistry,
+    atlas: Rc<Atlas>,
     sprite_sheet: Rc<SpriteSheet>,
     offset_x: i16,
 ) -> Vec<Box<dyn Obstacle>> {
+    segment
+        .obstacles
+        .iter()
+        .filter_map(|obstacle| {
+            registry.build(
+                &obstacle.id,
+                Point {
+                    x: offset_x + obstacle.x,
+                    y: obstacle.y,
+                },
+                &atlas,
+                &sprite_sheet,
+            )
+        })
+        .collect()
+}
+
+/// Builds a segment's obstacles a few at a time over several frames, so a
+/// big segment doesn't all land on the frame it's needed. They are built
+/// at the start of the course and moved to where the segment goes once it
+/// is placed.
+pub struct SegmentBuilder {
+    index: usize,
+    data: SegmentData,
+    built: Vec<Box<dyn Obstacle>>,
+    next: usize,
+}
+
+impl SegmentBuilder {
+    pub fn new(index: usize, ground: i16) -> Self {
+        SegmentBuilder {
+            index,
+            data: segment(index).on_ground(ground),
+            built: vec![],
+            next: 0,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Builds up to `count` more of the segment's obstacles, and returns
+    /// the ones it built.
+    pub fn step(
+        &mut self,
+        count: usize,
+        registry: &ObstacleRegistry,
+        atlas: &Rc<Atlas>,
+        sprite_sheet: &Rc<SpriteSheet>,
+    ) -> &[Box<dyn Obstacle>] {
+        let start = self.built.len();
+        self.step_with(count, |obstacle| {
+            registry.build(
+                &obstacle.id,
+                Point {
+                    x: obstacle.x,
+                    y: obstacle.y,
+                },
+                atlas,
+                sprite_sheet,
+            )
+        });
+        &self.built[start..]
+    }
+
+    /// Builds whatever of the segment is still to be built.
+    pub fn finish(
+        &mut self,
+        registry: &ObstacleRegistry,
+        atlas: &Rc<Atlas>,
+        sprite_sheet: &Rc<SpriteSheet>,
+    ) {
+        self.step(usize::MAX, registry, atlas, sprite_sheet);
+    }
+
+    fn step_with(
+        &mut self,
+        count: usize,
+        mut build: impl FnMut(&ObstacleData) -> Option<Box<dyn Obstacle>>,
+    ) {
+        let end = self
+            .next
+            .saturating_add(count)
+            .min(self.data.obstacles.len());
+        for obstacle in &self.data.obstacles[self.next..end] {
+            match build(obstacle) {
+                Some(built) => self.built.push(built),
+                None => log::error!("Could not build {} in segment {}", obstacle.id, self.index),
+            }
+        }
+        self.next = end;
+    }
+
+    fn finished(&self) -> bool {
+        self.next == self.data.obstacles.len()
+    }
+
+    /// The segment's data and obstacles, moved `offset_x` along. It has to
+    /// be finished first.
+    pub fn place(self, offset_x: i16) -> (SegmentData, Vec<Box<dyn Obstacle>>) {
+        debug_assert!(self.finished(), "Placed a segment before it was built");
+        let mut obstacles = self.built;
+        obstacles
+            .iter_mut()
+            .for_each(|obstacle| obstacle.move_horizontally(offset_x));
+        (self.data, obstacles)
+    }
+}
+
+/// Segment `index` of the course. Past the last one it is empty.
+pub fn segment(index: usize) -> SegmentData {
+    match index {
+        0 => stone_and_platform(),
+        1 => platform_and_stone(),
+        2 => low_ceiling(),
+        3 => hill(),
+        4 => springboard(),
+        5 => gap(),
+        6 => pebbles(),
+        7 => tower(),
+        8 => pond(),
+        9 => gust(),
+        10 => hops(),
+        11 => beams(),
+        _ => SegmentData::default(),
+    }
+}
+
+/// Something in a segment the boy can't get past.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Problem {
+    Gap { start: i16, end: i16 },
+    Hurdle { x: i16, height: i16 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Gap { start, end } => {
+                write!(f, "the gap from {} to {} is too long to jump", start, end)
+            }
+            Problem::Hurdle { x, height } => {
+                write!(f, "the barrier at {} is too high to jump ({})", x, height)
+            }
+        }
+    }
+}
+
+/// What in `segment` the boy can't get past with a jump, where `reach` is
+/// how far a jump goes in a zone with the modifier it is given.
+pub fn problems(
+    segment: &SegmentData,
+    registry: &ObstacleRegistry,
+    reach: impl Fn(Modifier) -> Reach,
+) -> Vec<Problem> {
+    let hurdles = segment
+        .obstacles
+        .iter()
+        .filter(|obstacle| registry.is_hurdle(&obstacle.id))
+        .map(|obstacle| (obstacle.x, HEIGHT - obstacle.y))
+        .collect();
+    out_of_reach(gaps(segment, registry), hurdles, |x| {
+        reach(modifier_at(segment, x))
+    })
+}
+
+/// How the boy moves at `x` across `segment`, in whichever zone is there.
+fn modifier_at(segment: &SegmentData, x: i16) -> Modifier {
+    segment
+        .zones
+        .iter()
+        .find(|zone| (zone.x..zone.x + zone.width).contains(&x))
+        .map_or_else(Modifier::default, |zone| zone.kind.modifier())
+}
+
+/// The gaps and hurdles a jump of `reach` at where it is taken from
+/// doesn't get past.
+fn out_of_reach(
+    gaps: Vec<(i16, i16)>,
+    hurdles: Vec<(i16, i16)>,
+    reach: impl Fn(i16) -> Reach,
+) -> Vec<Problem> {
+    gaps.into_iter()
+        .filter(|&(start, end)| end - start > reach(start).distance)
+        .map(|(start, end)| Problem::Gap { start, end })
+        .chain(
+            hurdles
+                .into_iter()
+                .filter(|&(x, height)| height > reach(x).height)
+                .map(|(x, height)| Problem::Hurdle { x, height }),
+        )
+        .collect()
+}
+
+/// The stretches of `segment` with no ground to run on, as the x each one
+/// starts and ends at. Pits that touch make one longer gap.
+fn gaps(segment: &SegmentData, registry: &ObstacleRegistry) -> Vec<(i16, i16)> {
+    merge_spans(
+        segment
+            .obstacles
+            .iter()
+            .filter_map(|obstacle| {
+                registry
+                    .gap_width(&obstacle.id)
+                    .map(|width| (obstacle.x, obstacle.x + width))
+            })
+            .collect(),
+    )
+}
+
+fn merge_spans(mut spans: Vec<(i16, i16)>) -> Vec<(i16, i16)> {
+    spans.sort_unstable();
+    let mut merged: Vec<(i16, i16)> = vec![];
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// A stone sunk in a pond, where the boy wades slowly and floats over it.
+fn pond() -> SegmentData {
+    const POND_OFFSET: i16 = 100;
+    const POND_WIDTH: i16 = 700;
+    const POND_DEPTH: i16 = 200;
+    const STONE_IN_POND: i16 = 300;
+    SegmentData {
+        obstacles: vec![ObstacleData::new(
+            "stone",
+            POND_OFFSET + STONE_IN_POND,
+            STONE_ON_GROUND,
+        )],
+        coins: vec![],
+        zones: vec![ZoneData {
+            kind: ZoneKind::Water,
+            x: POND_OFFSET,
+            y: HEIGHT - POND_DEPTH,
+            width: POND_WIDTH,
+            height: POND_DEPTH,
+        }],
+    }
+}
+
+/// A tailwind that carries the boy faster towards a pit.
+fn gust() -> SegmentData {
+    const GUST_OFFSET: i16 = 0;
+    const GUST_WIDTH: i16 = 900;
+    const PIT_OFFSET: i16 = 600;
+    SegmentData {
+        obstacles: vec![ObstacleData::new("pit", PIT_OFFSET, PIT_TOP)],
+        coins: vec![],
+        zones: vec![ZoneData {
+            kind: ZoneKind::Wind,
+            x: GUST_OFFSET,
+            y: 0,
+            width: GUST_WIDTH,
+            height: HEIGHT,
+        }],
+    }
+}
+
+pub fn stone_and_platform() -> SegmentData {
     const INITIAL_STONE_OFFSET: i16 = 150;
-    vec![
-        Box::new(Barrier::new(Image::new(
-            stone,
-            Point {
-                x: offset_x + INITIAL_STONE_OFFSET,
-                y: STONE_ON_GROUND,
-            },
-        ))),
-        Box::new(create_floating_platform(
-            sprite_sheet,
-            Point {
-                x: offset_x + FIRST_PLATFORM,
-                y: LOW_PLATFORM,
-            },
-        )),
-    ]
+    SegmentData {
+        obstacles: vec![
+            ObstacleData::new("stone", INITIAL_STONE_OFFSET, STONE_ON_GROUND),
+            ObstacleData::new("platform", FIRST_PLATFORM, LOW_PLATFORM),
+        ],
+        coins: vec![],
+        zones: vec![],
+    }
 }
 
-pub fn platform_and_stone(
-    stone: HtmlImageElement,
-    sprite_sheet: Rc<SpriteSheet>,
-    offset_x: i16,
-) -> Vec<Box<dyn Obstacle>> {
+fn platform_and_stone() -> SegmentData {
     const STONE_OFFSET: i16 = 370;
     const PLATFORM_OFFSET: i16 = 150;
-    vec![
-        Box::new(create_floating_platform(
-            sprite_sheet,
-            Point {
-                x: offset_x + PLATFORM_OFFSET,
-                y: HIGH_PLATFORM,
-            },
-        )),
-        Box::new(Barrier::new(Image::new(
-            stone,
-            Point {
-                x: offset_x + STONE_OFFSET,
-                y: STONE_ON_GROUND,
-            },
-        ))),
-    ]
+    SegmentData {
+        obstacles: vec![
+            ObstacleData::new("platform", PLATFORM_OFFSET, HIGH_PLATFORM),
+            ObstacleData::new("stone", STONE_OFFSET, STONE_ON_GROUND),
+        ],
+        coins: vec![],
+        zones: vec![],
+    }
 }
 
-fn create_floating_platform(sprite_sheet: Rc<SpriteSheet>, position: Point) -> Platform {
-    Platform::new(
-        sprite_sheet,
-        position,
-        &FLOATING_PLATFORM_SPRITES,
-        &FLOATING_PLATFOPRM_BOUNDING_BOXES,
-    )
+/// A long, low ceiling that is too wide to slide under in one go, so the boy
+/// has to crouch along beneath it.
+fn low_ceiling() -> SegmentData {
+    const CEILING_OFFSET: i16 = 150;
+    SegmentData {
+        obstacles: vec![ObstacleData::new("ceiling", CEILING_OFFSET, CEILING)],
+        coins: vec![],
+        zones: vec![],
+    }
+}
+
+fn hill() -> SegmentData {
+    const HILL_OFFSET: i16 = 150;
+    SegmentData {
+        obstacles: vec![
+            ObstacleData::new("up_ramp", HILL_OFFSET, RAMP_TOP),
+            ObstacleData::new("down_ramp", HILL_OFFSET + RAMP_WIDTH, RAMP_TOP),
+        ],
+        coins: vec![],
+        zones: vec![],
+    }
+}
+
+fn springboard() -> SegmentData {
+    const SPRING_OFFSET: i16 = 150;
+    const SKY_PLATFORM_OFFSET: i16 = SPRING_OFFSET + 120;
+    SegmentData {
+        obstacles: vec![
+            ObstacleData::new("spring", SPRING_OFFSET, SPRING_ON_GROUND),
+            ObstacleData::new("platform", SKY_PLATFORM_OFFSET, SKY_PLATFORM),
+        ],
+        coins: vec![],
+        zones: vec![],
+    }
+}
+
+fn gap() -> SegmentData {
+    const PIT_OFFSET: i16 = 150;
+    SegmentData {
+        obstacles: vec![ObstacleData::new("pit", PIT_OFFSET, PIT_TOP)],
+        coins: vec![],
+        zones: vec![],
+    }
+}
+
+/// A row of pebbles, which only trip the boy up.
+fn pebbles() -> SegmentData {
+    const PEBBLE_OFFSET: i16 = 150;
+    SegmentData {
+        obstacles: (0..3)
+            .map(|index| {
+                ObstacleData::new(
+                    "pebble",
+                    PEBBLE_OFFSET + index * PEBBLE_SPACING,
+                    PEBBLE_ON_GROUND,
+                )
+            })
+            .collect(),
+        coins: vec![],
+        zones: vec![],
+    }
+}
+
+/// Platforms climbing up out of the top of the screen, with coins at the
+/// top for whoever makes it up there. The boy drops back down to the ground
+/// off the end of the last one.
+fn tower() -> SegmentData {
+    const TOWER_OFFSET: i16 = 150;
+    const FLOORS: i16 = 3;
+    let top = LOW_PLATFORM - (FLOORS - 1) * TOWER_STEP;
+    let top_x = TOWER_OFFSET + (FLOORS - 1) * (PLATFORM_WIDTH + TOWER_GAP);
+    SegmentData {
+        obstacles: (0..FLOORS)
+            .map(|floor| {
+                ObstacleData::new(
+                    "platform",
+                    TOWER_OFFSET + floor * (PLATFORM_WIDTH + TOWER_GAP),
+                    LOW_PLATFORM - floor * TOWER_STEP,
+                )
+            })
+            .collect(),
+        coins: vec![CoinData {
+            x: top_x + TOWER_GAP,
+            y: top - TOWER_COIN_HEIGHT,
+            count: TOWER_COIN_ROW,
+        }],
+        zones: vec![],
+    }
+}
+
+/// Two beams to slide under, too far apart to do in one slide.
+fn beams() -> SegmentData {
+    const BEAMS_OFFSET: i16 = 150;
+    SegmentData {
+        obstacles: (0..2)
+            .map(|beam| ObstacleData::new("beam", BEAMS_OFFSET + beam * BEAM_SPACING, BEAM_TOP))
+            .collect(),
+        coins: vec![],
+        zones: vec![],
+    }
+}
+
+/// A chain of ledges, up and down, too low to run under, so the boy hops
+/// from one to the next. Coins over each gap reward keeping it going.
+fn hops() -> SegmentData {
+    const HOPS_OFFSET: i16 = 150;
+    const LEDGES: i16 = 4;
+    let ledge_y = |ledge: i16| LOW_LEDGE - (ledge % 2) * HOP_STEP;
+    let ledge_x = |ledge: i16| HOPS_OFFSET + ledge * (LEDGE_WIDTH + HOP_GAP);
+    SegmentData {
+        obstacles: (0..LEDGES)
+            .map(|ledge| ObstacleData::new("ledge", ledge_x(ledge), ledge_y(ledge)))
+            .collect(),
+        coins: (1..LEDGES)
+            .map(|ledge| CoinData {
+                x: ledge_x(ledge) - HOP_GAP,
+                y: ledge_y(ledge).min(ledge_y(ledge - 1)) - HOP_COIN_HEIGHT,
+                count: HOP_COIN_ROW,
+            })
+            .collect(),
+        zones: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hops_go_up_and_down_with_coins_over_each_gap() {
+        let hops = hops();
+        let heights: Vec<i16> = hops.obstacles.iter().map(|ledge| ledge.y).collect();
+        assert_eq!(
+            heights,
+            [
+                LOW_LEDGE,
+                LOW_LEDGE - HOP_STEP,
+                LOW_LEDGE,
+                LOW_LEDGE - HOP_STEP
+            ]
+        );
+        hops.obstacles
+            .windows(2)
+            .zip(&hops.coins)
+            .for_each(|(ledges, coins)| {
+                assert_eq!(ledges[1].x - ledges[0].x - LEDGE_WIDTH, HOP_GAP);
+                assert_eq!(coins.x, ledges[0].x + LEDGE_WIDTH);
+            });
+    }
+
+    #[test]
+    fn a_segment_is_built_a_few_obstacles_at_a_time() {
+        let mut builder = SegmentBuilder::new(10, HEIGHT);
+        let mut built = 0;
+        builder.step_with(3, |_| {
+            built += 1;
+            None
+        });
+        assert_eq!(built, 3);
+        assert!(!builder.finished());
+        builder.step_with(3, |_| {
+            built += 1;
+            None
+        });
+        assert_eq!(built, hops().obstacles.len());
+        assert!(builder.finished());
+        builder.step_with(3, |_| panic!("Built past the end"));
+    }
+
+    #[test]
+    fn finishes_a_partly_built_segment() {
+        let mut builder = SegmentBuilder::new(10, HEIGHT);
+        let mut built = 0;
+        builder.step_with(2, |_| {
+            built += 1;
+            None
+        });
+        builder.step_with(usize::MAX, |_| {
+            built += 1;
+            None
+        });
+        assert_eq!(built, hops().obstacles.len());
+        assert!(builder.finished());
+    }
+
+    #[test]
+    fn segments_move_down_onto_a_lower_ground() {
+        let hops = hops();
+        let lowered = hops.clone().on_ground(HEIGHT + 120);
+        hops.obstacles
+            .iter()
+            .zip(&lowered.obstacles)
+            .for_each(|(ledge, lowered)| assert_eq!(lowered.y, ledge.y + 120));
+        hops.coins
+            .iter()
+            .zip(&lowered.coins)
+            .for_each(|(coins, lowered)| assert_eq!(lowered.y, coins.y + 120));
+        assert_eq!(gust().on_ground(HEIGHT).zones[0].y, gust().zones[0].y);
+    }
+
+    #[test]
+    fn pits_that_touch_make_one_gap() {
+        assert_eq!(
+            merge_spans(vec![(400, 496), (100, 196), (196, 292)]),
+            [(100, 292), (400, 496)]
+        );
+        assert_eq!(merge_spans(vec![]), []);
+    }
+
+    #[test]
+    fn jumps_are_checked_with_the_zone_they_are_taken_in() {
+        let gust = gust();
+        assert_eq!(modifier_at(&gust, 600), ZoneKind::Wind.modifier());
+        assert_eq!(modifier_at(&gust, 900), Modifier::default());
+        let reach = |x| Reach {
+            distance: if modifier_at(&gust, x).drag < 0.0 {
+                300
+            } else {
+                200
+            },
+            height: 100,
+        };
+        assert_eq!(
+            out_of_reach(vec![(600, 850), (1000, 1250)], vec![], reach),
+            [Problem::Gap {
+                start: 1000,
+                end: 1250
+            }]
+        );
+    }
+
+    #[test]
+    fn flags_what_a_jump_cannot_get_past() {
+        let reach = Reach {
+            distance: 200,
+            height: 100,
+        };
+        assert_eq!(
+            out_of_reach(
+                vec![(0, 200), (300, 501)],
+                vec![(50, 54), (600, 120)],
+                |_| reach
+            ),
+            [
+                Problem::Gap {
+                    start: 300,
+                    end: 501
+                },
+                Problem::Hurdle {
+                    x: 600,
+                    height: 120
+                },
+            ]
+        );
+    }
 }
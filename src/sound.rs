@@ -1,15 +1,42 @@
 use anyhow::{anyhow, Result};
+use serde::Deserialize;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    js_sys::ArrayBuffer, AudioBuffer, AudioBufferSourceNode, AudioContext, AudioDestinationNode,
-    AudioNode,
+    js_sys::ArrayBuffer, AudioBuffer, AudioBufferSourceNode, AudioContext, AudioNode,
+    AudioScheduledSourceNode, GainNode,
 };
+
+const DUCK_ATTACK: f64 = 0.05;
+const DUCK_RELEASE: f64 = 0.4;
+
+#[derive(Clone, Copy)]
 pub enum Looping {
     No,
     Yes,
 }
 
+/// A region of an audio buffer, in seconds. A cue without a duration plays to
+/// the end of the buffer.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Cue {
+    pub start: f64,
+    pub duration: Option<f64>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Pitch {
+    pub playback_rate: f32,
+    pub detune: f32,
+}
+
+impl Pitch {
+    pub const NATURAL: Pitch = Pitch {
+        playback_rate: 1.0,
+        detune: 0.0,
+    };
+}
+
 pub fn create_audio_context() -> Result<AudioContext> {
     AudioContext::new().map_err(|err| anyhow!("Counld not create audio context: {:#?}", err))
 }
@@ -23,25 +50,108 @@ fn connect_with_param_audio_node(
     ctx: &AudioContext,
     volume: f32,
     buffer_source: &AudioBufferSourceNode,
-    destination: &AudioDestinationNode,
-) -> Result<AudioNode> {
+    destination: &AudioNode,
+) -> Result<GainNode> {
     let g = ctx.create_gain().unwrap();
     g.gain().set_value(volume);
 
     buffer_source.connect_with_audio_node(&g).unwrap();
     g.connect_with_audio_node(destination)
-        .map_err(|err| anyhow!("Error connecting audio source to destination {:#?}", err))
+        .map_err(|err| anyhow!("Error connecting audio source to destination {:#?}", err))?;
+    Ok(g)
+}
+
+/// A playing sound along with the gain node controlling its volume.
+pub struct Track {
+    source: AudioBufferSourceNode,
+    gain: GainNode,
+}
+
+impl Track {
+    /// Ramps the volume linearly from where it is now to `volume` over
+    /// `duration` seconds.
+    pub fn fade(&self, ctx: &AudioContext, volume: f32, duration: f64) -> Result<()> {
+        let now = ctx.current_time();
+        let gain = self.gain.gain();
+        gain.cancel_scheduled_values(now)
+            .and_then(|gain| gain.set_value_at_time(gain.value(), now))
+            .and_then(|gain| gain.linear_ramp_to_value_at_time(volume, now + duration))
+            .map(|_gain| ())
+            .map_err(|err| anyhow!("Could not fade track {:#?}", err))
+    }
+
+    pub fn stop(&self, ctx: &AudioContext, delay: f64) -> Result<()> {
+        let source: &AudioScheduledSourceNode = self.source.as_ref();
+        source
+            .stop_with_when(ctx.current_time() + delay)
+            .map_err(|err| anyhow!("Could not stop track {:#?}", err))
+    }
+}
+
+/// The mixer graph every track plays through. Music and sound effects each
+/// have their own bus, and both feed the master gain.
+#[derive(Clone)]
+pub struct Mixer {
+    master: GainNode,
+    music: GainNode,
+    sfx: GainNode,
+}
+
+impl Mixer {
+    pub fn new(ctx: &AudioContext) -> Result<Self> {
+        let master = create_bus(ctx, &ctx.destination())?;
+        let music = create_bus(ctx, &master)?;
+        let sfx = create_bus(ctx, &master)?;
+        Ok(Mixer { master, music, sfx })
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.master.gain().set_value(if muted { 0.0 } else { 1.0 });
+    }
+
+    pub fn music(&self) -> &AudioNode {
+        &self.music
+    }
+
+    pub fn sfx(&self) -> &AudioNode {
+        &self.sfx
+    }
+
+    /// Drops the music bus to `level`, holds it there for `hold` seconds and
+    /// then ramps it back to full volume.
+    pub fn duck(&self, ctx: &AudioContext, level: f32, hold: f64) -> Result<()> {
+        let now = ctx.current_time();
+        let release_at = now + DUCK_ATTACK + hold;
+        let gain = self.music.gain();
+        gain.cancel_scheduled_values(now)
+            .and_then(|gain| gain.set_value_at_time(gain.value(), now))
+            .and_then(|gain| gain.linear_ramp_to_value_at_time(level, now + DUCK_ATTACK))
+            .and_then(|gain| gain.set_value_at_time(level, release_at))
+            .and_then(|gain| gain.linear_ramp_to_value_at_time(1.0, release_at + DUCK_RELEASE))
+            .map(|_gain| ())
+            .map_err(|err| anyhow!("Could not duck music {:#?}", err))
+    }
+}
+
+fn create_bus(ctx: &AudioContext, destination: &AudioNode) -> Result<GainNode> {
+    let bus = ctx
+        .create_gain()
+        .map_err(|err| anyhow!("Error creating gain node {:#?}", err))?;
+    bus.connect_with_audio_node(destination)
+        .map_err(|err| anyhow!("Error connecting bus to destination {:#?}", err))?;
+    Ok(bus)
 }
 
 fn create_track_sound(
     ctx: &AudioContext,
     buffer: &AudioBuffer,
     volume: f32,
-) -> Result<AudioBufferSourceNode> {
-    let track_source = create_buffer_source(ctx)?;
-    track_source.set_buffer(Some(buffer));
-    connect_with_param_audio_node(ctx, volume, &track_source, &ctx.destination())?;
-    Ok(track_source)
+    destination: &AudioNode,
+) -> Result<Track> {
+    let source = create_buffer_source(ctx)?;
+    source.set_buffer(Some(buffer));
+    let gain = connect_with_param_audio_node(ctx, volume, &source, destination)?;
+    Ok(Track { source, gain })
 }
 
 pub fn play_sound(
@@ -49,14 +159,33 @@ pub fn play_sound(
     buffer: &AudioBuffer,
     looping: Looping,
     volume: f32,
-) -> Result<()> {
-    let track_source = create_track_sound(ctx, buffer, volume)?;
+    cue: Option<&Cue>,
+    pitch: Pitch,
+    destination: &AudioNode,
+) -> Result<Track> {
+    let track = create_track_sound(ctx, buffer, volume, destination)?;
+    let track_source = &track.source;
+    track_source.playback_rate().set_value(pitch.playback_rate);
+    track_source.detune().set_value(pitch.detune);
     if matches!(looping, Looping::Yes) {
         track_source.set_loop(true);
+        if let Some(cue) = cue {
+            track_source.set_loop_start(cue.start);
+            track_source.set_loop_end(cue.start + cue.duration.unwrap_or(buffer.duration()));
+        }
+    }
+    match cue {
+        Some(Cue {
+            start,
+            duration: Some(duration),
+        }) if matches!(looping, Looping::No) => {
+            track_source.start_with_when_and_grain_offset_and_grain_duration(0.0, *start, *duration)
+        }
+        Some(cue) => track_source.start_with_when_and_grain_offset(0.0, cue.start),
+        None => track_source.start(),
     }
-    track_source
-        .start()
-        .map_err(|err| anyhow!("Could not start sound! {:#?}", err))
+    .map_err(|err| anyhow!("Could not start sound! {:#?}", err))?;
+    Ok(track)
 }
 
 pub async fn decode_audio_data(
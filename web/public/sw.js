@@ -0,0 +1,74 @@
+// Caches the game for offline play. The page registers this worker with a
+// hash of the build in its URL and posts the cache manifest (built by the
+// crate's `cache_manifest`, plus the page, scripts and wasm it loaded) once it
+// is active.
+const CACHE_PREFIX = "walk-the-dog-";
+// Fixed for the life of the worker, however often the browser restarts it,
+// since it comes from the URL the worker was registered with.
+const CACHE_NAME =
+  CACHE_PREFIX + (new URL(self.location.href).searchParams.get("version") || "unversioned");
+
+self.addEventListener("install", () => self.skipWaiting());
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((names) =>
+        Promise.all(
+          names
+            .filter((name) => name.startsWith(CACHE_PREFIX) && name !== CACHE_NAME)
+            .map((name) => caches.delete(name)),
+        ),
+      )
+      .then(() => self.clients.claim()),
+  );
+});
+
+self.addEventListener("message", (event) => {
+  const { assets } = event.data;
+  event.waitUntil(caches.open(CACHE_NAME).then((cache) => cache.addAll(assets)));
+});
+
+// The page, scripts, wasm module and config change with each deploy, so
+// they come from the network when it is there, and from the cache offline.
+function networkFirst(request, url) {
+  return (
+    request.mode === "navigate" ||
+    url.pathname.endsWith(".js") ||
+    url.pathname.endsWith(".wasm") ||
+    url.pathname.endsWith("config.json")
+  );
+}
+
+function fetchAndCache(request) {
+  return fetch(request).then((response) => {
+    if (response.ok) {
+      const copy = response.clone();
+      caches.open(CACHE_NAME).then((cache) => cache.put(request, copy));
+    }
+    return response;
+  });
+}
+
+// Everything else, the images, sounds and data, is cache first, falling back
+// to the network. Anything fetched from this origin is cached too.
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  const url = new URL(request.url);
+  if (request.method !== "GET" || url.origin !== self.location.origin) {
+    return;
+  }
+  if (networkFirst(request, url)) {
+    event.respondWith(
+      fetchAndCache(request).catch(() =>
+        // The page is cached without its query, which changes between links.
+        caches
+          .match(request, { ignoreSearch: request.mode === "navigate" })
+          .then((cached) => cached || Promise.reject(new Error("Offline"))),
+      ),
+    );
+  } else {
+    event.respondWith(caches.match(request).then((cached) => cached || fetchAndCache(request)));
+  }
+});
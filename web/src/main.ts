@@ -1,5 +1,5 @@
 import "./style.css";
 
-import init from "../pkg";
+import init, { start_page } from "../pkg";
 
-init();
+init().then(start_page);